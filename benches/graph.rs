@@ -0,0 +1,97 @@
+#[path = "../tests/common/mod.rs"]
+mod common;
+
+use std::hint::black_box;
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use git2::Oid;
+
+use gitui::backend::{layout_commits, load_commits, GraphSize, LayoutMode, ReferenceGroups};
+use gitui::config::Orientation;
+
+use common::Fixture;
+
+// One branch of history
+fn linear(commits: usize) -> Fixture {
+    let mut fixture = Fixture::new("linear");
+    let mut head = fixture.numbered_commit(&[]);
+    for _ in 1..commits {
+        head = fixture.numbered_commit(&[head]);
+    }
+    fixture.branch("main", head);
+    fixture
+}
+
+// Rounds of `width` short branches forking off the main line together and merged back into it one after another
+fn wide_merge(rounds: usize, width: usize) -> Fixture {
+    let mut fixture = Fixture::new("wide-merge");
+    let mut head = fixture.numbered_commit(&[]);
+    for _ in 0..rounds {
+        let fork = head;
+        let tips: Vec<Oid> = (0..width).map(|_| {
+            let mut tip = fixture.numbered_commit(&[fork]);
+            for _ in 0..2 {
+                tip = fixture.numbered_commit(&[tip]);
+            }
+            tip
+        }).collect();
+        for tip in tips {
+            head = fixture.numbered_commit(&[head, tip]);
+        }
+    }
+    fixture.branch("main", head);
+    fixture
+}
+
+// A main line with a branch of its own growing off every few of its commits, none of them merged
+fn many_branches(branches: usize, length: usize) -> Fixture {
+    let mut fixture = Fixture::new("many-branches");
+    let mut trunk = vec![fixture.numbered_commit(&[])];
+    for _ in 1..branches * 2 {
+        let head = fixture.numbered_commit(&[*trunk.last().unwrap()]);
+        trunk.push(head);
+    }
+    fixture.branch("main", *trunk.last().unwrap());
+    for branch in 0..branches {
+        let mut tip = trunk[branch * 2];
+        for _ in 0..length {
+            tip = fixture.numbered_commit(&[tip]);
+        }
+        fixture.branch(&format!("feature-{}", branch), tip);
+    }
+    fixture
+}
+
+fn shapes() -> Vec<(&'static str, Fixture)> {
+    vec![
+        ("linear", linear(2000)),
+        ("wide-merge", wide_merge(40, 8)),
+        ("many-branches", many_branches(200, 5)),
+    ]
+}
+
+fn graph_benchmarks(c: &mut Criterion) {
+    let shapes = shapes();
+
+    let mut construction = c.benchmark_group("construction");
+    for (name, fixture) in shapes.iter() {
+        construction.bench_function(BenchmarkId::from_parameter(name), |b| b.iter(|| {
+            load_commits(&fixture.repository, ReferenceGroups::ALL, &mut |_| true).unwrap().unwrap()
+        }));
+    }
+    construction.finish();
+
+    let mut layout = c.benchmark_group("layout");
+    for (name, fixture) in shapes.iter() {
+        let mut commits = load_commits(&fixture.repository, ReferenceGroups::ALL, &mut |_| true).unwrap().unwrap();
+        for mode in LayoutMode::ALL {
+            layout.bench_function(BenchmarkId::new(mode.to_string(), name), |b| b.iter(|| {
+                black_box(layout_commits(&mut commits, mode, Orientation::Horizontal, GraphSize::default()))
+            }));
+        }
+    }
+    layout.finish();
+}
+
+criterion_group!(benches, graph_benchmarks);
+criterion_main!(benches);
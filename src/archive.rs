@@ -0,0 +1,134 @@
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+use chrono::{Datelike, Timelike, TimeZone, Utc};
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use git2::{ObjectType, Oid, Repository, TreeWalkMode, TreeWalkResult};
+
+use crate::error::Error;
+
+const MODE_SYMLINK: i32 = 0o120000;
+const MODE_EXECUTABLE: i32 = 0o100755;
+
+struct ArchiveEntry {
+    path: String,
+    mode: i32,
+    data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArchiveFormat {
+    TarGz,
+    Zip,
+}
+
+impl ArchiveFormat {
+    pub fn from_path(path: &Path) -> Option<ArchiveFormat> {
+        let name = path.file_name()?.to_str()?.to_ascii_lowercase();
+        if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(ArchiveFormat::TarGz)
+        } else if name.ends_with(".zip") {
+            Some(ArchiveFormat::Zip)
+        } else {
+            None
+        }
+    }
+}
+
+// Blobs of the commit's tree in tree order; submodules are skipped like `git archive` does
+fn collect_entries(repository: &Repository, id: Oid) -> Result<Vec<ArchiveEntry>, git2::Error> {
+    let tree = repository.find_commit(id)?.tree()?;
+
+    let mut entries = Vec::new();
+    let mut error = None;
+    tree.walk(TreeWalkMode::PreOrder, |root, entry| {
+        if entry.kind() != Some(ObjectType::Blob) {
+            return TreeWalkResult::Ok;
+        }
+        match repository.find_blob(entry.id()) {
+            Ok(blob) => {
+                let path = format!("{}{}", root, entry.name().unwrap_or_default());
+                entries.push(ArchiveEntry { path, mode: entry.filemode(), data: blob.content().to_vec() });
+                TreeWalkResult::Ok
+            },
+            Err(e) => {
+                error = Some(e);
+                TreeWalkResult::Abort
+            },
+        }
+    })?;
+
+    match error {
+        Some(e) => Err(e),
+        None => Ok(entries),
+    }
+}
+
+fn write_tar_gz(file: File, entries: &[ArchiveEntry], prefix: &str, time: i64) -> std::io::Result<()> {
+    let mut builder = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+
+    for entry in entries {
+        let mut header = tar::Header::new_gnu();
+        header.set_mtime(time.max(0) as u64);
+        if entry.mode == MODE_SYMLINK {
+            header.set_entry_type(tar::EntryType::Symlink);
+            header.set_size(0);
+            header.set_mode(0o777);
+            let target = String::from_utf8_lossy(&entry.data).to_string();
+            builder.append_link(&mut header, format!("{}{}", prefix, entry.path), target)?;
+        } else {
+            header.set_entry_type(tar::EntryType::Regular);
+            header.set_size(entry.data.len() as u64);
+            header.set_mode(if entry.mode == MODE_EXECUTABLE { 0o755 } else { 0o644 });
+            builder.append_data(&mut header, format!("{}{}", prefix, entry.path), entry.data.as_slice())?;
+        }
+    }
+
+    builder.into_inner()?.finish()?;
+    Ok(())
+}
+
+fn write_zip(file: File, entries: &[ArchiveEntry], prefix: &str, time: i64) -> zip::result::ZipResult<()> {
+    let mut writer = zip::ZipWriter::new(file);
+
+    let mut options = zip::write::FileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    if let Some(date) = Utc.timestamp_opt(time, 0).single() {
+        let date = zip::DateTime::from_date_and_time(date.year() as u16, date.month() as u8, date.day() as u8, date.hour() as u8, date.minute() as u8, date.second() as u8);
+        if let Ok(date) = date {
+            options = options.last_modified_time(date);
+        }
+    }
+
+    for entry in entries {
+        let path = format!("{}{}", prefix, entry.path);
+        if entry.mode == MODE_SYMLINK {
+            writer.add_symlink(path, String::from_utf8_lossy(&entry.data), options)?;
+        } else {
+            writer.start_file(path, options.unix_permissions(if entry.mode == MODE_EXECUTABLE { 0o755 } else { 0o644 }))?;
+            writer.write_all(&entry.data)?;
+        }
+    }
+
+    writer.finish()?;
+    Ok(())
+}
+
+// Equivalent of `git archive --prefix=<prefix> <commit>`, with the format picked from the file name
+pub fn archive_commit(repository: &Repository, commit: &str, path: &Path, prefix: &str) -> Result<usize, Error> {
+    let format = ArchiveFormat::from_path(path).ok_or(Error::from("the file must end in .tar.gz, .tgz or .zip"))?;
+
+    let id = Oid::from_str(commit)?;
+    let time = repository.find_commit(id)?.time().seconds();
+    let entries = collect_entries(repository, id)?;
+
+    let prefix = if prefix.is_empty() || prefix.ends_with('/') { prefix.to_string() } else { format!("{}/", prefix) };
+    let file = File::create(path)?;
+    match format {
+        ArchiveFormat::TarGz => write_tar_gz(file, &entries, &prefix, time)?,
+        ArchiveFormat::Zip => write_zip(file, &entries, &prefix, time).map_err(|e| Error::Message(e.to_string()))?,
+    }
+
+    Ok(entries.len())
+}
@@ -1,83 +1,1454 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
 
+use git2::{BranchType, Config, DiffFormat, Oid, Repository, RepositoryInitOptions, Sort, StatusOptions};
+use iced::Point;
+use tracing::instrument;
+
+use crate::commitgraph::{CommitGraph, graph_descendant_of};
+use crate::config::{Orientation, get_config_signature, get_signature};
+use crate::lfs::{LfsPointer, blob_pointer, describe_change};
+use crate::maintenance::format_size;
+use crate::preview::{ImagePreview, is_image_path, load_preview};
+use crate::signing::{SignatureStatus, sign_commit_buffer};
+
+#[derive(Clone)]
 pub struct CommitNode {
     pub id: String,
     pub parents: Vec<String>,
     pub children: Vec<String>,
-    pub reference: Option<String>,
+    // Every branch and tag pointing at the commit, in the order of their full names
+    pub references: Vec<ReferenceLabel>,
+    pub signature: Option<SignatureStatus>,
+    pub summary: String,
+    pub author: String,
+    pub email: String,
+    pub time: git2::Time,
+    // Lanes the branches growing out of the commit take up besides its own, as of the last `update_tree_sizes`
+    pub tree_size: usize,
 }
 
 impl CommitNode {
-    pub fn create(commit: git2::Commit, commits: &mut HashMap<String, CommitNode>, reference: Option<String>) -> String {
-        if commits.contains_key(&commit.id().to_string()) && reference.is_none() {
-            commit.id().to_string()
-        } else {
-            let mut result = CommitNode { id: commit.id().to_string(), parents: Vec::new(), children: Vec::new(), reference };
+    // Names of the local branches pointing at the commit, the references that can be checked out or moved
+    pub fn branches(&self) -> impl Iterator<Item = &String> {
+        self.references.iter().filter(|reference| reference.kind == ReferenceKind::Branch).map(|reference| &reference.name)
+    }
 
-            for parent in commit.parents() {
-                let commit = CommitNode::create(parent, commits, None);
-                result.parents.push(commit.clone());
+    // Returns None once `progress`, called with the number of commits created so far, asks to stop. Parents are
+    // created before their children from a stack of their own, since a chain of commits is as long as the history
+    pub fn create(commit: git2::Commit, commits: &mut BTreeMap<String, CommitNode>, reference: Option<ReferenceLabel>, progress: &mut dyn FnMut(usize) -> bool) -> Option<String> {
+        let tip = commit.id().to_string();
+        if let Some(existing) = commits.get_mut(&tip) {
+            // Building it again would lose the children found so far, so a reference to a commit that is already in
+            // the graph only labels it
+            existing.references.extend(reference);
+            return Some(tip);
+        }
+
+        // A commit is pushed again once its parents are on the stack above it, and created when it comes back up
+        let mut pending = vec![(commit, false)];
+        let mut reference = reference;
+        while let Some((commit, parents_created)) = pending.pop() {
+            let id = commit.id().to_string();
+            if commits.contains_key(&id) {
+                continue;
+            }
+            if !parents_created {
+                let parents: Vec<git2::Commit> = commit.parents().collect();
+                pending.push((commit, true));
+                // Reversed, so the first parent's history is created first
+                pending.extend(parents.into_iter().rev().filter(|parent| !commits.contains_key(&parent.id().to_string())).map(|parent| (parent, false)));
+                continue;
             }
 
-            for parent in commit.parents() {
-                let commit = CommitNode::create(parent, commits, None);
-                let commit = commits.get_mut(&commit).unwrap();
-                commit.children.push(result.id.clone());
+            let signature = commit.header_field_bytes("gpgsig").ok().map(|_| SignatureStatus::Unchecked);
+            let author = commit.author();
+            let parents: Vec<String> = commit.parent_ids().map(|parent| parent.to_string()).collect();
+            for parent in &parents {
+                add_child(commits, parent, &id, commit.time());
+            }
+            let result = CommitNode {
+                id: id.clone(),
+                parents,
+                children: Vec::new(),
+                references: if id == tip { reference.take().into_iter().collect() } else { Vec::new() },
+                signature,
+                summary: commit.summary().unwrap_or_default().to_string(),
+                author: author.name().unwrap_or_default().to_string(),
+                email: author.email().unwrap_or_default().to_string(),
+                time: commit.time(),
+                tree_size: 0,
+            };
+
+            commits.insert(id, result);
+            if !progress(commits.len()) {
+                return None;
+            }
+        }
+        Some(tip)
+    }
+}
+
+// Children are kept oldest first, ties broken by id, so which side of a fork a branch goes on doesn't depend on the
+// order the history was walked in, and a graph brought up to date is laid out like one loaded from scratch
+fn add_child(commits: &mut BTreeMap<String, CommitNode>, parent: &str, child: &str, time: git2::Time) {
+    let key = (time.seconds(), child);
+    let position = commits[parent].children.iter()
+        .position(|other| (commits[other].time.seconds(), other.as_str()) > key)
+        .unwrap_or(commits[parent].children.len());
+    commits.get_mut(parent).unwrap().children.insert(position, child.to_string());
+}
+
+// Which references besides local branches the graph is built from. Repositories can have hundreds of remote branches
+// and tags, so walking their history is left until it is asked for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ReferenceGroups {
+    pub remotes: bool,
+    pub tags: bool,
+}
+
+impl ReferenceGroups {
+    pub const ALL: ReferenceGroups = ReferenceGroups { remotes: true, tags: true };
+
+    pub fn includes(self, kind: ReferenceKind) -> bool {
+        match kind {
+            ReferenceKind::Branch => true,
+            ReferenceKind::Remote => self.remotes,
+            ReferenceKind::Tag => self.tags,
+        }
+    }
+
+    pub fn set(&mut self, kind: ReferenceKind, loaded: bool) {
+        match kind {
+            ReferenceKind::Branch => (),
+            ReferenceKind::Remote => self.remotes = loaded,
+            ReferenceKind::Tag => self.tags = loaded,
+        }
+    }
+}
+
+fn reference_kind(reference: &git2::Reference) -> Option<ReferenceKind> {
+    if reference.is_branch() {
+        Some(ReferenceKind::Branch)
+    } else if reference.is_remote() {
+        Some(ReferenceKind::Remote)
+    } else if reference.is_tag() {
+        Some(ReferenceKind::Tag)
+    } else {
+        None
+    }
+}
+
+// Number of commits reachable from the references in `groups`, used as the total when reporting load progress
+#[instrument(skip(repository), err)]
+pub fn count_commits(repository: &Repository, groups: ReferenceGroups) -> Result<usize, git2::Error> {
+    let mut tips = Vec::new();
+    for reference in repository.references()? {
+        let reference = reference?;
+        if reference_kind(&reference).is_none_or(|kind| groups.includes(kind)) {
+            if let Ok(commit) = reference.peel_to_commit() {
+                tips.push(commit.id());
             }
+        }
+    }
+
+    // The commit-graph has the parents of every commit, so counting doesn't have to read every commit twice. Only
+    // while it knows all the tips, newer commits aren't in it
+    if let Some(count) = CommitGraph::open(repository).and_then(|graph| graph.reachable_count(&tips)) {
+        return Ok(count);
+    }
+    let mut revwalk = repository.revwalk()?;
+    for tip in tips {
+        revwalk.push(tip)?;
+    }
+    Ok(revwalk.count())
+}
+
+// Builds the graph from the references in `groups`; None when `progress` cancelled the load
+pub fn load_commits(repository: &Repository, groups: ReferenceGroups, progress: &mut dyn FnMut(usize) -> bool) -> Result<Option<BTreeMap<String, CommitNode>>, git2::Error> {
+    update_commits(repository, BTreeMap::new(), groups, progress)
+}
 
-            commits.insert(commit.id().to_string(), result);
-            commit.id().to_string()
+// Brings a graph built earlier up to date with the references. Only references that moved or are new are walked, and
+// only until they reach commits already in the graph, so refreshing after a commit or a fetch doesn't go through the
+// whole history again. Commits no longer reachable from any reference are dropped, and so are those only reachable from
+// references outside `groups`, starting from the references that went away
+#[instrument(skip_all, fields(previous = previous.len()), err)]
+pub fn update_commits(repository: &Repository, previous: BTreeMap<String, CommitNode>, groups: ReferenceGroups, progress: &mut dyn FnMut(usize) -> bool) -> Result<Option<BTreeMap<String, CommitNode>>, git2::Error> {
+    let mut commits = previous;
+    // What the graph was built from: the labelled commits, and those nothing grew out of, which covers references
+    // that aren't labelled. Only the graph in memory is gone through, no commit is read
+    let mut previous_tips = Vec::new();
+    for commit in commits.values_mut() {
+        if !commit.references.is_empty() || commit.children.is_empty() {
+            previous_tips.push(commit.id.clone());
         }
+        commit.references.clear();
+    }
+
+    // Loose references come in the order the file system lists them, so they are sorted by name for the labels on a
+    // commit to come out the same every time
+    let mut references = repository.references()?.collect::<Result<Vec<_>, _>>()?;
+    references.sort_by(|a, b| a.name_bytes().cmp(b.name_bytes()));
+    let mut tips = HashSet::new();
+    for reference in references {
+        // Names that are not valid UTF-8 can't be displayed, so those references are skipped
+        if reference.name().is_none() {
+            continue;
+        }
+        // Others, like the stash, still have their history shown but aren't labelled
+        let kind = reference_kind(&reference);
+        if kind.is_some_and(|kind| !groups.includes(kind)) {
+            continue;
+        }
+        let label = kind.map(|kind| ReferenceLabel { name: reference.shorthand().unwrap_or_default().to_string(), kind });
+
+        if let Ok(commit) = reference.peel_to_commit() {
+            // A reference that didn't move only labels its commit again
+            match CommitNode::create(commit, &mut commits, label, progress) {
+                Some(tip) => tips.insert(tip),
+                None => return Ok(None),
+            };
+        }
+    }
+
+    // A commit that no reference reaches any more has nothing left growing out of it once its unreachable children are
+    // gone, so removing them down from the old tips that have none finds every one of them
+    let mut pending: Vec<String> = previous_tips.into_iter().filter(|id| !tips.contains(id)).collect();
+    while let Some(id) = pending.pop() {
+        if tips.contains(&id) || commits.get(&id).is_none_or(|commit| !commit.children.is_empty()) {
+            continue;
+        }
+        let commit = commits.remove(&id).unwrap();
+        for parent in commit.parents {
+            if let Some(parent_commit) = commits.get_mut(&parent) {
+                parent_commit.children.retain(|child| *child != id);
+                if parent_commit.children.is_empty() {
+                    pending.push(parent);
+                }
+            }
+        }
+    }
+
+    Ok(Some(commits))
+}
+
+// How big the graph is drawn. Dense histories fit better with smaller nodes packed closer together
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GraphSize {
+    pub node_radius: f32,
+    // Distance between neighbouring commits along the direction of history, in node radii
+    pub column_spacing: f32,
+    // Distance between neighbouring branches, in node radii
+    pub row_spacing: f32,
+    // Multiplies the size of every label on the graph
+    pub text_scale: f32,
+}
+
+impl Default for GraphSize {
+    fn default() -> Self {
+        Self { node_radius: 50.0, column_spacing: 2.5, row_spacing: 1.5, text_scale: 1.0 }
+    }
+}
+
+impl GraphSize {
+    pub fn column_width(&self) -> f32 {
+        self.node_radius * self.column_spacing
+    }
+
+    pub fn row_height(&self) -> f32 {
+        self.node_radius * self.row_spacing
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LayoutMode {
+    #[default]
+    Topological,
+    // One column per commit in order of commit time, like gitk
+    Chronological,
+}
+
+impl LayoutMode {
+    pub const ALL: [LayoutMode; 2] = [LayoutMode::Topological, LayoutMode::Chronological];
+}
+
+impl fmt::Display for LayoutMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LayoutMode::Topological => write!(f, "Topological"),
+            LayoutMode::Chronological => write!(f, "Chronological"),
+        }
+    }
+}
+
+// Every commit after all of its parents, so each pass over it finds the parents' results already there
+fn parents_first(commits: &BTreeMap<String, CommitNode>) -> Vec<&CommitNode> {
+    let mut waiting: HashMap<&str, usize> = commits.values()
+        .map(|commit| (commit.id.as_str(), commit.parents.iter().filter(|parent| commits.contains_key(*parent)).count()))
+        .collect();
+    let mut ready: Vec<&CommitNode> = commits.values().filter(|commit| waiting[commit.id.as_str()] == 0).collect();
+    let mut ordered = Vec::with_capacity(commits.len());
+    while let Some(commit) = ready.pop() {
+        ordered.push(commit);
+        for child in &commit.children {
+            if let Some(count) = waiting.get_mut(child.as_str()).filter(|count| **count > 0) {
+                *count -= 1;
+                if *count == 0 {
+                    ready.push(&commits[child]);
+                }
+            }
+        }
+    }
+    ordered
+}
+
+// Fills in `tree_size` for every commit in one pass from the newest commits down, instead of walking up the whole
+// history above every commit again
+pub fn update_tree_sizes(commits: &mut BTreeMap<String, CommitNode>) {
+    let mut sizes: HashMap<String, usize> = HashMap::new();
+    for commit in parents_first(commits).into_iter().rev() {
+        let size = commit.children.iter()
+            .fold(commit.children.len().saturating_sub(1), |size, child| size.saturating_add(sizes.get(child).copied().unwrap_or(0)));
+        sizes.insert(commit.id.clone(), size);
+    }
+    for (id, size) in sizes {
+        if let Some(commit) = commits.get_mut(&id) {
+            commit.tree_size = size;
+        }
+    }
+}
+
+// One past the nearest parent, so a merge sits right after the earliest branch it joins
+pub fn get_commit_depths(commits: &BTreeMap<String, CommitNode>) -> HashMap<String, usize> {
+    let mut depths: HashMap<String, usize> = HashMap::new();
+    for commit in parents_first(commits) {
+        let depth = commit.parents.iter().filter_map(|parent| depths.get(parent)).min().map_or(0, |depth| depth + 1);
+        depths.insert(commit.id.clone(), depth);
+    }
+    depths
+}
+
+// Heights of every commit, from the tree sizes `update_tree_sizes` filled in. The history of each root commit is laid
+// out around height 0 on its own, so with orphan branches the histories would be drawn over each other; instead every
+// root after the oldest gets a band of its own, with an empty lane between it and the band above
+pub fn get_commit_heights(commits: &BTreeMap<String, CommitNode>) -> HashMap<String, isize> {
+    let mut heights: HashMap<String, isize> = HashMap::new();
+    // The commit at the end of each first-parent chain, whose lane everything on the chain is laid out relative to
+    let mut roots: HashMap<&str, &str> = HashMap::new();
+    let mut extents: HashMap<&str, (isize, isize)> = HashMap::new();
+    for commit in parents_first(commits) {
+        let (height, root) = match commit.parents.first().and_then(|parent| commits.get(parent)) {
+            None => (0, commit.id.as_str()),
+            Some(parent) => {
+                let above = heights[&parent.id];
+                let height = if parent.children.len() == 1 {
+                    above
+                } else {
+                    // The first child goes above the parent and the others below it, each one further down than the one
+                    // before so the children of an octopus merge's parent don't share a lane
+                    let position = parent.children.iter().position(|child| *child == commit.id).unwrap_or(0);
+                    let size = |id: &String| 1 + commits.get(id).map_or(0, |commit| commit.tree_size) as isize;
+                    match position {
+                        0 => above - size(&commit.id),
+                        _ => above + parent.children[1..=position].iter().map(size).sum::<isize>(),
+                    }
+                };
+                (height, roots[parent.id.as_str()])
+            },
+        };
+        let extent = extents.entry(root).or_insert((height, height));
+        *extent = (extent.0.min(height), extent.1.max(height));
+        heights.insert(commit.id.clone(), height);
+        roots.insert(commit.id.as_str(), root);
+    }
+
+    let mut ordered: Vec<&CommitNode> = commits.values().filter(|commit| roots.get(commit.id.as_str()) == Some(&commit.id.as_str())).collect();
+    ordered.sort_by(|a, b| (a.time.seconds(), &a.id).cmp(&(b.time.seconds(), &b.id)));
+    let mut offsets = HashMap::new();
+    let mut next_free = None;
+    for root in ordered {
+        let (top, bottom) = extents[root.id.as_str()];
+        let offset = next_free.map_or(0, |next_free| next_free - top);
+        offsets.insert(root.id.as_str(), offset);
+        next_free = Some(offset + bottom + 2);
+    }
+
+    for (id, height) in heights.iter_mut() {
+        *height += offsets[roots[id.as_str()]];
     }
+    heights
 }
 
-pub fn get_commit_depth(commit: &CommitNode, commits: &HashMap<String, CommitNode>) -> usize {
-    if commit.parents.len() > 0 {
-        let mut min_parent_depth = usize::MAX;
+// Where a layout engine put everything. Column positions are x coordinates for a horizontal graph and y coordinates
+// for a vertical one
+#[derive(Debug, Clone, Default)]
+pub struct GraphLayout {
+    pub locations: BTreeMap<String, Point>,
+    // Points the edge from a child to a parent bends through on the way, keyed by the child and the parent. Edges
+    // without a route go straight from one node to the other
+    pub edge_routes: HashMap<(String, String), Vec<Point>>,
+    // The position and time of every column, when the columns follow commit time
+    pub time_axis: Vec<(f32, git2::Time)>,
+}
+
+// Turns the commit graph into positions. `tree_size` is up to date for every commit when an engine is called
+pub trait LayoutEngine {
+    fn layout(&self, commits: &BTreeMap<String, CommitNode>, orientation: Orientation, size: GraphSize) -> GraphLayout;
+}
+
+// Every commit one column after its nearest parent, each branch in a lane of its own
+pub struct TopologicalLayout;
+
+impl LayoutEngine for TopologicalLayout {
+    fn layout(&self, commits: &BTreeMap<String, CommitNode>, orientation: Orientation, size: GraphSize) -> GraphLayout {
+        let heights = get_commit_heights(commits);
+        let depths = get_commit_depths(commits);
+        let locations = commits.keys().map(|id| {
+            (id.clone(), orientation.orient(depths[id] as f32 * size.column_width(), heights[id] as f32 * size.row_height()))
+        });
+        GraphLayout { locations: locations.collect(), ..Default::default() }
+    }
+}
+
+// The lanes of the topological layout, with one column per commit in order of commit time
+pub struct ChronologicalLayout;
+
+impl LayoutEngine for ChronologicalLayout {
+    fn layout(&self, commits: &BTreeMap<String, CommitNode>, orientation: Orientation, size: GraphSize) -> GraphLayout {
+        let heights = get_commit_heights(commits);
+        let mut ordered: Vec<&CommitNode> = commits.values().collect();
+        ordered.sort_by(|a, b| (a.time.seconds(), &a.id).cmp(&(b.time.seconds(), &b.id)));
+
+        let mut layout = GraphLayout::default();
+        for (column, commit) in ordered.into_iter().enumerate() {
+            let along = column as f32 * size.column_width();
+            let across = heights[&commit.id] as f32 * size.row_height();
+            let location = orientation.orient(along, across);
+            layout.locations.insert(commit.id.clone(), location);
+            layout.time_axis.push((if orientation == Orientation::Horizontal { location.x } else { location.y }, commit.time));
+        }
+        layout
+    }
+}
+
+impl LayoutMode {
+    // A new layout is an engine plus a mode picking it
+    pub fn engine(self) -> &'static dyn LayoutEngine {
+        match self {
+            LayoutMode::Topological => &TopologicalLayout,
+            LayoutMode::Chronological => &ChronologicalLayout,
+        }
+    }
+}
+
+#[instrument(skip(commits), fields(commits = commits.len()))]
+pub fn layout_commits(commits: &mut BTreeMap<String, CommitNode>, mode: LayoutMode, orientation: Orientation, size: GraphSize) -> GraphLayout {
+    update_tree_sizes(commits);
+    mode.engine().layout(commits, orientation, size)
+}
+
+fn collect_reachable(start: &str, commits: &BTreeMap<String, CommitNode>, next: fn(&CommitNode) -> &Vec<String>) -> HashSet<String> {
+    let mut reachable = HashSet::new();
+    let mut pending = vec![start.to_string()];
+    while let Some(id) = pending.pop() {
+        if let Some(commit) = commits.get(&id) {
+            for other in next(commit) {
+                if reachable.insert(other.clone()) {
+                    pending.push(other.clone());
+                }
+            }
+        }
+    }
+    reachable
+}
+
+pub fn get_commit_ancestors(id: &str, commits: &BTreeMap<String, CommitNode>) -> HashSet<String> {
+    collect_reachable(id, commits, |commit| &commit.parents)
+}
+
+pub fn get_commit_descendants(id: &str, commits: &BTreeMap<String, CommitNode>) -> HashSet<String> {
+    collect_reachable(id, commits, |commit| &commit.children)
+}
+
+// History of `tips` only, with links to the commits left out removed so the layout ignores them
+pub fn filter_commits(commits: &BTreeMap<String, CommitNode>, tips: &[String]) -> BTreeMap<String, CommitNode> {
+    let mut included = HashSet::new();
+    for tip in tips.iter().filter(|tip| commits.contains_key(*tip)) {
+        included.insert(tip.clone());
+        included.extend(get_commit_ancestors(tip, commits));
+    }
+
+    included.iter().filter_map(|id| commits.get(id)).map(|commit| {
+        let mut commit = commit.clone();
+        commit.children.retain(|child| included.contains(child));
+        (commit.id.clone(), commit)
+    }).collect()
+}
+
+// The history `git log --first-parent` shows from `tip`, except merges listed in `expanded` also show the first-parent
+// history of the branches they merged in. Also returns the other merges with how many commits each one brought in,
+// every commit counted for the oldest shown merge that brought it in
+pub fn first_parent_history(commits: &BTreeMap<String, CommitNode>, tip: &str, expanded: &HashSet<String>) -> (BTreeMap<String, CommitNode>, HashMap<String, usize>) {
+    let mut kept = HashSet::new();
+    // Newest first along every line, so going through it backwards meets older merges first
+    let mut order = Vec::new();
+    let mut lines = vec![tip.to_string()];
+    while let Some(start) = lines.pop() {
+        let mut current = Some(start);
+        while let Some(commit) = current.and_then(|id| commits.get(&id)).filter(|commit| !kept.contains(&commit.id)) {
+            kept.insert(commit.id.clone());
+            order.push(commit);
+            if expanded.contains(&commit.id) {
+                lines.extend(commit.parents.iter().skip(1).cloned());
+            }
+            current = commit.parents.first().cloned();
+        }
+    }
+
+    let mut merges = HashMap::new();
+    let mut counted = HashSet::new();
+    for commit in order.iter().rev() {
+        let mut pending: Vec<&String> = commit.parents.iter().skip(1).filter(|parent| !kept.contains(*parent)).collect();
+        if pending.is_empty() {
+            continue
+        }
+        let mut count = 0;
+        while let Some(id) = pending.pop() {
+            if kept.contains(id) || !counted.insert(id.clone()) {
+                continue
+            }
+            if let Some(merged) = commits.get(id) {
+                count += 1;
+                pending.extend(&merged.parents);
+            }
+        }
+        merges.insert(commit.id.clone(), count);
+    }
+
+    let history = order.iter().map(|commit| {
+        let mut commit = (*commit).clone();
+        commit.parents.retain(|parent| kept.contains(parent));
+        commit.children.retain(|child| kept.contains(child));
+        (commit.id.clone(), commit)
+    }).collect();
+    (history, merges)
+}
+
+// Commits matching `keep` that are reached from `start` without passing through another one, and how many others were passed
+fn nearest_kept(start: &str, commits: &BTreeMap<String, CommitNode>, keep: &dyn Fn(&CommitNode) -> bool, next: fn(&CommitNode) -> &Vec<String>) -> (Vec<String>, usize) {
+    let mut found = Vec::new();
+    let mut hidden = HashSet::new();
+    let mut pending = vec![start.to_string()];
+    while let Some(id) = pending.pop() {
+        let commit = match commits.get(&id) {
+            Some(commit) => commit,
+            None => continue,
+        };
+        if keep(commit) {
+            if !found.contains(&id) {
+                found.push(id);
+            }
+        } else if hidden.insert(id) {
+            pending.extend(next(commit).iter().rev().cloned());
+        }
+    }
+    (found, hidden.len())
+}
+
+// Leaves out commits not matching `keep` and links the rest to their nearest remaining relatives,
+// along with the number of commits left out on each new link between a child and its parent
+pub fn filter_history(commits: &BTreeMap<String, CommitNode>, keep: &dyn Fn(&CommitNode) -> bool) -> (BTreeMap<String, CommitNode>, HashMap<(String, String), usize>) {
+    let mut filtered = BTreeMap::new();
+    let mut hidden_edges = HashMap::new();
+
+    for commit in commits.values().filter(|commit| keep(commit)) {
+        let mut node = commit.clone();
+        node.parents.clear();
+        node.children.clear();
+
         for parent in &commit.parents {
-            let parent_depth = get_commit_depth(commits.get(parent).unwrap(), commits);
-            if parent_depth < min_parent_depth {
-                min_parent_depth = parent_depth;
+            let (parents, hidden) = nearest_kept(parent, commits, keep, |commit| &commit.parents);
+            for parent in parents {
+                if !node.parents.contains(&parent) {
+                    if hidden > 0 {
+                        hidden_edges.insert((commit.id.clone(), parent.clone()), hidden);
+                    }
+                    node.parents.push(parent);
+                }
+            }
+        }
+
+        for child in &commit.children {
+            for child in nearest_kept(child, commits, keep, |commit| &commit.children).0 {
+                if !node.children.contains(&child) {
+                    node.children.push(child);
+                }
             }
         }
 
-        min_parent_depth + 1
+        filtered.insert(commit.id.clone(), node);
+    }
+
+    (filtered, hidden_edges)
+}
+
+pub fn get_local_branches(repository: &Repository) -> Result<Vec<String>, git2::Error> {
+    let mut branches = Vec::new();
+    for branch in repository.branches(Some(BranchType::Local))? {
+        let (branch, _) = branch?;
+        if let Some(name) = branch.name()? {
+            branches.push(name.to_string());
+        }
+    }
+    branches.sort();
+    Ok(branches)
+}
+
+// The branch HEAD is on, which a detached HEAD has none of
+pub fn get_head_branch(repository: &Repository) -> Result<String, git2::Error> {
+    let head = repository.head()?;
+    match head.shorthand() {
+        Some(name) if head.is_branch() => Ok(name.to_string()),
+        _ => Err(git2::Error::from_str("HEAD is not on a branch")),
+    }
+}
+
+#[instrument(skip(repository, message), err)]
+pub fn create_tag_at_branch(repository: &Repository, branch: &str, tag_name: &str, message: &str) -> Result<Oid, git2::Error> {
+    let branch = repository.find_branch(branch, BranchType::Local)?;
+    let target = branch.get().peel(git2::ObjectType::Commit)?;
+    let tagger = get_signature(repository)?;
+    repository.tag(tag_name, &target, &tagger, message, false)
+}
+
+// Deletes a local branch or a tag by the short name git would resolve on the command line
+#[instrument(skip(repository), err)]
+pub fn delete_reference(repository: &Repository, name: &str) -> Result<(), git2::Error> {
+    let mut reference = repository.resolve_reference_from_short_name(name)?;
+    if reference.is_branch() {
+        // Unlike deleting the reference itself, this refuses to delete the branch that is checked out
+        git2::Branch::wrap(reference).delete()
+    } else if reference.is_tag() {
+        reference.delete()
+    } else {
+        Err(git2::Error::from_str(&format!("{} is not a local branch or tag, only those can be deleted here", name)))
+    }
+}
+
+// Renames a local branch; libgit2 carries its own `branch.<name>.*` config along, and branches tracking it
+// locally are pointed at the new name here
+#[instrument(skip(repository), err)]
+pub fn rename_branch(repository: &Repository, branch: &str, new_name: &str) -> Result<(), git2::Error> {
+    let mut renamed = repository.find_branch(branch, BranchType::Local)?;
+    renamed.rename(new_name, false)?;
+
+    let old_reference = format!("refs/heads/{}", branch);
+    let mut config = repository.config()?;
+    for other in repository.branches(Some(BranchType::Local))? {
+        let (other, _) = other?;
+        let name = match other.name()? {
+            Some(name) => name.to_string(),
+            None => continue,
+        };
+        let remote = config.get_string(&format!("branch.{}.remote", name));
+        let merge = config.get_string(&format!("branch.{}.merge", name));
+        if remote.as_deref() == Ok(".") && merge.as_deref() == Ok(old_reference.as_str()) {
+            config.set_str(&format!("branch.{}.merge", name), &format!("refs/heads/{}", new_name))?;
+        }
+    }
+    Ok(())
+}
+
+const MAX_PATCH_LINES: usize = 2000;
+
+pub struct DiffFile {
+    pub path: String,
+    // Where a renamed or copied file came from, with how much of it is unchanged in percent
+    pub origin: Option<(String, u32)>,
+    pub status: char,
+    pub insertions: usize,
+    pub deletions: usize,
+}
+
+// The changed files right inside a directory, with their changes added up
+pub struct DirectoryChanges<'a> {
+    // Empty for the top of the repository
+    pub directory: &'a str,
+    pub files: Vec<&'a DiffFile>,
+    pub insertions: usize,
+    pub deletions: usize,
+}
+
+// Sorted by directory, with the files in the order of the diff
+pub fn group_by_directory(files: &[DiffFile]) -> Vec<DirectoryChanges<'_>> {
+    let mut directories: BTreeMap<&str, DirectoryChanges> = BTreeMap::new();
+    for file in files {
+        let directory = file.path.rsplit_once('/').map_or("", |(directory, _)| directory);
+        let changes = directories.entry(directory).or_insert_with(|| DirectoryChanges { directory, files: Vec::new(), insertions: 0, deletions: 0 });
+        changes.files.push(file);
+        changes.insertions += file.insertions;
+        changes.deletions += file.deletions;
+    }
+    directories.into_values().collect()
+}
+
+// Counted by libgit2 over the whole diff
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DiffTotals {
+    pub files: usize,
+    pub insertions: usize,
+    pub deletions: usize,
+}
+
+fn get_diff_totals(diff: &git2::Diff) -> Result<DiffTotals, git2::Error> {
+    let stats = diff.stats()?;
+    Ok(DiffTotals { files: stats.files_changed(), insertions: stats.insertions(), deletions: stats.deletions() })
+}
+
+// The part of a patch from `format_patch` about the file at `path`, found by its `diff --git` header
+pub fn file_patch<'a>(patch: &'a str, path: &str) -> Option<&'a str> {
+    let header = format!(" b/{}", path);
+    let starts: Vec<usize> = patch.match_indices("diff --git ").map(|(i, _)| i).filter(|i| *i == 0 || patch.as_bytes()[i - 1] == b'\n').collect();
+    starts.iter().enumerate().find_map(|(n, &start)| {
+        let section = &patch[start..starts.get(n + 1).copied().unwrap_or(patch.len())];
+        section.lines().next().is_some_and(|line| line.ends_with(&header)).then_some(section)
+    })
+}
+
+// Context line counts offered, git's own default of 3 among them
+pub const CONTEXT_LINE_CHOICES: [u32; 6] = [0, 1, 3, 5, 10, 25];
+
+// How the diffs of commits and ranges are made
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiffSettings {
+    pub ignore_whitespace: bool,
+    pub find_renames: bool,
+    // Files copied from others changed in the same diff, the only copies found without reading every file
+    pub find_copies: bool,
+    pub context_lines: u32,
+}
+
+impl Default for DiffSettings {
+    // Like `git diff`, which finds renames unless told not to
+    fn default() -> Self {
+        Self { ignore_whitespace: false, find_renames: true, find_copies: false, context_lines: 3 }
+    }
+}
+
+impl DiffSettings {
+    pub fn diff_trees<'a>(&self, repository: &'a Repository, old: Option<&git2::Tree>, new: Option<&git2::Tree>) -> Result<git2::Diff<'a>, git2::Error> {
+        let mut options = git2::DiffOptions::new();
+        options.ignore_whitespace(self.ignore_whitespace).context_lines(self.context_lines);
+        let mut diff = repository.diff_tree_to_tree(old, new, Some(&mut options))?;
+        if self.find_renames || self.find_copies {
+            let mut find = git2::DiffFindOptions::new();
+            find.renames(self.find_renames).copies(self.find_copies).ignore_whitespace(self.ignore_whitespace);
+            diff.find_similar(Some(&mut find))?;
+        }
+        Ok(diff)
+    }
+}
+
+// libgit2 doesn't hand out the similarity it found, so it's worked out again from the lines: those of the larger
+// side that are left unchanged
+fn get_similarity(repository: &Repository, delta: &git2::DiffDelta, deletions: usize) -> Option<u32> {
+    let (old, new) = (delta.old_file().id(), delta.new_file().id());
+    if old == new {
+        return Some(100);
+    }
+    let lines = |id| repository.find_blob(id).ok().filter(|blob| !blob.is_binary()).map(|blob| blob.content().split(|byte| *byte == b'\n').count());
+    let (old_lines, new_lines) = (lines(old)?, lines(new)?);
+    Some((old_lines.saturating_sub(deletions) * 100 / old_lines.max(new_lines).max(1)) as u32)
+}
+
+pub struct RangeDiff {
+    pub from: String,
+    pub to: String,
+    // Commits reachable from `to` but not from `from`, newest first, with their summaries
+    pub commits: Vec<(String, String)>,
+    pub files: Vec<DiffFile>,
+    pub totals: DiffTotals,
+    pub patch: String,
+}
+
+// A commit's changes against its first parent, or everything it adds for a root commit
+pub struct CommitDiff {
+    pub id: String,
+    pub parents: Vec<String>,
+    pub author: CommitIdentity,
+    // Differs from the author for commits that were applied, rebased or amended by someone else or later
+    pub committer: CommitIdentity,
+    // Kind of the signature block in the commit, whether or not it verifies
+    pub signature_kind: Option<&'static str>,
+    // Annotated tags of the commit
+    pub tags: Vec<TagAnnotation>,
+    pub note: Option<String>,
+    pub files: Vec<DiffFile>,
+    pub totals: DiffTotals,
+    pub patch: String,
+    // Files stored with Git LFS whose objects at this commit aren't downloaded
+    pub lfs_missing: Vec<String>,
+    pub images: Vec<ImagePreview>,
+}
+
+#[derive(PartialEq)]
+pub struct CommitIdentity {
+    pub name: String,
+    pub email: String,
+    pub time: git2::Time,
+}
+
+impl CommitIdentity {
+    fn from_signature(signature: &git2::Signature) -> CommitIdentity {
+        CommitIdentity { name: signature.name().unwrap_or_default().to_string(), email: signature.email().unwrap_or_default().to_string(), time: signature.when() }
+    }
+}
+
+// Told apart by the armor line the block starts with
+fn get_signature_kind(commit: &git2::Commit) -> Option<&'static str> {
+    let block = commit.header_field_bytes("gpgsig").ok()?;
+    let block = String::from_utf8_lossy(&block);
+    Some(if block.starts_with("-----BEGIN PGP") {
+        "PGP"
+    } else if block.starts_with("-----BEGIN SSH") {
+        "SSH"
+    } else if block.starts_with("-----BEGIN SIGNED MESSAGE") {
+        "X.509"
     } else {
-        0
+        "unknown"
+    })
+}
+
+// What an annotated tag object adds on top of the commit it points at
+pub struct TagAnnotation {
+    pub name: String,
+    // Git allows tags made without a tagger
+    pub tagger: Option<(String, git2::Time)>,
+    pub message: String,
+}
+
+// Notes are read from and written to `core.notesRef`, `refs/notes/commits` unless configured otherwise
+pub fn get_note(repository: &Repository, id: &str) -> Option<String> {
+    let note = repository.find_note(None, Oid::from_str(id).ok()?).ok()?;
+    note.message().map(|message| message.trim_end().to_string())
+}
+
+pub fn get_noted_commits(repository: &Repository) -> HashSet<String> {
+    match repository.notes(None) {
+        Ok(notes) => notes.flatten().map(|(_, annotated)| annotated.to_string()).collect(),
+        // There is no notes reference until the first note is added
+        Err(_) => HashSet::new(),
+    }
+}
+
+// An empty message takes the note away, like `git notes edit` does
+#[instrument(skip(repository, message), err)]
+pub fn set_note(repository: &Repository, id: &str, message: &str) -> Result<(), git2::Error> {
+    let id = Oid::from_str(id)?;
+    let signature = get_signature(repository)?;
+    if message.trim().is_empty() {
+        return match repository.note_delete(id, None, &signature, &signature) {
+            Err(e) if e.code() == git2::ErrorCode::NotFound => Ok(()),
+            result => result,
+        };
     }
+    repository.note(&signature, &signature, None, id, &format!("{}\n", message.trim_end()), true)?;
+    Ok(())
 }
 
-fn get_commit_tree_size(commit: &CommitNode, commits: &HashMap<String, CommitNode>) -> usize {
-    let mut size = commit.children.len();
-    if size > 0 {
-        size -= 1;
+fn get_tag_annotations(repository: &Repository, id: Oid) -> Result<Vec<TagAnnotation>, git2::Error> {
+    let mut annotations = Vec::new();
+    for reference in repository.references_glob("refs/tags/*")? {
+        let reference = reference?;
+        // Lightweight tags point at the commit itself and have nothing to add
+        let tag = match reference.peel_to_tag() {
+            Ok(tag) => tag,
+            Err(_) => continue,
+        };
+        if tag.target().and_then(|target| target.peel_to_commit()).map(|commit| commit.id()).ok() != Some(id) {
+            continue;
+        }
+        annotations.push(TagAnnotation {
+            name: reference.shorthand().unwrap_or_default().to_string(),
+            tagger: tag.tagger().map(|tagger| (format!("{} <{}>", tagger.name().unwrap_or_default(), tagger.email().unwrap_or_default()), tagger.when())),
+            message: tag.message().unwrap_or_default().trim_end().to_string(),
+        });
     }
+    annotations.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(annotations)
+}
+
+pub fn get_delta_status_char(status: git2::Delta) -> char {
+    match status {
+        git2::Delta::Added => 'A',
+        git2::Delta::Deleted => 'D',
+        git2::Delta::Modified => 'M',
+        git2::Delta::Renamed => 'R',
+        git2::Delta::Copied => 'C',
+        git2::Delta::Typechange => 'T',
+        git2::Delta::Untracked => '?',
+        git2::Delta::Conflicted => 'U',
+        _ => ' ',
+    }
+}
+
+// Commits reachable from `to` but not from `from`, newest first, with their summaries
+fn get_commits_between(repository: &Repository, from: Oid, to: Oid) -> Result<Vec<(String, String)>, git2::Error> {
+    let mut revwalk = repository.revwalk()?;
+    revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
+    revwalk.push(to)?;
+    revwalk.hide(from)?;
 
-    for child in &commit.children {
-        let child = commits.get(child).unwrap();
-        size += get_commit_tree_size(child, commits);
+    let mut commits = Vec::new();
+    for id in revwalk {
+        let commit = repository.find_commit(id?)?;
+        commits.push((commit.id().to_string(), commit.summary().unwrap_or_default().to_string()));
     }
-    size
+    Ok(commits)
 }
 
-pub fn get_commit_height(commit: &CommitNode, commits: &HashMap<String, CommitNode>) -> isize {
-    // Removed for testing, I'm not sure how to exactly to handle this
-    // assert!(commit.parents.len() <= 1);
+// Like git, content with a NUL byte near the start counts as binary
+const BINARY_CHECK_LENGTH: usize = 8000;
 
-    if commit.parents.len() == 0 {
-        0
+pub fn is_binary_content(content: &[u8]) -> bool {
+    content.iter().take(BINARY_CHECK_LENGTH).any(|byte| *byte == 0)
+}
+
+// "2.9 KiB (4d7a214)" for one side of a binary file, or "nothing" where there is no file
+pub fn describe_binary_change(old: Option<(Oid, u64)>, new: Option<(Oid, u64)>) -> String {
+    let side = |side: Option<(Oid, u64)>| match side {
+        Some((id, size)) => format!("{} ({:.7})", format_size(size), id.to_string()),
+        None => String::from("nothing"),
+    };
+    format!("Binary file {} -> {}", side(old), side(new))
+}
+
+// Shown in place of the patch of a file: the objects Git LFS pointers stand for, with the pointer the file has now,
+// or the sizes and IDs of a binary file
+type FileSummaries = HashMap<PathBuf, (String, Option<LfsPointer>)>;
+
+fn get_file_summaries(repository: &Repository, diff: &git2::Diff) -> FileSummaries {
+    let mut summaries = HashMap::new();
+    for delta in diff.deltas() {
+        let (old, new) = (delta.old_file(), delta.new_file());
+        let Some(path) = new.path().or(old.path()) else {
+            continue;
+        };
+        let (old_blob, new_blob) = (repository.find_blob(old.id()).ok(), repository.find_blob(new.id()).ok());
+
+        let (old_pointer, new_pointer) = (blob_pointer(repository, old.id()), blob_pointer(repository, new.id()));
+        if let Some(change) = describe_change(old_pointer.as_ref(), !old.id().is_zero(), new_pointer.as_ref(), !new.id().is_zero()) {
+            summaries.insert(path.to_path_buf(), (change, new_pointer));
+        } else if old_blob.iter().chain(&new_blob).any(|blob| blob.is_binary()) {
+            let side = |blob: &Option<git2::Blob>| blob.as_ref().map(|blob| (blob.id(), blob.size() as u64));
+            summaries.insert(path.to_path_buf(), (describe_binary_change(side(&old_blob), side(&new_blob)), None));
+        }
+    }
+    summaries
+}
+
+fn get_image_previews(repository: &Repository, diff: &git2::Diff) -> Vec<ImagePreview> {
+    let side = |path: &Path, id: Oid| repository.find_blob(id).ok().and_then(|blob| load_preview(path, blob.content()));
+    diff.deltas()
+        .filter_map(|delta| {
+            let path = delta.new_file().path().or(delta.old_file().path())?;
+            let (old, new) = (side(path, delta.old_file().id()), side(path, delta.new_file().id()));
+            (is_image_path(path) && (old.is_some() || new.is_some())).then(|| ImagePreview::new(path.display().to_string(), old, new))
+        })
+        .collect()
+}
+
+// The patch as text, cut off after `MAX_PATCH_LINES` lines
+fn format_patch(diff: &git2::Diff, summaries: &FileSummaries) -> Result<String, git2::Error> {
+    let mut patch = String::new();
+    let mut lines = 0;
+    diff.print(DiffFormat::Patch, |delta, _, line| {
+        if lines >= MAX_PATCH_LINES {
+            return false;
+        }
+        let summary = delta.new_file().path().or(delta.old_file().path()).and_then(|path| summaries.get(path));
+        match (summary, line.origin()) {
+            // The file header is kept and the content left out
+            (Some((summary, _)), 'F') => {
+                patch.push_str(&String::from_utf8_lossy(line.content()));
+                patch.push_str(summary);
+                patch.push('\n');
+            },
+            (Some(_), _) => return true,
+            (None, origin) => {
+                if matches!(origin, '+' | '-' | ' ') {
+                    patch.push(origin);
+                }
+                patch.push_str(&String::from_utf8_lossy(line.content()));
+            },
+        }
+        lines += 1;
+        true
+    }).or_else(|e| if e.code() == git2::ErrorCode::User { Ok(()) } else { Err(e) })?;
+    Ok(patch)
+}
+
+fn get_diff_files(repository: &Repository, diff: &git2::Diff) -> Result<Vec<DiffFile>, git2::Error> {
+    let mut files = Vec::new();
+    for (index, delta) in diff.deltas().enumerate() {
+        let path = delta.new_file().path().or(delta.old_file().path()).map(|p| p.display().to_string()).unwrap_or_default();
+        let (insertions, deletions) = match git2::Patch::from_diff(diff, index)? {
+            Some(patch) => {
+                let (_, insertions, deletions) = patch.line_stats()?;
+                (insertions, deletions)
+            },
+            None => (0, 0),
+        };
+        let origin = match delta.status() {
+            git2::Delta::Renamed | git2::Delta::Copied => delta.old_file().path()
+                .map(|old| (old.display().to_string(), get_similarity(repository, &delta, deletions).unwrap_or_default())),
+            _ => None,
+        };
+        files.push(DiffFile { path, origin, status: get_delta_status_char(delta.status()), insertions, deletions });
+    }
+    Ok(files)
+}
+
+#[instrument(skip(repository, settings), err)]
+pub fn get_range_diff(repository: &Repository, first: &str, second: &str, settings: DiffSettings) -> Result<RangeDiff, git2::Error> {
+    let first_id = Oid::from_str(first)?;
+    let second_id = Oid::from_str(second)?;
+
+    // Diff from the older commit to the newer one regardless of selection order
+    let (from, to) = if graph_descendant_of(repository, CommitGraph::open(repository).as_ref(), first_id, second_id)? { (second_id, first_id) } else { (first_id, second_id) };
+
+    let commits = get_commits_between(repository, from, to)?;
+
+    let from_tree = repository.find_commit(from)?.tree()?;
+    let to_tree = repository.find_commit(to)?.tree()?;
+    let diff = settings.diff_trees(repository, Some(&from_tree), Some(&to_tree))?;
+    let files = get_diff_files(repository, &diff)?;
+    let patch = format_patch(&diff, &get_file_summaries(repository, &diff))?;
+
+    Ok(RangeDiff { from: from.to_string(), to: to.to_string(), commits, files, totals: get_diff_totals(&diff)?, patch })
+}
+
+#[instrument(skip(repository, settings), err)]
+pub fn get_commit_diff(repository: &Repository, id: &str, settings: DiffSettings) -> Result<CommitDiff, git2::Error> {
+    let commit = repository.find_commit(Oid::from_str(id)?)?;
+    let parent_tree = commit.parents().next().map(|parent| parent.tree()).transpose()?;
+    let diff = settings.diff_trees(repository, parent_tree.as_ref(), Some(&commit.tree()?))?;
+    let author = CommitIdentity::from_signature(&commit.author());
+    let committer = CommitIdentity::from_signature(&commit.committer());
+    let summaries = get_file_summaries(repository, &diff);
+    let mut lfs_missing: Vec<String> = summaries.iter()
+        .filter(|(_, (_, pointer))| pointer.as_ref().is_some_and(|pointer| !pointer.is_downloaded(repository)))
+        .map(|(path, _)| path.display().to_string())
+        .collect();
+    lfs_missing.sort();
+
+    Ok(CommitDiff {
+        id: id.to_string(),
+        parents: commit.parent_ids().map(|parent| parent.to_string()).collect(),
+        author,
+        committer,
+        signature_kind: get_signature_kind(&commit),
+        tags: get_tag_annotations(repository, commit.id())?,
+        note: get_note(repository, id),
+        files: get_diff_files(repository, &diff)?,
+        totals: get_diff_totals(&diff)?,
+        patch: format_patch(&diff, &summaries)?,
+        lfs_missing,
+        images: get_image_previews(repository, &diff),
+    })
+}
+
+pub struct ReferenceComparison {
+    pub left: String,
+    pub right: String,
+    pub left_only: Vec<(String, String)>,
+    pub right_only: Vec<(String, String)>,
+    // Changes the right reference would bring in when merged into the left one
+    pub files: Vec<DiffFile>,
+    pub totals: DiffTotals,
+}
+
+// A commit with nothing but one parent and one child, that can be folded into the run around it
+fn is_linear(commit: &CommitNode) -> bool {
+    commit.parents.len() == 1 && commit.children.len() == 1 && commit.references.is_empty()
+}
+
+// Replaces runs of at least `min_length` linear commits with one node named after the newest commit of the run,
+// except for runs listed in `expanded`; also returns how many commits each of those nodes stands for.
+// Hidden commit counts on the link below a run move to the node replacing it
+pub fn collapse_chains(commits: &BTreeMap<String, CommitNode>, hidden_edges: &mut HashMap<(String, String), usize>, expanded: &HashSet<String>, min_length: usize) -> (BTreeMap<String, CommitNode>, HashMap<String, usize>) {
+    let mut collapsed = commits.clone();
+    let mut chains = HashMap::new();
+
+    for newest in commits.values().filter(|commit| is_linear(commit)) {
+        let child_is_linear = commits.get(&newest.children[0]).is_some_and(is_linear);
+        if child_is_linear || expanded.contains(&newest.id) {
+            continue
+        }
+
+        let mut chain = vec![newest];
+        while let Some(parent) = commits.get(&chain[chain.len() - 1].parents[0]).filter(|parent| is_linear(parent)) {
+            chain.push(parent);
+        }
+        if chain.len() < min_length {
+            continue
+        }
+
+        let oldest = chain[chain.len() - 1];
+        for commit in &chain[1..] {
+            collapsed.remove(&commit.id);
+        }
+        if let Some(node) = collapsed.get_mut(&newest.id) {
+            node.parents = oldest.parents.clone();
+            node.summary = format!("{} commits", chain.len());
+            node.signature = None;
+        }
+        if let Some(parent) = collapsed.get_mut(&oldest.parents[0]) {
+            for child in parent.children.iter_mut().filter(|child| **child == oldest.id) {
+                *child = newest.id.clone();
+            }
+        }
+        if let Some(hidden) = hidden_edges.remove(&(oldest.id.clone(), oldest.parents[0].clone())) {
+            hidden_edges.insert((newest.id.clone(), oldest.parents[0].clone()), hidden);
+        }
+        chains.insert(newest.id.clone(), chain.len());
+    }
+
+    (collapsed, chains)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum ReferenceKind {
+    Branch,
+    Remote,
+    Tag,
+}
+
+// A reference as it is labelled on the graph, by the short name git takes on the command line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReferenceLabel {
+    pub name: String,
+    pub kind: ReferenceKind,
+}
+
+pub struct ReferenceTip {
+    pub name: String,
+    pub kind: ReferenceKind,
+    pub target: String,
+}
+
+impl ReferenceTip {
+    // Names are grouped by what comes before their first slash, like `feature/` or the remote of a remote branch
+    pub fn folder(&self) -> Option<&str> {
+        self.name.split_once('/').map(|(folder, _)| folder)
+    }
+}
+
+// Branches, remote branches and tags with the commit each one points to
+#[instrument(skip(repository), err)]
+pub fn get_reference_tips(repository: &Repository) -> Result<Vec<ReferenceTip>, git2::Error> {
+    let mut tips = Vec::new();
+    for reference in repository.references()? {
+        let reference = reference?;
+        let Some(kind) = reference_kind(&reference) else { continue };
+        if let Ok(commit) = reference.peel_to_commit() {
+            tips.push(ReferenceTip { name: reference.shorthand().unwrap_or_default().to_string(), kind, target: commit.id().to_string() });
+        }
+    }
+    tips.sort_by(|a, b| (a.kind, &a.name).cmp(&(b.kind, &b.name)));
+    Ok(tips)
+}
+
+pub fn get_reference_names(repository: &Repository) -> Result<Vec<String>, git2::Error> {
+    let mut names = Vec::new();
+    for reference in repository.references()? {
+        let reference = reference?;
+        if reference.is_branch() || reference.is_remote() || reference.is_tag() {
+            names.push(reference.shorthand().unwrap_or_default().to_string());
+        }
+    }
+    names.sort();
+    names.dedup();
+    Ok(names)
+}
+
+#[instrument(skip(repository), err)]
+pub fn compare_references(repository: &Repository, left: &str, right: &str) -> Result<ReferenceComparison, git2::Error> {
+    let left_commit = repository.revparse_single(left)?.peel_to_commit()?;
+    let right_commit = repository.revparse_single(right)?.peel_to_commit()?;
+
+    let left_only = get_commits_between(repository, right_commit.id(), left_commit.id())?;
+    let right_only = get_commits_between(repository, left_commit.id(), right_commit.id())?;
+
+    let base_tree = match repository.merge_base(left_commit.id(), right_commit.id()) {
+        Ok(base) => Some(repository.find_commit(base)?.tree()?),
+        Err(e) if e.code() == git2::ErrorCode::NotFound => None,
+        Err(e) => return Err(e),
+    };
+    let diff = DiffSettings::default().diff_trees(repository, base_tree.as_ref(), Some(&right_commit.tree()?))?;
+    let files = get_diff_files(repository, &diff)?;
+
+    Ok(ReferenceComparison { left: left.to_string(), right: right.to_string(), left_only, right_only, files, totals: get_diff_totals(&diff)? })
+}
+
+// Commits the current index, optionally replacing HEAD instead of building on top of it
+#[instrument(skip(repository, message), err)]
+pub fn create_commit(repository: &Repository, message: &str, amend: bool, sign: bool) -> Result<Oid, git2::Error> {
+    let tree = repository.find_tree(repository.index()?.write_tree()?)?;
+    let committer = get_signature(repository)?;
+
+    let head = match repository.head() {
+        Ok(head) => Some(head.peel_to_commit()?),
+        Err(e) if e.code() == git2::ErrorCode::UnbornBranch => None,
+        Err(e) => return Err(e),
+    };
+
+    let (author, parents) = match (&head, amend) {
+        (Some(head), true) => (head.author().to_owned(), head.parents().collect()),
+        (Some(head), false) => (committer.clone(), vec![head.clone()]),
+        (None, true) => return Err(git2::Error::from_str("there is no commit to amend")),
+        (None, false) => (committer.clone(), Vec::new()),
+    };
+    let parents: Vec<&git2::Commit> = parents.iter().collect();
+
+    let id = if sign {
+        let buffer = repository.commit_create_buffer(&author, &committer, message, &tree, &parents)?;
+        let buffer = buffer.as_str().ok_or_else(|| git2::Error::from_str("commit buffer is not valid UTF-8"))?;
+        let signature = sign_commit_buffer(repository, buffer)?;
+        repository.commit_signed(buffer, &signature, None)?
     } else {
-        let parent = commit.parents.get(0).unwrap();
-        let parent = commits.get(parent).unwrap();
-        // Removed for testing, I'm not sure how to exactly to handle this
-        //assert!(parent.children.len() <= 2);
-
-        if parent.children.len() == 1 {
-            get_commit_height(parent, commits)
-        } else {
-            let multiplier = if parent.children.iter().position(|c| c == &commit.id).unwrap() == 0 { -1 } else { 1 };
-            let value = get_commit_tree_size(commit, commits) as isize;
-            get_commit_height(parent, commits) + multiplier * (1 + value)
+        repository.commit(None, &author, &committer, message, &tree, &parents)?
+    };
+
+    // HEAD is moved by hand since amending replaces the tip rather than extending it
+    let reflog_message = format!("commit{}: {}", if amend { " (amend)" } else { "" }, message.lines().next().unwrap_or_default());
+    let head_reference = repository.find_reference("HEAD")?;
+    match head_reference.symbolic_target() {
+        Some(target) => { repository.reference(target, id, true, &reflog_message)?; },
+        None => repository.set_head_detached(id)?,
+    }
+
+    Ok(id)
+}
+
+pub struct WorktreeInfo {
+    pub name: String,
+    pub path: PathBuf,
+    // Checked out branch, or the abbreviated commit when HEAD is detached
+    pub head: String,
+    pub locked: bool,
+}
+
+pub fn describe_head(repository: &Repository) -> Result<String, git2::Error> {
+    let head = repository.head()?;
+    if head.is_branch() {
+        Ok(head.shorthand().unwrap_or_default().to_string())
+    } else {
+        let target = head.target().map(|t| t.to_string()).unwrap_or_default();
+        Ok(format!("detached at {}", &target[..7.min(target.len())]))
+    }
+}
+
+// Branch that was checked out before HEAD got detached, read from the HEAD reflog
+pub fn get_previous_branch(repository: &Repository) -> Option<String> {
+    let reflog = repository.reflog("HEAD").ok()?;
+    let found = reflog.iter().find_map(|entry| {
+        let (from, _) = entry.message()?.strip_prefix("checkout: moving from ")?.split_once(" to ")?;
+        repository.find_branch(from, BranchType::Local).is_ok().then(|| from.to_string())
+    });
+    found
+}
+
+// Local branches, the ones checked out most recently first as the HEAD reflog tells, then the rest by name
+pub fn get_recent_branches(repository: &Repository) -> Result<Vec<String>, git2::Error> {
+    let mut branches: Vec<String> = Vec::new();
+    if let Ok(reflog) = repository.reflog("HEAD") {
+        for entry in reflog.iter() {
+            let Some((from, to)) = entry.message().and_then(|message| message.strip_prefix("checkout: moving from ")?.split_once(" to ")) else {
+                continue;
+            };
+            for name in [to, from] {
+                if !branches.iter().any(|branch| branch == name) && repository.find_branch(name, BranchType::Local).is_ok() {
+                    branches.push(name.to_string());
+                }
+            }
         }
     }
+
+    let mut rest = Vec::new();
+    for branch in repository.branches(Some(BranchType::Local))? {
+        if let Some(name) = branch?.0.name()? {
+            if !branches.iter().any(|branch| branch == name) {
+                rest.push(name.to_string());
+            }
+        }
+    }
+    rest.sort();
+    branches.extend(rest);
+    Ok(branches)
+}
+
+// Gives the detached HEAD a branch, so commits made on it are kept
+#[instrument(skip(repository), err)]
+pub fn create_branch_at_head(repository: &Repository, name: &str) -> Result<(), git2::Error> {
+    let head = repository.head()?.peel_to_commit()?;
+    let branch = repository.branch(name, &head, false)?;
+    repository.set_head(branch.get().name().unwrap_or_default())
+}
+
+// Creates a repository in the directory, making it if needed. The first branch is named `branch` or whatever
+// `init.defaultBranch` says, and an empty commit is put on it when asked so it exists right away
+#[instrument(err)]
+pub fn init_repository(path: &Path, branch: Option<&str>, initial_commit: bool) -> Result<Repository, git2::Error> {
+    // Checked first so a missing identity doesn't leave a half made repository behind
+    let signature = match initial_commit {
+        true => Some(get_config_signature(&Config::open_default()?)?),
+        false => None,
+    };
+
+    let mut options = RepositoryInitOptions::new();
+    options.mkpath(true).no_reinit(true);
+    if let Some(branch) = branch {
+        options.initial_head(branch);
+    }
+    let repository = Repository::init_opts(path, &options)?;
+
+    if let Some(signature) = signature {
+        let tree = repository.find_tree(repository.index()?.write_tree()?)?;
+        repository.commit(Some("HEAD"), &signature, &signature, "Initial commit", &tree, &[])?;
+    }
+    Ok(repository)
+}
+
+// Files that `git status` would list, untracked ones included
+pub fn get_dirty_file_count(repository: &Repository) -> Result<usize, git2::Error> {
+    let mut options = StatusOptions::new();
+    options.include_untracked(true).include_ignored(false);
+    Ok(repository.statuses(Some(&mut options))?.len())
+}
+
+pub fn get_worktrees(repository: &Repository) -> Result<Vec<WorktreeInfo>, git2::Error> {
+    let mut worktrees = Vec::new();
+    for name in repository.worktrees()?.iter().flatten() {
+        let worktree = repository.find_worktree(name)?;
+        let head = match Repository::open_from_worktree(&worktree).and_then(|r| describe_head(&r)) {
+            Ok(head) => head,
+            Err(e) => format!("unavailable ({})", e.message()),
+        };
+        let locked = matches!(worktree.is_locked()?, git2::WorktreeLockStatus::Locked(_));
+        worktrees.push(WorktreeInfo { name: name.to_string(), path: worktree.path().to_path_buf(), head, locked });
+    }
+    Ok(worktrees)
+}
+
+// Checks out `target` in a new worktree; a commit that is not a local branch gets a new branch named after the worktree
+pub fn create_worktree(repository: &Repository, name: &str, path: &Path, target: &str) -> Result<(), git2::Error> {
+    let branch = match repository.find_branch(target, BranchType::Local) {
+        Ok(branch) => branch,
+        Err(_) => {
+            let commit = repository.revparse_single(target)?.peel_to_commit()?;
+            repository.branch(name, &commit, false)?
+        },
+    };
+
+    let mut options = git2::WorktreeAddOptions::new();
+    options.reference(Some(branch.get()));
+    repository.worktree(name, path, Some(&options))?;
+    Ok(())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::collections::BTreeMap;
+
+    use super::{CommitNode, get_commit_depths, get_commit_heights, update_tree_sizes};
+
+    // Builds a graph without a repository from `(id, parents)` pairs, parents listed before their children. Children
+    // are recorded in the order they are listed, which with their times going up is oldest first, like loading records them
+    pub fn synthetic_graph(commits: &[(&str, &[&str])]) -> BTreeMap<String, CommitNode> {
+        let mut graph: BTreeMap<String, CommitNode> = BTreeMap::new();
+        for (i, (id, parents)) in commits.iter().enumerate() {
+            for parent in parents.iter() {
+                graph.get_mut(*parent).expect("parents must be listed before their children").children.push(id.to_string());
+            }
+            graph.insert(id.to_string(), CommitNode {
+                id: id.to_string(),
+                parents: parents.iter().map(|parent| parent.to_string()).collect(),
+                children: Vec::new(),
+                references: Vec::new(),
+                signature: None,
+                summary: id.to_string(),
+                author: String::from("author"),
+                email: String::from("author@example.com"),
+                time: git2::Time::new(i as i64 * 60, 0),
+                tree_size: 0,
+            });
+        }
+        update_tree_sizes(&mut graph);
+        graph
+    }
+
+    fn depth(graph: &BTreeMap<String, CommitNode>, id: &str) -> usize {
+        get_commit_depths(graph)[id]
+    }
+
+    fn height(graph: &BTreeMap<String, CommitNode>, id: &str) -> isize {
+        get_commit_heights(graph)[id]
+    }
+
+    #[test]
+    fn linear_history_stays_in_one_lane() {
+        let graph = synthetic_graph(&[("a", &[]), ("b", &["a"]), ("c", &["b"])]);
+        assert_eq!([depth(&graph, "a"), depth(&graph, "b"), depth(&graph, "c")], [0, 1, 2]);
+        assert_eq!([height(&graph, "a"), height(&graph, "b"), height(&graph, "c")], [0, 0, 0]);
+    }
+
+    #[test]
+    fn branches_go_either_side_of_the_fork() {
+        let graph = synthetic_graph(&[("a", &[]), ("b", &["a"]), ("c", &["a"]), ("d", &["b"])]);
+        assert_eq!([depth(&graph, "b"), depth(&graph, "c"), depth(&graph, "d")], [1, 1, 2]);
+        assert_eq!(height(&graph, "b"), -1);
+        assert_eq!(height(&graph, "c"), 1);
+        assert_eq!(height(&graph, "d"), height(&graph, "b"));
+    }
+
+    #[test]
+    fn nested_forks_get_room_for_their_branches() {
+        let graph = synthetic_graph(&[("a", &[]), ("b", &["a"]), ("c", &["a"]), ("d", &["b"]), ("e", &["b"])]);
+        // b's own fork takes a lane above and below it, so a's other child moves further out
+        assert_eq!(height(&graph, "b"), -2);
+        assert_eq!(height(&graph, "d"), -3);
+        assert_eq!(height(&graph, "e"), -1);
+        assert_eq!(height(&graph, "c"), 1);
+    }
+
+    #[test]
+    fn merge_follows_its_first_parent() {
+        let graph = synthetic_graph(&[("a", &[]), ("b", &["a"]), ("c", &["b"]), ("d", &["a"]), ("m", &["c", "d"])]);
+        // One past the nearest parent, not the furthest
+        assert_eq!(depth(&graph, "m"), 2);
+        assert_eq!(height(&graph, "m"), height(&graph, "c"));
+        assert_ne!(height(&graph, "m"), height(&graph, "d"));
+    }
+
+    #[test]
+    fn octopus_branches_get_their_own_lanes() {
+        let graph = synthetic_graph(&[("a", &[]), ("b", &["a"]), ("c", &["a"]), ("d", &["a"]), ("m", &["b", "c", "d"])]);
+        let lanes = [height(&graph, "b"), height(&graph, "c"), height(&graph, "d")];
+        assert_eq!(lanes, [-1, 1, 2]);
+        assert_eq!(depth(&graph, "m"), 2);
+        assert_eq!(height(&graph, "m"), height(&graph, "b"));
+    }
+
+    #[test]
+    fn orphan_histories_get_bands_of_their_own() {
+        let graph = synthetic_graph(&[("a", &[]), ("b", &["a"]), ("c", &["a"]), ("x", &[]), ("y", &["x"]), ("z", &["y"]), ("w", &["y"])]);
+        let heights = get_commit_heights(&graph);
+        // The oldest root keeps its place, with a's fork taking the lanes either side of it
+        assert_eq!([heights["a"], heights["b"], heights["c"]], [0, -1, 1]);
+        // x's history reaches from one lane above it to one below, and starts an empty lane below c
+        assert_eq!([heights["x"], heights["y"], heights["z"], heights["w"]], [4, 4, 3, 5]);
+    }
 }
@@ -0,0 +1,77 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+use git2::Repository;
+
+use crate::backend::CommitNode;
+use crate::error::Error;
+use crate::signing::SignatureStatus;
+
+// Bumped whenever the format changes, so older caches are ignored instead of misread
+const CACHE_HEADER: &str = "gitui commit cache 2";
+
+// Kept next to the repository's own data, so every clone and worktree has its own
+fn cache_path(repository: &Repository) -> PathBuf {
+    repository.path().join("gitui-commits")
+}
+
+// Tabs and newlines separate the fields and entries
+fn clean(field: &str) -> String {
+    field.replace(['\t', '\n', '\r'], " ")
+}
+
+fn parse_entry(line: &str) -> Option<CommitNode> {
+    let fields: Vec<&str> = line.splitn(9, '\t').collect();
+    let [id, parents, children, signed, seconds, offset, author, email, summary] = fields[..] else {
+        return None;
+    };
+    let list = |field: &str| field.split(',').filter(|id| !id.is_empty()).map(str::to_string).collect();
+
+    Some(CommitNode {
+        id: id.to_string(),
+        parents: list(parents),
+        children: list(children),
+        references: Vec::new(),
+        // Verifying is left for when the commit is selected, like for a freshly loaded one
+        signature: (signed == "1").then_some(SignatureStatus::Unchecked),
+        summary: summary.to_string(),
+        author: author.to_string(),
+        email: email.to_string(),
+        time: git2::Time::new(seconds.parse().ok()?, offset.parse().ok()?),
+        tree_size: 0,
+    })
+}
+
+// The graph as it was last saved, without reference labels since those are read fresh from the repository. None when
+// there is no cache or it can't be read, and any commit missing a relative counts as unreadable
+pub fn load_commit_cache(repository: &Repository) -> Option<BTreeMap<String, CommitNode>> {
+    let contents = fs::read_to_string(cache_path(repository)).ok()?;
+    let mut lines = contents.lines();
+    if lines.next()? != CACHE_HEADER {
+        return None;
+    }
+
+    let commits: BTreeMap<String, CommitNode> = lines.map(|line| parse_entry(line).map(|commit| (commit.id.clone(), commit)))
+        .collect::<Option<_>>()?;
+    let complete = commits.values()
+        .all(|commit| commit.parents.iter().chain(&commit.children).all(|relative| commits.contains_key(relative)));
+    complete.then_some(commits)
+}
+
+pub fn save_commit_cache(repository: &Repository, commits: &BTreeMap<String, CommitNode>) -> Result<(), Error> {
+    let mut contents = String::from(CACHE_HEADER);
+    contents.push('\n');
+    for commit in commits.values() {
+        let signed = if commit.signature.is_some() { "1" } else { "0" };
+        contents.push_str(&format!("{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\n", commit.id, commit.parents.join(","), commit.children.join(","), signed,
+            commit.time.seconds(), commit.time.offset_minutes(), clean(&commit.author), clean(&commit.email), clean(&commit.summary)));
+    }
+
+    // Written aside and moved into place, so a gitui closed halfway through never leaves half a cache behind
+    let path = cache_path(repository);
+    let temporary = path.with_extension("tmp");
+    fs::write(&temporary, contents)?;
+    fs::rename(&temporary, &path)?;
+    Ok(())
+}
@@ -0,0 +1,91 @@
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+use git2::{Oid, Repository, Sort};
+
+use crate::config::{DisplayConfig, to_date};
+use crate::error::Error;
+
+pub struct Release {
+    // None for the commits on HEAD that no tag includes yet
+    pub tag: Option<String>,
+    pub time: Option<git2::Time>,
+    // Ids and summaries of the commits since the previous release, newest first
+    pub commits: Vec<(String, String)>,
+}
+
+// Commits reachable from `to` but from none of `from`
+fn get_release_commits(repository: &Repository, from: &[Oid], to: Oid) -> Result<Vec<(String, String)>, git2::Error> {
+    let mut revwalk = repository.revwalk()?;
+    revwalk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
+    revwalk.push(to)?;
+    for id in from {
+        revwalk.hide(*id)?;
+    }
+
+    let mut commits = Vec::new();
+    for id in revwalk {
+        let commit = repository.find_commit(id?)?;
+        commits.push((commit.id().to_string(), commit.summary().unwrap_or_default().to_string()));
+    }
+    Ok(commits)
+}
+
+// Tags in the order their commits were made, newest first, each with the commits no earlier tag includes.
+// Tags that don't point at a commit are left out
+pub fn get_releases(repository: &Repository) -> Result<Vec<Release>, git2::Error> {
+    let mut tags = Vec::new();
+    for name in repository.tag_names(None)?.iter().flatten() {
+        let commit = match repository.revparse_single(&format!("refs/tags/{}", name)).and_then(|object| object.peel_to_commit()) {
+            Ok(commit) => commit,
+            Err(_) => continue,
+        };
+        tags.push((name.to_string(), commit.id(), commit.time()));
+    }
+    tags.sort_by_key(|(name, _, time)| (time.seconds(), name.clone()));
+
+    let mut releases = Vec::new();
+    let mut earlier = Vec::new();
+    for (name, id, time) in tags {
+        let commits = get_release_commits(repository, &earlier, id)?;
+        releases.push(Release { tag: Some(name), time: Some(time), commits });
+        earlier.push(id);
+    }
+
+    if let Some(head) = repository.head().ok().and_then(|head| head.target()) {
+        let commits = get_release_commits(repository, &earlier, head)?;
+        if !commits.is_empty() {
+            releases.push(Release { tag: None, time: None, commits });
+        }
+    }
+
+    releases.reverse();
+    Ok(releases)
+}
+
+pub fn format_release_date(time: git2::Time) -> String {
+    to_date(time).map(|date| date.format("%Y-%m-%d").to_string()).unwrap_or_default()
+}
+
+pub fn changelog_markdown(releases: &[Release], display: &DisplayConfig) -> String {
+    let mut markdown = String::from("# Changelog\n");
+    for release in releases {
+        match (&release.tag, release.time) {
+            (Some(tag), Some(time)) => { let _ = write!(markdown, "\n## {} - {}\n\n", tag, format_release_date(time)); },
+            _ => markdown.push_str("\n## Unreleased\n\n"),
+        }
+        if release.commits.is_empty() {
+            markdown.push_str("No changes\n");
+        }
+        for (id, summary) in &release.commits {
+            let _ = writeln!(markdown, "- {} ({})", summary, display.abbreviate(id));
+        }
+    }
+    markdown
+}
+
+pub fn export_changelog(path: &Path, releases: &[Release], display: &DisplayConfig) -> Result<(), Error> {
+    fs::write(path, changelog_markdown(releases, display))?;
+    Ok(())
+}
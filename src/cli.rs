@@ -0,0 +1,148 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs;
+use std::io::{self, Write as _};
+use std::path::PathBuf;
+
+use git2::Repository;
+use iced::Point;
+
+use crate::backend::{CommitNode, LayoutMode, ReferenceGroups, layout_commits, load_commits};
+use crate::config::{DisplayConfig, Orientation};
+use crate::dot::render_dot;
+use crate::error::Error;
+use crate::export::render_graph_svg;
+use crate::json::escape_json;
+use crate::settings::Settings;
+
+pub const USAGE: &str = "usage: gitui --export <json|dot|svg> [--output <file>] [--layout <topological|chronological>] [--orientation <horizontal|vertical>] [<repository>]";
+
+pub const WINDOW_USAGE: &str = "usage: gitui [--dot <file>]";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LayoutFormat {
+    Json,
+    Dot,
+    Svg,
+}
+
+pub struct ExportArgs {
+    pub format: LayoutFormat,
+    // Standard output when not given
+    pub output: Option<PathBuf>,
+    pub repository: PathBuf,
+    pub layout: LayoutMode,
+    pub orientation: Orientation,
+}
+
+// Names are matched like they are shown in the GUI's pick lists, regardless of case
+fn parse_choice<T: Copy + ToString>(all: &[T], value: &str, what: &str) -> Result<T, Error> {
+    all.iter().copied().find(|choice| choice.to_string().eq_ignore_ascii_case(value))
+        .ok_or_else(|| Error::from(format!("unknown {} '{}'", what, value)))
+}
+
+pub fn parse_export_args(args: &[String]) -> Result<ExportArgs, Error> {
+    let mut format = None;
+    let mut output = None;
+    let mut repository = None;
+    let mut layout = LayoutMode::default();
+    let mut orientation = Orientation::Horizontal;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| Error::from(format!("{} needs a value", arg)));
+        match arg.as_str() {
+            "--export" => format = Some(match value()?.to_ascii_lowercase().as_str() {
+                "json" => LayoutFormat::Json,
+                "dot" => LayoutFormat::Dot,
+                "svg" => LayoutFormat::Svg,
+                other => return Err(Error::from(format!("unknown export format '{}'", other))),
+            }),
+            "--output" | "-o" => output = Some(PathBuf::from(value()?)),
+            "--layout" => layout = parse_choice(&LayoutMode::ALL, value()?, "layout")?,
+            "--orientation" => orientation = parse_choice(&Orientation::ALL, value()?, "orientation")?,
+            other if other.starts_with('-') => return Err(Error::from(format!("unknown option '{}'", other))),
+            other => match repository {
+                None => repository = Some(PathBuf::from(other)),
+                Some(_) => return Err(Error::from("only one repository can be exported at a time")),
+            },
+        }
+    }
+
+    Ok(ExportArgs {
+        format: format.ok_or(Error::from("--export needs a format"))?,
+        output,
+        repository: repository.unwrap_or(PathBuf::from(".")),
+        layout,
+        orientation,
+    })
+}
+
+// Plain `gitui` opens the tabs of the last session, and `--dot` a DOT file in their place
+pub fn parse_window_args(args: &[String]) -> Result<Option<PathBuf>, Error> {
+    let mut dot = None;
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| Error::from(format!("{} needs a value", arg)));
+        match arg.as_str() {
+            "--dot" => dot = Some(PathBuf::from(value()?)),
+            other => return Err(Error::from(format!("unknown argument '{}'", other))),
+        }
+    }
+    Ok(dot)
+}
+
+fn json_string_list(items: &[String]) -> String {
+    let items: Vec<String> = items.iter().map(|item| format!("\"{}\"", escape_json(item))).collect();
+    format!("[{}]", items.join(", "))
+}
+
+// Oldest first, so the output reads in the order the history was made and is the same on every run
+pub fn ordered_commits(commits: &BTreeMap<String, CommitNode>) -> Vec<&CommitNode> {
+    let mut ordered: Vec<&CommitNode> = commits.values().collect();
+    ordered.sort_by(|a, b| (a.time.seconds(), &a.id).cmp(&(b.time.seconds(), &b.id)));
+    ordered
+}
+
+fn render_json(commits: &BTreeMap<String, CommitNode>, locations: &BTreeMap<String, Point>, args: &ExportArgs) -> String {
+    let mut json = String::new();
+    let _ = writeln!(json, "{{");
+    let _ = writeln!(json, "  \"layout\": \"{}\",", args.layout.to_string().to_lowercase());
+    let _ = writeln!(json, "  \"orientation\": \"{}\",", args.orientation.to_string().to_lowercase());
+    let _ = writeln!(json, "  \"commits\": [");
+
+    let ordered = ordered_commits(commits);
+    for (i, commit) in ordered.iter().enumerate() {
+        let location = locations.get(&commit.id).copied().unwrap_or(Point::ORIGIN);
+        let references: Vec<String> = commit.references.iter().map(|reference| reference.name.clone()).collect();
+        let _ = write!(json, "    {{\"id\": \"{}\", \"x\": {}, \"y\": {}, \"parents\": {}, \"references\": {}, \"summary\": \"{}\", \"author\": \"{}\", \"time\": {}}}",
+            commit.id, location.x, location.y, json_string_list(&commit.parents), json_string_list(&references), escape_json(&commit.summary), escape_json(&commit.author), commit.time.seconds());
+        json.push_str(if i + 1 < ordered.len() { ",\n" } else { "\n" });
+    }
+
+    json.push_str("  ]\n}\n");
+    json
+}
+
+// Lays out the whole history like a freshly opened tab does, without starting the GUI
+pub fn export_layout(args: &ExportArgs) -> Result<(), Error> {
+    let repository = Repository::open(&args.repository)?;
+    let display = DisplayConfig::load(&repository);
+    let mut commits = load_commits(&repository, ReferenceGroups::ALL, &mut |_| true)?.unwrap_or_default();
+    // Sized like the GUI draws it
+    let size = Settings::load().graph_size;
+    let locations = layout_commits(&mut commits, args.layout, args.orientation, size).locations;
+
+    let contents = match args.format {
+        LayoutFormat::Json => render_json(&commits, &locations, args),
+        LayoutFormat::Dot => render_dot(&commits, &locations, &display),
+        LayoutFormat::Svg => render_graph_svg(&commits, &locations, &display, args.orientation, size.node_radius),
+    };
+
+    match &args.output {
+        Some(path) => fs::write(path, contents)?,
+        None => io::stdout().lock().write_all(contents.as_bytes())?,
+    }
+    Ok(())
+}
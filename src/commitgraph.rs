@@ -0,0 +1,237 @@
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::fs;
+use std::path::PathBuf;
+
+use git2::{Oid, Repository};
+
+const SIGNATURE: &[u8] = b"CGPH";
+const OID_SIZE: usize = 20;
+// Tree, two parents and the generation with the commit time
+const COMMIT_DATA_SIZE: usize = OID_SIZE + 16;
+const NO_PARENT: u32 = 0x7000_0000;
+// Set on the second parent when the parents continue in the extra edges, and on the last of those
+const EXTRA_EDGES: u32 = 0x8000_0000;
+
+const LOCAL: u8 = 1;
+const UPSTREAM: u8 = 2;
+
+// The `objects/info/commit-graph` file `git commit-graph write` and `git gc` leave behind, which has the parents and
+// generation of every commit without having to read the commits themselves. Split graphs aren't read, nor are files
+// for other hashes than SHA-1
+pub struct CommitGraph {
+    data: Vec<u8>,
+    count: usize,
+    fanout: usize,
+    lookup: usize,
+    commits: usize,
+    extra_edges: Option<usize>,
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(offset..offset + 4)?.try_into().ok()?))
+}
+
+fn read_u64(data: &[u8], offset: usize) -> Option<u64> {
+    Some(u64::from_be_bytes(data.get(offset..offset + 8)?.try_into().ok()?))
+}
+
+// Worktrees keep their objects in the main repository, which their `commondir` file points at
+fn objects_directory(repository: &Repository) -> PathBuf {
+    let common = fs::read_to_string(repository.path().join("commondir")).ok()
+        .map(|common| repository.path().join(common.trim()))
+        .unwrap_or(repository.path().to_path_buf());
+    common.join("objects")
+}
+
+impl CommitGraph {
+    // None when the repository has no commit-graph file or it isn't one this can read
+    pub fn open(repository: &Repository) -> Option<CommitGraph> {
+        let data = fs::read(objects_directory(repository).join("info").join("commit-graph")).ok()?;
+        if data.get(..4)? != SIGNATURE || data[4] != 1 || data[5] != 1 || data[7] != 0 {
+            return None;
+        }
+
+        let (mut fanout, mut lookup, mut commits, mut extra_edges) = (None, None, None, None);
+        for i in 0..data[6] as usize {
+            let entry = 8 + i * 12;
+            let offset = read_u64(&data, entry + 4)? as usize;
+            match data.get(entry..entry + 4)? {
+                b"OIDF" => fanout = Some(offset),
+                b"OIDL" => lookup = Some(offset),
+                b"CDAT" => commits = Some(offset),
+                b"EDGE" => extra_edges = Some(offset),
+                _ => (),
+            }
+        }
+
+        let fanout = fanout?;
+        let count = read_u32(&data, fanout + 255 * 4)? as usize;
+        let (lookup, commits) = (lookup?, commits?);
+        // Everything the lookups below index into has to be there
+        if data.len() < lookup + count * OID_SIZE || data.len() < commits + count * COMMIT_DATA_SIZE {
+            return None;
+        }
+        Some(CommitGraph { data, count, fanout, lookup, commits, extra_edges })
+    }
+
+    pub fn commit_count(&self) -> usize {
+        self.count
+    }
+
+    fn id(&self, position: u32) -> &[u8] {
+        let start = self.lookup + position as usize * OID_SIZE;
+        &self.data[start..start + OID_SIZE]
+    }
+
+    // Commits newer than the file aren't in it
+    fn position(&self, id: Oid) -> Option<u32> {
+        let id = id.as_bytes();
+        let first = id[0] as usize;
+        let start = if first == 0 { 0 } else { read_u32(&self.data, self.fanout + (first - 1) * 4)? };
+        let end = read_u32(&self.data, self.fanout + first * 4)?;
+
+        let (mut low, mut high) = (start, end);
+        while low < high {
+            let middle = low + (high - low) / 2;
+            match self.id(middle).cmp(id) {
+                std::cmp::Ordering::Less => low = middle + 1,
+                std::cmp::Ordering::Greater => high = middle,
+                std::cmp::Ordering::Equal => return Some(middle),
+            }
+        }
+        None
+    }
+
+    fn parents(&self, position: u32) -> Option<Vec<u32>> {
+        let data = self.commits + position as usize * COMMIT_DATA_SIZE + OID_SIZE;
+        let mut parents = Vec::new();
+        let first = read_u32(&self.data, data)?;
+        if first != NO_PARENT {
+            parents.push(first);
+        }
+
+        let second = read_u32(&self.data, data + 4)?;
+        if second & EXTRA_EDGES == 0 {
+            if second != NO_PARENT {
+                parents.push(second);
+            }
+        } else {
+            let mut edge = self.extra_edges? + (second & !EXTRA_EDGES) as usize * 4;
+            loop {
+                let parent = read_u32(&self.data, edge)?;
+                parents.push(parent & !EXTRA_EDGES);
+                if parent & EXTRA_EDGES != 0 {
+                    break;
+                }
+                edge += 4;
+            }
+        }
+
+        parents.iter().all(|parent| (*parent as usize) < self.count).then_some(parents)
+    }
+
+    // The topological level, one more than the highest of the parents. Zero for files written before git stored them
+    fn generation(&self, position: u32) -> u32 {
+        read_u32(&self.data, self.commits + position as usize * COMMIT_DATA_SIZE + OID_SIZE + 8).unwrap_or(0) >> 2
+    }
+
+    // Commits reachable from `tips`, read from the parents alone. None when a tip is newer than the file
+    pub fn reachable_count(&self, tips: &[Oid]) -> Option<usize> {
+        let mut seen = HashSet::new();
+        let mut stack = Vec::new();
+        for tip in tips {
+            let position = self.position(*tip)?;
+            if seen.insert(position) {
+                stack.push(position);
+            }
+        }
+        while let Some(position) = stack.pop() {
+            for parent in self.parents(position)? {
+                if seen.insert(parent) {
+                    stack.push(parent);
+                }
+            }
+        }
+        Some(seen.len())
+    }
+
+    // Commits only reachable from `local` and only from `upstream`, like `Repository::graph_ahead_behind`. Commits are
+    // visited from the highest generation down, so the walk ends as soon as only shared history is left instead of going
+    // all the way down to the roots. None when either commit or a generation is missing
+    pub fn ahead_behind(&self, local: Oid, upstream: Oid) -> Option<(usize, usize)> {
+        let (local, upstream) = (self.position(local)?, self.position(upstream)?);
+        if local == upstream {
+            return Some((0, 0));
+        }
+
+        let mut flags: HashMap<u32, u8> = HashMap::from([(local, LOCAL), (upstream, UPSTREAM)]);
+        let mut queued: HashSet<u32> = HashSet::from([local, upstream]);
+        let mut queue = BinaryHeap::new();
+        for position in [local, upstream] {
+            let generation = self.generation(position);
+            if generation == 0 {
+                return None;
+            }
+            queue.push((generation, position));
+        }
+        // Queued commits reachable from only one side, the walk is done once there are none
+        let mut one_sided = 2;
+
+        let (mut ahead, mut behind) = (0, 0);
+        while one_sided > 0 {
+            let (_, position) = queue.pop()?;
+            queued.remove(&position);
+            let flag = flags[&position];
+            match flag {
+                LOCAL => ahead += 1,
+                UPSTREAM => behind += 1,
+                _ => (),
+            }
+            if flag != LOCAL | UPSTREAM {
+                one_sided -= 1;
+            }
+
+            for parent in self.parents(position)? {
+                let previous = flags.get(&parent).copied().unwrap_or(0);
+                let combined = previous | flag;
+                if combined == previous {
+                    continue;
+                }
+                flags.insert(parent, combined);
+
+                if queued.contains(&parent) {
+                    if combined == LOCAL | UPSTREAM {
+                        one_sided -= 1;
+                    }
+                } else {
+                    let generation = self.generation(parent);
+                    if generation == 0 {
+                        return None;
+                    }
+                    queue.push((generation, parent));
+                    queued.insert(parent);
+                    if combined != LOCAL | UPSTREAM {
+                        one_sided += 1;
+                    }
+                }
+            }
+        }
+        Some((ahead, behind))
+    }
+}
+
+// Reads the commit-graph when the repository has one that knows both commits, and asks libgit2 otherwise
+pub fn graph_ahead_behind(repository: &Repository, graph: Option<&CommitGraph>, local: Oid, upstream: Oid) -> Result<(usize, usize), git2::Error> {
+    match graph.and_then(|graph| graph.ahead_behind(local, upstream)) {
+        Some(counts) => Ok(counts),
+        None => repository.graph_ahead_behind(local, upstream),
+    }
+}
+
+// Whether `ancestor` is in the history of `commit`, not counting the commit itself like `Repository::graph_descendant_of`
+pub fn graph_descendant_of(repository: &Repository, graph: Option<&CommitGraph>, commit: Oid, ancestor: Oid) -> Result<bool, git2::Error> {
+    match graph.and_then(|graph| graph.ahead_behind(commit, ancestor)) {
+        Some((_, behind)) => Ok(commit != ancestor && behind == 0),
+        None => repository.graph_descendant_of(commit, ancestor),
+    }
+}
@@ -0,0 +1,193 @@
+use std::fmt;
+
+use chrono::{DateTime, FixedOffset, Local, TimeZone, Utc};
+use git2::{Config, Repository, Signature};
+use iced::Point;
+
+const DEFAULT_ABBREV: usize = 7;
+
+// Direction history flows in: from the root towards the right, or from newer commits at the top down to the root
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Orientation {
+    #[default]
+    Horizontal,
+    Vertical,
+}
+
+impl Orientation {
+    pub const ALL: [Orientation; 2] = [Orientation::Horizontal, Orientation::Vertical];
+
+    // Position from the distance along the direction of history and the distance across it
+    pub fn orient(self, along: f32, across: f32) -> Point {
+        match self {
+            Orientation::Horizontal => Point::new(along, across),
+            Orientation::Vertical => Point::new(across, -along),
+        }
+    }
+
+    // Points where the edge from a child to its parent leaves and enters the two nodes
+    pub fn edge(self, child: Point, parent: Point, radius: f32) -> (Point, Point) {
+        match self {
+            Orientation::Horizontal => (Point::new(child.x - radius, child.y), Point::new(parent.x + radius, parent.y)),
+            Orientation::Vertical => (Point::new(child.x, child.y + radius), Point::new(parent.x, parent.y - radius)),
+        }
+    }
+
+    // Where the `index`th of `count` reference names goes so edges don't run through it, and whether it is centered
+    // there or starts there. Several names are stacked, upwards from the node or around its middle beside it
+    pub fn reference_label(self, node: Point, radius: f32, index: usize, count: usize) -> (Point, bool) {
+        let line = radius * 0.36;
+        match self {
+            Orientation::Horizontal => (Point::new(node.x, node.y - radius * 1.2 - index as f32 * line), true),
+            Orientation::Vertical => (Point::new(node.x + radius * 1.2, node.y + (index as f32 - (count - 1) as f32 / 2.0) * line), false),
+        }
+    }
+}
+
+impl fmt::Display for Orientation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Orientation::Horizontal => write!(f, "Horizontal"),
+            Orientation::Vertical => write!(f, "Vertical"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DateFormat {
+    Default,
+    Local,
+    Iso,
+    IsoStrict,
+    Rfc,
+    Short,
+    Relative,
+    Unix,
+    Custom(String),
+}
+
+impl DateFormat {
+    // Parses `log.date` values the way `git log --date=` does
+    fn parse(value: &str) -> DateFormat {
+        match value {
+            "local" | "default-local" => DateFormat::Local,
+            "iso" | "iso8601" => DateFormat::Iso,
+            "iso-strict" | "iso8601-strict" => DateFormat::IsoStrict,
+            "rfc" | "rfc2822" => DateFormat::Rfc,
+            "short" => DateFormat::Short,
+            "relative" => DateFormat::Relative,
+            "unix" | "raw" => DateFormat::Unix,
+            _ => match value.strip_prefix("format:") {
+                Some(format) => DateFormat::Custom(format.to_string()),
+                None => DateFormat::Default,
+            },
+        }
+    }
+}
+
+pub struct DisplayConfig {
+    pub abbrev: usize,
+    pub date_format: DateFormat,
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        Self { abbrev: DEFAULT_ABBREV, date_format: DateFormat::Default }
+    }
+}
+
+impl DisplayConfig {
+    pub fn load(repository: &Repository) -> DisplayConfig {
+        let config = match repository.config() {
+            Ok(config) => config,
+            Err(_) => return Default::default(),
+        };
+
+        let abbrev = match config.get_string("core.abbrev").as_deref() {
+            Ok("no") => 40,
+            Ok(value) => value.parse::<usize>().map(|a| a.clamp(4, 40)).unwrap_or(DEFAULT_ABBREV),
+            Err(_) => DEFAULT_ABBREV,
+        };
+
+        let date_format = config.get_string("log.date").map(|d| DateFormat::parse(&d)).unwrap_or(DateFormat::Default);
+
+        DisplayConfig { abbrev, date_format }
+    }
+
+    pub fn abbreviate<'a>(&self, id: &'a str) -> &'a str {
+        &id[..self.abbrev.min(id.len())]
+    }
+
+    pub fn format_time(&self, time: git2::Time) -> String {
+        let date = match to_date(time) {
+            Some(date) => date,
+            None => return time.seconds().to_string(),
+        };
+
+        match &self.date_format {
+            DateFormat::Default => date.format("%a %b %-d %H:%M:%S %Y %z").to_string(),
+            DateFormat::Local => date.with_timezone(&Local).format("%a %b %-d %H:%M:%S %Y").to_string(),
+            DateFormat::Iso => date.format("%Y-%m-%d %H:%M:%S %z").to_string(),
+            DateFormat::IsoStrict => date.to_rfc3339(),
+            DateFormat::Rfc => date.to_rfc2822(),
+            DateFormat::Short => date.format("%Y-%m-%d").to_string(),
+            DateFormat::Relative => format_relative(date),
+            DateFormat::Unix => time.seconds().to_string(),
+            DateFormat::Custom(format) => date.format(format).to_string(),
+        }
+    }
+
+    // In the local timezone whatever `log.date` says, along with how long ago that was
+    pub fn format_local(&self, time: git2::Time) -> String {
+        match to_date(time) {
+            Some(date) => format!("{} ({})", date.with_timezone(&Local).format("%a %b %-d %H:%M:%S %Y"), format_relative(date)),
+            None => time.seconds().to_string(),
+        }
+    }
+
+    // Just the day, for places without room for the configured format
+    pub fn format_day(&self, time: git2::Time) -> String {
+        match to_date(time) {
+            Some(date) => date.format("%Y-%m-%d").to_string(),
+            None => time.seconds().to_string(),
+        }
+    }
+}
+
+// The time in the timezone it was recorded in
+pub fn to_date(time: git2::Time) -> Option<DateTime<FixedOffset>> {
+    let offset = FixedOffset::east_opt(time.offset_minutes() * 60).unwrap_or(FixedOffset::east_opt(0).unwrap());
+    offset.timestamp_opt(time.seconds(), 0).single()
+}
+
+pub fn format_relative(date: DateTime<FixedOffset>) -> String {
+    let seconds = (Utc::now().timestamp() - date.timestamp()).max(0);
+
+    let (amount, unit) = match seconds {
+        s if s < 90 => (s, "second"),
+        s if s < 90 * 60 => (s / 60, "minute"),
+        s if s < 36 * 60 * 60 => (s / (60 * 60), "hour"),
+        s if s < 14 * 24 * 60 * 60 => (s / (24 * 60 * 60), "day"),
+        s if s < 10 * 7 * 24 * 60 * 60 => (s / (7 * 24 * 60 * 60), "week"),
+        s if s < 365 * 24 * 60 * 60 => (s / (30 * 24 * 60 * 60), "month"),
+        s => (s / (365 * 24 * 60 * 60), "year"),
+    };
+
+    format!("{} {}{} ago", amount, unit, if amount == 1 { "" } else { "s" })
+}
+
+// Identity from `user.name`/`user.email`, with a clearer error than libgit2 gives when they are unset
+pub fn get_signature(repository: &Repository) -> Result<Signature<'static>, git2::Error> {
+    get_config_signature(&repository.config()?)
+}
+
+// For when there is no repository yet, with the global config
+pub fn get_config_signature(config: &Config) -> Result<Signature<'static>, git2::Error> {
+    let name = config.get_string("user.name");
+    let email = config.get_string("user.email");
+
+    match (name, email) {
+        (Ok(name), Ok(email)) => Signature::now(&name, &email),
+        _ => Err(git2::Error::from_str("user.name and user.email must be set in git config")),
+    }
+}
@@ -0,0 +1,92 @@
+use std::backtrace::Backtrace;
+use std::cell::Cell;
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::panic::{self, AssertUnwindSafe, PanicHookInfo};
+use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+
+use chrono::Local;
+
+use crate::logging::try_last_lines;
+
+// Log lines put in a report, the latest ones
+const REPORT_LOG_LINES: usize = 50;
+
+// Only the first panic gets a report, the ones it causes on other threads would say the same
+static REPORTED: AtomicBool = AtomicBool::new(false);
+
+thread_local! {
+    // Set while a panic on this thread is caught by `catch_panic`, so the app keeps running and needs no report
+    static CATCHING: Cell<bool> = const { Cell::new(false) };
+}
+
+// None if `f` panicked. The panic is still printed and logged
+pub fn catch_panic<T>(f: impl FnOnce() -> T) -> Option<T> {
+    let catching = CATCHING.replace(true);
+    let result = panic::catch_unwind(AssertUnwindSafe(f));
+    CATCHING.set(catching);
+    result.ok()
+}
+
+fn panic_message(info: &PanicHookInfo) -> String {
+    let payload = info.payload();
+    payload.downcast_ref::<&str>().map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| String::from("(no message)"))
+}
+
+fn build_report(info: &PanicHookInfo) -> String {
+    let mut report = String::new();
+    let _ = writeln!(report, "gitui {} crashed", env!("CARGO_PKG_VERSION"));
+    let _ = writeln!(report, "Time: {}", Local::now().format("%Y-%m-%d %H:%M:%S %z"));
+    let _ = writeln!(report, "Platform: {} {}", env::consts::OS, env::consts::ARCH);
+    let _ = writeln!(report, "Thread: {}", thread::current().name().unwrap_or("(unnamed)"));
+    if let Some(location) = info.location() {
+        let _ = writeln!(report, "Location: {}:{}:{}", location.file(), location.line(), location.column());
+    }
+    let _ = writeln!(report, "Message: {}", panic_message(info));
+
+    let _ = writeln!(report, "\nRecent log:");
+    for line in try_last_lines(REPORT_LOG_LINES) {
+        let duration = line.duration.map(|duration| format!(" ({:.1} ms)", duration.as_secs_f64() * 1000.0)).unwrap_or_default();
+        let _ = writeln!(report, "{} {:5} {}{}", line.at.format("%H:%M:%S%.3f"), line.level, line.message, duration);
+    }
+
+    let _ = writeln!(report, "\nBacktrace:\n{}", Backtrace::force_capture());
+    report
+}
+
+// Where the report of a crash is kept until it is saved somewhere else
+fn report_path() -> PathBuf {
+    env::temp_dir().join(format!("gitui-crash-{}-{}.txt", Local::now().format("%Y%m%d-%H%M%S"), process::id()))
+}
+
+// A panic still prints like it would have, and is then shown by a gitui started with `--crash-report`, since the
+// window of this one goes away as the panic unwinds out of it
+pub fn install_panic_hook() {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        default_hook(info);
+        if CATCHING.get() {
+            tracing::error!("caught a panic on {}: {}", thread::current().name().unwrap_or("(unnamed)"), panic_message(info));
+            return;
+        }
+        if REPORTED.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let path = report_path();
+        if let Err(e) = fs::write(&path, build_report(info)) {
+            eprintln!("gitui: couldn't write the crash report: {}", e);
+            return;
+        }
+        eprintln!("gitui: the crash report was written to {}", path.display());
+        if let Ok(executable) = env::current_exe() {
+            let _ = process::Command::new(executable).arg("--crash-report").arg(&path).spawn();
+        }
+    }));
+}
@@ -0,0 +1,246 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::Write;
+
+use iced::Point;
+
+use crate::backend::{CommitNode, ReferenceKind, ReferenceLabel, update_tree_sizes};
+use crate::cli::ordered_commits;
+use crate::config::DisplayConfig;
+use crate::error::Error;
+
+fn quote(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+// Node positions are pinned, so `neato -n` draws the same layout as the GUI. Graphviz has y pointing up. Commits
+// without a location are left out along with their edges
+pub fn render_dot(commits: &BTreeMap<String, CommitNode>, locations: &BTreeMap<String, Point>, display: &DisplayConfig) -> String {
+    let mut dot = String::from("digraph history {\n  node [shape=circle];\n");
+
+    let ordered: Vec<&CommitNode> = ordered_commits(commits).into_iter().filter(|commit| locations.contains_key(&commit.id)).collect();
+    for commit in &ordered {
+        let location = locations[&commit.id];
+        let mut label = quote(display.abbreviate(&commit.id));
+        for reference in &commit.references {
+            label = format!("{}\\n{}", label, quote(&reference.name));
+        }
+        let _ = writeln!(dot, "  \"{}\" [label=\"{}\", pos=\"{},{}!\"];", quote(&commit.id), label, location.x, -location.y);
+    }
+    for commit in &ordered {
+        for parent in commit.parents.iter().filter(|parent| locations.contains_key(*parent)) {
+            let _ = writeln!(dot, "  \"{}\" -> \"{}\";", quote(&commit.id), quote(parent));
+        }
+    }
+
+    dot.push_str("}\n");
+    dot
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    // Names, numbers and quoted strings alike, since DOT uses them interchangeably
+    Word(String),
+    Symbol(char),
+    Arrow,
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, Error> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => (),
+            '/' if chars.peek() == Some(&'/') => while chars.next_if(|c| *c != '\n').is_some() {},
+            '#' => while chars.next_if(|c| *c != '\n').is_some() {},
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut last = ' ';
+                loop {
+                    match chars.next() {
+                        Some('/') if last == '*' => break,
+                        Some(c) => last = c,
+                        None => return Err(Error::from("a comment isn't closed")),
+                    }
+                }
+            },
+            '-' if chars.peek() == Some(&'>') => {
+                chars.next();
+                tokens.push(Token::Arrow);
+            },
+            '-' if chars.peek() == Some(&'-') => return Err(Error::from("only directed graphs can be shown, with -> edges")),
+            '"' => {
+                let mut word = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some('n' | 'l' | 'r') => word.push('\n'),
+                            Some(c) => word.push(c),
+                            None => return Err(Error::from("a string isn't closed")),
+                        },
+                        Some(c) => word.push(c),
+                        None => return Err(Error::from("a string isn't closed")),
+                    }
+                }
+                tokens.push(Token::Word(word));
+            },
+            '{' | '}' | '[' | ']' | '=' | ';' | ',' | ':' => tokens.push(Token::Symbol(c)),
+            c if c.is_alphanumeric() || c == '_' || c == '.' || c == '-' => {
+                let mut word = String::from(c);
+                while let Some(c) = chars.next_if(|c| c.is_alphanumeric() || *c == '_' || *c == '.') {
+                    word.push(c);
+                }
+                tokens.push(Token::Word(word));
+            },
+            c => return Err(Error::from(format!("unexpected '{}'", c))),
+        }
+    }
+    Ok(tokens)
+}
+
+// The attributes of one `[...]` list, which may be followed by more lists
+fn parse_attributes(tokens: &mut std::iter::Peekable<std::vec::IntoIter<Token>>) -> Result<HashMap<String, String>, Error> {
+    let mut attributes = HashMap::new();
+    while tokens.next_if_eq(&Token::Symbol('[')).is_some() {
+        loop {
+            match tokens.next() {
+                Some(Token::Symbol(']')) => break,
+                Some(Token::Symbol(',' | ';')) => (),
+                Some(Token::Word(key)) => {
+                    let value = match tokens.next_if_eq(&Token::Symbol('=')) {
+                        Some(_) => match tokens.next() {
+                            Some(Token::Word(value)) => value,
+                            _ => return Err(Error::from(format!("attribute '{}' has no value", key))),
+                        },
+                        None => String::from("true"),
+                    };
+                    attributes.insert(key, value);
+                },
+                _ => return Err(Error::from("an attribute list isn't closed")),
+            }
+        }
+    }
+    Ok(attributes)
+}
+
+// A directed graph in DOT as the commits of a history, for showing graphs that aren't from git with the same
+// renderer. An edge `a -> b` makes b a parent of a, like the export writes them. The first line of a node's label is
+// its summary and the others are shown like branch names on it. Subgraphs are flattened and ports are ignored
+pub fn parse_dot(text: &str) -> Result<BTreeMap<String, CommitNode>, Error> {
+    let mut tokens = tokenize(text)?.into_iter().peekable();
+    tokens.next_if_eq(&Token::Word(String::from("strict")));
+    match tokens.next() {
+        Some(Token::Word(kind)) if kind.eq_ignore_ascii_case("digraph") => (),
+        Some(Token::Word(kind)) if kind.eq_ignore_ascii_case("graph") => return Err(Error::from("only directed graphs can be shown, not undirected ones")),
+        _ => return Err(Error::from("the file doesn't start with a digraph")),
+    }
+    if let Some(Token::Word(_)) = tokens.peek() {
+        tokens.next();
+    }
+    if tokens.next() != Some(Token::Symbol('{')) {
+        return Err(Error::from("the graph has no body"));
+    }
+
+    // Nodes in the order they are first mentioned, which also orders them in time
+    let mut order: Vec<String> = Vec::new();
+    let mut seen: HashSet<String> = HashSet::new();
+    let mut labels: HashMap<String, String> = HashMap::new();
+    let mut edges: Vec<(String, String)> = Vec::new();
+    let mut depth = 1;
+    while depth > 0 {
+        match tokens.next() {
+            None => return Err(Error::from("the graph isn't closed")),
+            Some(Token::Symbol('{')) => depth += 1,
+            Some(Token::Symbol('}')) => depth -= 1,
+            Some(Token::Symbol(';' | ',')) => (),
+            Some(Token::Word(word)) if matches!(word.as_str(), "node" | "edge" | "graph") && tokens.peek() == Some(&Token::Symbol('[')) => {
+                parse_attributes(&mut tokens)?;
+            },
+            Some(Token::Word(word)) if word == "subgraph" => {
+                if let Some(Token::Word(_)) = tokens.peek() {
+                    tokens.next();
+                }
+            },
+            Some(Token::Word(first)) => {
+                // `name = value` sets an attribute of the graph
+                if tokens.next_if_eq(&Token::Symbol('=')).is_some() {
+                    tokens.next();
+                    continue;
+                }
+                let mut chain = vec![first];
+                loop {
+                    // A port after the name doesn't change which node it is
+                    while tokens.next_if_eq(&Token::Symbol(':')).is_some() {
+                        tokens.next();
+                    }
+                    if tokens.next_if_eq(&Token::Arrow).is_none() {
+                        break;
+                    }
+                    match tokens.next() {
+                        Some(Token::Word(next)) => chain.push(next),
+                        _ => return Err(Error::from(format!("an edge from '{}' doesn't go anywhere", chain.last().unwrap()))),
+                    }
+                }
+                let attributes = parse_attributes(&mut tokens)?;
+                for id in &chain {
+                    if seen.insert(id.clone()) {
+                        order.push(id.clone());
+                    }
+                }
+                if chain.len() == 1 {
+                    if let Some(label) = attributes.get("label") {
+                        labels.insert(chain[0].clone(), label.clone());
+                    }
+                }
+                edges.extend(chain.windows(2).map(|pair| (pair[0].clone(), pair[1].clone())));
+            },
+            Some(token) => return Err(Error::from(format!("unexpected {:?}", token))),
+        }
+    }
+
+    let mut commits: BTreeMap<String, CommitNode> = BTreeMap::new();
+    for (i, id) in order.iter().enumerate() {
+        let label = labels.get(id).map(String::as_str).unwrap_or(id);
+        let mut lines = label.lines();
+        commits.insert(id.clone(), CommitNode {
+            id: id.clone(),
+            parents: Vec::new(),
+            children: Vec::new(),
+            references: lines.clone().skip(1).map(|name| ReferenceLabel { name: name.to_string(), kind: ReferenceKind::Branch }).collect(),
+            signature: None,
+            summary: lines.next().unwrap_or(id).to_string(),
+            author: String::new(),
+            email: String::new(),
+            time: git2::Time::new(i as i64, 0),
+            tree_size: 0,
+        });
+    }
+    for (child, parent) in edges {
+        if child == parent || commits[&child].parents.contains(&parent) {
+            continue;
+        }
+        commits.get_mut(&child).unwrap().parents.push(parent.clone());
+        commits.get_mut(&parent).unwrap().children.push(child);
+    }
+
+    // The layouts need every commit after its parents, which a cycle doesn't allow
+    let mut waiting: HashMap<&str, usize> = commits.values().map(|commit| (commit.id.as_str(), commit.parents.len())).collect();
+    let mut ready: Vec<&str> = waiting.iter().filter(|(_, count)| **count == 0).map(|(id, _)| *id).collect();
+    let mut placed = 0;
+    while let Some(id) = ready.pop() {
+        placed += 1;
+        for child in &commits[id].children {
+            let count = waiting.get_mut(child.as_str()).unwrap();
+            *count -= 1;
+            if *count == 0 {
+                ready.push(child);
+            }
+        }
+    }
+    if placed < commits.len() {
+        return Err(Error::from("the graph has a cycle, so it isn't a history that can be laid out"));
+    }
+
+    update_tree_sizes(&mut commits);
+    Ok(commits)
+}
@@ -0,0 +1,59 @@
+use std::fmt;
+
+#[derive(Debug)]
+pub enum Error {
+    Git(git2::Error),
+    Io(std::io::Error),
+    Message(String),
+}
+
+impl Error {
+    pub fn message(&self) -> String {
+        match self {
+            Error::Git(e) => e.message().to_string(),
+            Error::Io(e) => e.to_string(),
+            Error::Message(message) => message.clone(),
+        }
+    }
+
+    // Extra information for bug reports, beyond the one line message
+    pub fn details(&self) -> String {
+        match self {
+            Error::Git(e) => format!("git error (class {:?}, code {:?}): {}", e.class(), e.code(), e.message()),
+            Error::Io(e) => format!("io error ({:?}): {}", e.kind(), e),
+            Error::Message(message) => message.clone(),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message())
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<git2::Error> for Error {
+    fn from(e: git2::Error) -> Self {
+        Error::Git(e)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<String> for Error {
+    fn from(message: String) -> Self {
+        Error::Message(message)
+    }
+}
+
+impl From<&str> for Error {
+    fn from(message: &str) -> Self {
+        Error::Message(message.to_string())
+    }
+}
@@ -0,0 +1,211 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+use cosmic_text::{Attrs, Buffer, FontSystem, Metrics, Shaping, SwashCache};
+use iced::{Point, Rectangle};
+use tiny_skia::{Paint, PathBuilder, Pixmap, Rect, Stroke, Transform};
+
+use crate::backend::CommitNode;
+use crate::config::{DisplayConfig, Orientation};
+use crate::dot::render_dot;
+use crate::error::Error;
+
+const FONT_SIZE: f32 = 15.0;
+// Keeps exports of huge histories within what image viewers can open
+const MAX_PNG_SIZE: f32 = 16384.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ExportFormat {
+    Svg,
+    Png,
+    // The graph itself rather than a picture of it, with the node positions pinned
+    Dot,
+}
+
+impl ExportFormat {
+    pub fn from_path(path: &Path) -> Option<ExportFormat> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "svg" => Some(ExportFormat::Svg),
+            "png" => Some(ExportFormat::Png),
+            "dot" | "gv" => Some(ExportFormat::Dot),
+            _ => None,
+        }
+    }
+}
+
+struct Label {
+    position: Point,
+    content: String,
+    dark: bool,
+    // Otherwise the text starts at `position`
+    centered: bool,
+}
+
+// Everything needed to draw the graph, translated so the top left corner is at the origin
+struct Scene {
+    width: f32,
+    height: f32,
+    radius: f32,
+    nodes: Vec<Point>,
+    edges: Vec<(Point, Point)>,
+    labels: Vec<Label>,
+}
+
+fn build_scene(commits: &BTreeMap<String, CommitNode>, locations: &BTreeMap<String, Point>, display: &DisplayConfig, orientation: Orientation, radius: f32, area: Option<Rectangle>) -> Scene {
+    let area = area.unwrap_or_else(|| {
+        let (mut min, mut max) = (Point::new(f32::MAX, f32::MAX), Point::new(f32::MIN, f32::MIN));
+        for location in locations.values() {
+            min = Point::new(min.x.min(location.x), min.y.min(location.y));
+            max = Point::new(max.x.max(location.x), max.y.max(location.y));
+        }
+        if locations.is_empty() {
+            min = Point::ORIGIN;
+            max = Point::ORIGIN;
+        }
+        // Leave room for the reference labels drawn above or beside the nodes
+        let margin = match orientation {
+            Orientation::Horizontal => iced::Size::new(radius * 2.0, radius * 2.0),
+            Orientation::Vertical => iced::Size::new(radius * 6.0, radius * 2.0),
+        };
+        Rectangle::new(Point::new(min.x - margin.width, min.y - margin.height), iced::Size::new(max.x - min.x + margin.width * 2.0, max.y - min.y + margin.height * 2.0))
+    });
+
+    let translate = |p: &Point| Point::new(p.x - area.x, p.y - area.y);
+    let visible = |p: &Point| p.x >= area.x - radius && p.x <= area.x + area.width + radius && p.y >= area.y - radius && p.y <= area.y + area.height + radius;
+
+    let mut scene = Scene { width: area.width, height: area.height, radius, nodes: Vec::new(), edges: Vec::new(), labels: Vec::new() };
+
+    for (id, commit) in commits {
+        let location = match locations.get(id) {
+            Some(location) => location,
+            None => continue,
+        };
+
+        for parent in &commit.parents {
+            if let Some(parent_location) = locations.get(parent) {
+                if visible(location) || visible(parent_location) {
+                    scene.edges.push(orientation.edge(translate(location), translate(parent_location), radius));
+                }
+            }
+        }
+
+        if !visible(location) {
+            continue;
+        }
+
+        let position = translate(location);
+        scene.nodes.push(position);
+        scene.labels.push(Label { position, content: display.abbreviate(id).to_string(), dark: false, centered: true });
+        for (i, reference) in commit.references.iter().enumerate() {
+            let (position, centered) = orientation.reference_label(position, radius, i, commit.references.len());
+            scene.labels.push(Label { position, content: reference.name.clone(), dark: true, centered });
+        }
+    }
+
+    scene
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+fn render_svg(scene: &Scene) -> String {
+    let mut svg = String::new();
+    let _ = writeln!(svg, r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="0 0 {} {}">"#, scene.width, scene.height, scene.width, scene.height);
+    let _ = writeln!(svg, r#"<rect width="100%" height="100%" fill="white"/>"#);
+
+    for (from, to) in &scene.edges {
+        let _ = writeln!(svg, r#"<line x1="{}" y1="{}" x2="{}" y2="{}" stroke="black" stroke-width="2"/>"#, from.x, from.y, to.x, to.y);
+    }
+    for node in &scene.nodes {
+        let _ = writeln!(svg, r#"<circle cx="{}" cy="{}" r="{}" fill="rgb(89,89,89)"/>"#, node.x, node.y, scene.radius);
+    }
+    for label in &scene.labels {
+        let fill = if label.dark { "rgb(51,51,51)" } else { "rgb(204,204,204)" };
+        let anchor = if label.centered { "middle" } else { "start" };
+        let _ = writeln!(svg, r#"<text x="{}" y="{}" font-family="sans-serif" font-size="{}" fill="{}" text-anchor="{}" dominant-baseline="central">{}</text>"#,
+            label.position.x, label.position.y, FONT_SIZE, fill, anchor, escape_xml(&label.content));
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn render_png(scene: &Scene) -> Result<Vec<u8>, Error> {
+    let scale = (MAX_PNG_SIZE / scene.width.max(scene.height)).min(1.0);
+    let mut pixmap = Pixmap::new((scene.width * scale).ceil().max(1.0) as u32, (scene.height * scale).ceil().max(1.0) as u32)
+        .ok_or(Error::from("graph is too large to export"))?;
+    pixmap.fill(tiny_skia::Color::WHITE);
+    let transform = Transform::from_scale(scale, scale);
+
+    let mut paint = Paint { anti_alias: true, ..Default::default() };
+
+    paint.set_color_rgba8(0, 0, 0, 255);
+    let stroke = Stroke { width: 2.0, ..Default::default() };
+    for (from, to) in &scene.edges {
+        let mut path = PathBuilder::new();
+        path.move_to(from.x, from.y);
+        path.line_to(to.x, to.y);
+        if let Some(path) = path.finish() {
+            pixmap.stroke_path(&path, &paint, &stroke, transform, None);
+        }
+    }
+
+    paint.set_color_rgba8(89, 89, 89, 255);
+    for node in &scene.nodes {
+        if let Some(path) = PathBuilder::from_circle(node.x, node.y, scene.radius) {
+            pixmap.fill_path(&path, &paint, tiny_skia::FillRule::Winding, transform, None);
+        }
+    }
+
+    let mut font_system = FontSystem::new();
+    let mut cache = SwashCache::new();
+    let font_size = FONT_SIZE * scale;
+    for label in &scene.labels {
+        let mut buffer = Buffer::new(&mut font_system, Metrics::new(font_size, font_size * 1.2));
+        buffer.set_size(&mut font_system, f32::MAX, font_size * 2.0);
+        buffer.set_text(&mut font_system, &label.content, Attrs::new(), Shaping::Advanced);
+        buffer.shape_until_scroll(&mut font_system);
+
+        let width = buffer.layout_runs().map(|run| run.line_w).fold(0.0, f32::max);
+        let origin_x = if label.centered { label.position.x * scale - width / 2.0 } else { label.position.x * scale };
+        let origin_y = label.position.y * scale - font_size * 0.6;
+        let color = if label.dark { cosmic_text::Color::rgb(51, 51, 51) } else { cosmic_text::Color::rgb(204, 204, 204) };
+
+        buffer.draw(&mut font_system, &mut cache, color, |x, y, w, h, color| {
+            let mut paint = Paint::default();
+            paint.set_color_rgba8(color.r(), color.g(), color.b(), color.a());
+            if let Some(rect) = Rect::from_xywh(origin_x + x as f32, origin_y + y as f32, w as f32, h as f32) {
+                pixmap.fill_rect(rect, &paint, Transform::identity(), None);
+            }
+        });
+    }
+
+    pixmap.encode_png().map_err(|e| Error::Message(e.to_string()))
+}
+
+// The whole graph as SVG, for the command line export
+pub fn render_graph_svg(commits: &BTreeMap<String, CommitNode>, locations: &BTreeMap<String, Point>, display: &DisplayConfig, orientation: Orientation, radius: f32) -> String {
+    render_svg(&build_scene(commits, locations, display, orientation, radius, None))
+}
+
+// Renders the graph from the given layout, limited to `area` (in graph coordinates) when one is given
+pub fn export_graph(path: &Path, commits: &BTreeMap<String, CommitNode>, locations: &BTreeMap<String, Point>, display: &DisplayConfig, orientation: Orientation, radius: f32, area: Option<Rectangle>) -> Result<(), Error> {
+    let format = ExportFormat::from_path(path).ok_or(Error::from("the file must end in .svg, .png or .dot"))?;
+    let contents = match format {
+        ExportFormat::Svg => render_svg(&build_scene(commits, locations, display, orientation, radius, area)).into_bytes(),
+        ExportFormat::Png => render_png(&build_scene(commits, locations, display, orientation, radius, area))?,
+        // Only the nodes in the area, with the edges between them
+        ExportFormat::Dot => {
+            let locations: BTreeMap<String, Point> = locations.iter()
+                .filter(|(_, location)| area.is_none_or(|area| area.contains(**location)))
+                .map(|(id, location)| (id.clone(), *location))
+                .collect();
+            render_dot(commits, &locations, display).into_bytes()
+        },
+    };
+    fs::write(path, contents)?;
+    Ok(())
+}
@@ -0,0 +1,108 @@
+use iced::advanced::mouse::Cursor;
+use iced::alignment::{Horizontal, Vertical};
+use iced::widget::canvas::{Program, Geometry, Frame, Text};
+use iced::{Rectangle, Theme, Renderer, Pixels, Point, Size};
+
+use super::{Message, ERROR_COLOR, VERIFIED_COLOR};
+
+// Height of a row in a horizontal chart
+pub const ROW_HEIGHT: f32 = 20.0;
+// Room left of the bars for the labels of a horizontal chart, and below the columns for those of a column chart
+const LABEL_WIDTH: f32 = 160.0;
+const LABEL_HEIGHT: f32 = 16.0;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ChartKind {
+    // One labelled row per bar, for a handful of named values
+    Rows,
+    // Columns side by side, for many values over time; only the first and last are labelled
+    Columns,
+}
+
+pub struct BarChart {
+    pub kind: ChartKind,
+    pub bars: Vec<(String, f32)>,
+}
+
+impl Program<Message> for BarChart {
+    type State = ();
+
+    fn draw(&self, _state: &(), renderer: &Renderer, theme: &Theme, bounds: Rectangle, _cursor: Cursor) -> Vec<Geometry> {
+        let mut frame = Frame::new(renderer, bounds.size());
+        // From the theme, so the charts stay readable in the high contrast one
+        let palette = theme.palette();
+        let largest = self.bars.iter().map(|(_, value)| *value).fold(0.0, f32::max).max(1.0);
+
+        match self.kind {
+            ChartKind::Rows => {
+                let width = (bounds.width - LABEL_WIDTH - 50.0).max(0.0);
+                for (i, (label, value)) in self.bars.iter().enumerate() {
+                    let y = i as f32 * ROW_HEIGHT;
+                    frame.fill_text(Text {
+                        content: label.clone(),
+                        position: Point::new(LABEL_WIDTH - 5.0, y + ROW_HEIGHT / 2.0),
+                        size: Pixels(13.0),
+                        color: palette.text,
+                        horizontal_alignment: Horizontal::Right,
+                        vertical_alignment: Vertical::Center,
+                        ..Default::default()
+                    });
+                    let length = width * value / largest;
+                    frame.fill_rectangle(Point::new(LABEL_WIDTH, y + 3.0), Size::new(length, ROW_HEIGHT - 6.0), palette.primary);
+                    frame.fill_text(Text {
+                        content: value.to_string(),
+                        position: Point::new(LABEL_WIDTH + length + 5.0, y + ROW_HEIGHT / 2.0),
+                        size: Pixels(13.0),
+                        color: palette.text,
+                        vertical_alignment: Vertical::Center,
+                        ..Default::default()
+                    });
+                }
+            },
+            ChartKind::Columns => {
+                let height = (bounds.height - LABEL_HEIGHT).max(0.0);
+                let width = bounds.width / self.bars.len().max(1) as f32;
+                for (i, (_, value)) in self.bars.iter().enumerate() {
+                    let length = height * value / largest;
+                    frame.fill_rectangle(Point::new(i as f32 * width, height - length), Size::new((width - 1.0).max(1.0), length), palette.primary);
+                }
+                let ends = [(self.bars.first(), 0.0, Horizontal::Left), (self.bars.last(), bounds.width, Horizontal::Right)];
+                for (bar, x, alignment) in ends {
+                    if let Some((label, _)) = bar {
+                        frame.fill_text(Text {
+                            content: label.clone(),
+                            position: Point::new(x, bounds.height),
+                            size: Pixels(13.0),
+                            color: palette.text,
+                            horizontal_alignment: alignment,
+                            vertical_alignment: Vertical::Bottom,
+                            ..Default::default()
+                        });
+                    }
+                }
+            },
+        }
+
+        vec![frame.into_geometry()]
+    }
+}
+
+// Insertions and deletions of a file side by side, as long against the width as against the most changed file
+pub struct ChangeBar {
+    pub insertions: usize,
+    pub deletions: usize,
+    pub largest: usize,
+}
+
+impl Program<Message> for ChangeBar {
+    type State = ();
+
+    fn draw(&self, _state: &(), renderer: &Renderer, _theme: &Theme, bounds: Rectangle, _cursor: Cursor) -> Vec<Geometry> {
+        let mut frame = Frame::new(renderer, bounds.size());
+        let scale = bounds.width / self.largest.max(1) as f32;
+        let inserted = self.insertions as f32 * scale;
+        frame.fill_rectangle(Point::ORIGIN, Size::new(inserted, bounds.height), VERIFIED_COLOR);
+        frame.fill_rectangle(Point::new(inserted, 0.0), Size::new(self.deletions as f32 * scale, bounds.height), ERROR_COLOR);
+        vec![frame.into_geometry()]
+    }
+}
@@ -0,0 +1,55 @@
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::error::Error;
+use crate::remote::{CloneOptions, clone_repository};
+
+use super::worker::JobHandle;
+
+#[derive(Default)]
+struct CloneProgress {
+    received: AtomicUsize,
+    total: AtomicUsize,
+}
+
+// Clones on its own thread like TreeLoad loads, polled by the GUI until it is done. Dropping it cancels the clone
+pub struct CloneJob {
+    progress: Arc<CloneProgress>,
+    // Where the clone ended up
+    job: JobHandle<PathBuf>,
+}
+
+impl CloneJob {
+    pub fn start(options: CloneOptions) -> Result<CloneJob, Error> {
+        let progress = Arc::new(CloneProgress::default());
+
+        let thread_progress = Arc::clone(&progress);
+        let job = JobHandle::spawn("cloner", move |cancel| {
+            let progress = thread_progress;
+            let result = clone_repository(&options, &mut |received, total| {
+                progress.received.store(received, Ordering::Relaxed);
+                progress.total.store(total, Ordering::Relaxed);
+                !cancel.is_cancelled()
+            });
+
+            // libgit2 reports a cancelled transfer with whatever error it ran into while stopping
+            match result {
+                _ if cancel.is_cancelled() => Err(Error::from("cloning was cancelled")),
+                Ok(repository) => Ok(repository.workdir().unwrap_or(repository.path()).to_path_buf()),
+                Err(e) => Err(Error::from(e)),
+            }
+        })?;
+
+        Ok(CloneJob { progress, job })
+    }
+
+    // Objects received so far and the total, which is zero until the remote said
+    pub fn progress(&self) -> (usize, usize) {
+        (self.progress.received.load(Ordering::Relaxed), self.progress.total.load(Ordering::Relaxed))
+    }
+
+    pub fn take_result(&self) -> Option<Result<PathBuf, Error>> {
+        self.job.take_result()
+    }
+}
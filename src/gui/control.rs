@@ -0,0 +1,292 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{Ipv4Addr, Shutdown, TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::error::Error;
+use crate::json::{JsonValue, parse_json};
+use crate::settings::settings_directory;
+
+// How long a connection waits for the app to handle its command before giving up on it
+const REPLY_TIMEOUT: Duration = Duration::from_secs(10);
+
+// How often the listener checks whether the server was stopped, since waiting for a connection can't be interrupted
+const ACCEPT_INTERVAL: Duration = Duration::from_millis(200);
+
+// A tool that stops reading for this long is disconnected, instead of its writer waiting on it forever
+const WRITE_TIMEOUT: Duration = Duration::from_secs(5);
+
+// A tool has this long to authenticate before it is disconnected, so a connection left idle doesn't hold a thread
+const AUTHENTICATE_TIMEOUT: Duration = Duration::from_secs(10);
+
+// A request this long without its line ending closes the connection, instead of being buffered until it ends
+const MAX_LINE_LENGTH: u64 = 64 * 1024;
+
+// Each tool gets a thread of its own, so connections past this many are closed right away
+const MAX_CLIENTS: usize = 16;
+
+// Lines waiting to be written to one tool. One that falls this far behind is disconnected too
+const OUTGOING_CAPACITY: usize = 256;
+
+// In the settings directory, with a new token whenever the server starts. Tools have to send it in an `authenticate`
+// request before anything else, which keeps out web pages that can reach localhost but not the file
+pub const TOKEN_FILE: &str = "control-token";
+
+// What an external tool can ask for. Without a repository the active tab is meant
+#[derive(Debug, Clone)]
+pub enum ControlCommand {
+    // `commit` is anything git can resolve to a commit, like an abbreviated id or a branch name
+    SelectCommit { commit: String, repository: Option<PathBuf> },
+    Refresh { repository: Option<PathBuf> },
+    OpenRepository(PathBuf),
+    GetSelection,
+}
+
+// A command waiting for the app, which answers it with the result of a JSON-RPC response
+pub struct ControlRequest {
+    pub command: ControlCommand,
+    reply: Sender<Result<JsonValue, String>>,
+}
+
+impl ControlRequest {
+    pub fn reply(self, result: Result<JsonValue, String>) {
+        let _ = self.reply.send(result);
+    }
+}
+
+// A connected tool, which events are sent to once it authenticated
+struct Client {
+    id: usize,
+    authenticated: bool,
+    lines: SyncSender<String>,
+    stream: TcpStream,
+}
+
+type Clients = Arc<Mutex<Vec<Client>>>;
+
+// Accepts JSON-RPC 2.0 over TCP on localhost, one message per line, for editors to drive the graph. Commands are
+// handed to the app through `take_requests`, and `broadcast` sends events to every connected tool. Every tool has a
+// thread writing to it, so the app only ever queues lines. Dropping the server stops it
+pub struct ControlServer {
+    requests: Receiver<ControlRequest>,
+    clients: Clients,
+    stopped: Arc<AtomicBool>,
+}
+
+// Only the user can read it, since it lets anyone holding it open repositories in the app
+fn write_token(token: &str) -> Result<(), Error> {
+    let directory = settings_directory().ok_or(Error::from("there is no settings directory to keep the token in"))?;
+    fs::create_dir_all(&directory)?;
+    let path = directory.join(TOKEN_FILE);
+    // Removed first, so it is always created anew with the permissions below and not through a link
+    match fs::remove_file(&path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e.into()),
+        _ => {},
+    }
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(&path)?.write_all(token.as_bytes())?;
+    Ok(())
+}
+
+fn response(id: &JsonValue, result: Result<JsonValue, (i32, String)>) -> String {
+    match result {
+        Ok(result) => format!("{{\"jsonrpc\": \"2.0\", \"id\": {}, \"result\": {}}}", id.to_json(), result.to_json()),
+        Err((code, message)) => format!("{{\"jsonrpc\": \"2.0\", \"id\": {}, \"error\": {{\"code\": {}, \"message\": {}}}}}", id.to_json(), code, JsonValue::String(message).to_json()),
+    }
+}
+
+fn parse_command(method: &str, params: Option<&JsonValue>) -> Result<ControlCommand, (i32, String)> {
+    let text = |key: &str| params.and_then(|params| params.get(key)).and_then(JsonValue::as_str);
+    let repository = text("repository").map(PathBuf::from);
+    match method {
+        "select_commit" => {
+            let commit = text("commit").ok_or((-32602, String::from("select_commit needs a commit")))?;
+            Ok(ControlCommand::SelectCommit { commit: commit.to_string(), repository })
+        },
+        "refresh" => Ok(ControlCommand::Refresh { repository }),
+        "open_repository" => {
+            let path = text("path").ok_or((-32602, String::from("open_repository needs a path")))?;
+            Ok(ControlCommand::OpenRepository(PathBuf::from(path)))
+        },
+        "get_selection" => Ok(ControlCommand::GetSelection),
+        _ => Err((-32601, format!("unknown method '{}'", method))),
+    }
+}
+
+fn parse_message(line: &str) -> Result<JsonValue, String> {
+    parse_json(line).ok_or_else(|| response(&JsonValue::Null, Err((-32700, String::from("the message isn't valid JSON or is nested too deeply")))))
+}
+
+// Answers the first message, which has to authenticate. An error is answered before the connection is closed
+fn authenticate(line: &str, token: &str) -> Result<Option<String>, String> {
+    let message = parse_message(line)?;
+    let id = message.get("id").cloned();
+    let given = message.get("params").and_then(|params| params.get("token")).and_then(JsonValue::as_str);
+    match message.get("method").and_then(JsonValue::as_str) {
+        Some("authenticate") if given == Some(token) => Ok(id.map(|id| response(&id, Ok(JsonValue::Bool(true))))),
+        Some("authenticate") => Err(response(&id.unwrap_or(JsonValue::Null), Err((-32001, String::from("the token is wrong"))))),
+        _ => Err(response(&id.unwrap_or(JsonValue::Null), Err((-32001, format!("authenticate with the token in {} first", TOKEN_FILE))))),
+    }
+}
+
+// The response to one line, or None for a notification, which isn't answered. A line that isn't JSON is answered
+// with an error and ends the connection, since whatever sent it doesn't speak the protocol
+fn handle_line(line: &str, requests: &Sender<ControlRequest>) -> Result<Option<String>, String> {
+    let message = parse_message(line)?;
+    let id = message.get("id").cloned();
+    let method = match message.get("method").and_then(JsonValue::as_str) {
+        Some(method) => method,
+        None => return Ok(Some(response(&id.unwrap_or(JsonValue::Null), Err((-32600, String::from("the message has no method")))))),
+    };
+
+    let result = parse_command(method, message.get("params")).and_then(|command| {
+        let (reply, replied) = mpsc::channel();
+        requests.send(ControlRequest { command, reply }).map_err(|_| (-32000, String::from("the app has stopped")))?;
+        match replied.recv_timeout(REPLY_TIMEOUT) {
+            Ok(result) => result.map_err(|e| (-32000, e)),
+            Err(_) => Err((-32000, String::from("the app didn't answer in time"))),
+        }
+    });
+    Ok(id.map(|id| response(&id, result)))
+}
+
+// Writes the queued lines until every sender is gone, then closes the connection. A write that times out closes it
+// early, which also ends the reading side
+fn write_lines(mut stream: TcpStream, lines: Receiver<String>) {
+    for line in lines {
+        if stream.write_all(line.as_bytes()).and_then(|_| stream.write_all(b"\n")).is_err() {
+            break;
+        }
+    }
+    let _ = stream.shutdown(Shutdown::Both);
+}
+
+fn serve(stream: TcpStream, client: usize, clients: &Clients, requests: &Sender<ControlRequest>, token: &str) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    reader.get_ref().set_read_timeout(Some(AUTHENTICATE_TIMEOUT))?;
+    let writer = stream.try_clone()?;
+    writer.set_write_timeout(Some(WRITE_TIMEOUT))?;
+    let (lines, outgoing) = mpsc::sync_channel(OUTGOING_CAPACITY);
+    thread::Builder::new().name(String::from("control writer")).spawn(move || write_lines(writer, outgoing))?;
+    clients.lock().unwrap().push(Client { id: client, authenticated: false, lines: lines.clone(), stream });
+
+    let mut authenticated = false;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        let read = (&mut reader).take(MAX_LINE_LENGTH).read_line(&mut line)?;
+        if read == 0 || (read as u64 == MAX_LINE_LENGTH && !line.ends_with('\n')) {
+            break;
+        }
+        let line = line.trim_end();
+        if line.trim().is_empty() {
+            continue;
+        }
+        let handled = match authenticated {
+            true => handle_line(line, requests),
+            false => authenticate(line, token),
+        };
+        match handled {
+            Ok(response) => {
+                if let Some(response) = response {
+                    if lines.send(response).is_err() {
+                        break;
+                    }
+                }
+                if !authenticated {
+                    authenticated = true;
+                    reader.get_ref().set_read_timeout(None)?;
+                    if let Some(client) = clients.lock().unwrap().iter_mut().find(|other| other.id == client) {
+                        client.authenticated = true;
+                    }
+                }
+            },
+            Err(response) => {
+                let _ = lines.send(response);
+                break;
+            },
+        }
+    }
+    Ok(())
+}
+
+impl ControlServer {
+    // Only on the loopback interface, so nothing outside this machine can connect
+    pub fn start(port: u16) -> Result<ControlServer, Error> {
+        // Bound first, so a port already in use leaves the token of the server on it alone
+        let listener = TcpListener::bind((Ipv4Addr::LOCALHOST, port))?;
+        listener.set_nonblocking(true)?;
+        let token: Arc<str> = Arc::from(format!("{:032x}", rand::random::<u128>()));
+        write_token(&token)?;
+        let (sender, requests) = mpsc::channel();
+        let clients: Clients = Arc::new(Mutex::new(Vec::new()));
+        let stopped = Arc::new(AtomicBool::new(false));
+
+        let (server_clients, server_stopped) = (Arc::clone(&clients), Arc::clone(&stopped));
+        thread::Builder::new().name(String::from("control server")).spawn(move || {
+            let mut next_client = 0;
+            let connected = Arc::new(AtomicUsize::new(0));
+            while !server_stopped.load(Ordering::Relaxed) {
+                match listener.accept() {
+                    Ok((stream, _)) if connected.load(Ordering::Relaxed) < MAX_CLIENTS && stream.set_nonblocking(false).is_ok() => {
+                        let (clients, requests, token, served) = (Arc::clone(&server_clients), sender.clone(), Arc::clone(&token), Arc::clone(&connected));
+                        let client = next_client;
+                        next_client += 1;
+                        connected.fetch_add(1, Ordering::Relaxed);
+                        let spawned = thread::Builder::new().name(String::from("control client")).spawn(move || {
+                            let _ = serve(stream, client, &clients, &requests, &token);
+                            clients.lock().unwrap().retain(|other| other.id != client);
+                            served.fetch_sub(1, Ordering::Relaxed);
+                        });
+                        if spawned.is_err() {
+                            connected.fetch_sub(1, Ordering::Relaxed);
+                        }
+                    },
+                    Ok(_) => (),
+                    Err(_) => thread::sleep(ACCEPT_INTERVAL),
+                }
+            }
+        })?;
+
+        Ok(ControlServer { requests, clients, stopped })
+    }
+
+    // The commands that came in since the last call, in the order they came in
+    pub fn take_requests(&self) -> Vec<ControlRequest> {
+        self.requests.try_iter().collect()
+    }
+
+    // A JSON-RPC notification to every connected tool, queued without waiting for any of them. Tools that have
+    // stopped reading are disconnected
+    pub fn broadcast(&self, method: &str, params: JsonValue) {
+        let line = format!("{{\"jsonrpc\": \"2.0\", \"method\": {}, \"params\": {}}}", JsonValue::String(method.to_string()).to_json(), params.to_json());
+        self.clients.lock().unwrap().retain(|client| {
+            if !client.authenticated || client.lines.try_send(line.clone()).is_ok() {
+                return true;
+            }
+            let _ = client.stream.shutdown(Shutdown::Both);
+            false
+        });
+    }
+}
+
+impl Drop for ControlServer {
+    // Connected tools are disconnected too, which ends their threads
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+        for client in self.clients.lock().unwrap().drain(..) {
+            let _ = client.stream.shutdown(Shutdown::Both);
+        }
+    }
+}
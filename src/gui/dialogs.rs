@@ -0,0 +1,2452 @@
+use std::collections::{BTreeMap, HashSet};
+use std::path::PathBuf;
+
+use chrono::{DateTime, Local, NaiveDate, TimeZone};
+use git2::{Direction, Repository};
+use iced::widget::{text, Canvas, Column, Row, Space, button, checkbox, image, mouse_area, pick_list, progress_bar, scrollable, slider, svg, text_input};
+use iced::{theme, Alignment, Element, Font, Length};
+use tracing::Level;
+
+use crate::changelog::{Release, format_release_date};
+use crate::backend::{CommitNode, CommitDiff, RangeDiff, DiffFile, DiffSettings, DiffTotals, CONTEXT_LINE_CHOICES, file_patch, group_by_directory, GraphSize, ReferenceComparison, get_recent_branches, ReferenceGroups, ReferenceKind, ReferenceTip, WorktreeInfo, compare_references};
+use crate::config::DisplayConfig;
+use crate::error::Error;
+use crate::hooks::HookOutput;
+use crate::links::{Forge, MessagePart, link_message_line};
+use crate::logging::LogLine;
+use crate::message::{CONVENTIONAL_TYPES, MessageConfig, check_message, clean_message, ruler};
+use crate::rebase::ReorderPlan;
+use crate::remote::{BranchTracking, CloneOptions, RemoteReference, RemoteReferenceState, RemoteRefspecs, fetch_refspec, get_branch_tracking, get_remote_names, get_remote_refspecs, get_upstream_candidates};
+use crate::keymap::Action;
+use crate::settings::{Bookmark, BookmarkColor, COLUMN_SPACING_RANGE, DEFAULT_CONTROL_PORT, NODE_RADIUS_RANGE, NodeClickAction, ROW_SPACING_RANGE, Settings, TEXT_SCALE_RANGE, UiScale};
+use crate::stats::HistoryStats;
+use crate::health::HealthIssue;
+use crate::maintenance::{MaintenanceTask, format_size};
+use crate::preview::{ImageCompareMode, ImagePreview, PreviewImage};
+use crate::status::{FileDiff, StatusEntry, get_file_diff, get_status};
+
+use super::chart::{BarChart, ChangeBar, ChartKind, ROW_HEIGHT};
+use super::cloner::CloneJob;
+use super::control::TOKEN_FILE;
+use super::fetcher::{FetchJob, PushJob};
+use super::loader::TreeLoad;
+use super::maintainer::MaintenanceJob;
+use super::tree::GraphColors;
+use super::worker::JobHandle;
+use super::{Message, Notification, ERROR_COLOR, LINK_COLOR, UNVERIFIED_COLOR, VERIFIED_COLOR, bookmark_color, repository_name};
+
+pub struct WorktreePanel {
+    pub worktrees: Vec<WorktreeInfo>,
+    pub new_name: String,
+    pub new_path: String,
+    pub result: Option<Result<String, String>>,
+}
+
+pub struct CommitComposer {
+    pub subject: String,
+    // Lines after the subject, starting with the blank separator line
+    pub body: Vec<String>,
+    pub kind: Option<&'static str>,
+    pub scope: String,
+    pub config: MessageConfig,
+    pub amend: bool,
+    pub sign: bool,
+    // Commit without running the pre-commit and commit-msg hooks, like `--no-verify`
+    pub skip_hooks: bool,
+    pub hook_outputs: Vec<HookOutput>,
+    pub result: Option<Result<String, String>>,
+}
+
+pub struct CompareDialog {
+    pub references: Vec<String>,
+    pub left: Option<String>,
+    pub right: Option<String>,
+    pub comparison: Option<ReferenceComparison>,
+}
+
+pub struct ExportDialog {
+    pub path: String,
+    pub visible_only: bool,
+    pub result: Option<Result<String, String>>,
+}
+
+pub struct ReleaseTimeline {
+    pub releases: Vec<Release>,
+    // Markdown file the changelog is exported to
+    pub path: String,
+    pub result: Option<Result<String, String>>,
+}
+
+pub struct ArchiveDialog {
+    pub commit: String,
+    pub path: String,
+    pub prefix: String,
+    pub result: Option<Result<String, String>>,
+}
+
+// Offered after a branch label is dropped on another commit
+pub struct DropMenu {
+    pub branch: String,
+    pub target: String,
+    // Local branch at the target commit, which the dropped branch can be merged into
+    pub target_branch: Option<String>,
+}
+
+// Waits for confirmation before a dragged commit rewrites the branch
+pub struct ReorderConfirmation {
+    pub commit: String,
+    pub target: String,
+    pub plan: ReorderPlan,
+}
+
+// Working tree and index changes, with the diff of one file whose lines can be picked for staging
+pub struct StatusPanel {
+    pub entries: Vec<StatusEntry>,
+    pub diff: Option<FileDiff>,
+    // Checked lines of the diff, by hunk and line
+    pub selected_lines: Vec<Vec<bool>>,
+}
+
+// Discarding can't be undone, so it waits for confirmation
+pub struct DiscardConfirmation {
+    pub path: String,
+    pub untracked: bool,
+}
+
+pub struct RestoreConfirmation {
+    pub commit: String,
+    pub path: String,
+    // Whether the file is also staged the way the commit has it
+    pub index: bool,
+}
+
+pub struct BookmarkEditor {
+    pub commit: String,
+    pub label: String,
+    pub color: BookmarkColor,
+    // Whether the commit had a bookmark already, which can then be removed
+    pub existing: bool,
+}
+
+pub struct NoteEditor {
+    pub commit: String,
+    pub lines: Vec<String>,
+    // Whether the commit had a note already, which can then be removed
+    pub existing: bool,
+}
+
+pub struct TrackingDialog {
+    pub branches: Vec<BranchTracking>,
+    pub candidates: Vec<String>,
+    pub remotes: Vec<String>,
+}
+
+impl TrackingDialog {
+    pub fn open(repository: &Repository) -> Result<TrackingDialog, git2::Error> {
+        Ok(TrackingDialog {
+            branches: get_branch_tracking(repository)?,
+            candidates: get_upstream_candidates(repository)?,
+            remotes: get_remote_names(repository)?,
+        })
+    }
+}
+
+// Looks at what a remote has without fetching all of it. `remote` is a configured remote or a URL, which can only
+// be listed
+pub struct RemoteBrowser {
+    pub remotes: Vec<String>,
+    pub remote: String,
+    // The remote the references were listed for
+    pub listed: String,
+    pub references: Vec<RemoteReference>,
+    pub picked: HashSet<String>,
+    pub listing: Option<JobHandle<Vec<RemoteReference>>>,
+    pub fetching: Option<JobHandle<Vec<String>>>,
+    pub result: Option<Result<String, String>>,
+}
+
+impl RemoteBrowser {
+    pub fn open(repository: &Repository) -> Result<RemoteBrowser, git2::Error> {
+        let remotes = get_remote_names(repository)?;
+        Ok(RemoteBrowser {
+            remote: remotes.first().cloned().unwrap_or_default(),
+            remotes,
+            listed: String::new(),
+            references: Vec::new(),
+            picked: HashSet::new(),
+            listing: None,
+            fetching: None,
+            result: None,
+        })
+    }
+
+    pub fn can_fetch(&self) -> bool {
+        self.remotes.contains(&self.listed)
+    }
+
+    // In the order they were listed
+    pub fn refspecs(&self) -> Vec<String> {
+        self.references.iter()
+            .filter(|reference| self.picked.contains(&reference.name))
+            .filter_map(fetch_refspec)
+            .collect()
+    }
+}
+
+// The refspecs of one remote at a time, edited as lines and only written to the config once saved
+pub struct RefspecEditor {
+    pub remotes: Vec<String>,
+    pub remote: String,
+    pub fetch: Vec<String>,
+    pub push: Vec<String>,
+    pub result: Option<Result<String, String>>,
+}
+
+impl RefspecEditor {
+    // None without remotes, since there is nothing to edit then
+    pub fn open(repository: &Repository, remote: Option<String>) -> Result<Option<RefspecEditor>, git2::Error> {
+        let remotes = get_remote_names(repository)?;
+        let Some(remote) = remote.or_else(|| remotes.first().cloned()) else {
+            return Ok(None);
+        };
+        let refspecs = get_remote_refspecs(repository, &remote)?;
+        Ok(Some(RefspecEditor { remotes, remote, fetch: refspecs.fetch, push: refspecs.push, result: None }))
+    }
+
+    pub fn lines_mut(&mut self, direction: Direction) -> &mut Vec<String> {
+        match direction {
+            Direction::Fetch => &mut self.fetch,
+            Direction::Push => &mut self.push,
+        }
+    }
+
+    // Blank lines are left out
+    pub fn refspecs(&self) -> RemoteRefspecs {
+        let lines = |lines: &[String]| lines.iter().map(|line| line.trim().to_string()).filter(|line| !line.is_empty()).collect();
+        RemoteRefspecs { fetch: lines(&self.fetch), push: lines(&self.push) }
+    }
+}
+
+// Offered instead of pushing when the branch and its upstream went separate ways
+pub struct PushDialog {
+    pub branch: String,
+    pub upstream: String,
+    pub ahead: usize,
+    pub behind: usize,
+}
+
+#[derive(Default)]
+pub struct CloneDialog {
+    pub url: String,
+    pub destination: String,
+    pub depth: String,
+    pub single_branch: bool,
+    pub branch: String,
+    pub job: Option<CloneJob>,
+}
+
+impl CloneDialog {
+    pub fn options(&self) -> Result<CloneOptions, Error> {
+        if self.url.trim().is_empty() || self.destination.trim().is_empty() {
+            return Err(Error::from("enter the URL to clone and where to put it"));
+        }
+        let depth = match self.depth.trim() {
+            "" => None,
+            depth => Some(depth.parse::<u32>().ok().filter(|depth| *depth > 0).ok_or(Error::from("the depth must be a positive number"))?),
+        };
+        let branch = match (self.single_branch, self.branch.trim()) {
+            (false, _) => None,
+            (true, "") => return Err(Error::from("enter the branch to clone")),
+            (true, branch) => Some(branch.to_string()),
+        };
+        Ok(CloneOptions { url: self.url.trim().to_string(), destination: PathBuf::from(self.destination.trim()), depth, branch })
+    }
+}
+
+#[derive(Default)]
+pub struct InitDialog {
+    pub path: String,
+    // Empty for `init.defaultBranch`
+    pub branch: String,
+    pub initial_commit: bool,
+}
+
+// Shown in place of the graph when the tab opens on a merge, rebase or the like left halfway done
+pub struct InterruptedOperation {
+    pub operation: &'static str,
+    pub unmerged: Vec<String>,
+    pub error: Option<String>,
+}
+
+pub struct MaintenancePanel {
+    // Of the git directory, in bytes
+    pub size: u64,
+    pub job: Option<MaintenanceJob>,
+    // What each task run so far did, the latest last
+    pub results: Vec<Result<String, String>>,
+}
+
+// Shown for as long as HEAD is detached
+pub struct DetachedHead {
+    pub commit: String,
+    pub previous_branch: Option<String>,
+    pub branch_name: String,
+}
+
+// Opened by right-clicking an untracked file
+pub struct IgnoreMenu {
+    pub path: String,
+    pub patterns: Vec<String>,
+}
+
+// Edits the top level .gitignore line by line
+pub struct GitignoreEditor {
+    pub lines: Vec<String>,
+    // Untracked files the edited rules would hide
+    pub preview: Vec<String>,
+    pub result: Option<Result<String, String>>,
+}
+
+pub struct TagDialog {
+    pub version: String,
+    pub message: String,
+    pub name_template: String,
+    pub branches: Vec<(String, bool)>,
+    // The other repositories open in the workspace, tagged at the branch they have checked out
+    pub repositories: Vec<(PathBuf, bool)>,
+    pub results: Vec<(String, Result<String, String>)>,
+}
+
+// Kept when the panel is closed, so the graph stays filtered
+#[derive(Default)]
+pub struct HistoryFilter {
+    pub authors: Vec<String>,
+    pub author: Option<String>,
+    pub since: String,
+    pub until: String,
+}
+
+// Newest commits of the graph the quick switcher offers besides the branches
+const QUICK_SWITCH_COMMITS: usize = 200;
+
+// Matches the quick switcher shows at once
+const QUICK_SWITCH_RESULTS: usize = 12;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QuickSwitchItem {
+    Branch(String),
+    // ID and summary
+    Commit(String, String),
+}
+
+pub struct QuickSwitcher {
+    pub query: String,
+    // Among the matches shown
+    pub selected: usize,
+    items: Vec<QuickSwitchItem>,
+}
+
+pub fn quick_switcher_input() -> text_input::Id {
+    text_input::Id::new("quick-switcher")
+}
+
+// The letters of the query in the same order anywhere in the candidate, regardless of case. Higher is better, with letters
+// following each other and ones starting a word counting for more
+fn fuzzy_score(query: &str, candidate: &str) -> Option<i32> {
+    let candidate: Vec<char> = candidate.chars().collect();
+    let (mut score, mut position, mut previous) = (0, 0, None);
+    for wanted in query.chars().filter(|c| !c.is_whitespace()) {
+        let found = (position..candidate.len()).find(|&i| candidate[i].to_lowercase().eq(wanted.to_lowercase()))?;
+        score += 1;
+        if previous.is_some_and(|previous| previous + 1 == found) {
+            score += 5;
+        }
+        if found == 0 || !candidate[found - 1].is_alphanumeric() {
+            score += 3;
+        }
+        (position, previous) = (found + 1, Some(found));
+    }
+    Some(score)
+}
+
+impl QuickSwitcher {
+    pub fn open(repository: &Repository, commits: &BTreeMap<String, CommitNode>) -> Result<QuickSwitcher, git2::Error> {
+        let mut items: Vec<QuickSwitchItem> = get_recent_branches(repository)?.into_iter().map(QuickSwitchItem::Branch).collect();
+        let mut recent: Vec<&CommitNode> = commits.values().collect();
+        recent.sort_by_key(|commit| std::cmp::Reverse(commit.time.seconds()));
+        items.extend(recent.into_iter().take(QUICK_SWITCH_COMMITS).map(|commit| QuickSwitchItem::Commit(commit.id.clone(), commit.summary.clone())));
+        Ok(QuickSwitcher { query: String::new(), selected: 0, items })
+    }
+
+    // A commit is also found by the start of its ID
+    fn score(&self, item: &QuickSwitchItem) -> Option<i32> {
+        match item {
+            QuickSwitchItem::Branch(name) => fuzzy_score(&self.query, name),
+            QuickSwitchItem::Commit(id, _) if self.query.len() >= 4 && id.starts_with(self.query.trim()) => Some(i32::MAX),
+            QuickSwitchItem::Commit(_, summary) => fuzzy_score(&self.query, summary),
+        }
+    }
+
+    // Best first, and in the order they were offered when they match as well, so recent branches come before older ones
+    // and branches before commits. Nothing typed yet shows the first ones offered
+    pub fn matches(&self) -> Vec<&QuickSwitchItem> {
+        let mut scored: Vec<(i32, &QuickSwitchItem)> = self.items.iter().filter_map(|item| Some((self.score(item)?, item))).collect();
+        scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+        scored.into_iter().take(QUICK_SWITCH_RESULTS).map(|(_, item)| item).collect()
+    }
+
+    pub fn selected_item(&self) -> Option<QuickSwitchItem> {
+        self.matches().get(self.selected).map(|item| (*item).clone())
+    }
+
+    // Going past either end comes round to the other
+    pub fn move_selection(&mut self, step: isize) {
+        let count = self.matches().len();
+        if count > 0 {
+            self.selected = (self.selected as isize + step).rem_euclid(count as isize) as usize;
+        }
+    }
+}
+
+impl CompareDialog {
+    pub fn update_comparison(&mut self, repository: &Repository) -> Result<(), git2::Error> {
+        self.comparison = match (&self.left, &self.right) {
+            (Some(left), Some(right)) => Some(compare_references(repository, left, right)?),
+            _ => None,
+        };
+        Ok(())
+    }
+}
+
+impl CommitComposer {
+    pub fn new(config: MessageConfig, sign: bool) -> CommitComposer {
+        let mut composer = CommitComposer { subject: String::new(), body: Vec::new(), kind: None, scope: String::new(), config, amend: false, sign, skip_hooks: false, hook_outputs: Vec::new(), result: None };
+        composer.reset();
+        composer
+    }
+
+    // The message as typed, comments included
+    fn raw_message(&self) -> String {
+        let mut message = self.subject.clone();
+        for line in &self.body {
+            message.push('\n');
+            message.push_str(line);
+        }
+        message
+    }
+
+    pub fn message(&self) -> String {
+        clean_message(&self.raw_message())
+    }
+
+    pub fn set_message(&mut self, message: &str) {
+        let mut lines = message.lines().map(String::from);
+        self.subject = lines.next().unwrap_or_default();
+        self.body = lines.collect();
+    }
+
+    // Starts over from the template after a commit was made
+    pub fn reset(&mut self) {
+        self.set_message(&self.config.template.clone().unwrap_or_default());
+        self.kind = None;
+        self.scope.clear();
+        self.amend = false;
+        self.skip_hooks = false;
+    }
+}
+
+impl StatusPanel {
+    pub fn open(repository: &Repository) -> Result<StatusPanel, git2::Error> {
+        Ok(StatusPanel { entries: get_status(repository)?, diff: None, selected_lines: Vec::new() })
+    }
+
+    pub fn select_file(&mut self, repository: &Repository, path: &str, staged: bool) -> Result<(), git2::Error> {
+        let diff = get_file_diff(repository, path, staged)?;
+        self.selected_lines = diff.hunks.iter().map(|hunk| vec![false; hunk.lines.len()]).collect();
+        self.diff = Some(diff);
+        Ok(())
+    }
+
+    // Reads everything again after the index changed, keeping the shown file when it still has changes on that side
+    pub fn refresh(&mut self, repository: &Repository) -> Result<(), git2::Error> {
+        self.entries = get_status(repository)?;
+        match self.diff.take() {
+            Some(diff) => {
+                let still_changed = self.entries.iter().any(|entry| entry.path == diff.path && if diff.staged { entry.staged.is_some() } else { entry.unstaged.is_some() });
+                if still_changed {
+                    self.select_file(repository, &diff.path, diff.staged)?;
+                }
+            },
+            None => self.selected_lines.clear(),
+        }
+        Ok(())
+    }
+}
+
+// Start of the given day in local time, or None unless the text is a YYYY-MM-DD date
+fn parse_day(text: &str) -> Option<i64> {
+    let date = NaiveDate::parse_from_str(text.trim(), "%Y-%m-%d").ok()?;
+    Some(Local.from_local_datetime(&date.and_hms_opt(0, 0, 0)?).earliest()?.timestamp())
+}
+
+impl HistoryFilter {
+    pub fn is_active(&self) -> bool {
+        self.author.is_some() || self.since_time().is_some() || self.until_time().is_some()
+    }
+
+    fn since_time(&self) -> Option<i64> {
+        parse_day(&self.since)
+    }
+
+    // Until is inclusive, so the bound is the start of the following day
+    fn until_time(&self) -> Option<i64> {
+        parse_day(&self.until).map(|time| time + 24 * 60 * 60)
+    }
+
+    // Dates are parsed once up front rather than for every commit
+    pub fn matcher(&self) -> impl Fn(&CommitNode) -> bool + '_ {
+        let (since, until) = (self.since_time(), self.until_time());
+        move |commit| {
+            let time = commit.time.seconds();
+            self.author.as_ref().is_none_or(|author| &commit.author == author)
+                && since.is_none_or(|since| time >= since)
+                && until.is_none_or(|until| time < until)
+        }
+    }
+}
+
+impl TagDialog {
+    pub fn tag_name(&self, branch: &str, repository: &str) -> String {
+        self.name_template.replace("{version}", &self.version).replace("{branch}", branch).replace("{repository}", repository)
+    }
+
+    pub fn has_targets(&self) -> bool {
+        self.branches.iter().any(|(_, selected)| *selected) || self.repositories.iter().any(|(_, selected)| *selected)
+    }
+}
+
+pub fn view_interrupted_operation(interrupted: &InterruptedOperation) -> Element<'_, Message> {
+    Column::with_children({
+        let mut children: Vec<Element<Message>> = Vec::new();
+
+        children.push(text(format!("A {} was left in progress", interrupted.operation)).size(24).into());
+        children.push(text("Finish it or abort it before doing anything else, so nothing gets committed on top of a half done history").size(16).into());
+
+        if !interrupted.unmerged.is_empty() {
+            children.push(text(format!("Unmerged paths, resolve and stage them before continuing: {}", interrupted.unmerged.join(", "))).size(14).into());
+        }
+        if let Some(e) = &interrupted.error {
+            children.push(text(e).size(14).style(ERROR_COLOR).into());
+        }
+
+        // A bisect goes on by marking commits in the graph
+        let resume = match interrupted.operation {
+            "bisect" => button("Continue bisecting").on_press(Message::DismissInterruptedOperation),
+            operation => button(text(format!("Continue {}", operation))).on_press(Message::ContinueOperation),
+        };
+        children.push(Row::with_children(vec![
+            resume.into(),
+            button(text(format!("Abort {}", interrupted.operation))).on_press(Message::AbortOperation).into(),
+            button("Show the graph anyway").on_press(Message::DismissInterruptedOperation).into(),
+        ])
+        .spacing(10)
+        .into());
+
+        children
+    })
+    .spacing(10)
+    .padding(20)
+    .into()
+}
+
+pub fn view_tree_load(loading: &TreeLoad) -> Element<'_, Message> {
+    let (walked, total) = loading.progress();
+    let label = if loading.is_update() {
+        format!("Updating commits: {} new", walked)
+    } else if total == 0 {
+        String::from("Counting commits...")
+    } else {
+        format!("Loading commits: {} / {}", walked, total)
+    };
+
+    Row::with_children(vec![
+        text(label).size(16).into(),
+        progress_bar(0.0..=total.max(1) as f32, walked as f32).width(300).height(15).into(),
+        button("Cancel").on_press(Message::CancelTreeLoad).into(),
+    ])
+    .align_items(Alignment::Center)
+    .spacing(10)
+    .padding(10)
+    .into()
+}
+
+pub fn view_fetch_progress(fetching: &FetchJob) -> Element<'_, Message> {
+    let (remote, received, total) = fetching.progress();
+    let label = if total == 0 { String::from("Fetching: connecting...") } else { format!("Fetching {}: {} / {} objects", remote, received, total) };
+
+    Row::with_children(vec![
+        text(label).size(16).into(),
+        progress_bar(0.0..=total.max(1) as f32, received as f32).width(300).height(15).into(),
+        button("Cancel").on_press(Message::CancelFetch).into(),
+    ])
+    .align_items(Alignment::Center)
+    .spacing(10)
+    .padding(10)
+    .into()
+}
+
+// Cancelling only stops a push that hasn't sent anything yet, so the bar stays until the push says how it ended
+pub fn view_push_progress(push: &PushJob) -> Element<'_, Message> {
+    let (sent, total) = push.progress();
+    let label = match total {
+        _ if push.is_cancelled() => format!("Pushing {}: cancelling...", push.branch),
+        0 => format!("Pushing {}: connecting...", push.branch),
+        total => format!("Pushing {}: {} / {} objects", push.branch, sent, total),
+    };
+
+    Row::with_children(vec![
+        text(label).size(16).into(),
+        progress_bar(0.0..=total.max(1) as f32, sent as f32).width(300).height(15).into(),
+        button("Cancel").on_press_maybe((!push.is_cancelled()).then_some(Message::CancelPushing)).into(),
+    ])
+    .align_items(Alignment::Center)
+    .spacing(10)
+    .padding(10)
+    .into()
+}
+
+// Enter takes the highlighted match, moved with the arrow keys
+pub fn view_quick_switcher<'a>(switcher: &'a QuickSwitcher, display: &DisplayConfig) -> Element<'a, Message> {
+    Column::with_children({
+        let mut children: Vec<Element<Message>> = Vec::new();
+
+        children.push(Row::with_children(vec![
+            text_input("Branch or commit", &switcher.query)
+                .id(quick_switcher_input())
+                .on_input(Message::QuickSwitchQueryChanged)
+                .on_submit(Message::QuickSwitchSubmit)
+                .size(16)
+                .into(),
+            button("Close").on_press(Message::ToggleQuickSwitcher).into(),
+        ])
+        .align_items(Alignment::Center)
+        .spacing(10)
+        .into());
+
+        let matches = switcher.matches();
+        if matches.is_empty() {
+            children.push(text("Nothing matches").size(14).into());
+        }
+        for (i, item) in matches.into_iter().enumerate() {
+            let label = match item {
+                QuickSwitchItem::Branch(name) => format!("Branch {}", name),
+                QuickSwitchItem::Commit(id, summary) => format!("{} {}", display.abbreviate(id), summary),
+            };
+            let style = if i == switcher.selected { theme::Button::Primary } else { theme::Button::Text };
+            children.push(button(text(label).size(14)).style(style).width(Length::Fill).on_press(Message::QuickSwitchPick(i)).into());
+        }
+
+        children
+    })
+    .spacing(5)
+    .padding(10)
+    .into()
+}
+
+pub fn view_history_filter(filter: &HistoryFilter) -> Element<'_, Message> {
+    Column::with_children({
+        let mut children: Vec<Element<Message>> = Vec::new();
+
+        children.push(Row::with_children(vec![
+            pick_list(&filter.authors[..], filter.author.clone(), Message::FilterAuthorSelected).placeholder("Any author").into(),
+            text_input("Since (YYYY-MM-DD)", &filter.since).on_input(Message::FilterSinceChanged).width(200).into(),
+            text_input("Until (YYYY-MM-DD)", &filter.until).on_input(Message::FilterUntilChanged).width(200).into(),
+            button("Clear").on_press(Message::ClearFilters).into(),
+            button("Close").on_press(Message::ToggleFilterPanel).into(),
+        ])
+        .align_items(Alignment::Center)
+        .spacing(10)
+        .into());
+
+        for (label, value, parsed) in [("Since", &filter.since, filter.since_time()), ("Until", &filter.until, filter.until_time())] {
+            if !value.trim().is_empty() && parsed.is_none() {
+                children.push(text(format!("{} is not a YYYY-MM-DD date and is ignored", label)).size(16).style(ERROR_COLOR).into());
+            }
+        }
+
+        children
+    })
+    .spacing(5)
+    .padding(10)
+    .into()
+}
+
+pub fn view_drop_menu<'a>(menu: &'a DropMenu, display: &DisplayConfig) -> Element<'a, Message> {
+    Row::with_children({
+        let mut children: Vec<Element<Message>> = Vec::new();
+        let target = menu.target_branch.clone().unwrap_or(display.abbreviate(&menu.target).to_string());
+        children.push(text(format!("{} dropped on {}", menu.branch, target)).size(18).into());
+        children.push(button(text(format!("Rebase {} onto {}", menu.branch, target))).on_press(Message::RebaseDroppedBranch).into());
+        if let Some(target_branch) = &menu.target_branch {
+            children.push(button(text(format!("Merge {} into {}", menu.branch, target_branch))).on_press(Message::MergeDroppedBranch).into());
+        }
+        children.push(button("Cancel").on_press(Message::CloseDropMenu).into());
+        children
+    })
+    .align_items(Alignment::Center)
+    .spacing(10)
+    .padding(10)
+    .into()
+}
+
+pub fn view_reorder_confirmation<'a>(confirmation: &'a ReorderConfirmation, display: &DisplayConfig) -> Element<'a, Message> {
+    Row::with_children(vec![
+        text(format!("Move {} onto {}, replaying {} commits of {}", display.abbreviate(&confirmation.commit), display.abbreviate(&confirmation.target),
+            confirmation.plan.commits.len(), confirmation.plan.branch)).size(18).into(),
+        button("Apply").on_press(Message::ApplyReorder).into(),
+        button("Cancel").on_press(Message::CancelReorder).into(),
+    ])
+    .align_items(Alignment::Center)
+    .spacing(10)
+    .padding(10)
+    .into()
+}
+
+pub fn view_discard_confirmation(confirmation: &DiscardConfirmation) -> Element<'_, Message> {
+    let (question, action) = if confirmation.untracked {
+        (format!("Delete the untracked file {}?", confirmation.path), "Delete")
+    } else {
+        (format!("Discard all changes to {}, restoring it from HEAD?", confirmation.path), "Discard")
+    };
+    Row::with_children(vec![
+        text(question).size(18).into(),
+        text("This can't be undone").size(14).style(ERROR_COLOR).into(),
+        button(action).on_press(Message::ConfirmDiscard).into(),
+        button("Cancel").on_press(Message::CancelDiscard).into(),
+    ])
+    .align_items(Alignment::Center)
+    .spacing(10)
+    .padding(10)
+    .into()
+}
+
+pub fn view_restore_confirmation<'a>(confirmation: &'a RestoreConfirmation, display: &DisplayConfig) -> Element<'a, Message> {
+    Row::with_children(vec![
+        text(format!("Restore {} as it is in {}?", confirmation.path, display.abbreviate(&confirmation.commit))).size(18).into(),
+        text("Changes to it in the working tree are lost").size(14).style(ERROR_COLOR).into(),
+        checkbox("Stage it too", confirmation.index).on_toggle(Message::RestoreToIndexToggled).size(16).text_size(14).into(),
+        button("Restore").on_press(Message::ConfirmRestore).into(),
+        button("Cancel").on_press(Message::CancelRestore).into(),
+    ])
+    .align_items(Alignment::Center)
+    .spacing(10)
+    .padding(10)
+    .into()
+}
+
+pub fn view_bookmark_editor<'a>(editor: &'a BookmarkEditor, display: &DisplayConfig) -> Element<'a, Message> {
+    Row::with_children({
+        let mut children: Vec<Element<Message>> = vec![
+            text(format!("Bookmark {}", display.abbreviate(&editor.commit))).size(18).into(),
+            text_input("Label", &editor.label).on_input(Message::BookmarkLabelChanged).on_submit(Message::SaveBookmark).width(200).into(),
+            pick_list(&BookmarkColor::ALL[..], Some(editor.color), Message::BookmarkColorSelected).into(),
+            button("Save").on_press(Message::SaveBookmark).into(),
+        ];
+        if editor.existing {
+            children.push(button("Remove").on_press(Message::RemoveBookmark(editor.commit.clone())).into());
+        }
+        children.push(button("Cancel").on_press(Message::CancelBookmark).into());
+        children
+    })
+    .align_items(Alignment::Center)
+    .spacing(10)
+    .padding(10)
+    .into()
+}
+
+// Bookmarks of commits that aren't in the graph, like ones the filters hide, are listed all the same
+pub fn view_bookmark_list<'a>(bookmarks: &[Bookmark], display: &DisplayConfig) -> Element<'a, Message> {
+    scrollable(Column::with_children({
+        let mut children: Vec<Element<Message>> = Vec::new();
+
+        children.push(Row::with_children(vec![
+            text("Bookmarks").size(24).into(),
+            button("Close").on_press(Message::ToggleBookmarkList).into(),
+        ])
+        .align_items(Alignment::Center)
+        .spacing(10)
+        .into());
+
+        if bookmarks.is_empty() {
+            children.push(text("Bookmark a commit from its details").size(14).into());
+        }
+        for bookmark in bookmarks {
+            children.push(Row::with_children(vec![
+                button(text(&bookmark.label).size(16).style(bookmark_color(bookmark.color)))
+                    .style(theme::Button::Text)
+                    .padding(0)
+                    .width(Length::Fill)
+                    .on_press(Message::JumpToCommit(bookmark.commit.clone()))
+                    .into(),
+                text(display.abbreviate(&bookmark.commit)).size(12).into(),
+                button(text("Edit").size(14)).on_press(Message::EditBookmark(bookmark.commit.clone())).into(),
+            ])
+            .align_items(Alignment::Center)
+            .spacing(5)
+            .into());
+        }
+
+        children
+    })
+    .spacing(5)
+    .padding(10))
+    .width(300)
+    .height(Length::Fill)
+    .into()
+}
+
+pub fn view_note_editor<'a>(editor: &'a NoteEditor, display: &DisplayConfig) -> Element<'a, Message> {
+    Column::with_children({
+        let mut children: Vec<Element<Message>> = vec![text(format!("Note of {}", display.abbreviate(&editor.commit))).size(18).into()];
+
+        for (i, line) in editor.lines.iter().enumerate() {
+            children.push(Row::with_children(vec![
+                text_input("", line)
+                    .on_input(move |line| Message::NoteLineChanged(i, line))
+                    .on_submit(Message::InsertNoteLine(i + 1))
+                    .size(16)
+                    .width(COMPOSER_WIDTH)
+                    .into(),
+                button("Remove").on_press(Message::RemoveNoteLine(i)).into(),
+            ])
+            .align_items(Alignment::Center)
+            .spacing(10)
+            .into());
+        }
+
+        children.push(Row::with_children({
+            let mut children: Vec<Element<Message>> = vec![
+                button("Add line").on_press(Message::InsertNoteLine(editor.lines.len())).into(),
+                button("Save").on_press(Message::SaveNote).into(),
+            ];
+            if editor.existing {
+                children.push(button("Remove note").on_press(Message::RemoveNote).into());
+            }
+            children.push(button("Cancel").on_press(Message::CancelNote).into());
+            children
+        })
+        .spacing(10)
+        .into());
+
+        children
+    })
+    .spacing(5)
+    .padding(10)
+    .into()
+}
+
+pub fn view_ignore_menu(menu: &IgnoreMenu) -> Element<'_, Message> {
+    Row::with_children({
+        let mut children: Vec<Element<Message>> = vec![text(format!("Ignore {} with", menu.path)).size(18).into()];
+        for pattern in &menu.patterns {
+            children.push(button(text(pattern)).on_press(Message::IgnorePattern(pattern.clone())).into());
+        }
+        children.push(button("Cancel").on_press(Message::CloseIgnoreMenu).into());
+        children
+    })
+    .align_items(Alignment::Center)
+    .spacing(10)
+    .padding(10)
+    .into()
+}
+
+// Room for a 72 column line in the monospaced font
+const COMPOSER_WIDTH: u16 = 720;
+
+// Only the first few hidden files are listed, the rest are counted
+const IGNORE_PREVIEW_LENGTH: usize = 10;
+
+pub fn view_gitignore_editor(editor: &GitignoreEditor) -> Element<'_, Message> {
+    Column::with_children({
+        let mut children: Vec<Element<Message>> = Vec::new();
+
+        children.push(Row::with_children(vec![
+            text(".gitignore").size(24).into(),
+            button("Add line").on_press(Message::AddGitignoreLine).into(),
+            button("Save").on_press(Message::SaveGitignore).into(),
+            button("Close").on_press(Message::CloseGitignoreEditor).into(),
+        ])
+        .align_items(Alignment::Center)
+        .spacing(10)
+        .into());
+
+        for (i, line) in editor.lines.iter().enumerate() {
+            children.push(Row::with_children(vec![
+                text_input("pattern", line).on_input(move |line| Message::GitignoreLineChanged(i, line)).font(Font::MONOSPACE).into(),
+                button("Remove").on_press(Message::RemoveGitignoreLine(i)).into(),
+            ])
+            .align_items(Alignment::Center)
+            .spacing(10)
+            .into());
+        }
+
+        if editor.preview.is_empty() {
+            children.push(text("No untracked files would be hidden").size(14).into());
+        } else {
+            children.push(text(format!("Would hide {} untracked files:", editor.preview.len())).size(14).into());
+            for path in editor.preview.iter().take(IGNORE_PREVIEW_LENGTH) {
+                children.push(text(path).size(14).into());
+            }
+            if editor.preview.len() > IGNORE_PREVIEW_LENGTH {
+                children.push(text(format!("and {} more", editor.preview.len() - IGNORE_PREVIEW_LENGTH)).size(14).into());
+            }
+        }
+
+        match &editor.result {
+            Some(Ok(message)) => children.push(text(message).size(16).into()),
+            Some(Err(e)) => children.push(text(format!("Saving failed: {}", e)).size(16).style(ERROR_COLOR).into()),
+            None => (),
+        }
+
+        children
+    })
+    .spacing(5)
+    .padding(10)
+    .width(500)
+    .into()
+}
+
+pub fn view_detached_banner<'a>(detached: &'a DetachedHead, display: &DisplayConfig) -> Element<'a, Message> {
+    Row::with_children({
+        let mut children: Vec<Element<Message>> = vec![
+            text(format!("HEAD is detached at {}, so new commits won't be on any branch", display.abbreviate(&detached.commit))).size(18).style(UNVERIFIED_COLOR).into(),
+            text_input("New branch", &detached.branch_name).on_input(Message::DetachedBranchNameChanged).on_submit(Message::CreateBranchAtHead).width(200).into(),
+        ];
+        let mut create = button("Create branch here");
+        if !detached.branch_name.trim().is_empty() {
+            create = create.on_press(Message::CreateBranchAtHead);
+        }
+        children.push(create.into());
+        if let Some(branch) = &detached.previous_branch {
+            children.push(button(text(format!("Return to {}", branch))).on_press(Message::ReturnToBranch(branch.clone())).into());
+        }
+        children
+    })
+    .align_items(Alignment::Center)
+    .spacing(10)
+    .padding(10)
+    .into()
+}
+
+pub fn view_tracking_dialog(dialog: &TrackingDialog) -> Element<'_, Message> {
+    Column::with_children({
+        let mut children: Vec<Element<Message>> = Vec::new();
+
+        children.push(Row::with_children(vec![
+            text("Upstream branches").size(24).into(),
+            button("Close").on_press(Message::CloseTrackingDialog).into(),
+        ])
+        .align_items(Alignment::Center)
+        .spacing(10)
+        .into());
+
+        for branch in &dialog.branches {
+            let status = match (&branch.upstream, branch.ahead_behind) {
+                (Some(upstream), Some((0, 0))) => format!("tracks {}, up to date", upstream),
+                (Some(upstream), Some((ahead, behind))) => format!("tracks {}, {} ahead, {} behind", upstream, ahead, behind),
+                (Some(upstream), None) => format!("pushes to {}, not pushed yet", upstream),
+                (None, _) => String::from("no upstream"),
+            };
+            let name = branch.name.clone();
+            let push_name = branch.name.clone();
+            let selected = branch.upstream.clone().filter(|_| branch.fetched);
+
+            let mut row: Vec<Element<Message>> = vec![
+                text(&branch.name).size(16).width(150).into(),
+                text(status).size(14).width(300).into(),
+                pick_list(&dialog.candidates[..], selected, move |upstream| Message::UpstreamSelected(name.clone(), upstream)).placeholder("Track").into(),
+                pick_list(&dialog.remotes[..], None::<String>, move |remote| Message::PushTargetSelected(push_name.clone(), remote)).placeholder("Push to remote").into(),
+            ];
+            if branch.upstream.is_some() {
+                row.push(button("Unset").on_press(Message::UnsetUpstream(branch.name.clone())).into());
+            }
+            children.push(Row::with_children(row).align_items(Alignment::Center).spacing(10).into());
+        }
+
+        children
+    })
+    .spacing(5)
+    .padding(10)
+    .into()
+}
+
+pub fn view_remote_browser(browser: &RemoteBrowser) -> Element<'_, Message> {
+    Column::with_children({
+        let mut children: Vec<Element<Message>> = Vec::new();
+
+        children.push(Row::with_children(vec![
+            text("Remote references").size(24).into(),
+            button("Close").on_press(Message::CloseRemoteBrowser).into(),
+        ])
+        .align_items(Alignment::Center)
+        .spacing(10)
+        .into());
+
+        let selected = Some(browser.remote.clone()).filter(|remote| browser.remotes.contains(remote));
+        let idle = browser.listing.is_none() && browser.fetching.is_none();
+        let mut list = button("List");
+        if idle && !browser.remote.is_empty() {
+            list = list.on_press(Message::ListRemoteReferences);
+        }
+        children.push(Row::with_children(vec![
+            pick_list(&browser.remotes[..], selected, Message::RemoteBrowserRemoteChanged).placeholder("Remote").into(),
+            text_input("or a URL", &browser.remote)
+                .on_input(Message::RemoteBrowserRemoteChanged)
+                .on_submit(Message::ListRemoteReferences)
+                .size(16)
+                .width(400)
+                .into(),
+            list.into(),
+        ])
+        .align_items(Alignment::Center)
+        .spacing(10)
+        .into());
+
+        if browser.listing.is_some() {
+            children.push(text(format!("Listing {}...", browser.remote)).size(14).into());
+        }
+
+        if !browser.references.is_empty() {
+            children.push(scrollable(Column::with_children(browser.references.iter().map(|reference| {
+                let state = match reference.state {
+                    RemoteReferenceState::New => String::from("not fetched yet"),
+                    RemoteReferenceState::UpToDate => String::from("up to date"),
+                    RemoteReferenceState::Ahead(Some(ahead)) => format!("{} ahead", ahead),
+                    RemoteReferenceState::Ahead(None) => String::from("ahead"),
+                    RemoteReferenceState::Behind(behind) => format!("{} behind", behind),
+                    RemoteReferenceState::Diverged(ahead, behind) => format!("{} ahead, {} behind", ahead, behind),
+                };
+                let name = reference.name.clone();
+                let mut row: Vec<Element<Message>> = Vec::new();
+                if browser.can_fetch() {
+                    row.push(checkbox("", browser.picked.contains(&reference.name)).on_toggle(move |picked| Message::RemoteReferenceToggled(name.clone(), picked)).into());
+                }
+                row.push(text(&reference.name).size(14).width(350).into());
+                row.push(text(&reference.id.to_string()[..7]).font(Font::MONOSPACE).size(14).width(80).into());
+                row.push(text(state).size(14).into());
+                Row::with_children(row).align_items(Alignment::Center).spacing(10).into()
+            }).collect::<Vec<Element<Message>>>()).spacing(2)).height(300).into());
+
+            if browser.can_fetch() {
+                let refspecs = browser.refspecs();
+                for refspec in &refspecs {
+                    children.push(text(refspec).font(Font::MONOSPACE).size(12).into());
+                }
+                let mut fetch = button(text(format!("Fetch {} selected", refspecs.len())));
+                if idle && !refspecs.is_empty() {
+                    fetch = fetch.on_press(Message::FetchRemoteReferences);
+                }
+                children.push(fetch.into());
+            } else {
+                children.push(text("Add the URL as a remote to fetch from it").size(14).into());
+            }
+        }
+
+        if browser.fetching.is_some() {
+            children.push(text("Fetching...").size(14).into());
+        }
+        match &browser.result {
+            Some(Ok(message)) => children.push(text(message).size(14).into()),
+            Some(Err(e)) => children.push(text(e).size(14).style(ERROR_COLOR).into()),
+            None => {},
+        }
+
+        children
+    })
+    .spacing(5)
+    .padding(10)
+    .into()
+}
+
+pub fn view_refspec_editor(editor: &RefspecEditor) -> Element<'_, Message> {
+    Column::with_children({
+        let mut children: Vec<Element<Message>> = Vec::new();
+
+        children.push(Row::with_children(vec![
+            text("Refspecs of").size(24).into(),
+            pick_list(&editor.remotes[..], Some(editor.remote.clone()), Message::RefspecRemoteSelected).into(),
+            button("Close").on_press(Message::CloseRefspecEditor).into(),
+        ])
+        .align_items(Alignment::Center)
+        .spacing(10)
+        .into());
+
+        for (direction, label, lines) in [(Direction::Fetch, "Fetch", &editor.fetch), (Direction::Push, "Push", &editor.push)] {
+            children.push(text(label).size(18).into());
+            for (i, line) in lines.iter().enumerate() {
+                children.push(Row::with_children(vec![
+                    text_input("+refs/heads/*:refs/remotes/origin/*", line)
+                        .on_input(move |line| Message::RefspecChanged(direction, i, line))
+                        .font(Font::MONOSPACE)
+                        .size(14)
+                        .width(500)
+                        .into(),
+                    button("Remove").on_press(Message::RemoveRefspec(direction, i)).into(),
+                ])
+                .align_items(Alignment::Center)
+                .spacing(10)
+                .into());
+            }
+            children.push(button(text(format!("Add {} refspec", label.to_lowercase()))).on_press(Message::AddRefspec(direction)).into());
+        }
+
+        children.push(Row::with_children(vec![
+            // How GitHub publishes pull requests, which fetching then shows as remote branches
+            button("Add pull requests").on_press(Message::AddPullRequestRefspec).into(),
+            button("Save").on_press(Message::SaveRefspecs(false)).into(),
+            button("Save and fetch").on_press(Message::SaveRefspecs(true)).into(),
+        ])
+        .spacing(10)
+        .into());
+
+        match &editor.result {
+            Some(Ok(message)) => children.push(text(message).size(14).into()),
+            Some(Err(e)) => children.push(text(e).size(14).style(ERROR_COLOR).into()),
+            None => {},
+        }
+
+        children
+    })
+    .spacing(5)
+    .padding(10)
+    .into()
+}
+
+pub fn view_push_dialog(dialog: &PushDialog) -> Element<'_, Message> {
+    Column::with_children(vec![
+        text(format!("{0} and {1} have diverged: {1} has {2} commits that aren't on {0}, which has {3} commits that aren't on {1}",
+            dialog.branch, dialog.upstream, dialog.behind, dialog.ahead)).size(18).into(),
+        text("Pushing normally would be rejected; forcing replaces the remote commits with yours").size(14).into(),
+        Row::with_children(vec![
+            button(text(format!("Rebase onto {} first", dialog.upstream))).on_press(Message::RebaseBeforePush).into(),
+            button("Force push with lease").on_press(Message::ForcePush).into(),
+            button("Cancel").on_press(Message::CancelPush).into(),
+        ])
+        .align_items(Alignment::Center)
+        .spacing(10)
+        .into(),
+    ])
+    .spacing(5)
+    .padding(10)
+    .into()
+}
+
+// `recording` is the action waiting for a key to be pressed for its shortcut
+// Newest first
+pub fn view_notification_drawer(log: &[(DateTime<Local>, Notification)]) -> Element<'_, Message> {
+    Column::with_children({
+        let mut children: Vec<Element<Message>> = Vec::new();
+
+        children.push(Row::with_children(vec![
+            text("Notifications").size(18).into(),
+            button("Clear").on_press(Message::ClearNotifications).into(),
+            button("Close").on_press(Message::ToggleNotificationDrawer).into(),
+        ])
+        .align_items(Alignment::Center)
+        .spacing(10)
+        .into());
+
+        if log.is_empty() {
+            children.push(text("Nothing has happened yet").size(14).into());
+        }
+        children.push(scrollable(Column::with_children(log.iter().rev().map(|(at, notification)| {
+            let (message, color) = match notification {
+                Notification::Info(message) => (message, None),
+                Notification::Failure(message) => (message, Some(ERROR_COLOR)),
+            };
+            let message = match color {
+                Some(color) => text(message).size(14).style(color),
+                None => text(message).size(14),
+            };
+            Row::with_children(vec![
+                text(at.format("%H:%M:%S")).size(14).width(70).into(),
+                message.into(),
+            ])
+            .spacing(10)
+            .into()
+        }).collect::<Vec<Element<Message>>>())
+        .spacing(2))
+        .height(Length::Fixed(200.0))
+        .into());
+
+        children
+    })
+    .spacing(5)
+    .padding(10)
+    .into()
+}
+
+// Newest first, like the notifications
+pub fn view_debug_console(lines: Vec<LogLine>) -> Element<'static, Message> {
+    Column::with_children({
+        let mut children: Vec<Element<Message>> = vec![
+            Row::with_children(vec![
+                text("Debug console").size(18).into(),
+                button("Clear").on_press(Message::ClearDebugConsole).into(),
+                button("Close").on_press(Message::ToggleDebugConsole).into(),
+            ])
+            .align_items(Alignment::Center)
+            .spacing(10)
+            .into(),
+        ];
+
+        if lines.is_empty() {
+            children.push(text("Nothing has been logged yet").size(14).into());
+        }
+        children.push(scrollable(Column::with_children(lines.into_iter().rev().map(|line| {
+            let level = text(line.level).size(14).width(50).font(Font::MONOSPACE);
+            let level = match line.level {
+                Level::ERROR | Level::WARN => level.style(ERROR_COLOR),
+                _ => level,
+            };
+            let duration = line.duration.map(|duration| format!("{:.1} ms", duration.as_secs_f64() * 1000.0)).unwrap_or_default();
+            Row::with_children(vec![
+                text(line.at.format("%H:%M:%S%.3f")).size(14).width(100).font(Font::MONOSPACE).into(),
+                level.into(),
+                text(duration).size(14).width(80).font(Font::MONOSPACE).into(),
+                text(line.message).size(14).font(Font::MONOSPACE).into(),
+            ])
+            .spacing(10)
+            .into()
+        }).collect::<Vec<Element<Message>>>())
+        .spacing(2))
+        .height(Length::Fixed(250.0))
+        .into());
+
+        children
+    })
+    .spacing(5)
+    .padding(10)
+    .into()
+}
+
+fn view_size_slider<'a>(label: &'a str, (min, max): (f32, f32), value: f32, step: f32, shown: String, change: impl Fn(f32) -> GraphSize + 'a) -> Element<'a, Message> {
+    Row::with_children(vec![
+        text(label).size(16).width(220).into(),
+        slider(min..=max, value, move |value| Message::GraphSizeChanged(change(value))).step(step).on_release(Message::GraphSizeSettled).width(300).into(),
+        text(shown).size(16).into(),
+    ])
+    .align_items(Alignment::Center)
+    .spacing(10)
+    .into()
+}
+
+pub fn view_settings(settings: &Settings, recording: Option<Action>) -> Element<'_, Message> {
+    Column::with_children({
+        let mut children: Vec<Element<Message>> = vec![
+            Row::with_children(vec![
+                text("UI scale").size(16).into(),
+                pick_list(&UiScale::ALL[..], Some(settings.ui_scale), Message::UiScaleSelected).into(),
+                checkbox("High contrast", settings.high_contrast).on_toggle(Message::HighContrastToggled).into(),
+                button("Close").on_press(Message::CloseSettings).into(),
+            ])
+            .align_items(Alignment::Center)
+            .spacing(10)
+            .into(),
+            Row::with_children(vec![
+                text("Double click a commit").size(16).into(),
+                pick_list(&NodeClickAction::ALL[..], Some(settings.double_click), Message::DoubleClickSelected).into(),
+                text("Middle click a commit").size(16).into(),
+                pick_list(&NodeClickAction::ALL[..], Some(settings.middle_click), Message::MiddleClickSelected).into(),
+            ])
+            .align_items(Alignment::Center)
+            .spacing(10)
+            .into(),
+            checkbox("Keep the viewport, filters and bookmarks in the repository's .git/gitui-state.json", settings.repository_state).on_toggle(Message::RepositoryStateToggled).into(),
+            checkbox("Show frame rate, draw and layout times over the graph", settings.performance_overlay).on_toggle(Message::PerformanceOverlayToggled).into(),
+            checkbox(format!("Accept commands from editors on 127.0.0.1:{}, which authenticate with the {} file in the settings directory", settings.control_port.unwrap_or(DEFAULT_CONTROL_PORT), TOKEN_FILE), settings.control_port.is_some()).on_toggle(Message::ControlServerToggled).into(),
+            text("Graph").size(18).into(),
+        ];
+        let size = settings.graph_size;
+        children.push(view_size_slider("Node size", NODE_RADIUS_RANGE, size.node_radius, 1.0, format!("{}px", size.node_radius),
+            move |node_radius| GraphSize { node_radius, ..size }));
+        children.push(view_size_slider("Commit spacing", COLUMN_SPACING_RANGE, size.column_spacing, 0.1, format!("{:.1}x", size.column_spacing),
+            move |column_spacing| GraphSize { column_spacing, ..size }));
+        children.push(view_size_slider("Branch spacing", ROW_SPACING_RANGE, size.row_spacing, 0.1, format!("{:.1}x", size.row_spacing),
+            move |row_spacing| GraphSize { row_spacing, ..size }));
+        children.push(view_size_slider("Text size", TEXT_SCALE_RANGE, size.text_scale, 0.05, format!("{:.0}%", size.text_scale * 100.0),
+            move |text_scale| GraphSize { text_scale, ..size }));
+
+        children.push(text("Keyboard shortcuts").size(18).into());
+        for (action, chord) in settings.keymap.bindings() {
+            let shortcut = match (recording == Some(action), chord) {
+                (true, _) => String::from("Press a key, or Escape to keep the current one"),
+                (false, Some(chord)) => chord.to_string(),
+                (false, None) => String::from("None"),
+            };
+            children.push(Row::with_children(vec![
+                text(action).size(16).width(220).into(),
+                text(shortcut).size(16).width(320).into(),
+                button("Change").on_press(Message::RecordKeyBinding(action)).into(),
+                button("Clear").on_press(Message::ClearKeyBinding(action)).into(),
+            ])
+            .align_items(Alignment::Center)
+            .spacing(10)
+            .into());
+        }
+
+        children
+    })
+    .spacing(5)
+    .padding(10)
+    .into()
+}
+
+pub fn view_init_dialog(dialog: &InitDialog) -> Element<'_, Message> {
+    Row::with_children(vec![
+        text_input("Directory", &dialog.path).on_input(Message::InitPathChanged).on_submit(Message::InitRepository).width(300).into(),
+        text_input("Default branch", &dialog.branch).on_input(Message::InitBranchChanged).on_submit(Message::InitRepository).width(200).into(),
+        checkbox("Initial commit", dialog.initial_commit).on_toggle(Message::InitialCommitToggled).into(),
+        button("Create").on_press(Message::InitRepository).into(),
+        button("Close").on_press(Message::CloseInitDialog).into(),
+    ])
+    .align_items(Alignment::Center)
+    .spacing(10)
+    .padding(10)
+    .into()
+}
+
+pub fn view_clone_dialog(dialog: &CloneDialog) -> Element<'_, Message> {
+    Column::with_children({
+        let mut children: Vec<Element<Message>> = Vec::new();
+
+        children.push(Row::with_children(vec![
+            text_input("URL", &dialog.url).on_input(Message::CloneUrlChanged).width(400).into(),
+            text_input("Destination", &dialog.destination).on_input(Message::CloneDestinationChanged).on_submit(Message::StartClone).width(300).into(),
+            text_input("Depth", &dialog.depth).on_input(Message::CloneDepthChanged).width(80).into(),
+        ])
+        .align_items(Alignment::Center)
+        .spacing(10)
+        .into());
+
+        children.push(Row::with_children({
+            let mut children: Vec<Element<Message>> = vec![checkbox("Single branch", dialog.single_branch).on_toggle(Message::CloneSingleBranchToggled).into()];
+            if dialog.single_branch {
+                children.push(text_input("Branch", &dialog.branch).on_input(Message::CloneBranchChanged).width(200).into());
+            }
+            match &dialog.job {
+                Some(job) => {
+                    let (received, total) = job.progress();
+                    let label = if total == 0 { String::from("Connecting...") } else { format!("Receiving objects: {} / {}", received, total) };
+                    children.push(text(label).size(16).into());
+                    children.push(progress_bar(0.0..=total.max(1) as f32, received as f32).width(300).height(15).into());
+                    children.push(button("Cancel").on_press(Message::CancelClone).into());
+                },
+                None => {
+                    children.push(button("Clone").on_press(Message::StartClone).into());
+                    children.push(button("Close").on_press(Message::CloseCloneDialog).into());
+                },
+            }
+            children
+        })
+        .align_items(Alignment::Center)
+        .spacing(10)
+        .into());
+
+        children
+    })
+    .spacing(5)
+    .padding(10)
+    .into()
+}
+
+pub fn view_tag_dialog(dialog: &TagDialog) -> Element<'_, Message> {
+    Column::with_children({
+        let mut children: Vec<Element<Message>> = Vec::new();
+
+        children.push(Row::with_children(vec![
+            text_input("Version", &dialog.version).on_input(Message::TagVersionChanged).width(200).into(),
+            text_input("Tag name", &dialog.name_template).on_input(Message::TagTemplateChanged).width(250).into(),
+            text_input("Annotation message", &dialog.message).on_input(Message::TagMessageChanged).width(400).into(),
+        ])
+        .spacing(10)
+        .into());
+
+        children.push(Row::with_children({
+            dialog.branches.iter().enumerate().map(|(i, (branch, selected))| {
+                checkbox(branch, *selected).on_toggle(move |selected| Message::TagBranchToggled(i, selected)).into()
+            }).collect::<Vec<Element<Message>>>()
+        })
+        .spacing(10)
+        .into());
+
+        if !dialog.repositories.is_empty() {
+            children.push(Row::with_children({
+                dialog.repositories.iter().enumerate().map(|(i, (path, selected))| {
+                    checkbox(repository_name(path), *selected).on_toggle(move |selected| Message::TagRepositoryToggled(i, selected)).into()
+                }).collect::<Vec<Element<Message>>>()
+            })
+            .spacing(10)
+            .into());
+        }
+
+        children.push(Row::with_children({
+            let mut children: Vec<Element<Message>> = Vec::new();
+            let can_create = !dialog.version.is_empty() && dialog.has_targets();
+            let mut create = button("Create tags");
+            if can_create {
+                create = create.on_press(Message::CreateTags);
+            }
+            children.push(create.into());
+            children.push(button("Close").on_press(Message::CloseTagDialog).into());
+            children
+        })
+        .spacing(10)
+        .into());
+
+        for (target, result) in &dialog.results {
+            let line = match result {
+                Ok(tag_name) => format!("{}: created {}", target, tag_name),
+                Err(e) => format!("{}: failed ({})", target, e),
+            };
+            children.push(text(line).size(16).into());
+        }
+
+        children
+    })
+    .spacing(5)
+    .padding(10)
+    .into()
+}
+
+pub fn view_commit_composer<'a>(composer: &'a CommitComposer, display: &DisplayConfig) -> Element<'a, Message> {
+    Column::with_children({
+        let mut children: Vec<Element<Message>> = Vec::new();
+
+        children.push(Row::with_children(vec![
+            pick_list(&CONVENTIONAL_TYPES[..], composer.kind, Message::CommitTypeSelected).placeholder("Type").into(),
+            text_input("Scope", &composer.scope).on_input(Message::CommitScopeChanged).width(150).into(),
+        ])
+        .align_items(Alignment::Center)
+        .spacing(10)
+        .into());
+
+        // Monospaced, so the ruler lines up with the columns of the message
+        children.push(Row::with_children(vec![text(ruler(&composer.config)).size(16).font(Font::MONOSPACE).into()]).padding([0, 6]).into());
+
+        let length = composer.subject.chars().count();
+        children.push(Row::with_children(vec![
+            text_input("Subject", &composer.subject)
+                .on_input(Message::CommitSubjectChanged)
+                .on_submit(Message::InsertCommitBodyLine(0))
+                .font(Font::MONOSPACE)
+                .size(16)
+                .width(COMPOSER_WIDTH)
+                .into(),
+            if composer.config.subject_length > 0 && length > composer.config.subject_length {
+                text(format!("{}/{}", length, composer.config.subject_length)).size(14).style(UNVERIFIED_COLOR).into()
+            } else {
+                text(format!("{}/{}", length, composer.config.subject_length)).size(14).into()
+            },
+        ])
+        .align_items(Alignment::Center)
+        .spacing(10)
+        .into());
+
+        for (i, line) in composer.body.iter().enumerate() {
+            children.push(Row::with_children(vec![
+                text_input("", line)
+                    .on_input(move |line| Message::CommitBodyLineChanged(i, line))
+                    .on_submit(Message::InsertCommitBodyLine(i + 1))
+                    .font(Font::MONOSPACE)
+                    .size(16)
+                    .width(COMPOSER_WIDTH)
+                    .into(),
+                button("Remove").on_press(Message::RemoveCommitBodyLine(i)).into(),
+            ])
+            .align_items(Alignment::Center)
+            .spacing(10)
+            .into());
+        }
+
+        for warning in check_message(&composer.raw_message(), &composer.config) {
+            children.push(text(warning).size(14).style(UNVERIFIED_COLOR).into());
+        }
+
+        children.push(Row::with_children({
+            let mut children: Vec<Element<Message>> = vec![
+                button("Add line").on_press(Message::InsertCommitBodyLine(composer.body.len())).into(),
+                checkbox("Amend", composer.amend).on_toggle(Message::CommitAmendToggled).into(),
+                checkbox("Sign", composer.sign).on_toggle(Message::CommitSignToggled).into(),
+                checkbox("Skip hooks", composer.skip_hooks).on_toggle(Message::CommitSkipHooksToggled).into(),
+            ];
+
+            let mut commit = button("Commit");
+            if !composer.message().is_empty() {
+                commit = commit.on_press(Message::CreateCommit);
+            }
+            children.push(commit.into());
+            children.push(button("Close").on_press(Message::CloseCommitComposer).into());
+            children
+        })
+        .align_items(Alignment::Center)
+        .spacing(10)
+        .into());
+
+        match &composer.result {
+            Some(Ok(id)) => children.push(text(format!("Created commit {}", display.abbreviate(id))).size(16).into()),
+            Some(Err(e)) => children.push(text(format!("Commit failed: {}", e)).size(16).style(ERROR_COLOR).into()),
+            None => (),
+        }
+
+        for output in &composer.hook_outputs {
+            if output.success {
+                children.push(text(format!("{} hook passed", output.name)).size(16).style(VERIFIED_COLOR).into());
+            } else {
+                children.push(text(format!("{} hook failed, check Skip hooks to commit anyway", output.name)).size(16).style(ERROR_COLOR).into());
+            }
+            if !output.output.is_empty() {
+                children.push(text(&output.output).size(14).font(Font::MONOSPACE).into());
+            }
+        }
+
+        children
+    })
+    .spacing(5)
+    .padding(10)
+    .into()
+}
+
+pub fn view_worktree_panel(panel: &WorktreePanel, has_selection: bool) -> Element<'_, Message> {
+    Column::with_children({
+        let mut children: Vec<Element<Message>> = Vec::new();
+
+        if panel.worktrees.is_empty() {
+            children.push(text("No linked worktrees").size(16).into());
+        }
+
+        for worktree in &panel.worktrees {
+            children.push(Row::with_children(vec![
+                text(format!("{}{}", worktree.name, if worktree.locked { " (locked)" } else { "" })).size(16).width(200).into(),
+                text(&worktree.head).size(16).width(200).into(),
+                text(worktree.path.display()).size(16).width(400).into(),
+                button("Open").on_press(Message::OpenWorktree(worktree.path.clone())).into(),
+            ])
+            .align_items(Alignment::Center)
+            .spacing(10)
+            .into());
+        }
+
+        children.push(Row::with_children({
+            let mut children: Vec<Element<Message>> = Vec::new();
+            children.push(text_input("Worktree name", &panel.new_name).on_input(Message::WorktreeNameChanged).width(200).into());
+            children.push(text_input("Path", &panel.new_path).on_input(Message::WorktreePathChanged).width(400).into());
+
+            let mut create = button("Create for selected commit");
+            if has_selection && !panel.new_name.is_empty() && !panel.new_path.is_empty() {
+                create = create.on_press(Message::CreateWorktree);
+            }
+            children.push(create.into());
+            children.push(button("Close").on_press(Message::CloseWorktreePanel).into());
+            children
+        })
+        .align_items(Alignment::Center)
+        .spacing(10)
+        .into());
+
+        match &panel.result {
+            Some(Ok(message)) => children.push(text(message).size(16).into()),
+            Some(Err(e)) => children.push(text(e).size(16).style(ERROR_COLOR).into()),
+            None => (),
+        }
+
+        children
+    })
+    .spacing(5)
+    .padding(10)
+    .into()
+}
+
+pub fn view_export_dialog(dialog: &ExportDialog) -> Element<'_, Message> {
+    Column::with_children({
+        let mut children: Vec<Element<Message>> = Vec::new();
+
+        children.push(Row::with_children({
+            let mut children: Vec<Element<Message>> = Vec::new();
+            children.push(text_input("Output file (.svg, .png or .dot)", &dialog.path).on_input(Message::ExportPathChanged).width(400).into());
+            children.push(checkbox("Visible area only", dialog.visible_only).on_toggle(Message::ExportVisibleToggled).into());
+
+            let mut export = button("Export");
+            if !dialog.path.is_empty() {
+                export = export.on_press(Message::ExportGraph);
+            }
+            children.push(export.into());
+            children.push(button("Close").on_press(Message::CloseExportDialog).into());
+            children
+        })
+        .align_items(Alignment::Center)
+        .spacing(10)
+        .into());
+
+        match &dialog.result {
+            Some(Ok(message)) => children.push(text(message).size(16).into()),
+            Some(Err(e)) => children.push(text(e).size(16).style(ERROR_COLOR).into()),
+            None => (),
+        }
+
+        children
+    })
+    .spacing(5)
+    .padding(10)
+    .into()
+}
+
+pub fn view_archive_dialog<'a>(dialog: &'a ArchiveDialog, display: &DisplayConfig) -> Element<'a, Message> {
+    Column::with_children({
+        let mut children: Vec<Element<Message>> = Vec::new();
+
+        children.push(Row::with_children({
+            let mut children: Vec<Element<Message>> = Vec::new();
+            children.push(text(format!("Archive {}", display.abbreviate(&dialog.commit))).size(18).into());
+            children.push(text_input("Output file (.tar.gz or .zip)", &dialog.path).on_input(Message::ArchivePathChanged).width(400).into());
+            children.push(text_input("Prefix", &dialog.prefix).on_input(Message::ArchivePrefixChanged).width(200).into());
+
+            let mut archive = button("Archive");
+            if !dialog.path.is_empty() {
+                archive = archive.on_press(Message::ArchiveCommit);
+            }
+            children.push(archive.into());
+            children.push(button("Close").on_press(Message::CloseArchiveDialog).into());
+            children
+        })
+        .align_items(Alignment::Center)
+        .spacing(10)
+        .into());
+
+        match &dialog.result {
+            Some(Ok(message)) => children.push(text(message).size(16).into()),
+            Some(Err(e)) => children.push(text(e).size(16).style(ERROR_COLOR).into()),
+            None => (),
+        }
+
+        children
+    })
+    .spacing(5)
+    .padding(10)
+    .into()
+}
+
+pub fn view_compare_dialog(dialog: &CompareDialog) -> Element<'_, Message> {
+    Row::with_children(vec![
+        text("Compare").size(18).into(),
+        pick_list(&dialog.references[..], dialog.left.clone(), Message::CompareLeftSelected).placeholder("Left").into(),
+        text("with").size(18).into(),
+        pick_list(&dialog.references[..], dialog.right.clone(), Message::CompareRightSelected).placeholder("Right").into(),
+        button("Close").on_press(Message::CloseCompareDialog).into(),
+    ])
+    .align_items(Alignment::Center)
+    .spacing(10)
+    .padding(10)
+    .into()
+}
+
+fn view_commit_list<'a>(commits: &'a [(String, String)], display: &DisplayConfig) -> Vec<Element<'a, Message>> {
+    commits.iter().map(|(id, summary)| {
+        button(text(format!("{} {}", display.abbreviate(id), summary)).size(14))
+            .on_press(Message::SelectCommit(id.clone()))
+            .into()
+    }).collect()
+}
+
+// Length of the bar of the most changed file
+const CHANGE_BAR_WIDTH: f32 = 80.0;
+
+// Diffs with more files than this list them under their directories, when there are several
+const GROUP_FILES_OVER: usize = 20;
+
+// Which file of the commit or range diff has its patch shown alone, and which directories of the file list are open
+#[derive(Debug, Default)]
+pub struct FileList {
+    pub focused: Option<String>,
+    pub expanded: HashSet<String>,
+}
+
+fn view_change_row<'a>(label: Element<'a, Message>, insertions: usize, deletions: usize, largest: usize, indent: f32) -> Element<'a, Message> {
+    Row::with_children(vec![
+        Space::with_width(indent).into(),
+        Canvas::new(ChangeBar { insertions, deletions, largest }).width(CHANGE_BAR_WIDTH).height(8).into(),
+        label,
+    ])
+    .align_items(Alignment::Center)
+    .spacing(10)
+    .into()
+}
+
+// With the commit the files are at, they are clicked to show only their part of the patch, and again to show all of
+// it, and can be restored from it
+fn view_diff_file<'a>(file: &DiffFile, list: &FileList, commit: Option<&str>, largest: usize, indent: f32) -> Element<'a, Message> {
+    let path = match &file.origin {
+        Some((old, similarity)) => format!("{} -> {} ({}%)", old, file.path, similarity),
+        None => file.path.clone(),
+    };
+    let label = text(format!("{} {} +{} -{}", file.status, path, file.insertions, file.deletions)).size(14);
+    let Some(commit) = commit else {
+        return view_change_row(label.into(), file.insertions, file.deletions, largest, indent);
+    };
+
+    let selected = list.focused.as_deref() == Some(file.path.as_str());
+    let mut label = Row::with_children(vec![
+        button(label).style(theme::Button::Text).padding(0)
+            .on_press(Message::SelectDiffFile((!selected).then(|| file.path.clone())))
+            .into(),
+    ])
+    .align_items(Alignment::Center)
+    .spacing(10);
+    // A deleted file isn't there to restore
+    if file.status != 'D' {
+        label = label.push(button(text("Restore").size(12)).padding([2, 5]).on_press(Message::RestoreFile(commit.to_string(), file.path.clone())));
+    }
+    view_change_row(label.into(), file.insertions, file.deletions, largest, indent)
+}
+
+fn view_diff_files<'a>(files: &'a [DiffFile], totals: DiffTotals, list: &FileList, commit: Option<&str>) -> Vec<Element<'a, Message>> {
+    let mut children: Vec<Element<Message>> = Vec::new();
+
+    children.push(text(format!("{} files changed, +{} -{}", totals.files, totals.insertions, totals.deletions)).size(18).into());
+    let largest = files.iter().map(|file| file.insertions + file.deletions).max().unwrap_or_default();
+    let directories = group_by_directory(files);
+    if files.len() <= GROUP_FILES_OVER || directories.len() < 2 {
+        children.extend(files.iter().map(|file| view_diff_file(file, list, commit, largest, 0.0)));
+        return children;
+    }
+
+    // Directory bars are measured against each other, since they hold more changes than any one file
+    let largest_directory = directories.iter().map(|changes| changes.insertions + changes.deletions).max().unwrap_or_default();
+    for changes in directories {
+        let expanded = list.expanded.contains(changes.directory);
+        let name = if changes.directory.is_empty() { "(top level)" } else { changes.directory };
+        let label = button(text(format!("{} {}/ {} files +{} -{}", if expanded { "v" } else { ">" }, name, changes.files.len(), changes.insertions, changes.deletions)).size(14))
+            .style(theme::Button::Text)
+            .padding(0)
+            .on_press(Message::ToggleDiffDirectory(changes.directory.to_string()));
+        children.push(view_change_row(label.into(), changes.insertions, changes.deletions, largest_directory, 0.0));
+        if expanded {
+            children.extend(changes.files.into_iter().map(|file| view_diff_file(file, list, commit, largest, 15.0)));
+        }
+    }
+
+    children
+}
+
+// Only the focused file's part of the patch, when it has one
+fn view_patch<'a>(patch: &'a str, focused: Option<&str>) -> Element<'a, Message> {
+    match focused.and_then(|path| file_patch(patch, path)) {
+        Some(file) => Column::with_children(vec![
+            button(text("Show all files").size(14)).on_press(Message::SelectDiffFile(None)).into(),
+            text(file).size(12).into(),
+        ])
+        .spacing(5)
+        .into(),
+        None => text(patch).size(12).into(),
+    }
+}
+
+pub fn view_comparison<'a>(comparison: &'a ReferenceComparison, display: &DisplayConfig, list: &FileList) -> Element<'a, Message> {
+    scrollable(Column::with_children({
+        let mut children: Vec<Element<Message>> = Vec::new();
+
+        children.push(text(format!("{}...{}", comparison.left, comparison.right)).size(20).into());
+
+        children.push(text(format!("Only in {}: {} commits", comparison.left, comparison.left_only.len())).size(18).into());
+        children.extend(view_commit_list(&comparison.left_only, display));
+
+        children.push(text(format!("Only in {}: {} commits", comparison.right, comparison.right_only.len())).size(18).into());
+        children.extend(view_commit_list(&comparison.right_only, display));
+
+        children.extend(view_diff_files(&comparison.files, comparison.totals, list, None));
+
+        children
+    })
+    .spacing(5)
+    .padding(10))
+    .width(450)
+    .height(Length::Fill)
+    .into()
+}
+
+// Every change makes the diffs shown again with the new settings
+fn view_diff_settings<'a>(settings: DiffSettings) -> Element<'a, Message> {
+    Column::with_children(vec![
+        Row::with_children(vec![
+            checkbox("Ignore whitespace", settings.ignore_whitespace).on_toggle(move |ignore| Message::DiffSettingsChanged(DiffSettings { ignore_whitespace: ignore, ..settings })).size(16).text_size(14).into(),
+            text("Context lines").size(14).into(),
+            pick_list(&CONTEXT_LINE_CHOICES[..], Some(settings.context_lines), move |lines| Message::DiffSettingsChanged(DiffSettings { context_lines: lines, ..settings })).text_size(14).into(),
+        ])
+        .align_items(Alignment::Center)
+        .spacing(10)
+        .into(),
+        Row::with_children(vec![
+            checkbox("Detect renames", settings.find_renames).on_toggle(move |find| Message::DiffSettingsChanged(DiffSettings { find_renames: find, ..settings })).size(16).text_size(14).into(),
+            checkbox("Detect copies", settings.find_copies).on_toggle(move |find| Message::DiffSettingsChanged(DiffSettings { find_copies: find, ..settings })).size(16).text_size(14).into(),
+        ])
+        .spacing(10)
+        .into(),
+    ])
+    .spacing(5)
+    .into()
+}
+
+pub fn view_range_diff<'a>(range: &'a RangeDiff, display: &DisplayConfig, settings: DiffSettings, list: &FileList) -> Element<'a, Message> {
+    scrollable(Column::with_children({
+        let mut children: Vec<Element<Message>> = Vec::new();
+
+        children.push(text(format!("{}..{}", display.abbreviate(&range.from), display.abbreviate(&range.to))).size(20).into());
+        children.push(button("Close").on_press(Message::UnselectCommit).into());
+
+        children.push(text(format!("{} commits", range.commits.len())).size(18).into());
+        children.extend(view_commit_list(&range.commits, display));
+
+        children.push(view_diff_settings(settings));
+        children.extend(view_diff_files(&range.files, range.totals, list, Some(&range.to)));
+
+        children.push(view_patch(&range.patch, list.focused.as_deref()));
+
+        children
+    })
+    .spacing(5)
+    .padding(10))
+    .width(450)
+    .height(Length::Fill)
+    .into()
+}
+
+// Row of buttons that select the commits and bring them into view
+pub fn view_commit_links<'a>(label: &str, ids: &[String], display: &DisplayConfig) -> Element<'a, Message> {
+    Row::with_children({
+        let mut children: Vec<Element<Message>> = vec![text(label).size(14).into()];
+        for id in ids {
+            children.push(button(text(display.abbreviate(id)).size(14)).on_press(Message::JumpToCommit(id.clone())).into());
+        }
+        children
+    })
+    .align_items(Alignment::Center)
+    .spacing(5)
+    .into()
+}
+
+// Shown next to the graph, or in a window of its own when `windowed`. Children come from the loaded graph, since git
+// only records parents
+pub fn view_commit_details<'a>(details: &'a CommitDiff, commit_children: &[String], display: &DisplayConfig, windowed: bool, settings: DiffSettings, list: &FileList, comparison: ImageComparison) -> Element<'a, Message> {
+    scrollable(Column::with_children({
+        let mut children: Vec<Element<Message>> = Vec::new();
+
+        children.push(Row::with_children({
+            let mut children: Vec<Element<Message>> = vec![
+                text(display.abbreviate(&details.id)).size(20).into(),
+                button("Copy ID").on_press(Message::CopyCommitId(details.id.clone())).into(),
+            ];
+            if !windowed {
+                children.push(button("Open in window").on_press(Message::OpenCommitWindow(details.id.clone())).into());
+            }
+            children.push(button("Bookmark").on_press(Message::EditBookmark(details.id.clone())).into());
+            children.push(button("Close").on_press(Message::CloseCommitDetails).into());
+            children
+        })
+        .align_items(Alignment::Center)
+        .spacing(10)
+        .into());
+
+        // For the changes in the working tree, folded into the commit with the rebase
+        children.push(Row::with_children(vec![
+            button(text("Create fixup! commit").size(14)).on_press(Message::CreateFixupCommit(details.id.clone(), false)).into(),
+            button(text("Create squash! commit").size(14)).on_press(Message::CreateFixupCommit(details.id.clone(), true)).into(),
+            button(text("Autosquash").size(14)).on_press(Message::Autosquash).into(),
+        ])
+        .spacing(10)
+        .into());
+
+        let author = &details.author;
+        children.push(text(format!("Author: {} <{}>, {}", author.name, author.email, display.format_local(author.time))).size(14).into());
+        let committer = &details.committer;
+        if committer == author {
+            children.push(text("Committed by the author at the same time").size(14).into());
+        } else {
+            children.push(text(format!("Committer: {} <{}>, {}", committer.name, committer.email, display.format_local(committer.time))).size(14).into());
+        }
+        children.push(text(match details.signature_kind {
+            Some(kind) => format!("Has a {} signature block", kind),
+            None => String::from("Not signed"),
+        }).size(14).into());
+
+        if details.parents.is_empty() {
+            children.push(text("Root commit").size(14).into());
+        } else {
+            children.push(view_commit_links("Parents:", &details.parents, display));
+        }
+        if !commit_children.is_empty() {
+            children.push(view_commit_links("Children:", commit_children, display));
+        }
+
+        for tag in &details.tags {
+            children.push(text(match &tag.tagger {
+                Some((tagger, time)) => format!("Tag {} by {}, {}", tag.name, tagger, display.format_time(*time)),
+                None => format!("Tag {}", tag.name),
+            }).size(16).into());
+            if !tag.message.is_empty() {
+                children.push(text(&tag.message).size(14).into());
+            }
+        }
+
+        match &details.note {
+            Some(note) => {
+                children.push(Row::with_children(vec![
+                    text("Note").size(16).into(),
+                    button(text("Edit note").size(14)).on_press(Message::EditNote(details.id.clone())).into(),
+                ])
+                .align_items(Alignment::Center)
+                .spacing(10)
+                .into());
+                children.push(text(note).size(14).into());
+            },
+            None => children.push(button(text("Add note").size(14)).on_press(Message::EditNote(details.id.clone())).into()),
+        }
+
+        children.push(view_diff_settings(settings));
+        children.extend(view_diff_files(&details.files, details.totals, list, Some(&details.id)));
+
+        for preview in &details.images {
+            children.push(text(&preview.path).size(14).into());
+            children.push(view_image_preview(preview, comparison));
+        }
+
+        if !details.lfs_missing.is_empty() {
+            children.push(Row::with_children(vec![
+                text(format!("{} LFS objects aren't downloaded", details.lfs_missing.len())).size(14).into(),
+                button("Download with git lfs").on_press(Message::DownloadLfsObjects).into(),
+            ])
+            .align_items(Alignment::Center)
+            .spacing(10)
+            .into());
+        }
+
+        children.push(view_patch(&details.patch, list.focused.as_deref()));
+
+        children
+    })
+    .spacing(5)
+    .padding(10))
+    .width(if windowed { Length::Fill } else { Length::Fixed(450.0) })
+    .height(Length::Fill)
+    .into()
+}
+
+pub fn view_release_timeline<'a>(timeline: &'a ReleaseTimeline, display: &DisplayConfig) -> Element<'a, Message> {
+    scrollable(Column::with_children({
+        let mut children: Vec<Element<Message>> = Vec::new();
+
+        children.push(Row::with_children(vec![
+            text("Releases").size(24).into(),
+            button("Close").on_press(Message::ToggleReleaseTimeline).into(),
+        ])
+        .align_items(Alignment::Center)
+        .spacing(10)
+        .into());
+
+        children.push(Row::with_children({
+            let mut export = button("Export Markdown");
+            if !timeline.path.is_empty() {
+                export = export.on_press(Message::ExportChangelog);
+            }
+            vec![
+                text_input("Changelog file (.md)", &timeline.path).on_input(Message::ChangelogPathChanged).on_submit(Message::ExportChangelog).width(250).into(),
+                export.into(),
+            ]
+        })
+        .align_items(Alignment::Center)
+        .spacing(10)
+        .into());
+
+        match &timeline.result {
+            Some(Ok(message)) => children.push(text(message).size(14).into()),
+            Some(Err(e)) => children.push(text(e).size(14).style(ERROR_COLOR).into()),
+            None => (),
+        }
+
+        if timeline.releases.is_empty() {
+            children.push(text("There are no tags yet").size(14).into());
+        }
+        for release in &timeline.releases {
+            let heading = match (&release.tag, release.time) {
+                (Some(tag), Some(time)) => format!("{} - {}", tag, format_release_date(time)),
+                _ => String::from("Unreleased"),
+            };
+            children.push(text(format!("{} ({} commits)", heading, release.commits.len())).size(18).into());
+            children.extend(view_commit_list(&release.commits, display));
+        }
+
+        children
+    })
+    .spacing(5)
+    .padding(10))
+    .width(450)
+    .height(Length::Fill)
+    .into()
+}
+
+// Labels of the charts are cut down to this many characters, keeping the end where file names are
+const CHART_LABEL_LENGTH: usize = 24;
+
+fn chart_label(label: &str) -> String {
+    let length = label.chars().count();
+    if length > CHART_LABEL_LENGTH {
+        format!("...{}", label.chars().skip(length - CHART_LABEL_LENGTH + 3).collect::<String>())
+    } else {
+        label.to_string()
+    }
+}
+
+fn view_row_chart<'a>(bars: &[(String, usize)]) -> Element<'a, Message> {
+    let bars: Vec<(String, f32)> = bars.iter().map(|(label, value)| (chart_label(label), *value as f32)).collect();
+    let height = bars.len() as f32 * ROW_HEIGHT;
+    Canvas::new(BarChart { kind: ChartKind::Rows, bars }).width(Length::Fill).height(height).into()
+}
+
+pub fn view_statistics_panel(stats: &HistoryStats) -> Element<'_, Message> {
+    scrollable(Column::with_children({
+        let mut children: Vec<Element<Message>> = Vec::new();
+
+        children.push(Row::with_children(vec![
+            text("Statistics").size(24).into(),
+            button("Close").on_press(Message::ToggleStatisticsPanel).into(),
+        ])
+        .align_items(Alignment::Center)
+        .spacing(10)
+        .into());
+
+        children.push(text(format!("{} commits, {} merges, {:.2} merges per week on average", stats.commits, stats.merges, stats.merges_per_week)).size(14).into());
+
+        children.push(text("Commits per author").size(18).into());
+        children.push(view_row_chart(&stats.authors));
+
+        children.push(text(format!("Commits per week, last {} weeks", stats.weeks.len())).size(18).into());
+        let weeks = stats.weeks.iter().map(|(week, count)| (week.format("%Y-%m-%d").to_string(), *count as f32)).collect();
+        children.push(Canvas::new(BarChart { kind: ChartKind::Columns, bars: weeks }).width(Length::Fill).height(120).into());
+
+        children.push(text(format!("Most changed files, lines over the last {} commits", stats.churn_commits)).size(18).into());
+        children.push(view_row_chart(&stats.hotspots));
+
+        children
+    })
+    .spacing(5)
+    .padding(10))
+    .width(450)
+    .height(Length::Fill)
+    .into()
+}
+
+pub fn view_health_panel(issues: &[HealthIssue]) -> Element<'_, Message> {
+    scrollable(Column::with_children({
+        let mut children: Vec<Element<Message>> = Vec::new();
+
+        children.push(Row::with_children(vec![
+            text("Health").size(24).into(),
+            button("Check again").on_press(Message::CheckHealth).into(),
+            button("Close").on_press(Message::ToggleHealthPanel).into(),
+        ])
+        .align_items(Alignment::Center)
+        .spacing(10)
+        .into());
+
+        if issues.is_empty() {
+            children.push(text("No problems found").size(14).into());
+        }
+        for (i, issue) in issues.iter().enumerate() {
+            children.push(text(&issue.problem).size(16).style(ERROR_COLOR).into());
+            children.push(text(&issue.suggestion).size(14).into());
+            if let Some(fix) = &issue.fix {
+                children.push(button(fix.label()).on_press(Message::ApplyHealthFix(i)).into());
+            }
+        }
+
+        children
+    })
+    .spacing(5)
+    .padding(10))
+    .width(450)
+    .height(Length::Fill)
+    .into()
+}
+
+pub fn view_maintenance_panel(panel: &MaintenancePanel) -> Element<'_, Message> {
+    scrollable(Column::with_children({
+        let mut children: Vec<Element<Message>> = Vec::new();
+
+        children.push(Row::with_children(vec![
+            text("Maintenance").size(24).into(),
+            button("Close").on_press(Message::ToggleMaintenancePanel).into(),
+        ])
+        .align_items(Alignment::Center)
+        .spacing(10)
+        .into());
+
+        children.push(text(format!("The repository takes {}", format_size(panel.size))).size(14).into());
+
+        // One task at a time, since they all rewrite the same files
+        for task in MaintenanceTask::ALL {
+            let running = panel.job.as_ref().is_some_and(|job| job.task == task);
+            let mut run = button(text(if running { format!("{}...", task) } else { task.to_string() }));
+            if panel.job.is_none() {
+                run = run.on_press(Message::RunMaintenance(task));
+            }
+            children.push(run.into());
+        }
+
+        for result in panel.results.iter().rev() {
+            match result {
+                Ok(message) => children.push(text(message).size(14).into()),
+                Err(e) => children.push(text(e).size(14).style(ERROR_COLOR).into()),
+            }
+        }
+
+        children
+    })
+    .spacing(5)
+    .padding(10))
+    .width(450)
+    .height(Length::Fill)
+    .into()
+}
+
+// Lines of the message shown before it scrolls
+const MESSAGE_LINES: usize = 6;
+
+// The full message of the selected commit, with its URLs and issue references as links
+pub fn view_commit_message<'a>(message: &str, forge: Option<&Forge>) -> Element<'a, Message> {
+    let lines: Vec<&str> = message.trim_end().lines().collect();
+    let height = lines.len().clamp(1, MESSAGE_LINES) as f32 * 22.0;
+
+    scrollable(Column::with_children(lines.iter().map(|line| {
+        Row::with_children(link_message_line(line, forge).into_iter().map(|part| match part {
+            MessagePart::Text(content) => text(content).size(16).into(),
+            MessagePart::Link { label, url } => button(text(label).size(16).style(LINK_COLOR))
+                .style(theme::Button::Text)
+                .padding(0)
+                .on_press(Message::OpenUrl(url))
+                .into(),
+        }).collect::<Vec<Element<Message>>>())
+        .height(22)
+        .align_items(Alignment::Center)
+        .into()
+    }).collect::<Vec<Element<Message>>>()))
+    .height(height)
+    .into()
+}
+
+// `renaming` holds the branch being renamed and the name typed so far
+pub fn view_graph_legend<'a>(colors: &GraphColors) -> Element<'a, Message> {
+    Column::with_children({
+        let mut children: Vec<Element<Message>> = Vec::new();
+
+        children.push(Row::with_children(vec![
+            text("Legend").size(20).into(),
+            button("Close").on_press(Message::ToggleLegend).into(),
+        ])
+        .align_items(Alignment::Center)
+        .spacing(10)
+        .into());
+
+        for (symbol, color, description) in colors.legend() {
+            children.push(Row::with_children(vec![
+                text(symbol).size(18).style(color).width(20).into(),
+                text(description).size(14).into(),
+            ])
+            .align_items(Alignment::Center)
+            .spacing(5)
+            .into());
+        }
+
+        children
+    })
+    .spacing(5)
+    .padding(10)
+    .width(250)
+    .into()
+}
+
+pub fn view_reference_sidebar<'a>(references: &'a [(ReferenceTip, bool)], groups: ReferenceGroups, expanded: &HashSet<(ReferenceKind, String)>, renaming: Option<&'a (String, String)>) -> Element<'a, Message> {
+    scrollable(Column::with_children({
+        let mut children: Vec<Element<Message>> = Vec::new();
+
+        children.push(Row::with_children(vec![
+            button("Show all").on_press(Message::ShowAllReferences).into(),
+            button("Close").on_press(Message::ToggleReferenceSidebar).into(),
+        ])
+        .spacing(10)
+        .into());
+
+        let mut kind = None;
+        for (i, (tip, shown)) in references.iter().enumerate() {
+            if kind != Some(tip.kind) {
+                kind = Some(tip.kind);
+                let heading = match tip.kind {
+                    ReferenceKind::Branch => "Branches",
+                    ReferenceKind::Remote => "Remotes",
+                    ReferenceKind::Tag => "Tags",
+                };
+                if tip.kind == ReferenceKind::Branch {
+                    children.push(text(heading).size(18).into());
+                } else {
+                    let kind = tip.kind;
+                    children.push(Row::with_children(vec![
+                        text(heading).size(18).width(Length::Fill).into(),
+                        checkbox("Load", groups.includes(kind)).on_toggle(move |loaded| Message::ReferenceGroupToggled(kind, loaded)).size(16).text_size(14).into(),
+                    ])
+                    .align_items(Alignment::Center)
+                    .into());
+                    if !groups.includes(kind) {
+                        let count = references.iter().filter(|(other, _)| other.kind == kind).count();
+                        children.push(text(format!("{} not loaded", count)).size(14).into());
+                    }
+                }
+            }
+            // The references of a group that isn't loaded aren't on the graph to show or hide
+            if !groups.includes(tip.kind) {
+                continue
+            }
+            // Names come sorted, so the references of a folder follow each other
+            if let Some(folder) = tip.folder() {
+                let first = i == 0 || references[i - 1].0.kind != tip.kind || references[i - 1].0.folder() != Some(folder);
+                let open = expanded.contains(&(tip.kind, folder.to_string()));
+                if first {
+                    let members: Vec<bool> = references.iter().filter(|(other, _)| other.kind == tip.kind && other.folder() == Some(folder)).map(|(_, shown)| *shown).collect();
+                    let (kind, all_shown) = (tip.kind, members.iter().all(|shown| *shown));
+                    children.push(Row::with_children(vec![
+                        checkbox("", all_shown).on_toggle(move |shown| Message::ReferenceFolderToggled(kind, folder.to_string(), shown)).size(16).spacing(0).into(),
+                        button(text(format!("{} {}/ ({})", if open { "v" } else { ">" }, folder, members.len())).size(14))
+                            .style(theme::Button::Text)
+                            .padding(0)
+                            .on_press(Message::ToggleReferenceFolder(kind, folder.to_string()))
+                            .into(),
+                    ])
+                    .align_items(Alignment::Center)
+                    .spacing(5)
+                    .into());
+                }
+                if !open {
+                    continue
+                }
+            }
+            let row: Element<Message> = match renaming {
+                Some((branch, new_name)) if tip.kind == ReferenceKind::Branch && *branch == tip.name => {
+                    Row::with_children(vec![
+                        text_input("New name", new_name).on_input(Message::RenameBranchChanged).on_submit(Message::RenameBranch).size(14).into(),
+                        button(text("Cancel").size(14)).on_press(Message::CancelRenameBranch).into(),
+                    ])
+                    .align_items(Alignment::Center)
+                    .spacing(5)
+                    .into()
+                },
+                _ if tip.kind == ReferenceKind::Branch => {
+                    Row::with_children(vec![
+                        checkbox(&tip.name, *shown).on_toggle(move |shown| Message::ReferenceToggled(i, shown)).size(16).text_size(14).width(Length::Fill).into(),
+                        button(text("Rename").size(14)).on_press(Message::StartRenameBranch(tip.name.clone())).into(),
+                    ])
+                    .align_items(Alignment::Center)
+                    .spacing(5)
+                    .into()
+                },
+                _ => checkbox(&tip.name, *shown).on_toggle(move |shown| Message::ReferenceToggled(i, shown)).size(16).text_size(14).into(),
+            };
+            if tip.folder().is_some() {
+                children.push(Row::with_children(vec![Space::with_width(15).into(), row]).into());
+            } else {
+                children.push(row);
+            }
+        }
+
+        children
+    })
+    .spacing(5)
+    .padding(10))
+    .width(250)
+    .height(Length::Fill)
+    .into()
+}
+
+fn view_status_entry<'a>(entry: &'a StatusEntry, staged: bool) -> Element<'a, Message> {
+    let (status, action) = if staged {
+        (entry.staged, button("Unstage").on_press(Message::UnstageFile(entry.path.clone())))
+    } else {
+        (entry.unstaged, button("Stage").on_press(Message::StageFile(entry.path.clone())))
+    };
+    let mut children: Vec<Element<Message>> = vec![
+        text(status.unwrap_or(' ')).size(14).width(15).into(),
+        button(text(&entry.path).size(14)).on_press(Message::SelectStatusFile(entry.path.clone(), staged)).into(),
+        action.into(),
+    ];
+    if !staged && status == Some('U') {
+        children.push(button("Merge tool").on_press(Message::OpenMergetool(entry.path.clone())).into());
+    }
+    if !staged {
+        let label = if status == Some('?') { "Delete" } else { "Discard changes" };
+        children.push(button(label).on_press(Message::DiscardFile(entry.path.clone())).into());
+    }
+    let row = Row::with_children(children)
+        .align_items(Alignment::Center)
+        .spacing(5);
+
+    if !staged && status == Some('?') {
+        mouse_area(row).on_right_press(Message::OpenIgnoreMenu(entry.path.clone())).into()
+    } else {
+        row.into()
+    }
+}
+
+// How changed images are compared, the same for all of them
+#[derive(Debug, Clone, Copy)]
+pub struct ImageComparison {
+    pub mode: ImageCompareMode,
+    // Where the swipe is or how opaque the onion skin, from 0 to 1
+    pub position: f32,
+}
+
+impl Default for ImageComparison {
+    fn default() -> Self {
+        Self { mode: ImageCompareMode::SideBySide, position: 0.5 }
+    }
+}
+
+// Height changed images are previewed at
+const PREVIEW_HEIGHT: f32 = 200.0;
+
+fn view_preview_image<'a>(label: &'a str, preview: Option<&PreviewImage>) -> Element<'a, Message> {
+    Column::with_children(vec![
+        text(label).size(14).into(),
+        match preview {
+            Some(PreviewImage::Raster { handle, .. }) => image(handle.clone()).height(PREVIEW_HEIGHT).into(),
+            Some(PreviewImage::Vector(handle)) => svg(handle.clone()).height(PREVIEW_HEIGHT).into(),
+            None => text("Nothing, or too large to preview").size(14).into(),
+        },
+    ])
+    .width(Length::FillPortion(1))
+    .spacing(5)
+    .into()
+}
+
+fn view_image_preview(preview: &ImagePreview, comparison: ImageComparison) -> Element<'_, Message> {
+    let mut children: Vec<Element<Message>> = Vec::new();
+
+    // Images that can't be laid over each other are only ever shown side by side
+    let mode = if preview.can_overlay() { comparison.mode } else { ImageCompareMode::SideBySide };
+    if preview.can_overlay() {
+        let mut controls: Vec<Element<Message>> = vec![pick_list(&ImageCompareMode::ALL[..], Some(mode), Message::ImageCompareModeSelected).into()];
+        if mode != ImageCompareMode::SideBySide {
+            controls.push(slider(0.0..=1.0, comparison.position, Message::ImageComparePositionChanged).step(0.01).width(200).into());
+        }
+        children.push(Row::with_children(controls).align_items(Alignment::Center).spacing(10).into());
+    }
+
+    match preview.overlay(mode, comparison.position) {
+        Some(handle) => children.push(image(handle).height(PREVIEW_HEIGHT).into()),
+        None => children.push(Row::with_children(vec![
+            view_preview_image("Before", preview.old.as_ref()),
+            view_preview_image("After", preview.new.as_ref()),
+        ])
+        .spacing(10)
+        .into()),
+    }
+
+    Column::with_children(children).spacing(5).into()
+}
+
+fn view_file_diff<'a>(diff: &'a FileDiff, selected_lines: &[Vec<bool>], comparison: ImageComparison) -> Vec<Element<'a, Message>> {
+    let mut children: Vec<Element<Message>> = Vec::new();
+
+    let action = if diff.staged { "Unstage" } else { "Stage" };
+    children.push(Row::with_children(vec![
+        text(format!("{} {}", diff.status, diff.path)).size(18).into(),
+        if diff.is_partial_allowed() {
+            button(text(format!("{} selected lines", action))).on_press(Message::ApplySelectedLines).into()
+        } else {
+            text(format!("Only whole files can be {}d here", action.to_lowercase())).size(14).into()
+        },
+        button("Diff tool").on_press(Message::OpenDifftool).into(),
+    ])
+    .align_items(Alignment::Center)
+    .spacing(10)
+    .into());
+
+    if let Some(summary) = &diff.summary {
+        children.push(text(summary).size(14).into());
+    }
+    if let Some(preview) = &diff.image {
+        children.push(view_image_preview(preview, comparison));
+    }
+    if diff.summary.is_some() {
+        return children;
+    }
+
+    for (i, hunk) in diff.hunks.iter().enumerate() {
+        let mut header: Vec<Element<Message>> = vec![text(&hunk.header).size(14).into()];
+        if diff.is_partial_allowed() {
+            header.push(button(text(format!("{} hunk", action)).size(14)).on_press(Message::ApplyHunk(i)).into());
+        }
+        children.push(Row::with_children(header).align_items(Alignment::Center).spacing(10).into());
+
+        for (j, line) in hunk.lines.iter().enumerate() {
+            let content = format!("{}{}", line.origin, line.content.trim_end_matches('\n'));
+            if line.origin == ' ' || !diff.is_partial_allowed() {
+                children.push(text(content).size(14).font(Font::MONOSPACE).into());
+            } else {
+                let selected = selected_lines.get(i).and_then(|lines| lines.get(j)).copied().unwrap_or(false);
+                children.push(checkbox(content, selected).on_toggle(move |selected| Message::DiffLineToggled(i, j, selected))
+                    .size(14)
+                    .text_size(14)
+                    .font(Font::MONOSPACE)
+                    .into());
+            }
+        }
+    }
+
+    children
+}
+
+pub fn view_status_panel(panel: &StatusPanel, comparison: ImageComparison) -> Element<'_, Message> {
+    scrollable(Column::with_children({
+        let mut children: Vec<Element<Message>> = Vec::new();
+
+        children.push(Row::with_children(vec![
+            text("Status").size(24).into(),
+            button("Edit .gitignore").on_press(Message::OpenGitignoreEditor).into(),
+            button("Close").on_press(Message::ToggleStatusPanel).into(),
+        ])
+        .align_items(Alignment::Center)
+        .spacing(10)
+        .into());
+
+        let staged: Vec<&StatusEntry> = panel.entries.iter().filter(|entry| entry.staged.is_some()).collect();
+        let unstaged: Vec<&StatusEntry> = panel.entries.iter().filter(|entry| entry.unstaged.is_some()).collect();
+        if staged.is_empty() && unstaged.is_empty() {
+            children.push(text("Nothing to commit, working tree clean").size(14).into());
+        }
+        if !staged.is_empty() {
+            children.push(text("Staged").size(18).into());
+            children.extend(staged.into_iter().map(|entry| view_status_entry(entry, true)));
+        }
+        if !unstaged.is_empty() {
+            children.push(text("Unstaged").size(18).into());
+            children.extend(unstaged.into_iter().map(|entry| view_status_entry(entry, false)));
+        }
+
+        if let Some(diff) = &panel.diff {
+            children.extend(view_file_diff(diff, &panel.selected_lines, comparison));
+        }
+
+        children
+    })
+    .spacing(5)
+    .padding(10))
+    .width(450)
+    .height(Length::Fill)
+    .into()
+}
@@ -0,0 +1,24 @@
+use crate::error::Error;
+use crate::tools::ToolRun;
+
+use super::worker::JobHandle;
+
+type ToolResult = Result<String, Error>;
+
+// Runs a difftool or mergetool on its own thread, since it stays open for as long as the user works in it
+pub struct ExternalTool {
+    job: JobHandle<String>,
+}
+
+impl ExternalTool {
+    pub fn start(run: ToolRun) -> Result<ExternalTool, Error> {
+        let name = run.name().to_string();
+        let job = JobHandle::spawn(&name, move |_| run.run())?;
+        Ok(ExternalTool { job })
+    }
+
+    // Only there once the tool was closed
+    pub fn take_result(&self) -> Option<ToolResult> {
+        self.job.take_result()
+    }
+}
@@ -0,0 +1,140 @@
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use git2::Repository;
+
+use crate::error::Error;
+use crate::lfs::{check_out_objects, fetch_objects};
+use crate::remote::{fetch_all, push_branch};
+
+use super::worker::JobHandle;
+
+// Remote branches that were updated
+type FetchResult = Result<Vec<String>, Error>;
+
+#[derive(Default)]
+struct FetchProgress {
+    // The remote being fetched from
+    remote: Mutex<String>,
+    received: AtomicUsize,
+    total: AtomicUsize,
+}
+
+// Fetches all remotes on its own thread, since the network can take arbitrarily long. Dropping it cancels the fetch,
+// since a fetch of a closed tab has nobody left to show it to
+pub struct FetchJob {
+    progress: Arc<FetchProgress>,
+    job: JobHandle<Vec<String>>,
+}
+
+impl FetchJob {
+    pub fn start(path: PathBuf) -> Result<FetchJob, Error> {
+        let progress = Arc::new(FetchProgress::default());
+
+        let thread_progress = Arc::clone(&progress);
+        let job = JobHandle::spawn("fetcher", move |cancel| {
+            let progress = thread_progress;
+            let fetched = Repository::open(path).and_then(|repository| fetch_all(&repository, &mut |remote, received, total| {
+                if *progress.remote.lock().unwrap() != remote {
+                    *progress.remote.lock().unwrap() = remote.to_string();
+                }
+                progress.received.store(received, Ordering::Relaxed);
+                progress.total.store(total, Ordering::Relaxed);
+                !cancel.is_cancelled()
+            }));
+
+            // Like a cancelled clone, the error libgit2 stopped with says little
+            match fetched {
+                _ if cancel.is_cancelled() => Err(Error::from("fetching was cancelled")),
+                fetched => fetched.map_err(Error::from),
+            }
+        })?;
+
+        Ok(FetchJob { progress, job })
+    }
+
+    // The remote, objects received from it so far and their total, which is zero until the remote said
+    pub fn progress(&self) -> (String, usize, usize) {
+        (self.progress.remote.lock().unwrap().clone(), self.progress.received.load(Ordering::Relaxed), self.progress.total.load(Ordering::Relaxed))
+    }
+
+    // Only there once the fetch is done
+    pub fn take_result(&self) -> Option<FetchResult> {
+        self.job.take_result()
+    }
+}
+
+// Pushes a branch on its own thread like a fetch, since the network or asking for credentials can hold it up for long
+pub struct PushJob {
+    pub branch: String,
+    pub force: bool,
+    // Objects sent so far and their total, which is zero until libgit2 knows it
+    progress: Arc<(AtomicUsize, AtomicUsize)>,
+    job: JobHandle<()>,
+    cancelled: bool,
+}
+
+impl PushJob {
+    pub fn start(path: PathBuf, branch: String, force: bool) -> Result<PushJob, Error> {
+        let progress: Arc<(AtomicUsize, AtomicUsize)> = Arc::default();
+
+        let (thread_branch, thread_progress) = (branch.clone(), Arc::clone(&progress));
+        let job = JobHandle::spawn("pusher", move |cancel| {
+            let mut report = |sent, total| {
+                thread_progress.0.store(sent, Ordering::Relaxed);
+                thread_progress.1.store(total, Ordering::Relaxed);
+            };
+            Ok(push_branch(&Repository::open(path)?, &thread_branch, force, &mut report, &|| cancel.is_cancelled())?)
+        })?;
+
+        Ok(PushJob { branch, force, progress, job, cancelled: false })
+    }
+
+    pub fn progress(&self) -> (usize, usize) {
+        (self.progress.0.load(Ordering::Relaxed), self.progress.1.load(Ordering::Relaxed))
+    }
+
+    // The push only stops if nothing was sent yet, so it is still waited for to tell whether it went through
+    pub fn cancel(&mut self) {
+        self.job.cancel();
+        self.cancelled = true;
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled
+    }
+
+    // Only there once the push is done
+    pub fn take_result(&self) -> Option<Result<(), Error>> {
+        self.job.take_result()
+    }
+}
+
+// Downloads the Git LFS objects of files at a commit, which goes over the network like a fetch. At HEAD their
+// content is put in the working tree as well
+pub struct LfsDownload {
+    pub commit: String,
+    job: JobHandle<()>,
+}
+
+impl LfsDownload {
+    pub fn start(path: PathBuf, commit: String, paths: Vec<String>, check_out: bool) -> Result<LfsDownload, Error> {
+        let thread_commit = commit.clone();
+        let job = JobHandle::spawn("lfs", move |_| {
+            let repository = Repository::open(path)?;
+            if check_out {
+                check_out_objects(&repository, &paths)
+            } else {
+                fetch_objects(&repository, &thread_commit, &paths)
+            }
+        })?;
+
+        Ok(LfsDownload { commit, job })
+    }
+
+    // Only there once the download is done
+    pub fn take_result(&self) -> Option<Result<(), Error>> {
+        self.job.take_result()
+    }
+}
@@ -0,0 +1,83 @@
+use std::cell::RefCell;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+use iced::widget::{text, Canvas, Column, Row, pick_list};
+use iced::{Alignment, Element, Length};
+
+use crate::backend::LayoutMode;
+use crate::config::{DisplayConfig, Orientation};
+use crate::dot::parse_dot;
+use crate::error::Error;
+use crate::settings::Settings;
+
+use super::tab::SharedState;
+use super::tree::{GraphColors, TreeRenderer};
+use super::Message;
+
+// A graph read from a DOT file, drawn like a history but without a repository behind it, so nothing on it can be
+// changed. Commits can still be selected to highlight what they are connected to
+pub struct ImportedGraph {
+    path: PathBuf,
+    state: Rc<RefCell<SharedState>>,
+    edges: usize,
+}
+
+impl ImportedGraph {
+    pub fn load(path: &Path, settings: &Settings) -> Result<ImportedGraph, Error> {
+        let mut state = SharedState::new(DisplayConfig::default(), settings.graph_size);
+        state.commits = parse_dot(&fs::read_to_string(path)?)?;
+        state.relayout();
+        let edges = state.commits.values().map(|commit| commit.parents.len()).sum();
+        Ok(ImportedGraph { path: path.to_path_buf(), state: Rc::new(RefCell::new(state)), edges })
+    }
+
+    pub fn name(&self) -> String {
+        self.path.file_name().unwrap_or(self.path.as_os_str()).to_string_lossy().to_string()
+    }
+
+    // Only how the graph is looked at can change, every other message has nothing to act on
+    pub fn update(&mut self, message: Message) {
+        let state = &mut *self.state.borrow_mut();
+        match message {
+            Message::SelectCommit(id) => state.selection.set(Some(id), &state.commits),
+            Message::UnselectCommit => state.selection.set(None, &state.commits),
+            Message::LayoutSelected(layout) => {
+                state.layout = layout;
+                state.relayout();
+            },
+            Message::OrientationSelected(orientation) => {
+                state.orientation = orientation;
+                state.relayout();
+            },
+            _ => (),
+        }
+    }
+
+    pub fn view(&self, high_contrast: bool, scale: f32, overlay: bool) -> Element<'_, Message> {
+        let colors = if high_contrast { &GraphColors::HIGH_CONTRAST } else { &GraphColors::NORMAL };
+        let state = self.state.borrow();
+        let selected = state.selection.commit.as_ref().and_then(|id| state.commits.get(id))
+            .map(|node| format!("Selected {}", node.summary))
+            .unwrap_or_default();
+        Column::with_children(vec![
+            Row::with_children(vec![
+                text(format!("{}, {} nodes and {} edges (read only)", self.path.display(), state.commits.len(), self.edges)).size(16).into(),
+                pick_list(&LayoutMode::ALL[..], Some(state.layout), Message::LayoutSelected).text_size(14).into(),
+                pick_list(&Orientation::ALL[..], Some(state.orientation), Message::OrientationSelected).text_size(14).into(),
+                text(selected).size(14).into(),
+            ])
+            .align_items(Alignment::Center)
+            .spacing(10)
+            .padding([0, 5])
+            .into(),
+            Canvas::new(TreeRenderer { state: Rc::clone(&self.state), colors, scale, overlay })
+                .width(Length::Fill)
+                .height(Length::Fill)
+                .into(),
+        ])
+        .spacing(5)
+        .into()
+    }
+}
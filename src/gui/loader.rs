@@ -0,0 +1,91 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use git2::Repository;
+
+use crate::backend::{CommitNode, ReferenceGroups, ReferenceLabel, count_commits, update_commits};
+use crate::cache::{load_commit_cache, save_commit_cache};
+use crate::error::Error;
+
+use super::worker::JobHandle;
+
+#[derive(Default)]
+struct LoadProgress {
+    walked: AtomicUsize,
+    // Zero until the commits have been counted
+    total: AtomicUsize,
+    // Only new commits are walked, and they aren't counted beforehand
+    update: AtomicBool,
+}
+
+pub struct LoadedTree {
+    pub commits: BTreeMap<String, CommitNode>,
+    // False when a refresh found the history as it was, so there is nothing to lay out again
+    pub changed: bool,
+}
+
+fn reference_labels(commits: &BTreeMap<String, CommitNode>) -> Vec<(String, Vec<ReferenceLabel>)> {
+    commits.values().filter(|commit| !commit.references.is_empty()).map(|commit| (commit.id.clone(), commit.references.clone())).collect()
+}
+
+// Builds the commit graph on its own thread; the GUI polls it until the result is ready. A replaced or abandoned load
+// is cancelled once dropped, since nobody is left to read its result
+pub struct TreeLoad {
+    progress: Arc<LoadProgress>,
+    job: JobHandle<LoadedTree>,
+}
+
+impl TreeLoad {
+    // Given the graph loaded before, only what changed since is walked. The graph is shared rather than copied, and
+    // only copied on the loader's thread if the tab still holds it by then
+    pub fn start(path: PathBuf, previous: Option<Arc<BTreeMap<String, CommitNode>>>, groups: ReferenceGroups) -> Result<TreeLoad, Error> {
+        let progress = Arc::new(LoadProgress { update: AtomicBool::new(previous.is_some()), ..Default::default() });
+
+        let thread_progress = Arc::clone(&progress);
+        let job = JobHandle::spawn("tree loader", move |cancel| {
+            let progress = thread_progress;
+            let repository = Repository::open(path)?;
+            // A tab opened before starts from the graph saved on disk then
+            let previous = previous.map(Arc::unwrap_or_clone).or_else(|| load_commit_cache(&repository));
+            let known = previous.as_ref().map_or(0, |previous| previous.len());
+            match previous {
+                Some(_) => progress.update.store(true, Ordering::Relaxed),
+                None => progress.total.store(count_commits(&repository, groups)?, Ordering::Relaxed),
+            }
+
+            // Labels alone moving, like a new tag on a commit already there, counts as a change
+            let labels = previous.as_ref().map(reference_labels);
+            let mut walked = 0;
+            let commits = update_commits(&repository, previous.unwrap_or_default(), groups, &mut |total| {
+                walked = total.saturating_sub(known);
+                progress.walked.store(walked, Ordering::Relaxed);
+                !cancel.is_cancelled()
+            })?.ok_or(Error::from("loading was cancelled"))?;
+
+            // Only rewritten when the history changed, which it did when commits were walked or dropped
+            let history_changed = walked > 0 || commits.len() != known;
+            if history_changed {
+                let _ = save_commit_cache(&repository, &commits);
+            }
+            let changed = history_changed || labels.is_none_or(|labels| labels != reference_labels(&commits));
+            Ok(LoadedTree { commits, changed })
+        })?;
+
+        Ok(TreeLoad { progress, job })
+    }
+
+    // Commits walked so far and the estimated total, which is zero while still counting
+    pub fn progress(&self) -> (usize, usize) {
+        (self.progress.walked.load(Ordering::Relaxed), self.progress.total.load(Ordering::Relaxed))
+    }
+
+    pub fn is_update(&self) -> bool {
+        self.progress.update.load(Ordering::Relaxed)
+    }
+
+    pub fn take_result(&self) -> Option<Result<LoadedTree, Error>> {
+        self.job.take_result()
+    }
+}
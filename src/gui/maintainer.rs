@@ -0,0 +1,28 @@
+use std::path::PathBuf;
+
+use git2::Repository;
+
+use crate::error::Error;
+use crate::maintenance::{MaintenanceReport, MaintenanceTask, run_task};
+
+use super::worker::JobHandle;
+
+type MaintenanceResult = Result<MaintenanceReport, Error>;
+
+// Runs a maintenance task on its own thread, since repacking a large repository takes a while
+pub struct MaintenanceJob {
+    pub task: MaintenanceTask,
+    job: JobHandle<MaintenanceReport>,
+}
+
+impl MaintenanceJob {
+    pub fn start(path: PathBuf, task: MaintenanceTask) -> Result<MaintenanceJob, Error> {
+        let job = JobHandle::spawn("maintainer", move |_| run_task(&Repository::open(path)?, task))?;
+        Ok(MaintenanceJob { task, job })
+    }
+
+    // Only there once the task is done
+    pub fn take_result(&self) -> Option<MaintenanceResult> {
+        self.job.take_result()
+    }
+}
@@ -0,0 +1,1528 @@
+mod chart;
+mod cloner;
+mod control;
+mod dialogs;
+mod external;
+mod fetcher;
+mod imported;
+mod loader;
+mod maintainer;
+mod reporter;
+mod tab;
+mod worker;
+pub mod tree;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::iter;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::time::Duration;
+
+use chrono::{DateTime, Local};
+use git2::Direction;
+
+use iced::widget::{text, Column, Row, button, pick_list, text_input};
+use iced::theme::Palette;
+use iced::keyboard::{self, key::Named, Key};
+use iced::multi_window::Application;
+use iced::window;
+use iced::{event, executor, Event, Alignment, Command, Element, Settings, Subscription, Length, Color, Theme};
+
+use crate::backend::init_repository;
+use crate::config::Orientation;
+use crate::error::Error;
+use crate::json::JsonValue;
+use crate::keymap::{Action, KeyChord};
+use crate::logging::{clear_log, recent_lines};
+use crate::maintenance::MaintenanceTask;
+use crate::preview::ImageCompareMode;
+use crate::session::Session;
+use crate::uistate::RepositoryUiState;
+use crate::settings::{BookmarkColor, DEFAULT_CONTROL_PORT, NodeClickAction, Settings as UserSettings, UiScale};
+
+use cloner::CloneJob;
+use control::{ControlCommand, ControlRequest, ControlServer};
+use dialogs::{CloneDialog, InitDialog, quick_switcher_input, view_clone_dialog, view_init_dialog, view_notification_drawer, view_debug_console, view_settings};
+use imported::ImportedGraph;
+use tab::{RepositoryTab, SelectionChange};
+pub use reporter::show_crash_report;
+use crate::backend::{DiffSettings, GraphSize, LayoutMode, ReferenceKind};
+
+const VERIFIED_COLOR: Color = Color::from_rgb(0.2, 0.6, 0.3);
+const UNTRUSTED_COLOR: Color = Color::from_rgb(0.85, 0.6, 0.2);
+const UNVERIFIED_COLOR: Color = Color::from_rgb(0.75, 0.25, 0.2);
+const ERROR_COLOR: Color = Color::from_rgb(0.75, 0.25, 0.2);
+const LINK_COLOR: Color = Color::from_rgb(0.2, 0.4, 0.8);
+
+fn bookmark_color(color: BookmarkColor) -> Color {
+    match color {
+        BookmarkColor::Red => Color::from_rgb(0.85, 0.2, 0.2),
+        BookmarkColor::Orange => Color::from_rgb(0.95, 0.55, 0.1),
+        BookmarkColor::Yellow => Color::from_rgb(0.9, 0.8, 0.1),
+        BookmarkColor::Green => Color::from_rgb(0.2, 0.7, 0.3),
+        BookmarkColor::Blue => Color::from_rgb(0.2, 0.45, 0.9),
+        BookmarkColor::Purple => Color::from_rgb(0.6, 0.3, 0.8),
+    }
+}
+
+// The directory a repository is checked out in, or the git directory of a bare one
+fn repository_name(root: &Path) -> String {
+    match root.file_name() {
+        Some(name) => name.to_string_lossy().to_string(),
+        None => root.display().to_string(),
+    }
+}
+
+struct ErrorBanner {
+    message: String,
+    details: String,
+    expanded: bool,
+}
+
+impl ErrorBanner {
+    fn new(context: &str, error: &Error) -> ErrorBanner {
+        tracing::error!("{}: {}", context, error.details());
+        ErrorBanner { message: format!("{}: {}", context, error.message()), details: error.details(), expanded: false }
+    }
+}
+
+// Feedback from the last operation, shown in the status bar until the next one reports and kept in the drawer after
+#[derive(Debug, Clone)]
+pub enum Notification {
+    Info(String),
+    Failure(String),
+}
+
+impl Notification {
+    fn from_result(result: &Result<String, String>, failure: &str) -> Notification {
+        match result {
+            Ok(message) => Notification::Info(message.clone()),
+            Err(e) => Notification::Failure(format!("{}: {}", failure, e)),
+        }
+    }
+}
+
+// Oldest notifications are dropped from the drawer past this many
+const NOTIFICATION_LOG_LENGTH: usize = 200;
+
+// How much a zoom shortcut zooms by
+const ZOOM_STEP: f32 = 1.25;
+
+// None for a modifier pressed on its own, which only becomes part of the chord of the next key
+fn key_chord(key: &Key, modifiers: keyboard::Modifiers) -> Option<KeyChord> {
+    Some(KeyChord { control: modifiers.control(), alt: modifiers.alt(), shift: modifiers.shift(), logo: modifiers.logo(), key: key_name(key)? })
+}
+
+// iced now names keys by what they type, so they are given the names the bindings were saved with back
+fn key_name(key: &Key) -> Option<String> {
+    let name = match key {
+        Key::Named(Named::Shift | Named::Control | Named::Alt | Named::AltGraph | Named::Super | Named::Meta | Named::Hyper) | Key::Unidentified => return None,
+        Key::Named(Named::ArrowLeft) => "Left",
+        Key::Named(Named::ArrowRight) => "Right",
+        Key::Named(Named::ArrowUp) => "Up",
+        Key::Named(Named::ArrowDown) => "Down",
+        Key::Named(named) => return Some(format!("{:?}", named)),
+        Key::Character(character) => match character.as_str() {
+            "=" => "Equals",
+            "+" => "Plus",
+            "-" => "Minus",
+            "/" => "Slash",
+            "\\" => "Backslash",
+            "," => "Comma",
+            "." => "Period",
+            ";" => "Semicolon",
+            "'" => "Apostrophe",
+            "`" => "Grave",
+            "[" => "LBracket",
+            "]" => "RBracket",
+            digit if digit.len() == 1 && digit.chars().all(|c| c.is_ascii_digit()) => return Some(format!("Key{}", digit)),
+            other => return Some(other.to_uppercase()),
+        },
+    };
+    Some(name.to_string())
+}
+
+// What a window besides the main one shows. They all show the state of the one app, so a repository moved into a
+// window of its own is the same tab it was, and the details in a window follow the commit selected in its graph
+enum Window {
+    // Back among the main window's tabs once the window is closed
+    Repository(Box<RepositoryTab>),
+    // The details of the commit selected in the tab of this repository
+    CommitDetails(PathBuf),
+    // A graph from a DOT file instead of a repository. The main window shows one in place of the tabs when gitui
+    // was started with `--dot`
+    DotGraph(ImportedGraph),
+}
+
+// Where a tab is kept: among the main window's tabs, or alone in a window of its own
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TabSlot {
+    Main(usize),
+    Window(window::Id),
+}
+
+// Closing is handled in update, so the session can be saved and tabs handed back first
+fn window_settings() -> window::Settings {
+    window::Settings { exit_on_close_request: false, ..Default::default() }
+}
+
+// The tabs of the main window along with those moved into windows of their own
+fn all_tabs_mut<'a>(tabs: &'a mut [RepositoryTab], windows: &'a mut HashMap<window::Id, Window>) -> impl Iterator<Item = &'a mut RepositoryTab> {
+    tabs.iter_mut().chain(windows.values_mut().filter_map(|window| match window {
+        Window::Repository(tab) => Some(tab.as_mut()),
+        _ => None,
+    }))
+}
+
+// Null while no repository is open
+fn selection_json(selection: &Option<(PathBuf, Option<String>)>) -> JsonValue {
+    match selection {
+        Some((repository, commit)) => JsonValue::Object(vec![
+            (String::from("repository"), JsonValue::String(repository.display().to_string())),
+            (String::from("commit"), commit.clone().map_or(JsonValue::Null, JsonValue::String)),
+        ]),
+        None => JsonValue::Null,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AutoFetch {
+    Off,
+    Minutes(u64),
+}
+
+impl AutoFetch {
+    const ALL: [AutoFetch; 5] = [AutoFetch::Off, AutoFetch::Minutes(1), AutoFetch::Minutes(5), AutoFetch::Minutes(15), AutoFetch::Minutes(30)];
+}
+
+impl fmt::Display for AutoFetch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AutoFetch::Off => write!(f, "Auto-fetch off"),
+            AutoFetch::Minutes(1) => write!(f, "Auto-fetch every minute"),
+            AutoFetch::Minutes(minutes) => write!(f, "Auto-fetch every {} minutes", minutes),
+        }
+    }
+}
+
+pub struct GitUI {
+    // Every window but the main one, unless the main one shows a DOT file
+    windows: HashMap<window::Id, Window>,
+    // Where the message being handled came from, so it goes to the tab shown there
+    current_window: window::Id,
+    // Where keys pressed go
+    focused_window: window::Id,
+    // Started with `--dot`, so the session of the last start is neither reopened nor saved over
+    dot_only: bool,
+    tabs: Vec<RepositoryTab>,
+    active_tab: usize,
+    open_path: String,
+    errors: Vec<ErrorBanner>,
+    notification_sender: Sender<Notification>,
+    notifications: Receiver<Notification>,
+    last_notification: Option<Notification>,
+    // Every notification since the app started, with when it came in, so one that was replaced in the status bar
+    // before it was read isn't lost
+    notification_log: Vec<(DateTime<Local>, Notification)>,
+    // Come in since the drawer was last opened
+    unread_notifications: usize,
+    notification_drawer: bool,
+    auto_fetch: AutoFetch,
+    clone_dialog: Option<CloneDialog>,
+    init_dialog: Option<InitDialog>,
+    settings: UserSettings,
+    settings_open: bool,
+    // Action whose shortcut is set by the next key pressed
+    recording_key: Option<Action>,
+    debug_console: bool,
+    // Not started along with a DOT file, which has no repository to control
+    control: Option<ControlServer>,
+    // The repository and commit last reported to the connected tools as selected
+    reported_selection: Option<(PathBuf, Option<String>)>,
+}
+
+#[derive(Debug, Clone)]
+pub enum Message {
+    RefreshTree,
+    TreeLoadProgressed,
+    CommitDetailsProgressed,
+    RangeDiffProgressed,
+    SignatureChecked,
+    CancelTreeLoad,
+    LoadTick,
+    // Redraws the canvas while something on it is animating
+    AnimationFrame,
+    ToggleReferenceSidebar,
+    ToggleLegend,
+    ReferenceToggled(usize, bool),
+    ShowAllReferences,
+    ReferenceGroupToggled(ReferenceKind, bool),
+    ToggleReferenceFolder(ReferenceKind, String),
+    // Shows or hides every reference in the folder
+    ReferenceFolderToggled(ReferenceKind, String, bool),
+    StartRenameBranch(String),
+    RenameBranchChanged(String),
+    RenameBranch,
+    CancelRenameBranch,
+    OpenTrackingDialog,
+    CloseTrackingDialog,
+    UpstreamSelected(String, String),
+    PushTargetSelected(String, String),
+    UnsetUpstream(String),
+    OpenRemoteBrowser,
+    CloseRemoteBrowser,
+    // A configured remote picked or a URL typed
+    RemoteBrowserRemoteChanged(String),
+    ListRemoteReferences,
+    RemoteReferenceToggled(String, bool),
+    FetchRemoteReferences,
+    RemoteBrowserProgressed,
+    OpenRefspecEditor,
+    CloseRefspecEditor,
+    RefspecRemoteSelected(String),
+    RefspecChanged(Direction, usize, String),
+    AddRefspec(Direction),
+    RemoveRefspec(Direction, usize),
+    AddPullRequestRefspec,
+    // Whether to fetch with them right away
+    SaveRefspecs(bool),
+    LayoutSelected(LayoutMode),
+    OrientationSelected(Orientation),
+    CollapseChainsToggled(bool),
+    ExpandChain(String),
+    FirstParentToggled(bool),
+    ExpandMerge(String),
+    ToggleFilterPanel,
+    FilterAuthorSelected(String),
+    FilterSinceChanged(String),
+    FilterUntilChanged(String),
+    ClearFilters,
+    SelectCommit(String),
+    // Selects a commit and centers the view on it
+    JumpToCommit(String),
+    SelectionBack,
+    SelectionForward,
+    // A reference label on the graph was clicked, with the commit it is drawn next to
+    SelectReference(String, String),
+    CheckoutReference(String),
+    CompareReference(String),
+    DeleteReference(String),
+    // Turned into what the settings say these clicks do
+    CommitDoubleClicked(String),
+    CommitMiddleClicked(String),
+    OpenCommitDetails(String),
+    // Of the files in the commit shown in the details
+    DownloadLfsObjects,
+    DiffSettingsChanged(DiffSettings),
+    // None shows the patches of all the files again
+    SelectDiffFile(Option<String>),
+    ToggleDiffDirectory(String),
+    // The commit and the path of the file to restore from it
+    RestoreFile(String, String),
+    RestoreToIndexToggled(bool),
+    ConfirmRestore,
+    CancelRestore,
+    ImageCompareModeSelected(ImageCompareMode),
+    ImageComparePositionChanged(f32),
+    LfsDownloadProgressed,
+    CloseCommitDetails,
+    // The repository typed into the path field, or else the one of the active tab
+    OpenRepositoryWindow,
+    // Opens the file in the path box as a DOT graph, in a window of its own
+    OpenDotWindow,
+    OpenCommitWindow(String),
+    // A message from a window besides the main one, handled for the tab shown there
+    Window(window::Id, Box<Message>),
+    WindowFocused(window::Id),
+    CopyCommitId(String),
+    UnselectCommit,
+    SelectRangeEnd(String),
+    SelectionChanged(SelectionChange),
+    SwitchToCommit(String),
+    OpenUrl(String),
+    OpenTerminal,
+    Undo,
+    DetachedBranchNameChanged(String),
+    CreateBranchAtHead,
+    ReturnToBranch(String),
+    OpenTagDialog,
+    CloseTagDialog,
+    TagVersionChanged(String),
+    TagMessageChanged(String),
+    TagTemplateChanged(String),
+    TagBranchToggled(usize, bool),
+    TagRepositoryToggled(usize, bool),
+    CreateTags,
+    OpenCompareDialog,
+    CloseCompareDialog,
+    CompareLeftSelected(String),
+    CompareRightSelected(String),
+    OpenCommitComposer,
+    CloseCommitComposer,
+    CommitSubjectChanged(String),
+    CommitBodyLineChanged(usize, String),
+    InsertCommitBodyLine(usize),
+    RemoveCommitBodyLine(usize),
+    CommitTypeSelected(&'static str),
+    CommitScopeChanged(String),
+    CommitAmendToggled(bool),
+    CommitSignToggled(bool),
+    CommitSkipHooksToggled(bool),
+    CreateCommit,
+    // The commit the current changes are meant for, and whether they squash into it rather than fix it up
+    CreateFixupCommit(String, bool),
+    Autosquash,
+    OpenWorktreePanel,
+    CloseWorktreePanel,
+    WorktreeNameChanged(String),
+    WorktreePathChanged(String),
+    CreateWorktree,
+    OpenWorktree(PathBuf),
+    OpenExportDialog,
+    CloseExportDialog,
+    ExportPathChanged(String),
+    ExportVisibleToggled(bool),
+    ExportGraph,
+    OpenArchiveDialog(String),
+    CloseArchiveDialog,
+    ArchivePathChanged(String),
+    ArchivePrefixChanged(String),
+    ArchiveCommit,
+    DropReference(String, String),
+    CloseDropMenu,
+    RebaseDroppedBranch,
+    MergeDroppedBranch,
+    DropCommit(String, String),
+    ApplyReorder,
+    CancelReorder,
+    ToggleStatusPanel,
+    ToggleStatisticsPanel,
+    StatisticsProgressed,
+    ContinueOperation,
+    AbortOperation,
+    DismissInterruptedOperation,
+    ToggleHealthPanel,
+    ToggleMaintenancePanel,
+    RunMaintenance(MaintenanceTask),
+    MaintenanceProgressed,
+    CheckHealth,
+    // The fix of the health issue at that index
+    ApplyHealthFix(usize),
+    ToggleReleaseTimeline,
+    ChangelogPathChanged(String),
+    ExportChangelog,
+    SelectStatusFile(String, bool),
+    StageFile(String),
+    UnstageFile(String),
+    ApplyHunk(usize),
+    DiffLineToggled(usize, usize, bool),
+    ApplySelectedLines,
+    DiscardFile(String),
+    OpenDifftool,
+    OpenMergetool(String),
+    ExternalToolProgressed,
+    ConfirmDiscard,
+    CancelDiscard,
+    OpenIgnoreMenu(String),
+    CloseIgnoreMenu,
+    IgnorePattern(String),
+    OpenGitignoreEditor,
+    CloseGitignoreEditor,
+    GitignoreLineChanged(usize, String),
+    AddGitignoreLine,
+    RemoveGitignoreLine(usize),
+    SaveGitignore,
+    Fetch,
+    Push,
+    ForcePush,
+    RebaseBeforePush,
+    CancelPush,
+    PushProgressed,
+    CancelPushing,
+    FetchProgressed,
+    CancelFetch,
+    AutoFetchTick,
+    AutoFetchSelected(AutoFetch),
+    OpenSettings,
+    CloseSettings,
+    ToggleNotificationDrawer,
+    ToggleDebugConsole,
+    ClearDebugConsole,
+    ClearNotifications,
+    UiScaleSelected(UiScale),
+    HighContrastToggled(bool),
+    RepositoryStateToggled(bool),
+    PerformanceOverlayToggled(bool),
+    ControlServerToggled(bool),
+    // Picks up what the connected tools asked for
+    ControlTick,
+    // Previewed while a slider is dragged, and saved once it is let go
+    GraphSizeChanged(GraphSize),
+    GraphSizeSettled,
+    DoubleClickSelected(NodeClickAction),
+    MiddleClickSelected(NodeClickAction),
+    KeyPressed(KeyChord),
+    ToggleQuickSwitcher,
+    ToggleBookmarkList,
+    // Opens the editor for the bookmark of the commit, made new if it has none
+    EditBookmark(String),
+    BookmarkLabelChanged(String),
+    BookmarkColorSelected(BookmarkColor),
+    SaveBookmark,
+    CancelBookmark,
+    RemoveBookmark(String),
+    EditNote(String),
+    NoteLineChanged(usize, String),
+    InsertNoteLine(usize),
+    RemoveNoteLine(usize),
+    SaveNote,
+    RemoveNote,
+    CancelNote,
+    QuickSwitchQueryChanged(String),
+    QuickSwitchMove(isize),
+    QuickSwitchSubmit,
+    // The match to take, among those shown
+    QuickSwitchPick(usize),
+    RecordKeyBinding(Action),
+    ClearKeyBinding(Action),
+    CheckoutSelected,
+    CloseRequested(window::Id),
+    Zoom(f32),
+    OpenInitDialog,
+    CloseInitDialog,
+    InitPathChanged(String),
+    InitBranchChanged(String),
+    InitialCommitToggled(bool),
+    InitRepository,
+    OpenCloneDialog,
+    CloseCloneDialog,
+    CloneUrlChanged(String),
+    CloneDestinationChanged(String),
+    CloneDepthChanged(String),
+    CloneSingleBranchToggled(bool),
+    CloneBranchChanged(String),
+    StartClone,
+    CancelClone,
+    SelectTab(usize),
+    CloseTab(usize),
+    OpenPathChanged(String),
+    OpenRepository,
+    DismissError(usize),
+    ToggleErrorDetails(usize),
+}
+
+impl GitUI {
+    fn run_click_action(&mut self, action: NodeClickAction, id: String) -> Command<Message> {
+        let message = match action {
+            NodeClickAction::Nothing => return Command::none(),
+            NodeClickAction::Select => Message::SelectCommit(id),
+            NodeClickAction::OpenDetails => Message::OpenCommitDetails(id),
+            NodeClickAction::CopyId => Message::CopyCommitId(id),
+            NodeClickAction::Checkout => Message::SwitchToCommit(id),
+        };
+        self.update(message)
+    }
+
+    fn update_bookmarks(&mut self) {
+        for tab in all_tabs_mut(&mut self.tabs, &mut self.windows) {
+            let stored = self.settings.repository_state.then(|| RepositoryUiState::load(tab.git_directory())).flatten();
+            tab.set_bookmarks(match stored {
+                Some(stored) => stored.bookmarks,
+                None => self.settings.bookmarks_for(tab.git_directory()),
+            });
+        }
+    }
+
+    // Bookmarks are written as soon as they change, so the ones in the file are kept in case another window saved some
+    fn save_repository_state(tab: &RepositoryTab) -> Result<(), Error> {
+        let mut state = tab.repository_state();
+        if let Some(stored) = RepositoryUiState::load(tab.git_directory()) {
+            state.bookmarks = stored.bookmarks;
+        }
+        state.save(tab.git_directory())
+    }
+
+    fn save_settings(&mut self) {
+        if let Err(e) = self.settings.save() {
+            self.errors.push(ErrorBanner::new("Error saving settings", &e));
+        }
+    }
+
+    // With a DOT file to show in place of the tabs
+    pub fn start(dot: Option<PathBuf>) {
+        Self::run(Settings {
+            flags: dot,
+            antialiasing: true,
+            window: window_settings(),
+            ..Default::default()
+        }).unwrap()
+    }
+}
+
+impl GitUI {
+    fn with_tabs(settings: UserSettings, tabs: Vec<RepositoryTab>, active_tab: usize, errors: Vec<ErrorBanner>, notification_sender: Sender<Notification>, notifications: Receiver<Notification>) -> GitUI {
+        GitUI { windows: HashMap::new(), current_window: window::Id::MAIN, focused_window: window::Id::MAIN, dot_only: false, tabs, active_tab, open_path: String::new(), errors, notification_sender, notifications, last_notification: None, notification_log: Vec::new(), unread_notifications: 0, notification_drawer: false, auto_fetch: AutoFetch::Off, clone_dialog: None, init_dialog: None, settings, settings_open: false, recording_key: None, debug_console: false, control: None, reported_selection: None }
+    }
+
+    fn notify(&mut self, notification: Notification) {
+        if self.notification_log.len() >= NOTIFICATION_LOG_LENGTH {
+            self.notification_log.remove(0);
+        }
+        self.notification_log.push((Local::now(), notification.clone()));
+        if !self.notification_drawer {
+            self.unread_notifications += 1;
+        }
+        self.last_notification = Some(notification);
+    }
+
+    fn open_window(&mut self, window: Window) -> Command<Message> {
+        let (id, command) = window::spawn(window_settings());
+        self.windows.insert(id, window);
+        command
+    }
+
+    // A repository window hands its tab back to the main window, and a details window leaves the details to it
+    fn close_window(&mut self, id: window::Id) -> Command<Message> {
+        match self.windows.remove(&id) {
+            Some(Window::Repository(tab)) => self.tabs.push(*tab),
+            Some(Window::CommitDetails(path)) => {
+                if let Some(tab) = self.slot_of(&path).and_then(|slot| self.tab_mut(slot)) {
+                    tab.set_details_window(false);
+                    let _ = tab.update(Message::CloseCommitDetails);
+                }
+            },
+            Some(Window::DotGraph(_)) | None => (),
+        }
+        if self.focused_window == id {
+            self.focused_window = window::Id::MAIN;
+        }
+        window::close(id)
+    }
+
+    // The details windows of a repository whose tab is gone have nothing left to show
+    fn close_details_windows(&mut self, path: &Path) -> Command<Message> {
+        let ids: Vec<window::Id> = self.windows.iter().filter(|(_, window)| matches!(window, Window::CommitDetails(shown) if shown == path)).map(|(id, _)| *id).collect();
+        Command::batch(ids.into_iter().map(|id| self.close_window(id)).collect::<Vec<_>>())
+    }
+
+    fn remove_tab(&mut self, index: usize) -> RepositoryTab {
+        let tab = self.tabs.remove(index);
+        if self.active_tab >= index && self.active_tab > 0 {
+            self.active_tab -= 1;
+        }
+        tab
+    }
+
+    fn tab(&self, slot: TabSlot) -> Option<&RepositoryTab> {
+        match slot {
+            TabSlot::Main(index) => self.tabs.get(index),
+            TabSlot::Window(id) => match self.windows.get(&id) {
+                Some(Window::Repository(tab)) => Some(tab.as_ref()),
+                _ => None,
+            },
+        }
+    }
+
+    fn tab_mut(&mut self, slot: TabSlot) -> Option<&mut RepositoryTab> {
+        match slot {
+            TabSlot::Main(index) => self.tabs.get_mut(index),
+            TabSlot::Window(id) => match self.windows.get_mut(&id) {
+                Some(Window::Repository(tab)) => Some(tab.as_mut()),
+                _ => None,
+            },
+        }
+    }
+
+    // The main window's tab of the repository, or else the window it was moved into
+    fn slot_of(&self, path: &Path) -> Option<TabSlot> {
+        self.tabs.iter().position(|tab| tab.path() == path).map(TabSlot::Main).or_else(|| {
+            self.windows.iter().find_map(|(id, window)| matches!(window, Window::Repository(tab) if tab.path() == path).then_some(TabSlot::Window(*id)))
+        })
+    }
+
+    // The one a repository window shows, the one a details window follows, or the active one of the main window
+    fn slot_shown_in(&self, id: window::Id) -> Option<TabSlot> {
+        match self.windows.get(&id) {
+            Some(Window::Repository(_)) => Some(TabSlot::Window(id)),
+            Some(Window::CommitDetails(path)) => self.slot_of(path),
+            Some(Window::DotGraph(_)) => None,
+            None => (self.active_tab < self.tabs.len()).then_some(TabSlot::Main(self.active_tab)),
+        }
+    }
+
+    // The tab shown in the window the message came from
+    fn current_slot(&self) -> Option<TabSlot> {
+        self.slot_shown_in(self.current_window)
+    }
+
+    fn window_tabs(&self) -> impl Iterator<Item = &RepositoryTab> {
+        self.windows.values().filter_map(|window| match window {
+            Window::Repository(tab) => Some(tab.as_ref()),
+            _ => None,
+        })
+    }
+
+    fn all_slots(&self) -> impl Iterator<Item = TabSlot> + '_ {
+        (0..self.tabs.len()).map(TabSlot::Main).chain(self.windows.iter().filter(|(_, window)| matches!(window, Window::Repository(_))).map(|(id, _)| TabSlot::Window(*id)))
+    }
+
+    fn current_tab(&self) -> Option<&RepositoryTab> {
+        self.current_slot().and_then(|slot| self.tab(slot))
+    }
+
+    fn current_tab_mut(&mut self) -> Option<&mut RepositoryTab> {
+        self.current_slot().and_then(|slot| self.tab_mut(slot))
+    }
+
+    fn start_control_server(&mut self) {
+        let Some(port) = self.settings.control_port.filter(|_| !self.dot_only) else {
+            return;
+        };
+        match ControlServer::start(port) {
+            Ok(control) => self.control = Some(control),
+            Err(e) => self.errors.push(ErrorBanner::new(&format!("Error listening for commands on port {}", port), &e)),
+        }
+    }
+
+    // The tab showing `repository`, or the one of the focused window when no repository is named
+    fn control_slot(&self, repository: Option<&Path>) -> Result<TabSlot, String> {
+        let Some(repository) = repository else {
+            return self.slot_shown_in(self.focused_window).ok_or(String::from("no repository is open"));
+        };
+        let wanted = repository.canonicalize().map_err(|e| format!("{}: {}", repository.display(), e))?;
+        self.all_slots().find(|slot| self.tab(*slot).is_some_and(|tab| tab.path().canonicalize().is_ok_and(|path| path == wanted)))
+            .ok_or(format!("{} isn't open", repository.display()))
+    }
+
+    // Switches to a tab of the main window, or brings the window a tab was moved into to the front
+    fn show_slot(&mut self, slot: TabSlot) -> Command<Message> {
+        match slot {
+            TabSlot::Main(index) => {
+                self.active_tab = index;
+                Command::none()
+            },
+            TabSlot::Window(id) => window::gain_focus(id),
+        }
+    }
+
+    fn handle_control_request(&mut self, request: ControlRequest) -> Command<Message> {
+        let mut command = Command::none();
+        let result = match request.command.clone() {
+            ControlCommand::SelectCommit { commit, repository } => self.control_slot(repository.as_deref()).and_then(|slot| {
+                command = self.show_slot(slot);
+                let tab = self.tab_mut(slot).ok_or(String::from("no repository is open"))?;
+                let id = tab.resolve_commit(&commit).map_err(|e| e.message())?;
+                tab.update(Message::JumpToCommit(id.clone())).map_err(|e| e.message())?;
+                Ok(JsonValue::Object(vec![(String::from("commit"), JsonValue::String(id))]))
+            }),
+            ControlCommand::Refresh { repository } => self.control_slot(repository.as_deref()).and_then(|slot| {
+                let tab = self.tab_mut(slot).ok_or(String::from("no repository is open"))?;
+                tab.update(Message::RefreshTree).map_err(|e| e.message())?;
+                Ok(JsonValue::Null)
+            }),
+            // A repository that is already open is only switched to
+            ControlCommand::OpenRepository(path) => match self.control_slot(Some(&path)) {
+                Ok(slot) => {
+                    command = self.show_slot(slot);
+                    Ok(JsonValue::Null)
+                },
+                Err(_) => RepositoryTab::open(&path, &self.settings, self.notification_sender.clone()).map(|tab| {
+                    self.notify(Notification::Info(format!("Opened {}", tab.path().display())));
+                    self.tabs.push(tab);
+                    self.active_tab = self.tabs.len() - 1;
+                    JsonValue::Null
+                }).map_err(|e| e.message()),
+            },
+            ControlCommand::GetSelection => Ok(selection_json(&self.current_selection())),
+        };
+        request.reply(result);
+        command
+    }
+
+    // Of the tab in the focused window
+    fn current_selection(&self) -> Option<(PathBuf, Option<String>)> {
+        self.slot_shown_in(self.focused_window).and_then(|slot| self.tab(slot)).map(|tab| (tab.path().to_path_buf(), tab.selected_commit()))
+    }
+
+    // Tells the connected tools whenever the selection moved, whether they moved it or it was picked on the graph
+    fn report_selection(&mut self) {
+        let Some(control) = &self.control else {
+            return;
+        };
+        let selection = self.current_selection();
+        if selection != self.reported_selection {
+            control.broadcast("selection_changed", selection_json(&selection));
+            self.reported_selection = selection;
+        }
+    }
+
+    // Opens the clone in a new tab once it is done
+    fn poll_clone(&mut self) {
+        let dialog = match &mut self.clone_dialog {
+            Some(dialog) => dialog,
+            None => return,
+        };
+        let result = match dialog.job.as_ref().and_then(|job| job.take_result()) {
+            Some(result) => result,
+            None => return,
+        };
+        dialog.job = None;
+
+        match result.and_then(|path| RepositoryTab::open(&path, &self.settings, self.notification_sender.clone())) {
+            Ok(tab) => {
+                self.notify(Notification::Info(format!("Cloned into {}", tab.path().display())));
+                self.tabs.push(tab);
+                self.active_tab = self.tabs.len() - 1;
+                self.clone_dialog = None;
+            },
+            Err(e) => {
+                self.notify(Notification::Failure(String::from("Cloning failed")));
+                self.errors.push(ErrorBanner::new("Error cloning", &e));
+            },
+        }
+    }
+
+    fn view_main(&self) -> Element<'_, Message> {
+        Column::with_children({
+            let mut children: Vec<Element<Message>> = Vec::new();
+
+            // Started for a DOT file, there are no tabs to pick from or repositories to open
+            if !self.dot_only {
+                children.push(Row::with_children({
+                    let mut children: Vec<Element<Message>> = Vec::new();
+
+                    for (i, tab) in self.tabs.iter().enumerate() {
+                        let label = if i == self.active_tab { format!("[{}]", tab.name()) } else { tab.name() };
+                        children.push(button(text(label)).on_press(Message::SelectTab(i)).into());
+                        children.push(button("x").on_press(Message::CloseTab(i)).into());
+                    }
+
+                    children.push(text_input("Repository path", &self.open_path)
+                        .on_input(Message::OpenPathChanged)
+                        .on_submit(Message::OpenRepository)
+                        .width(300)
+                        .into());
+                    children.push(button("Open").on_press(Message::OpenRepository).into());
+                    children.push(button("New window").on_press(Message::OpenRepositoryWindow).into());
+                    children.push(button("View DOT").on_press(Message::OpenDotWindow).into());
+                    children.push(button("New").on_press(Message::OpenInitDialog).into());
+                    children.push(button("Settings").on_press(Message::OpenSettings).into());
+                    children.push(button("Clone").on_press(Message::OpenCloneDialog).into());
+
+                    children
+                })
+                .align_items(Alignment::Center)
+                .spacing(5)
+                .padding(5)
+                .into());
+            }
+
+            // Always present, even when empty, so the widgets below keep their state when an error or one of these dialogs shows up
+            children.push(Column::with_children({
+                let mut children: Vec<Element<Message>> = Vec::new();
+
+                if self.settings_open {
+                    children.push(view_settings(&self.settings, self.recording_key));
+                }
+
+                if self.notification_drawer {
+                    children.push(view_notification_drawer(&self.notification_log));
+                }
+                if self.debug_console {
+                    children.push(view_debug_console(recent_lines()));
+                }
+
+                if let Some(dialog) = &self.init_dialog {
+                    children.push(view_init_dialog(dialog));
+                }
+
+                if let Some(dialog) = &self.clone_dialog {
+                    children.push(view_clone_dialog(dialog));
+                }
+
+                children.extend(self.view_errors());
+
+                children
+            })
+            .spacing(5)
+            .padding([0, 5])
+            .into());
+
+            let tab = self.tabs.get(self.active_tab);
+            if let Some(Window::DotGraph(graph)) = self.windows.get(&window::Id::MAIN) {
+                children.push(graph.view(self.settings.high_contrast, self.settings.ui_scale.factor() as f32, self.settings.performance_overlay));
+            } else if let Some(tab) = tab {
+                children.push(tab.view(self.settings.high_contrast, self.settings.ui_scale.factor() as f32, self.settings.performance_overlay));
+            } else {
+                children.push(Column::new().height(Length::Fill).into());
+            }
+
+            children.push(self.view_status_bar(tab));
+
+            children
+        })
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .into()
+    }
+
+    // The dialogs and panels stay in the main window, while errors show up in every window
+    fn view_window<'a>(&'a self, window: &'a Window) -> Element<'a, Message> {
+        let scale = self.settings.ui_scale.factor() as f32;
+        let (content, tab) = match window {
+            Window::Repository(tab) => (tab.view(self.settings.high_contrast, scale, self.settings.performance_overlay), Some(tab.as_ref())),
+            Window::CommitDetails(path) => match self.slot_of(path).and_then(|slot| self.tab(slot)) {
+                Some(tab) => (tab.view_commit_window(), Some(tab)),
+                None => (Column::new().height(Length::Fill).into(), None),
+            },
+            Window::DotGraph(graph) => (graph.view(self.settings.high_contrast, scale, self.settings.performance_overlay), None),
+        };
+        Column::with_children(vec![
+            Column::with_children(self.view_errors()).spacing(5).padding([0, 5]).into(),
+            content,
+            self.view_status_bar(tab),
+        ])
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .into()
+    }
+
+    fn view_errors(&self) -> Vec<Element<'_, Message>> {
+        let mut children: Vec<Element<Message>> = Vec::new();
+        for (i, error) in self.errors.iter().enumerate() {
+            children.push(Row::with_children(vec![
+                text(&error.message).style(ERROR_COLOR).into(),
+                button(if error.expanded { "Hide details" } else { "Details" }).on_press(Message::ToggleErrorDetails(i)).into(),
+                button("Dismiss").on_press(Message::DismissError(i)).into(),
+            ])
+            .align_items(Alignment::Center)
+            .spacing(5)
+            .into());
+
+            if error.expanded {
+                children.push(text(&error.details).size(14).into());
+            }
+        }
+        children
+    }
+
+    // About the tab shown in the window
+    fn view_status_bar<'a>(&'a self, tab: Option<&'a RepositoryTab>) -> Element<'a, Message> {
+        Row::with_children({
+            let mut children: Vec<Element<Message>> = Vec::new();
+
+            if let Some(tab) = tab {
+                children.push(text(tab.path().display()).size(14).into());
+                children.push(text(tab.head()).size(14).into());
+                let dirty = match tab.dirty_files() {
+                    Some(0) => String::from("clean"),
+                    Some(count) => format!("{} changed files", count),
+                    None => String::from("no working directory"),
+                };
+                children.push(text(dirty).size(14).into());
+            }
+
+            children.push(pick_list(&AutoFetch::ALL[..], Some(self.auto_fetch), Message::AutoFetchSelected).text_size(14).into());
+
+            let drawer = match self.unread_notifications {
+                0 => String::from("Notifications"),
+                unread => format!("Notifications ({})", unread),
+            };
+            children.push(button(text(drawer).size(14)).on_press(Message::ToggleNotificationDrawer).into());
+
+            match &self.last_notification {
+                Some(Notification::Info(message)) => children.push(text(message).size(14).into()),
+                Some(Notification::Failure(message)) => children.push(text(message).size(14).style(ERROR_COLOR).into()),
+                None => (),
+            }
+
+            children
+        })
+        .align_items(Alignment::Center)
+        .spacing(20)
+        .padding(5)
+        .into()
+    }
+}
+
+impl Application for GitUI {
+    type Executor = executor::Default;
+    type Message = Message;
+    type Theme = Theme;
+    type Flags = Option<PathBuf>;
+
+    fn new(dot: Option<PathBuf>) -> (Self, Command<Message>) {
+        let (notification_sender, notifications) = channel();
+        let settings = UserSettings::load();
+        let mut tabs = Vec::new();
+        let mut errors = Vec::new();
+        let mut active_tab = 0;
+
+        if let Some(path) = dot {
+            let mut gui = Self::with_tabs(settings, tabs, 0, errors, notification_sender, notifications);
+            gui.dot_only = true;
+            match ImportedGraph::load(&path, &gui.settings) {
+                Ok(graph) => {
+                    gui.windows.insert(window::Id::MAIN, Window::DotGraph(graph));
+                },
+                Err(e) => gui.errors.push(ErrorBanner::new(&format!("Error reading {}", path.display()), &e)),
+            }
+            return (gui, Command::none());
+        }
+
+        // The tabs of the last session come back as they were left
+        let session = Session::load();
+        for (i, saved) in session.tabs.iter().enumerate() {
+            match RepositoryTab::open(&saved.path, &settings, notification_sender.clone()) {
+                Ok(mut tab) => {
+                    tab.restore(saved);
+                    if i == session.active {
+                        active_tab = tabs.len();
+                    }
+                    tabs.push(tab);
+                },
+                Err(e) => errors.push(ErrorBanner::new(&format!("Error reopening {}", saved.path.display()), &e)),
+            }
+        }
+
+        // The repository in the working directory is opened too, and shown since it was likely started there for it.
+        // Without one the app still starts, so one can be opened by path
+        match RepositoryTab::open(&PathBuf::from("."), &settings, notification_sender.clone()) {
+            Ok(tab) if !tabs.iter().any(|open| open.path() == tab.path()) => {
+                active_tab = tabs.len();
+                tabs.push(tab);
+            },
+            Ok(_) => (),
+            Err(e) if tabs.is_empty() => errors.push(ErrorBanner::new("Error opening repository", &e)),
+            Err(_) => (),
+        }
+
+        let mut gui = Self::with_tabs(settings, tabs, active_tab, errors, notification_sender, notifications);
+        gui.start_control_server();
+        (gui, Command::none())
+    }
+
+    fn title(&self, id: window::Id) -> String {
+        match self.windows.get(&id) {
+            Some(Window::Repository(tab)) => format!("GitUI - {}", tab.name()),
+            Some(Window::CommitDetails(path)) => match self.slot_of(path).and_then(|slot| self.tab(slot)) {
+                Some(tab) => format!("GitUI - {} - {}", tab.name(), tab.selected_commit().map(|id| tab.abbreviate(&id)).unwrap_or_default()),
+                None => format!("GitUI - {}", repository_name(path)),
+            },
+            Some(Window::DotGraph(graph)) => format!("GitUI - {}", graph.name()),
+            None => match self.tabs.get(self.active_tab) {
+                Some(tab) => format!("GitUI - {}", tab.name()),
+                None => String::from("GitUI"),
+            },
+        }
+    }
+
+    fn update(&mut self, message: Message) -> Command<Message> {
+        // Every message but the ticks, which come in many times a second, along with how long it took to handle
+        let _span = (!matches!(message, Message::LoadTick | Message::AnimationFrame | Message::ControlTick)).then(|| tracing::debug_span!("update", message = ?message).entered());
+        match message {
+            Message::LoadTick => {
+                // Every tab runs its jobs on its own, not only the active one
+                let mut failed = Vec::new();
+                for tab in all_tabs_mut(&mut self.tabs, &mut self.windows) {
+                    failed.extend(tab.poll_jobs());
+                }
+                for (context, e) in failed {
+                    self.errors.push(ErrorBanner::new(context, &e));
+                }
+                self.poll_clone();
+            },
+            Message::OpenSettings => {
+                self.settings_open = true;
+            },
+            Message::CloseSettings => {
+                self.settings_open = false;
+            },
+            Message::ToggleNotificationDrawer => {
+                self.notification_drawer = !self.notification_drawer;
+                self.unread_notifications = 0;
+            },
+            Message::ClearNotifications => {
+                self.notification_log.clear();
+            },
+            Message::ToggleDebugConsole => {
+                self.debug_console = !self.debug_console;
+            },
+            Message::ClearDebugConsole => {
+                clear_log();
+            },
+            Message::UiScaleSelected(scale) => {
+                self.settings.ui_scale = scale;
+                self.save_settings();
+            },
+            Message::HighContrastToggled(high_contrast) => {
+                self.settings.high_contrast = high_contrast;
+                self.save_settings();
+            },
+            Message::PerformanceOverlayToggled(overlay) => {
+                self.settings.performance_overlay = overlay;
+                self.save_settings();
+            },
+            Message::ControlServerToggled(enabled) => {
+                self.settings.control_port = enabled.then_some(DEFAULT_CONTROL_PORT);
+                self.save_settings();
+                self.control = None;
+                self.start_control_server();
+            },
+            Message::ControlTick => {
+                let requests = self.control.as_ref().map(|control| control.take_requests()).unwrap_or_default();
+                let commands: Vec<Command<Message>> = requests.into_iter().map(|request| self.handle_control_request(request)).collect();
+                // What a tool selected is reported back the same as a selection on the graph
+                self.report_selection();
+                return Command::batch(commands);
+            },
+            // Until a repository has a state file, its bookmarks stay the ones in the settings
+            Message::RepositoryStateToggled(repository_state) => {
+                self.settings.repository_state = repository_state;
+                self.save_settings();
+                self.update_bookmarks();
+            },
+            Message::GraphSizeChanged(size) => {
+                // Steps of the sliders, without the float noise they come with
+                let size = GraphSize {
+                    node_radius: size.node_radius.round(),
+                    column_spacing: (size.column_spacing * 10.0).round() / 10.0,
+                    row_spacing: (size.row_spacing * 10.0).round() / 10.0,
+                    text_scale: (size.text_scale * 20.0).round() / 20.0,
+                };
+                self.settings.graph_size = size;
+                for tab in all_tabs_mut(&mut self.tabs, &mut self.windows) {
+                    tab.set_graph_size(size);
+                }
+            },
+            Message::GraphSizeSettled => self.save_settings(),
+            Message::DoubleClickSelected(action) => {
+                self.settings.double_click = action;
+                self.save_settings();
+            },
+            Message::MiddleClickSelected(action) => {
+                self.settings.middle_click = action;
+                self.save_settings();
+            },
+            Message::CommitDoubleClicked(id) => return self.run_click_action(self.settings.double_click, id),
+            Message::CommitMiddleClicked(id) => return self.run_click_action(self.settings.middle_click, id),
+            Message::CopyCommitId(id) => {
+                self.notify(Notification::Info(format!("Copied {}", id)));
+                return iced::clipboard::write(id);
+            },
+            Message::Window(id, message) => {
+                self.current_window = id;
+                let command = self.update(*message);
+                self.current_window = window::Id::MAIN;
+                return command;
+            },
+            Message::WindowFocused(id) => {
+                self.focused_window = id;
+            },
+            // The active tab moves into the new window unless another repository was typed in
+            Message::OpenRepositoryWindow => {
+                let tab = if self.open_path.is_empty() {
+                    (self.active_tab < self.tabs.len()).then(|| Ok(self.remove_tab(self.active_tab)))
+                } else {
+                    let path = PathBuf::from(std::mem::take(&mut self.open_path));
+                    Some(match self.tabs.iter().position(|tab| tab.path() == path) {
+                        Some(index) => Ok(self.remove_tab(index)),
+                        None => RepositoryTab::open(&path, &self.settings, self.notification_sender.clone()),
+                    })
+                };
+                match tab {
+                    Some(Ok(tab)) => return self.open_window(Window::Repository(Box::new(tab))),
+                    Some(Err(e)) => self.errors.push(ErrorBanner::new("Error opening repository", &e)),
+                    None => (),
+                }
+            },
+            Message::OpenDotWindow => {
+                if !self.open_path.is_empty() {
+                    let path = PathBuf::from(std::mem::take(&mut self.open_path));
+                    match ImportedGraph::load(&path, &self.settings) {
+                        Ok(graph) => return self.open_window(Window::DotGraph(graph)),
+                        Err(e) => self.errors.push(ErrorBanner::new(&format!("Error reading {}", path.display()), &e)),
+                    }
+                }
+            },
+            // A tab has one details window at most, which follows its selection from then on
+            Message::OpenCommitWindow(id) => {
+                let Some(tab) = self.current_tab_mut() else {
+                    return Command::none();
+                };
+                tab.set_details_window(true);
+                let result = tab.update(Message::OpenCommitDetails(id));
+                let path = tab.path().to_path_buf();
+                if let Err(e) = result {
+                    self.errors.push(ErrorBanner::new("Error", &e));
+                }
+                if !self.windows.values().any(|window| matches!(window, Window::CommitDetails(shown) if *shown == path)) {
+                    return self.open_window(Window::CommitDetails(path));
+                }
+            },
+            // A window for a single commit has nothing left to show once its details are closed
+            Message::CloseCommitDetails if matches!(self.windows.get(&self.current_window), Some(Window::CommitDetails(_))) => return self.close_window(self.current_window),
+            // Nothing is left to show a failure in, and losing the state is no reason to keep the window open
+            Message::CloseRequested(id) if id == window::Id::MAIN => {
+                if self.settings.repository_state {
+                    for tab in all_tabs_mut(&mut self.tabs, &mut self.windows) {
+                        let _ = Self::save_repository_state(tab);
+                    }
+                }
+                // The tabs in windows of their own are kept along, as they go back to the main window when closed
+                if !self.dot_only {
+                    let mut tabs: Vec<_> = self.tabs.iter().map(|tab| tab.session()).collect();
+                    tabs.extend(self.windows.values().filter_map(|window| match window {
+                        Window::Repository(tab) => Some(tab.session()),
+                        _ => None,
+                    }));
+                    let _ = Session { tabs, active: self.active_tab }.save();
+                }
+                // The app quits once the last window is closed
+                let windows = self.windows.keys().filter(|id| **id != window::Id::MAIN).map(|id| window::close(*id)).collect::<Vec<_>>();
+                return Command::batch(windows.into_iter().chain(iter::once(window::close(window::Id::MAIN))));
+            },
+            Message::CloseRequested(id) => return self.close_window(id),
+            Message::RecordKeyBinding(action) => {
+                self.recording_key = Some(action);
+            },
+            Message::ClearKeyBinding(action) => {
+                self.settings.keymap.set(action, None);
+                self.save_settings();
+            },
+            Message::KeyPressed(chord) if self.current_window != self.focused_window => {
+                return self.update(Message::Window(self.focused_window, Box::new(Message::KeyPressed(chord))));
+            },
+            Message::KeyPressed(chord) => {
+                if let Some(action) = self.recording_key.take() {
+                    // Escape only stops recording, so it can't be bound by accident
+                    if chord.key != "Escape" {
+                        self.settings.keymap.set(action, Some(chord));
+                        self.save_settings();
+                    }
+                    return Command::none();
+                }
+
+                // The switcher's text input lets the arrow keys through, and Escape once it has lost focus
+                let switching = self.current_tab().is_some_and(|tab| tab.is_quick_switching());
+                let plain = !(chord.control || chord.alt || chord.shift || chord.logo);
+                if switching && plain {
+                    match chord.key.as_str() {
+                        "Up" => return self.update(Message::QuickSwitchMove(-1)),
+                        "Down" => return self.update(Message::QuickSwitchMove(1)),
+                        "Escape" => return self.update(Message::ToggleQuickSwitcher),
+                        _ => (),
+                    }
+                }
+
+                let message = match self.settings.keymap.lookup(&chord) {
+                    Some(Action::Refresh) => Message::RefreshTree,
+                    Some(Action::Checkout) => Message::CheckoutSelected,
+                    Some(Action::Search) => Message::ToggleFilterPanel,
+                    Some(Action::ZoomIn) => Message::Zoom(ZOOM_STEP),
+                    Some(Action::ZoomOut) => Message::Zoom(1.0 / ZOOM_STEP),
+                    Some(Action::Commit) => Message::OpenCommitComposer,
+                    Some(Action::Back) => Message::SelectionBack,
+                    Some(Action::Forward) => Message::SelectionForward,
+                    Some(Action::QuickSwitch) => Message::ToggleQuickSwitcher,
+                    Some(Action::DebugConsole) => Message::ToggleDebugConsole,
+                    None => return Command::none(),
+                };
+                return self.update(message);
+            },
+            Message::OpenInitDialog => {
+                self.init_dialog = Some(Default::default());
+            },
+            Message::CloseInitDialog => {
+                self.init_dialog = None;
+            },
+            Message::InitPathChanged(path) => {
+                if let Some(dialog) = &mut self.init_dialog {
+                    dialog.path = path;
+                }
+            },
+            Message::InitBranchChanged(branch) => {
+                if let Some(dialog) = &mut self.init_dialog {
+                    dialog.branch = branch;
+                }
+            },
+            Message::InitialCommitToggled(initial_commit) => {
+                if let Some(dialog) = &mut self.init_dialog {
+                    dialog.initial_commit = initial_commit;
+                }
+            },
+            Message::InitRepository => {
+                if let Some(dialog) = &self.init_dialog {
+                    let path = PathBuf::from(dialog.path.trim());
+                    let branch = Some(dialog.branch.trim()).filter(|branch| !branch.is_empty());
+                    let result = match path.as_os_str().is_empty() {
+                        true => Err(Error::from("enter the directory to create the repository in")),
+                        false => init_repository(&path, branch, dialog.initial_commit).map_err(Error::from),
+                    };
+
+                    match result.and_then(|repository| RepositoryTab::open(repository.workdir().unwrap_or(repository.path()), &self.settings, self.notification_sender.clone())) {
+                        Ok(tab) => {
+                            self.notify(Notification::Info(format!("Created a repository in {}", tab.path().display())));
+                            self.tabs.push(tab);
+                            self.active_tab = self.tabs.len() - 1;
+                            self.init_dialog = None;
+                        },
+                        Err(e) => self.errors.push(ErrorBanner::new("Error creating repository", &e)),
+                    }
+                }
+            },
+            Message::OpenCloneDialog => {
+                self.clone_dialog = Some(Default::default());
+            },
+            Message::CloseCloneDialog => {
+                self.clone_dialog = None;
+            },
+            Message::CloneUrlChanged(url) => {
+                if let Some(dialog) = &mut self.clone_dialog {
+                    dialog.url = url;
+                }
+            },
+            Message::CloneDestinationChanged(destination) => {
+                if let Some(dialog) = &mut self.clone_dialog {
+                    dialog.destination = destination;
+                }
+            },
+            Message::CloneDepthChanged(depth) => {
+                if let Some(dialog) = &mut self.clone_dialog {
+                    dialog.depth = depth;
+                }
+            },
+            Message::CloneSingleBranchToggled(single_branch) => {
+                if let Some(dialog) = &mut self.clone_dialog {
+                    dialog.single_branch = single_branch;
+                }
+            },
+            Message::CloneBranchChanged(branch) => {
+                if let Some(dialog) = &mut self.clone_dialog {
+                    dialog.branch = branch;
+                }
+            },
+            Message::StartClone => {
+                if let Some(dialog) = self.clone_dialog.as_mut().filter(|dialog| dialog.job.is_none()) {
+                    match dialog.options().and_then(CloneJob::start) {
+                        Ok(job) => dialog.job = Some(job),
+                        Err(e) => self.errors.push(ErrorBanner::new("Error cloning", &e)),
+                    }
+                }
+            },
+            Message::CancelClone => {
+                if let Some(dialog) = &mut self.clone_dialog {
+                    dialog.job = None;
+                }
+            },
+            Message::AutoFetchTick => {
+                for tab in all_tabs_mut(&mut self.tabs, &mut self.windows) {
+                    if let Err(e) = tab.update(Message::Fetch) {
+                        self.errors.push(ErrorBanner::new("Error fetching", &e));
+                    }
+                }
+            },
+            Message::AutoFetchSelected(auto_fetch) => {
+                self.auto_fetch = auto_fetch;
+            },
+            Message::SelectTab(index) => {
+                self.active_tab = index.min(self.tabs.len().saturating_sub(1));
+            },
+            Message::CloseTab(index) => {
+                if index < self.tabs.len() {
+                    let tab = self.remove_tab(index);
+                    if self.settings.repository_state {
+                        if let Err(e) = Self::save_repository_state(&tab) {
+                            self.errors.push(ErrorBanner::new(&format!("Error saving the state of {}", tab.path().display()), &e));
+                        }
+                    }
+                    return self.close_details_windows(tab.path());
+                }
+            },
+            Message::OpenPathChanged(path) => {
+                self.open_path = path;
+            },
+            Message::OpenRepository => {
+                match RepositoryTab::open(&PathBuf::from(&self.open_path), &self.settings, self.notification_sender.clone()) {
+                    Ok(tab) => {
+                        self.notify(Notification::Info(format!("Opened {}", tab.path().display())));
+                        self.tabs.push(tab);
+                        self.active_tab = self.tabs.len() - 1;
+                        self.open_path.clear();
+                    },
+                    Err(e) => {
+                        self.notify(Notification::Failure(format!("Opening {} failed", self.open_path)));
+                        self.errors.push(ErrorBanner::new("Error opening repository", &e));
+                    },
+                }
+            },
+            // Bookmarks are kept in the settings or the repository, so every tab of the repository shows the change. They
+            // are read again first, to keep the ones another window saved since
+            Message::SaveBookmark => {
+                let repository_state = self.settings.repository_state;
+                let taken = self.current_tab_mut().and_then(|tab| {
+                    let bookmark = tab.take_bookmark()?;
+                    Some((bookmark, repository_state.then(|| RepositoryUiState::load(tab.git_directory()).unwrap_or_else(|| tab.repository_state()))))
+                });
+                if let Some((bookmark, state)) = taken {
+                    match state {
+                        Some(mut state) => {
+                            let directory = bookmark.repository.clone();
+                            state.set_bookmark(bookmark);
+                            if let Err(e) = state.save(&directory) {
+                                self.errors.push(ErrorBanner::new("Error saving bookmark", &e));
+                            }
+                        },
+                        None => {
+                            self.settings.bookmarks = UserSettings::load().bookmarks;
+                            self.settings.set_bookmark(bookmark);
+                            self.save_settings();
+                        },
+                    }
+                    self.update_bookmarks();
+                }
+            },
+            Message::RemoveBookmark(commit) => {
+                let repository_state = self.settings.repository_state;
+                let closed = self.current_tab_mut().map(|tab| {
+                    tab.close_bookmark_editor();
+                    (tab.git_directory().to_path_buf(), repository_state.then(|| RepositoryUiState::load(tab.git_directory()).unwrap_or_else(|| tab.repository_state())))
+                });
+                if let Some((directory, state)) = closed {
+                    match state {
+                        Some(mut state) => {
+                            state.remove_bookmark(&commit);
+                            if let Err(e) = state.save(&directory) {
+                                self.errors.push(ErrorBanner::new("Error removing bookmark", &e));
+                            }
+                        },
+                        None => {
+                            self.settings.bookmarks = UserSettings::load().bookmarks;
+                            self.settings.remove_bookmark(&directory, &commit);
+                            self.save_settings();
+                        },
+                    }
+                    self.update_bookmarks();
+                }
+            },
+            Message::ToggleQuickSwitcher => {
+                if let Some(tab) = self.current_tab_mut() {
+                    if let Err(e) = tab.update(Message::ToggleQuickSwitcher) {
+                        self.notify(Notification::Failure(format!("Failed: {}", e.message())));
+                        self.errors.push(ErrorBanner::new("Error", &e));
+                    } else if tab.is_quick_switching() {
+                        return text_input::focus(quick_switcher_input());
+                    }
+                }
+            },
+            Message::OpenTagDialog => {
+                let current = self.current_slot();
+                let others: Vec<PathBuf> = self.all_slots().filter(|slot| Some(*slot) != current).filter_map(|slot| self.tab(slot)).map(|tab| tab.path().to_path_buf()).collect();
+                if let Some(tab) = self.current_tab_mut() {
+                    if let Err(e) = tab.open_tag_dialog(others) {
+                        self.errors.push(ErrorBanner::new("Error", &e));
+                    }
+                }
+            },
+            Message::CreateTags => {
+                if let Some(tab) = self.current_tab_mut() {
+                    if let Err(e) = tab.update(Message::CreateTags) {
+                        self.notify(Notification::Failure(format!("Failed: {}", e.message())));
+                        self.errors.push(ErrorBanner::new("Error", &e));
+                    }
+                }
+                // The other repositories may have been tagged as well
+                let current = self.current_slot();
+                for slot in self.all_slots().filter(|slot| Some(*slot) != current).collect::<Vec<_>>() {
+                    if let Some(Err(e)) = self.tab_mut(slot).map(|tab| tab.update(Message::RefreshTree)) {
+                        self.errors.push(ErrorBanner::new("Error refreshing", &e));
+                    }
+                }
+            },
+            Message::DismissError(index) => {
+                if index < self.errors.len() {
+                    self.errors.remove(index);
+                }
+            },
+            Message::ToggleErrorDetails(index) => {
+                if let Some(error) = self.errors.get_mut(index) {
+                    error.expanded = !error.expanded;
+                }
+            },
+            message => {
+                if let Some(tab) = self.current_tab_mut() {
+                    if let Err(e) = tab.update(message) {
+                        self.notify(Notification::Failure(format!("Failed: {}", e.message())));
+                        self.errors.push(ErrorBanner::new("Error", &e));
+                    }
+                } else if let Some(Window::DotGraph(graph)) = self.windows.get_mut(&self.current_window) {
+                    graph.update(message);
+                }
+            },
+        }
+
+        while let Ok(notification) = self.notifications.try_recv() {
+            self.notify(notification);
+        }
+        self.report_selection();
+
+        Command::none()
+    }
+
+    fn theme(&self, _: window::Id) -> Theme {
+        if self.settings.high_contrast {
+            Theme::custom(String::from("High contrast"), Palette {
+                background: Color::BLACK,
+                text: Color::WHITE,
+                primary: Color::from_rgb(1.0, 0.85, 0.0),
+                success: Color::from_rgb(0.3, 1.0, 0.4),
+                danger: Color::from_rgb(1.0, 0.35, 0.3),
+            })
+        } else {
+            Theme::Light
+        }
+    }
+
+    // Scales text, paddings and the graph all together. iced multiplies it with the monitor's own scale factor
+    fn scale_factor(&self, _: window::Id) -> f64 {
+        self.settings.ui_scale.factor()
+    }
+
+    fn subscription(&self) -> Subscription<Message> {
+        let mut subscriptions = Vec::new();
+        // Redraws the progress bars and picks up finished loads, fetches, downloads, maintenance and external tools. The
+        // debug console is redrawn along, to show what was logged since
+        let cloning = self.clone_dialog.as_ref().is_some_and(|dialog| dialog.job.is_some());
+        if cloning || self.debug_console || self.tabs.iter().chain(self.window_tabs()).any(|tab| tab.has_jobs()) {
+            subscriptions.push(iced::time::every(Duration::from_millis(100)).map(|_| Message::LoadTick));
+        }
+        subscriptions.push(event::listen_with(|event, status| match (event, status) {
+            // Keys typed into a text input are left to it
+            (Event::Keyboard(keyboard::Event::KeyPressed { key, modifiers, .. }), event::Status::Ignored) => {
+                key_chord(&key, modifiers).map(Message::KeyPressed)
+            },
+            (Event::Window(id, window::Event::CloseRequested), _) => Some(Message::CloseRequested(id)),
+            (Event::Window(id, window::Event::Focused), _) => Some(Message::WindowFocused(id)),
+            _ => None,
+        }));
+        if self.tabs.get(self.active_tab).into_iter().chain(self.window_tabs()).any(|tab| tab.is_animating()) {
+            subscriptions.push(window::frames().map(|_| Message::AnimationFrame));
+        }
+        if self.control.is_some() {
+            subscriptions.push(iced::time::every(Duration::from_millis(100)).map(|_| Message::ControlTick));
+        }
+        if let AutoFetch::Minutes(minutes) = self.auto_fetch {
+            subscriptions.push(iced::time::every(Duration::from_secs(minutes * 60)).map(|_| Message::AutoFetchTick));
+        }
+        Subscription::batch(subscriptions)
+    }
+
+    // The main window also shows a DOT file it was started for, with the messages of its graph left as they are
+    fn view(&self, id: window::Id) -> Element<'_, Message> {
+        match self.windows.get(&id) {
+            Some(window) if id != window::Id::MAIN => self.view_window(window).map(move |message| Message::Window(id, Box::new(message))),
+            _ => self.view_main(),
+        }
+    }
+}
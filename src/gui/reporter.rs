@@ -0,0 +1,111 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use iced::widget::{button, scrollable, text, text_input, Column, Row};
+use iced::{executor, window, Alignment, Application, Command, Element, Font, Length, Settings, Size, Theme};
+
+use crate::error::Error;
+
+use super::{ERROR_COLOR, VERIFIED_COLOR};
+
+// Shows the report a crashed gitui left behind, in a process of its own
+pub struct CrashReporter {
+    report: String,
+    save_path: String,
+    // Whether saving worked, and where to or why not
+    saved: Option<Result<PathBuf, String>>,
+}
+
+#[derive(Debug, Clone)]
+pub enum ReporterMessage {
+    SavePathChanged(String),
+    Save,
+    Copy,
+    Close,
+}
+
+// Next to the home directory, where it is easy to find for attaching to an issue
+fn default_save_path(report: &Path) -> PathBuf {
+    let name = report.file_name().map(PathBuf::from).unwrap_or_else(|| PathBuf::from("gitui-crash.txt"));
+    match std::env::var_os(if cfg!(windows) { "USERPROFILE" } else { "HOME" }) {
+        Some(home) => PathBuf::from(home).join(name),
+        None => name,
+    }
+}
+
+fn save_report(report: &str, path: &Path) -> Result<(), Error> {
+    if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, report)?;
+    Ok(())
+}
+
+impl Application for CrashReporter {
+    type Executor = executor::Default;
+    type Message = ReporterMessage;
+    type Theme = Theme;
+    type Flags = PathBuf;
+
+    fn new(report: PathBuf) -> (Self, Command<ReporterMessage>) {
+        let contents = fs::read_to_string(&report)
+            .unwrap_or_else(|e| format!("The crash report at {} couldn't be read: {}", report.display(), e));
+        let save_path = default_save_path(&report).display().to_string();
+        (CrashReporter { report: contents, save_path, saved: None }, Command::none())
+    }
+
+    fn title(&self) -> String {
+        String::from("GitUI - Crash report")
+    }
+
+    fn update(&mut self, message: ReporterMessage) -> Command<ReporterMessage> {
+        match message {
+            ReporterMessage::SavePathChanged(path) => {
+                self.save_path = path;
+                self.saved = None;
+            },
+            ReporterMessage::Save => {
+                let path = PathBuf::from(self.save_path.trim());
+                self.saved = Some(save_report(&self.report, &path).map(|_| path).map_err(|e| e.to_string()));
+            },
+            ReporterMessage::Copy => return iced::clipboard::write(self.report.clone()),
+            ReporterMessage::Close => return window::close(window::Id::MAIN),
+        }
+        Command::none()
+    }
+
+    fn view(&self) -> Element<'_, ReporterMessage> {
+        let message = self.report.lines().find_map(|line| line.strip_prefix("Message: ")).unwrap_or("GitUI crashed");
+        Column::with_children(vec![
+            text("GitUI crashed").size(24).into(),
+            text(message).style(ERROR_COLOR).into(),
+            text("The report below has what was logged before the crash. Saving it and attaching it to an issue helps getting it fixed.").size(14).into(),
+            scrollable(text(&self.report).size(13).font(Font::MONOSPACE)).height(Length::Fill).into(),
+            Row::with_children(vec![
+                text_input("Report file", &self.save_path).on_input(ReporterMessage::SavePathChanged).on_submit(ReporterMessage::Save).into(),
+                button("Save report").on_press(ReporterMessage::Save).into(),
+                button("Copy").on_press(ReporterMessage::Copy).into(),
+                button("Close").on_press(ReporterMessage::Close).into(),
+            ])
+            .align_items(Alignment::Center)
+            .spacing(10)
+            .into(),
+            match &self.saved {
+                Some(Ok(path)) => text(format!("Saved to {}", path.display())).size(14).style(VERIFIED_COLOR).into(),
+                Some(Err(e)) => text(format!("Saving failed: {}", e)).size(14).style(ERROR_COLOR).into(),
+                None => text("").size(14).into(),
+            },
+        ])
+        .spacing(10)
+        .padding(15)
+        .into()
+    }
+}
+
+pub fn show_crash_report(report: PathBuf) {
+    let _ = CrashReporter::run(Settings {
+        flags: report,
+        window: window::Settings { size: Size::new(800.0, 600.0), ..Default::default() },
+        ..Default::default()
+    });
+}
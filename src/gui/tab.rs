@@ -0,0 +1,2424 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Arc;
+use std::sync::mpsc::Sender;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+use std::{cell::RefCell, rc::Rc};
+
+use git2::{BranchType, Repository, Oid};
+use iced::widget::{text, Column, Row, Canvas, button, checkbox, pick_list};
+use iced::{Alignment, Element, Length, Point, Vector};
+
+use crate::config::{DisplayConfig, Orientation, format_relative, to_date};
+use crate::error::Error;
+use crate::export::export_graph;
+use crate::archive::archive_commit;
+use crate::rebase::{merge_branch, rebase_branch, plan_commit_move, apply_reorder_plan, fixup_message, plan_autosquash, apply_autosquash_plan};
+use crate::status::{get_status, apply_selection, stage_file, unstage_file, discard_file, restore_file, stage_tracked_changes};
+use crate::message::{MessageConfig, clean_message, set_conventional_prefix};
+use crate::hooks::run_commit_hooks;
+use crate::journal::{Journal, take_snapshot};
+use crate::remote::{set_upstream, set_push_target, check_push, get_push_upstream, PushCheck, list_remote_references, fetch_refspecs, set_remote_refspecs};
+use crate::ignore::{read_gitignore, write_gitignore, append_to_gitignore, suggest_patterns, preview_ignored};
+use crate::links::{Forge, get_forge, open_url};
+use crate::session::TabSession;
+use crate::settings::{Bookmark, BookmarkColor, Settings};
+use crate::stats::{HistoryStats, compute_stats};
+use crate::uistate::RepositoryUiState;
+use crate::maintenance::{MaintenanceTask, format_size, repository_size};
+use crate::health::{HealthIssue, abort_operation, apply_fix, check_health, continue_operation, operation_name, unmerged_paths};
+use crate::changelog::{get_releases, export_changelog};
+use crate::tools::{prepare_difftool, prepare_mergetool, open_terminal};
+use crate::signing::{SignatureStatus, verify_commit_signature, is_signing_enabled};
+use crate::backend::{CommitNode, CommitDiff, get_commit_diff, get_commit_ancestors, get_commit_descendants, get_local_branches, get_head_branch, create_tag_at_branch, get_range_diff, RangeDiff, get_reference_names, create_commit, get_worktrees, create_worktree, describe_head, get_dirty_file_count, filter_commits, filter_history, collapse_chains, first_parent_history, ReferenceGroups, GraphSize, LayoutMode, layout_commits, get_reference_tips, ReferenceTip, ReferenceKind, ReferenceLabel, get_previous_branch, create_branch_at_head, rename_branch, delete_reference, DiffSettings, get_note, get_noted_commits, set_note};
+
+use super::dialogs::{PushDialog, view_push_dialog, view_push_progress, TrackingDialog, view_tracking_dialog, RemoteBrowser, view_remote_browser, RefspecEditor, view_refspec_editor, DetachedHead, view_detached_banner, InterruptedOperation, view_interrupted_operation, IgnoreMenu, view_ignore_menu, GitignoreEditor, view_gitignore_editor, StatusPanel, view_status_panel, ImageComparison, FileList, DiscardConfirmation, view_discard_confirmation, RestoreConfirmation, view_restore_confirmation, DropMenu, view_drop_menu, ReorderConfirmation, view_reorder_confirmation, HistoryFilter, view_history_filter, BookmarkEditor, view_bookmark_editor, view_bookmark_list, NoteEditor, view_note_editor, QuickSwitcher, QuickSwitchItem, view_quick_switcher, TagDialog, CompareDialog, CommitComposer, WorktreePanel, ExportDialog, ArchiveDialog, view_tag_dialog, view_archive_dialog, view_export_dialog, view_compare_dialog, view_commit_composer, view_worktree_panel, view_range_diff, view_comparison, view_tree_load, view_fetch_progress, view_reference_sidebar, view_commit_message, view_statistics_panel, view_health_panel, MaintenancePanel, view_maintenance_panel, ReleaseTimeline, view_release_timeline, view_commit_details, view_commit_links, view_graph_legend};
+use super::external::ExternalTool;
+use super::fetcher::{FetchJob, LfsDownload, PushJob};
+use super::maintainer::MaintenanceJob;
+use super::loader::TreeLoad;
+use super::worker::{GitWorker, JobHandle};
+use super::tree::{GraphColors, PerformanceStats, TreeRenderer, Viewport};
+use super::{Message, Notification, VERIFIED_COLOR, UNTRUSTED_COLOR, UNVERIFIED_COLOR, repository_name};
+
+pub struct SharedState {
+    pub commits: BTreeMap<String, CommitNode>,
+    pub node_locations: BTreeMap<String, Point>,
+    pub selection: Selection,
+    pub display: DisplayConfig,
+    pub viewport: Viewport,
+    // Commits left out by the history filter between a child and the parent it is now linked to
+    pub hidden_edges: HashMap<(String, String), usize>,
+    // Nodes standing for a collapsed run of commits, with the length of the run
+    pub collapsed_chains: HashMap<String, usize>,
+    // Merges shown without the branches they merged in, with how many commits those brought in
+    pub folded_merges: HashMap<String, usize>,
+    pub layout: LayoutMode,
+    pub orientation: Orientation,
+    pub size: GraphSize,
+    pub arrival: Arrival,
+    // Column positions and their commit times, only filled in by the chronological layout
+    pub time_axis: Vec<(f32, git2::Time)>,
+    // Bends of the edges that don't go straight, by child and parent
+    pub edge_routes: HashMap<(String, String), Vec<Point>>,
+    // Of this repository, in the order they were made
+    pub bookmarks: Vec<Bookmark>,
+    // Commits with a git note
+    pub notes: HashSet<String>,
+    pub performance: PerformanceStats,
+}
+
+impl SharedState {
+    pub fn new(display: DisplayConfig, size: GraphSize) -> SharedState {
+        SharedState { commits: BTreeMap::new(), node_locations: BTreeMap::new(), selection: Default::default(), display, viewport: Default::default(), hidden_edges: HashMap::new(), collapsed_chains: HashMap::new(), folded_merges: HashMap::new(), layout: Default::default(), orientation: Default::default(), size, arrival: Default::default(), time_axis: Vec::new(), edge_routes: HashMap::new(), bookmarks: Vec::new(), notes: HashSet::new(), performance: Default::default() }
+    }
+
+    // After the commits or how they are laid out changed
+    pub fn relayout(&mut self) {
+        let started = Instant::now();
+        let layout = layout_commits(&mut self.commits, self.layout, self.orientation, self.size);
+        self.performance.layout_time = started.elapsed();
+        self.node_locations = layout.locations;
+        self.edge_routes = layout.edge_routes;
+        self.time_axis = layout.time_axis;
+    }
+}
+
+// Commits a refresh added, which move out from where their history was already shown to their own spots
+#[derive(Default)]
+pub struct Arrival {
+    // Where every new commit starts, at the nearest commit below it that was there before
+    origins: HashMap<String, Point>,
+    started: Option<Instant>,
+}
+
+impl Arrival {
+    fn start(previous: &HashSet<String>, commits: &BTreeMap<String, CommitNode>, locations: &BTreeMap<String, Point>) -> Arrival {
+        let mut origins: HashMap<String, Point> = HashMap::new();
+        for (id, location) in locations.iter().filter(|(id, _)| !previous.contains(*id)) {
+            // Down the first parents until a commit that was there before, or one whose origin is already known, so a
+            // long run of new commits is only walked once
+            let mut run = vec![id];
+            let mut origin = None;
+            while let Some(parent) = commits.get(run[run.len() - 1]).and_then(|commit| commit.parents.first()) {
+                if previous.contains(parent) {
+                    origin = locations.get(parent).copied();
+                    break;
+                }
+                if let Some(known) = origins.get(parent) {
+                    origin = Some(*known);
+                    break;
+                }
+                run.push(parent);
+            }
+            // New history of its own starts where it ends up, and only fades in
+            for id in run {
+                let origin = origin.or(locations.get(id).copied()).unwrap_or(*location);
+                origins.insert(id.clone(), origin);
+            }
+        }
+        Arrival { origins, started: Some(Instant::now()) }
+    }
+
+    // Eased from 0 when the commits came in to 1 once they are in place
+    pub fn progress(&self) -> f32 {
+        let progress = self.started.map_or(1.0, |started| (started.elapsed().as_secs_f32() / ARRIVAL_ANIMATION.as_secs_f32()).min(1.0));
+        progress * progress * (3.0 - 2.0 * progress)
+    }
+
+    pub fn is_animating(&self) -> bool {
+        self.started.is_some_and(|started| started.elapsed() < ARRIVAL_ANIMATION)
+    }
+
+    pub fn is_new(&self, id: &str) -> bool {
+        self.is_animating() && self.origins.contains_key(id)
+    }
+
+    // Where the commit laid out at `location` is drawn at the moment
+    pub fn location(&self, id: &str, location: Point) -> Point {
+        match self.origins.get(id).filter(|_| self.is_animating()) {
+            Some(origin) => *origin + (location - *origin) * self.progress(),
+            None => location,
+        }
+    }
+}
+
+// Every panel reads the selection from here, so selecting anywhere updates all of them
+#[derive(Default)]
+pub struct Selection {
+    pub commit: Option<String>,
+    // Second commit picked with Ctrl-click, forming a range with `commit`
+    pub range_end: Option<String>,
+    // Selected commit together with its ancestors and descendants, or the commits of the selected range
+    pub related_commits: HashSet<String>,
+    // The commit selected before and when the selection moved off it, so the canvas can animate the highlight over
+    pub previous: Option<String>,
+    changed_at: Option<Instant>,
+    // Label of the selected commit that was clicked, to act on the reference rather than the commit
+    pub reference: Option<ReferenceLabel>,
+}
+
+// How the selection changed. Whatever changes it, the graph, the sidebars, the quick switcher or a tool through the
+// control server, sends one of these for every panel following the selection to catch up with it
+#[derive(Debug, Clone, PartialEq)]
+pub enum SelectionChange {
+    Commit(String),
+    // The selected commit and the one picked with Ctrl-click
+    Range(String, String),
+    Cleared,
+}
+
+// Commits selected one after another, to go back and forth between like in a browser
+#[derive(Default)]
+struct SelectionHistory {
+    commits: Vec<String>,
+    // Of the commit selected now
+    position: usize,
+}
+
+// Older selections are forgotten once the history is this long
+const SELECTION_HISTORY_LENGTH: usize = 100;
+
+impl SelectionHistory {
+    // Selecting something new drops whatever could be gone forward to, and selecting the current commit again does nothing
+    fn visit(&mut self, commit: &str) {
+        if self.commits.get(self.position).is_some_and(|current| current == commit) {
+            return;
+        }
+        self.commits.truncate(self.position + 1);
+        if self.commits.len() == SELECTION_HISTORY_LENGTH {
+            self.commits.remove(0);
+        }
+        self.commits.push(commit.to_string());
+        self.position = self.commits.len() - 1;
+    }
+
+    fn can_go_back(&self) -> bool {
+        self.position > 0
+    }
+
+    fn can_go_forward(&self) -> bool {
+        self.position + 1 < self.commits.len()
+    }
+
+    // Where going back or forward leads, left for `go_to` once the commit there could be selected
+    fn peek(&self, forward: bool) -> Option<(usize, String)> {
+        let position = if forward { self.position + 1 } else { self.position.checked_sub(1)? };
+        self.commits.get(position).map(|commit| (position, commit.clone()))
+    }
+
+    fn go_to(&mut self, position: usize) {
+        self.position = position;
+    }
+}
+
+// Why a commit can't be jumped to
+const HIDDEN_COMMIT: &str = "the commit isn't shown on the graph, the filters or collapsed chains may hide it";
+
+// How long the highlight takes to grow around a newly selected node
+const SELECTION_ANIMATION: Duration = Duration::from_millis(200);
+// How long commits that came in with a refresh take to move out of their parents
+const ARRIVAL_ANIMATION: Duration = Duration::from_millis(500);
+
+impl Selection {
+    pub fn set(&mut self, commit: Option<String>, commits: &BTreeMap<String, CommitNode>) {
+        self.related_commits.clear();
+        self.range_end = None;
+        self.reference = None;
+        let commit = commit.filter(|commit| commits.contains_key(commit));
+        // Selecting the same commit again, like after a refresh, doesn't start the animation over
+        if commit != self.commit {
+            self.previous = std::mem::replace(&mut self.commit, commit);
+            self.changed_at = Some(Instant::now());
+        }
+
+        if let Some(commit) = &self.commit {
+            self.related_commits = get_commit_ancestors(commit, commits);
+            self.related_commits.extend(get_commit_descendants(commit, commits));
+            self.related_commits.insert(commit.clone());
+        }
+    }
+
+    // From 0 when the selection just changed to 1 once the highlight is fully drawn
+    pub fn highlight(&self) -> f32 {
+        self.changed_at.map_or(1.0, |changed_at| (changed_at.elapsed().as_secs_f32() / SELECTION_ANIMATION.as_secs_f32()).min(1.0))
+    }
+
+    pub fn is_animating(&self) -> bool {
+        self.changed_at.is_some_and(|changed_at| changed_at.elapsed() < SELECTION_ANIMATION)
+    }
+
+    // The selection as it is now, for when it was set without a range
+    fn change(&self) -> SelectionChange {
+        self.commit.clone().map_or(SelectionChange::Cleared, SelectionChange::Commit)
+    }
+
+    fn set_range(&mut self, range: &RangeDiff) {
+        self.range_end = self.commit.as_ref().map(|commit| if commit == &range.from { range.to.clone() } else { range.from.clone() });
+        self.related_commits = range.commits.iter().map(|(id, _)| id.clone()).collect();
+        self.related_commits.insert(range.from.clone());
+    }
+}
+
+// Shorter runs of commits are left alone, since collapsing them barely saves space
+const MIN_CHAIN_LENGTH: usize = 3;
+
+// HEAD description and dirty file count for the status bar
+fn read_status(repository: &Repository) -> (String, Option<usize>) {
+    // An unborn HEAD has no commit to describe yet
+    let head = describe_head(repository).unwrap_or(String::from("no commits yet"));
+    (head, get_dirty_file_count(repository).ok())
+}
+
+pub struct RepositoryTab {
+    repository: Repository,
+    state: Rc<RefCell<SharedState>>,
+    tag_dialog: Option<TagDialog>,
+    range_diff: Option<RangeDiff>,
+    range_job: Option<JobHandle<RangeDiff>>,
+    // Follows the selection once open
+    commit_details: Option<CommitDiff>,
+    // The details are shown in a window of their own, which follows the selection even before they are first opened
+    details_window: bool,
+    // Details being read for the commit to show next
+    details_job: Option<JobHandle<CommitDiff>>,
+    // The commit whose signature is being checked
+    signature_job: Option<(String, JobHandle<SignatureStatus>)>,
+    // Where blocking reads of the repository go, off the GUI thread
+    worker: GitWorker,
+    compare_dialog: Option<CompareDialog>,
+    commit_composer: Option<CommitComposer>,
+    worktree_panel: Option<WorktreePanel>,
+    export_dialog: Option<ExportDialog>,
+    archive_dialog: Option<ArchiveDialog>,
+    drop_menu: Option<DropMenu>,
+    reorder: Option<ReorderConfirmation>,
+    status_panel: Option<StatusPanel>,
+    statistics: Option<HistoryStats>,
+    statistics_job: Option<JobHandle<HistoryStats>>,
+    health: Option<Vec<HealthIssue>>,
+    image_comparison: ImageComparison,
+    diff_settings: DiffSettings,
+    file_list: FileList,
+    maintenance: Option<MaintenancePanel>,
+    interrupted: Option<InterruptedOperation>,
+    release_timeline: Option<ReleaseTimeline>,
+    // Operations that moved references, newest last, for undoing them
+    journal: Journal,
+    // Commit selected when the last session ended, selected again once the commits are loaded
+    restore_selection: Option<String>,
+    // Where commits and issues can be opened in the browser, if the remote is on GitHub or GitLab
+    forge: Option<Forge>,
+    detached: Option<DetachedHead>,
+    tracking_dialog: Option<TrackingDialog>,
+    remote_browser: Option<RemoteBrowser>,
+    refspec_editor: Option<RefspecEditor>,
+    discard: Option<DiscardConfirmation>,
+    restore: Option<RestoreConfirmation>,
+    ignore_menu: Option<IgnoreMenu>,
+    gitignore_editor: Option<GitignoreEditor>,
+    loading: Option<TreeLoad>,
+    fetching: Option<FetchJob>,
+    lfs_download: Option<LfsDownload>,
+    // Difftools and mergetools that are still open
+    external_tools: Vec<ExternalTool>,
+    push_dialog: Option<PushDialog>,
+    pushing: Option<PushJob>,
+    // Full graph as loaded; the shared state only holds the part the reference filter lets through
+    all_commits: Arc<BTreeMap<String, CommitNode>>,
+    references: Vec<(ReferenceTip, bool)>,
+    // Remote branches and tags only have their history walked once asked for in the sidebar
+    reference_groups: ReferenceGroups,
+    reference_sidebar: bool,
+    // References the state kept in the repository hides, for once they are loaded
+    hidden_on_load: HashSet<(String, ReferenceKind)>,
+    // Whether the viewport came from the state kept in the repository, which the session then leaves alone
+    viewport_from_repository: bool,
+    // Folders of the sidebar that are open, all of them closed to begin with
+    expanded_folders: HashSet<(ReferenceKind, String)>,
+    legend: bool,
+    // When FETCH_HEAD was last written, as of the last load
+    last_fetch: Option<git2::Time>,
+    selection_history: SelectionHistory,
+    // Branch being renamed in the sidebar, with the new name typed so far
+    renaming: Option<(String, String)>,
+    history_filter: HistoryFilter,
+    filter_panel: bool,
+    quick_switcher: Option<QuickSwitcher>,
+    bookmark_list: bool,
+    bookmark_editor: Option<BookmarkEditor>,
+    note_editor: Option<NoteEditor>,
+    collapse_chains: bool,
+    // Runs the user opened up again, by their newest commit
+    expanded_chains: HashSet<String>,
+    // Only the first parents from HEAD on, like `git log --first-parent`
+    first_parent: bool,
+    // Merges the user opened up again in the first-parent history
+    expanded_merges: HashSet<String>,
+    // Sending only fails once the app is shutting down, so send errors are ignored
+    notifications: Sender<Notification>,
+    head: String,
+    // None when the repository has no working directory to check
+    dirty_files: Option<usize>,
+}
+
+impl RepositoryTab {
+    pub fn open(path: &Path, settings: &Settings, notifications: Sender<Notification>) -> Result<RepositoryTab, Error> {
+        let repository = Repository::open(path)?;
+        let worker = GitWorker::start(repository.path().to_path_buf())?;
+
+        let display = DisplayConfig::load(&repository);
+        let state = SharedState::new(display, settings.graph_size);
+
+        let mut tab = Self { repository, state: Rc::new(RefCell::new(state)), tag_dialog: None, range_diff: None, range_job: None, commit_details: None, details_window: false, details_job: None, signature_job: None, worker, compare_dialog: None, commit_composer: None, worktree_panel: None, export_dialog: None, archive_dialog: None, drop_menu: None, reorder: None, status_panel: None, statistics: None, statistics_job: None, health: None, image_comparison: Default::default(), diff_settings: Default::default(), file_list: Default::default(), maintenance: None, interrupted: None, release_timeline: None, journal: Default::default(), restore_selection: None, forge: None, detached: None, tracking_dialog: None, remote_browser: None, refspec_editor: None, discard: None, restore: None, ignore_menu: None, gitignore_editor: None, loading: None, fetching: None, lfs_download: None, external_tools: Vec::new(), push_dialog: None, pushing: None, all_commits: Default::default(), references: Vec::new(), reference_groups: Default::default(), reference_sidebar: false, hidden_on_load: HashSet::new(), viewport_from_repository: false, expanded_folders: HashSet::new(), legend: false, last_fetch: None, selection_history: Default::default(), renaming: None, history_filter: Default::default(), filter_panel: false, quick_switcher: None, bookmark_list: false, bookmark_editor: None, note_editor: None, collapse_chains: false, expanded_chains: HashSet::new(), first_parent: false, expanded_merges: HashSet::new(), notifications, head: String::new(), dirty_files: None };
+        tab.set_bookmarks(settings.bookmarks_for(tab.git_directory()));
+        if settings.repository_state {
+            if let Some(stored) = RepositoryUiState::load(tab.git_directory()) {
+                tab.apply_repository_state(stored);
+            }
+        }
+        if let Some(operation) = operation_name(tab.repository.state()) {
+            tab.interrupted = Some(InterruptedOperation { operation, unmerged: unmerged_paths(&tab.repository)?, error: None });
+        }
+        tab.update(Message::RefreshTree)?;
+
+        Ok(tab)
+    }
+
+    // Lays the graph out again at once, so the settings show what they look like while they are being changed
+    pub fn set_graph_size(&mut self, size: GraphSize) {
+        let state = &mut *self.state.borrow_mut();
+        state.size = size;
+        state.relayout();
+    }
+
+    pub fn name(&self) -> String {
+        repository_name(self.path())
+    }
+
+    // `repositories` are the other ones open in the workspace, offered next to the branches of this one
+    pub fn open_tag_dialog(&mut self, repositories: Vec<PathBuf>) -> Result<(), Error> {
+        let branches = get_local_branches(&self.repository)?;
+        self.tag_dialog = Some(TagDialog {
+            version: String::new(),
+            message: String::new(),
+            name_template: String::from("{branch}/{version}"),
+            branches: branches.into_iter().map(|b| (b, false)).collect(),
+            repositories: repositories.into_iter().map(|path| (path, false)).collect(),
+            results: Vec::new(),
+        });
+        Ok(())
+    }
+
+    pub fn selected_commit(&self) -> Option<String> {
+        self.state.borrow().selection.commit.clone()
+    }
+
+    // Anything git can resolve to a commit, like an abbreviated id or a branch name
+    pub fn resolve_commit(&self, revision: &str) -> Result<String, Error> {
+        Ok(self.repository.revparse_single(revision)?.peel_to_commit()?.id().to_string())
+    }
+
+    pub fn path(&self) -> &Path {
+        self.repository.workdir().unwrap_or(self.repository.path())
+    }
+
+    pub fn head(&self) -> &str {
+        &self.head
+    }
+
+    pub fn dirty_files(&self) -> Option<usize> {
+        self.dirty_files
+    }
+
+    fn is_loading(&self) -> bool {
+        self.loading.is_some()
+    }
+
+    pub fn session(&self) -> TabSession {
+        let state = self.state.borrow();
+        TabSession {
+            path: self.path().to_path_buf(),
+            zoom: state.viewport.zoom,
+            offset: (state.viewport.offset.x, state.viewport.offset.y),
+            selected: state.selection.commit.clone(),
+        }
+    }
+
+    pub fn restore(&mut self, session: &TabSession) {
+        if !self.viewport_from_repository {
+            let viewport = &mut self.state.borrow_mut().viewport;
+            viewport.zoom = session.zoom.clamp(0.001, 4.0);
+            viewport.offset = Vector::new(session.offset.0, session.offset.1);
+        }
+        self.restore_selection = session.selected.clone();
+    }
+
+    pub fn repository_state(&self) -> RepositoryUiState {
+        let state = self.state.borrow();
+        let mut hidden_references: Vec<(String, ReferenceKind)> = self.references.iter()
+            .filter(|(_, shown)| !*shown)
+            .map(|(tip, _)| (tip.name.clone(), tip.kind))
+            .chain(self.hidden_on_load.iter().cloned())
+            .collect();
+        hidden_references.sort();
+        hidden_references.dedup();
+        RepositoryUiState {
+            zoom: state.viewport.zoom,
+            offset: (state.viewport.offset.x, state.viewport.offset.y),
+            author: self.history_filter.author.clone(),
+            since: self.history_filter.since.clone(),
+            until: self.history_filter.until.clone(),
+            first_parent: self.first_parent,
+            collapse_chains: self.collapse_chains,
+            hidden_references,
+            bookmarks: state.bookmarks.clone(),
+        }
+    }
+
+    // Before the first load, so the graph comes up filtered
+    fn apply_repository_state(&mut self, stored: RepositoryUiState) {
+        let state = &mut *self.state.borrow_mut();
+        state.viewport.zoom = stored.zoom.clamp(0.001, 4.0);
+        state.viewport.offset = Vector::new(stored.offset.0, stored.offset.1);
+        state.bookmarks = stored.bookmarks;
+        self.viewport_from_repository = true;
+        self.history_filter.author = stored.author;
+        self.history_filter.since = stored.since;
+        self.history_filter.until = stored.until;
+        self.first_parent = stored.first_parent;
+        self.collapse_chains = stored.collapse_chains;
+        self.hidden_on_load = stored.hidden_references.into_iter().collect();
+    }
+
+    // The history, the commit details, the signature, the range diff and the file list follow the selection; the
+    // graph, the sidebars and the control server read it from `Selection` when they need it
+    fn follow_selection(&mut self, change: SelectionChange) {
+        self.file_list = Default::default();
+        match change {
+            SelectionChange::Commit(commit) => {
+                self.range_diff = None;
+                self.range_job = None;
+                self.selection_history.visit(&commit);
+                if self.commit_details.as_ref().map_or(self.details_window, |details| details.id != commit) {
+                    self.load_commit_details(commit.clone());
+                }
+                self.check_signature(&commit);
+            },
+            SelectionChange::Range(selected, end) => self.load_range_diff(selected, end),
+            SelectionChange::Cleared => {
+                self.range_diff = None;
+                self.range_job = None;
+                self.commit_details = None;
+                self.details_job = None;
+            },
+        }
+    }
+
+    fn is_loading_details(&self) -> bool {
+        self.details_job.is_some()
+    }
+
+    // Diffs of large commits take a while, so they are read on the worker. A newer commit replaces the job for an
+    // older one, which is cancelled then
+    fn load_commit_details(&mut self, commit: String) {
+        let settings = self.diff_settings;
+        self.details_job = Some(self.worker.submit(move |repository, _| Ok(get_commit_diff(repository, &commit, settings)?)));
+    }
+
+    // Listing a remote or fetching from it in the remote browser
+    fn is_browsing_remote(&self) -> bool {
+        self.remote_browser.as_ref().is_some_and(|browser| browser.listing.is_some() || browser.fetching.is_some())
+    }
+
+    fn is_computing_statistics(&self) -> bool {
+        self.statistics_job.is_some()
+    }
+
+    fn is_loading_range_diff(&self) -> bool {
+        self.range_job.is_some()
+    }
+
+    // Diffing far apart commits with renames detected takes a while, so it is done on the worker like commit details
+    fn load_range_diff(&mut self, first: String, second: String) {
+        let settings = self.diff_settings;
+        self.range_job = Some(self.worker.submit(move |repository, _| Ok(get_range_diff(repository, &first, &second, settings)?)));
+    }
+
+    fn is_checking_signature(&self) -> bool {
+        self.signature_job.is_some()
+    }
+
+    // gpg and ssh-keygen can take a while, asking the agent or a keyserver, so they are run on the worker. Anything
+    // keeping the signature from being checked leaves it unverified
+    fn check_signature(&mut self, commit: &str) {
+        let unchecked = self.state.borrow().commits.get(commit).is_some_and(|node| node.signature == Some(SignatureStatus::Unchecked));
+        if !unchecked || self.signature_job.as_ref().is_some_and(|(checking, _)| checking == commit) {
+            return;
+        }
+        let id = commit.to_string();
+        let job = self.worker.submit(move |repository, _| {
+            Ok(verify_commit_signature(repository, &id).unwrap_or_else(|e| SignatureStatus::Unverified(e.message().to_string())))
+        });
+        self.signature_job = Some((commit.to_string(), job));
+    }
+
+    fn is_pushing(&self) -> bool {
+        self.pushing.is_some()
+    }
+
+    // A push already running is left to finish
+    fn start_push(&mut self, branch: String, force: bool) -> Result<(), Error> {
+        if self.pushing.is_none() {
+            self.pushing = Some(PushJob::start(self.repository.path().to_path_buf(), branch, force)?);
+        }
+        Ok(())
+    }
+
+    fn is_fetching(&self) -> bool {
+        self.fetching.is_some()
+    }
+
+    fn is_downloading_lfs(&self) -> bool {
+        self.lfs_download.is_some()
+    }
+
+    fn is_running_maintenance(&self) -> bool {
+        self.maintenance.as_ref().is_some_and(|panel| panel.job.is_some())
+    }
+
+    fn is_running_tools(&self) -> bool {
+        !self.external_tools.is_empty()
+    }
+
+    pub fn is_animating(&self) -> bool {
+        let state = self.state.borrow();
+        state.selection.is_animating() || state.viewport.is_moving() || state.arrival.is_animating()
+    }
+
+    // The message that picks up the result of each kind of background job, for those that are running, and what to
+    // call the error it might end with
+    fn running_jobs(&self) -> Vec<(Message, &'static str)> {
+        let jobs = [
+            (self.is_loading(), Message::TreeLoadProgressed, "Error loading commits"),
+            (self.is_loading_details(), Message::CommitDetailsProgressed, "Error reading commit"),
+            (self.is_computing_statistics(), Message::StatisticsProgressed, "Error counting commits"),
+            (self.is_loading_range_diff(), Message::RangeDiffProgressed, "Error diffing range"),
+            (self.is_checking_signature(), Message::SignatureChecked, "Error checking signature"),
+            (self.is_pushing(), Message::PushProgressed, "Error pushing"),
+            (self.is_fetching(), Message::FetchProgressed, "Error fetching"),
+            (self.is_browsing_remote(), Message::RemoteBrowserProgressed, "Error reading remote"),
+            (self.is_downloading_lfs(), Message::LfsDownloadProgressed, "Error downloading LFS objects"),
+            (self.is_running_maintenance(), Message::MaintenanceProgressed, "Error running maintenance"),
+            (self.is_running_tools(), Message::ExternalToolProgressed, "Error running tool"),
+        ];
+        jobs.into_iter().filter(|(running, _, _)| *running).map(|(_, message, context)| (message, context)).collect()
+    }
+
+    // Whether anything is running in the background that `poll_jobs` has to be called for
+    pub fn has_jobs(&self) -> bool {
+        !self.running_jobs().is_empty()
+    }
+
+    // Picks up whatever the background jobs finished since the last call, returning the errors of those that failed
+    pub fn poll_jobs(&mut self) -> Vec<(&'static str, Error)> {
+        let mut errors = Vec::new();
+        for (message, context) in self.running_jobs() {
+            let loading = matches!(message, Message::TreeLoadProgressed);
+            if let Err(e) = self.update(message) {
+                if loading {
+                    let _ = self.notifications.send(Notification::Failure(format!("Loading {} failed", self.name())));
+                }
+                errors.push((context, e));
+            }
+        }
+        errors
+    }
+
+
+    // Lays out only the first-parent history of HEAD or the history of the references left checked in the sidebar, minus the commits the history filter hides
+    fn apply_filters(&mut self) {
+        let change = {
+            let state = &mut *self.state.borrow_mut();
+            let head = self.repository.head().ok().and_then(|head| head.target()).map(|id| id.to_string());
+            let commits = if let Some(head) = head.filter(|_| self.first_parent) {
+                let commits;
+                (commits, state.folded_merges) = first_parent_history(&self.all_commits, &head, &self.expanded_merges);
+                commits
+            } else if self.references.iter().all(|(_, shown)| *shown) {
+                state.folded_merges.clear();
+                BTreeMap::clone(&self.all_commits)
+            } else {
+                state.folded_merges.clear();
+                let tips: Vec<String> = self.references.iter().filter(|(_, shown)| *shown).map(|(tip, _)| tip.target.clone()).collect();
+                filter_commits(&self.all_commits, &tips)
+            };
+            let (commits, mut hidden_edges) = if self.history_filter.is_active() {
+                filter_history(&commits, &self.history_filter.matcher())
+            } else {
+                (commits, HashMap::new())
+            };
+            (state.commits, state.collapsed_chains) = if self.collapse_chains {
+                collapse_chains(&commits, &mut hidden_edges, &self.expanded_chains, MIN_CHAIN_LENGTH)
+            } else {
+                (commits, HashMap::new())
+            };
+            state.hidden_edges = hidden_edges;
+
+            state.relayout();
+
+            let selected = state.selection.commit.clone();
+            let had_selection = selected.is_some();
+            state.selection.set(selected, &state.commits);
+            // A range is dropped, and the selected commit might be filtered out
+            let ranged = self.range_diff.is_some() || self.range_job.is_some();
+            (ranged || had_selection && state.selection.commit.is_none()).then(|| state.selection.change())
+        };
+        if let Some(change) = change {
+            self.follow_selection(change);
+        }
+    }
+
+    fn update_ignore_preview(&mut self) -> Result<(), Error> {
+        if let Some(editor) = &mut self.gitignore_editor {
+            let untracked: Vec<String> = get_status(&self.repository)?.into_iter()
+                .filter(|entry| entry.unstaged == Some('?'))
+                .map(|entry| entry.path)
+                .collect();
+            editor.preview = preview_ignored(&self.repository, &editor.lines.join("\n"), &untracked)?;
+        }
+        Ok(())
+    }
+
+    fn refresh_status_panel(&mut self) -> Result<(), Error> {
+        if let Some(panel) = &mut self.status_panel {
+            panel.refresh(&self.repository)?;
+        }
+        self.refresh_head();
+        Ok(())
+    }
+
+    fn refresh_head(&mut self) {
+        (self.head, self.dirty_files) = read_status(&self.repository);
+
+        let commit = match self.repository.head_detached() {
+            Ok(true) => self.repository.head().ok().and_then(|head| head.target()).map(|id| id.to_string()),
+            _ => None,
+        };
+        // A branch name being typed survives refreshes while HEAD stays detached
+        self.detached = commit.map(|commit| {
+            let branch_name = self.detached.take().map(|detached| detached.branch_name).unwrap_or_default();
+            DetachedHead { commit, previous_branch: get_previous_branch(&self.repository), branch_name }
+        });
+    }
+
+    // Checks out the branch, or detaches HEAD at the commit when there is none
+    fn checkout(&mut self, branch: Option<String>, id: Oid, name: String) -> Result<(), Error> {
+        let before = take_snapshot(&self.repository)?;
+        self.repository.checkout_tree(&self.repository.find_object(id, None)?, None)?;
+        match &branch {
+            Some(branch) => self.repository.set_head(&format!("refs/heads/{}", branch))?,
+            None => self.repository.set_head_detached(id)?,
+        }
+        self.journal.record(&self.repository, format!("checkout of {}", name), before)?;
+        let _ = self.notifications.send(Notification::Info(format!("Checked out {}", name)));
+        self.refresh_head();
+        Ok(())
+    }
+
+    pub fn update(&mut self, message: Message) -> Result<(), Error> {
+        match message {
+            Message::RefreshTree => {
+                // Starting over drops and so cancels a load that is still running. Once the history is there, only
+                // the commits that changed are walked
+                let previous = (!self.all_commits.is_empty()).then(|| Arc::clone(&self.all_commits));
+                self.loading = Some(TreeLoad::start(self.repository.path().to_path_buf(), previous, self.reference_groups)?);
+            },
+            Message::TreeLoadProgressed => {
+                let result = match self.loading.as_ref().and_then(|loading| loading.take_result()) {
+                    Some(result) => result,
+                    None => return Ok(()),
+                };
+                self.loading = None;
+                let loaded = result?;
+                // A refresh that found nothing new leaves the graph and its layout as they are, unless references are
+                // still to be hidden or the first-parent view follows a HEAD that might have been checked out elsewhere
+                let changed = loaded.changed || self.all_commits.is_empty() || !self.hidden_on_load.is_empty() || self.first_parent;
+                if changed {
+                    let mut commits = loaded.commits;
+                    // Keep signatures that were already verified instead of checking them again
+                    for (id, commit) in commits.iter_mut() {
+                        if let Some(previous) = self.all_commits.get(id) {
+                            if matches!(previous.signature, Some(SignatureStatus::Verified(_)) | Some(SignatureStatus::Untrusted(_)) | Some(SignatureStatus::Unverified(_))) {
+                                commit.signature = previous.signature.clone();
+                            }
+                        }
+                    }
+                    self.all_commits = Arc::new(commits);
+                }
+
+                // References that were hidden before stay hidden, new ones are shown
+                let mut hidden: HashSet<(String, ReferenceKind)> = self.references.iter()
+                    .filter(|(_, shown)| !*shown)
+                    .map(|(tip, _)| (tip.name.clone(), tip.kind))
+                    .collect();
+                hidden.extend(self.hidden_on_load.drain());
+                self.references = get_reference_tips(&self.repository)?.into_iter()
+                    .map(|tip| {
+                        let shown = !hidden.contains(&(tip.name.clone(), tip.kind));
+                        (tip, shown)
+                    })
+                    .collect();
+
+                self.state.borrow_mut().notes = get_noted_commits(&self.repository);
+                self.state.borrow_mut().display = DisplayConfig::load(&self.repository);
+                self.forge = get_forge(&self.repository);
+                self.last_fetch = fs::metadata(self.repository.path().join("FETCH_HEAD")).and_then(|metadata| metadata.modified()).ok()
+                    .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+                    .map(|since| git2::Time::new(since.as_secs() as i64, 0));
+                if changed {
+                    let previous: HashSet<String> = self.state.borrow().node_locations.keys().cloned().collect();
+                    self.apply_filters();
+                    // Nothing stands out as new when the graph was empty before
+                    if !previous.is_empty() {
+                        let state = &mut *self.state.borrow_mut();
+                        state.arrival = Arrival::start(&previous, &state.commits, &state.node_locations);
+                    }
+                }
+                self.refresh_head();
+                let _ = self.notifications.send(Notification::Info(format!("Loaded {} commits", self.all_commits.len())));
+                if let Some(selected) = self.restore_selection.take() {
+                    self.update(Message::SelectCommit(selected))?;
+                }
+            },
+            Message::Fetch => {
+                // A fetch still running from the last tick is left to finish
+                if self.fetching.is_none() {
+                    self.fetching = Some(FetchJob::start(self.repository.path().to_path_buf())?);
+                }
+            },
+            Message::CancelFetch => {
+                if self.fetching.take().is_some() {
+                    let _ = self.notifications.send(Notification::Info(String::from("Fetching cancelled")));
+                }
+            },
+            Message::FetchProgressed => {
+                let result = match self.fetching.as_ref().and_then(|fetching| fetching.take_result()) {
+                    Some(result) => result,
+                    None => return Ok(()),
+                };
+                self.fetching = None;
+                // Fetching mostly runs unattended, so failing is only noted in the status bar
+                match result {
+                    Ok(updated) if updated.is_empty() => (),
+                    Ok(updated) => {
+                        let _ = self.notifications.send(Notification::Info(format!("Fetched new commits on {}", updated.join(", "))));
+                        self.update(Message::RefreshTree)?;
+                    },
+                    Err(e) => {
+                        let _ = self.notifications.send(Notification::Failure(format!("Fetching {} failed: {}", self.name(), e.message())));
+                    },
+                }
+            },
+            Message::Push => {
+                let branch = self.repository.head()
+                    .map(|head| head.shorthand().filter(|_| head.is_branch()).map(String::from))?
+                    .ok_or(Error::from("check out a branch to push it"))?;
+                match check_push(&self.repository, &branch)? {
+                    PushCheck::UpToDate => {
+                        let _ = self.notifications.send(Notification::Info(format!("{} is already up to date", branch)));
+                    },
+                    PushCheck::FastForward => self.start_push(branch, false)?,
+                    PushCheck::Diverged { upstream, ahead, behind } => {
+                        self.push_dialog = Some(PushDialog { branch, upstream, ahead, behind });
+                    },
+                }
+            },
+            Message::ForcePush => {
+                if let Some(dialog) = self.push_dialog.take() {
+                    self.start_push(dialog.branch, true)?;
+                }
+            },
+            Message::RebaseBeforePush => {
+                if let Some(dialog) = self.push_dialog.take() {
+                    let onto = get_push_upstream(&self.repository, &dialog.branch)?;
+                    let before = take_snapshot(&self.repository)?;
+                    rebase_branch(&self.repository, &dialog.branch, &onto.to_string())?;
+                    self.journal.record(&self.repository, format!("rebase of {} onto {}", dialog.branch, dialog.upstream), before)?;
+                    let _ = self.notifications.send(Notification::Info(format!("Rebased {} onto {}, it can be pushed now", dialog.branch, dialog.upstream)));
+                    self.update(Message::RefreshTree)?;
+                }
+            },
+            Message::CancelPush => {
+                self.push_dialog = None;
+            },
+            Message::CancelPushing => {
+                if let Some(push) = &mut self.pushing {
+                    push.cancel();
+                }
+            },
+            Message::PushProgressed => {
+                let Some(result) = self.pushing.as_ref().and_then(|push| push.take_result()) else {
+                    return Ok(());
+                };
+                let push = self.pushing.take().unwrap();
+                let verb = if push.force { "Force pushed" } else { "Pushed" };
+                match result {
+                    // Cancelled too late to stop it, which is worth knowing as much as any other push
+                    Ok(()) if push.is_cancelled() => {
+                        let _ = self.notifications.send(Notification::Info(format!("{} {} before it could be cancelled", verb, push.branch)));
+                    },
+                    Ok(()) => {
+                        let _ = self.notifications.send(Notification::Info(format!("{} {}", verb, push.branch)));
+                    },
+                    Err(e) if push.is_cancelled() => {
+                        let _ = self.notifications.send(Notification::Info(format!("Pushing {} stopped: {}", push.branch, e.message())));
+                        return Ok(());
+                    },
+                    Err(e) => return Err(e),
+                }
+                self.update(Message::RefreshTree)?;
+            },
+            Message::ToggleLegend => {
+                self.legend = !self.legend;
+            },
+            Message::ToggleReferenceSidebar => {
+                self.reference_sidebar = !self.reference_sidebar;
+            },
+            Message::ReferenceToggled(index, shown) => {
+                if let Some(reference) = self.references.get_mut(index) {
+                    reference.1 = shown;
+                }
+                self.apply_filters();
+            },
+            Message::ShowAllReferences => {
+                for reference in self.references.iter_mut() {
+                    reference.1 = true;
+                }
+                self.apply_filters();
+            },
+            Message::ToggleReferenceFolder(kind, folder) => {
+                let folder = (kind, folder);
+                if !self.expanded_folders.remove(&folder) {
+                    self.expanded_folders.insert(folder);
+                }
+            },
+            Message::ReferenceFolderToggled(kind, folder, shown) => {
+                for (tip, reference_shown) in self.references.iter_mut() {
+                    if tip.kind == kind && tip.folder() == Some(folder.as_str()) {
+                        *reference_shown = shown;
+                    }
+                }
+                self.apply_filters();
+            },
+            Message::ReferenceGroupToggled(kind, loaded) => {
+                self.reference_groups.set(kind, loaded);
+                // Walks only the history the group adds, or drops what only it reached
+                self.update(Message::RefreshTree)?;
+            },
+            Message::StartRenameBranch(branch) => {
+                self.renaming = Some((branch.clone(), branch));
+            },
+            Message::RenameBranchChanged(name) => {
+                if let Some((_, new_name)) = &mut self.renaming {
+                    *new_name = name;
+                }
+            },
+            Message::RenameBranch => {
+                if let Some((branch, new_name)) = self.renaming.clone() {
+                    let new_name = new_name.trim();
+                    if new_name != branch {
+                        let before = take_snapshot(&self.repository)?;
+                        // The edit stays open when renaming fails, so the name can be fixed
+                        rename_branch(&self.repository, &branch, new_name)?;
+                        self.renaming = None;
+                        self.journal.record(&self.repository, format!("renaming {} to {}", branch, new_name), before)?;
+                        let _ = self.notifications.send(Notification::Info(format!("Renamed {} to {}", branch, new_name)));
+                        self.update(Message::RefreshTree)?;
+                    } else {
+                        self.renaming = None;
+                    }
+                }
+            },
+            Message::CancelRenameBranch => {
+                self.renaming = None;
+            },
+            Message::OpenTrackingDialog => {
+                self.tracking_dialog = Some(TrackingDialog::open(&self.repository)?);
+            },
+            Message::CloseTrackingDialog => {
+                self.tracking_dialog = None;
+            },
+            Message::UpstreamSelected(branch, upstream) => {
+                set_upstream(&self.repository, &branch, Some(&upstream))?;
+                let _ = self.notifications.send(Notification::Info(format!("{} now tracks {}", branch, upstream)));
+                self.tracking_dialog = Some(TrackingDialog::open(&self.repository)?);
+            },
+            Message::PushTargetSelected(branch, remote) => {
+                set_push_target(&self.repository, &branch, &remote)?;
+                let _ = self.notifications.send(Notification::Info(format!("{} now pushes to {}/{}", branch, remote, branch)));
+                self.tracking_dialog = Some(TrackingDialog::open(&self.repository)?);
+            },
+            Message::UnsetUpstream(branch) => {
+                set_upstream(&self.repository, &branch, None)?;
+                let _ = self.notifications.send(Notification::Info(format!("{} no longer has an upstream", branch)));
+                self.tracking_dialog = Some(TrackingDialog::open(&self.repository)?);
+            },
+            Message::OpenRemoteBrowser => {
+                self.remote_browser = Some(RemoteBrowser::open(&self.repository)?);
+            },
+            Message::CloseRemoteBrowser => {
+                self.remote_browser = None;
+            },
+            Message::RemoteBrowserRemoteChanged(remote) => {
+                if let Some(browser) = &mut self.remote_browser {
+                    browser.remote = remote;
+                }
+            },
+            Message::ListRemoteReferences => {
+                if let Some(browser) = &mut self.remote_browser {
+                    let remote = browser.remote.trim().to_string();
+                    browser.listed = remote.clone();
+                    browser.references.clear();
+                    browser.picked.clear();
+                    browser.result = None;
+                    browser.listing = Some(self.worker.submit(move |repository, _| Ok(list_remote_references(repository, &remote)?)));
+                }
+            },
+            Message::RemoteReferenceToggled(name, picked) => {
+                if let Some(browser) = &mut self.remote_browser {
+                    match picked {
+                        true => browser.picked.insert(name),
+                        false => browser.picked.remove(&name),
+                    };
+                }
+            },
+            Message::FetchRemoteReferences => {
+                if let Some(browser) = self.remote_browser.as_mut().filter(|browser| browser.can_fetch()) {
+                    let remote = browser.listed.clone();
+                    let refspecs = browser.refspecs();
+                    browser.result = None;
+                    browser.fetching = Some(self.worker.submit(move |repository, cancel| {
+                        Ok(fetch_refspecs(repository, &remote, &refspecs, &mut |_, _| !cancel.is_cancelled())?)
+                    }));
+                }
+            },
+            Message::RemoteBrowserProgressed => {
+                let Some(browser) = &mut self.remote_browser else {
+                    return Ok(());
+                };
+                if let Some(listed) = browser.listing.as_ref().and_then(|job| job.take_result()) {
+                    browser.listing = None;
+                    match listed {
+                        Ok(references) => browser.references = references,
+                        Err(e) => browser.result = Some(Err(e.to_string())),
+                    }
+                }
+                if let Some(fetched) = browser.fetching.as_ref().and_then(|job| job.take_result()) {
+                    browser.fetching = None;
+                    match fetched {
+                        Ok(updated) => {
+                            browser.result = Some(Ok(format!("{} references updated", updated.len())));
+                            // Listing again shows the fetched references as up to date
+                            let remote = browser.listed.clone();
+                            browser.listing = Some(self.worker.submit(move |repository, _| Ok(list_remote_references(repository, &remote)?)));
+                            self.update(Message::RefreshTree)?;
+                        },
+                        Err(e) => browser.result = Some(Err(e.to_string())),
+                    }
+                }
+            },
+            Message::OpenRefspecEditor => {
+                self.refspec_editor = RefspecEditor::open(&self.repository, None)?;
+                if self.refspec_editor.is_none() {
+                    let _ = self.notifications.send(Notification::Info(String::from("The repository has no remotes")));
+                }
+            },
+            Message::CloseRefspecEditor => {
+                self.refspec_editor = None;
+            },
+            Message::RefspecRemoteSelected(remote) => {
+                self.refspec_editor = RefspecEditor::open(&self.repository, Some(remote))?;
+            },
+            Message::RefspecChanged(direction, i, refspec) => {
+                if let Some(line) = self.refspec_editor.as_mut().and_then(|editor| editor.lines_mut(direction).get_mut(i)) {
+                    *line = refspec;
+                }
+            },
+            Message::AddRefspec(direction) => {
+                if let Some(editor) = &mut self.refspec_editor {
+                    editor.lines_mut(direction).push(String::new());
+                }
+            },
+            Message::RemoveRefspec(direction, i) => {
+                if let Some(lines) = self.refspec_editor.as_mut().map(|editor| editor.lines_mut(direction)).filter(|lines| i < lines.len()) {
+                    lines.remove(i);
+                }
+            },
+            Message::AddPullRequestRefspec => {
+                if let Some(editor) = &mut self.refspec_editor {
+                    let refspec = format!("+refs/pull/*/head:refs/remotes/{}/pr/*", editor.remote);
+                    if !editor.fetch.contains(&refspec) {
+                        editor.fetch.push(refspec);
+                    }
+                }
+            },
+            Message::SaveRefspecs(fetch) => {
+                let Some(editor) = &mut self.refspec_editor else {
+                    return Ok(());
+                };
+                match set_remote_refspecs(&self.repository, &editor.remote, &editor.refspecs()) {
+                    Ok(()) => {
+                        editor.result = Some(Ok(format!("Saved the refspecs of {}", editor.remote)));
+                        if fetch {
+                            self.update(Message::Fetch)?;
+                        }
+                    },
+                    // Kept in the dialog, next to the refspec to fix
+                    Err(e) => editor.result = Some(Err(e.message().to_string())),
+                }
+            },
+            Message::LayoutSelected(layout) => {
+                self.state.borrow_mut().layout = layout;
+                self.apply_filters();
+            },
+            Message::OrientationSelected(orientation) => {
+                self.state.borrow_mut().orientation = orientation;
+                self.apply_filters();
+            },
+            Message::CollapseChainsToggled(collapse) => {
+                self.collapse_chains = collapse;
+                self.expanded_chains.clear();
+                self.apply_filters();
+            },
+            Message::FirstParentToggled(first_parent) => {
+                self.first_parent = first_parent;
+                self.expanded_merges.clear();
+                self.apply_filters();
+            },
+            Message::ExpandMerge(commit) => {
+                self.expanded_merges.insert(commit);
+                self.apply_filters();
+            },
+            Message::ExpandChain(commit) => {
+                self.expanded_chains.insert(commit);
+                self.apply_filters();
+            },
+            Message::ToggleBookmarkList => {
+                self.bookmark_list = !self.bookmark_list;
+            },
+            Message::EditBookmark(commit) => {
+                let existing = self.state.borrow().bookmarks.iter().find(|bookmark| bookmark.commit == commit).cloned();
+                self.bookmark_editor = Some(match existing {
+                    Some(bookmark) => BookmarkEditor { commit, label: bookmark.label, color: bookmark.color, existing: true },
+                    None => BookmarkEditor { commit, label: String::new(), color: BookmarkColor::Blue, existing: false },
+                });
+            },
+            Message::BookmarkLabelChanged(label) => {
+                if let Some(editor) = &mut self.bookmark_editor {
+                    editor.label = label;
+                }
+            },
+            Message::BookmarkColorSelected(color) => {
+                if let Some(editor) = &mut self.bookmark_editor {
+                    editor.color = color;
+                }
+            },
+            Message::CancelBookmark => {
+                self.bookmark_editor = None;
+            },
+            Message::EditNote(commit) => {
+                let note = self.commit_details.as_ref().filter(|details| details.id == commit).and_then(|details| details.note.clone())
+                    .or_else(|| get_note(&self.repository, &commit));
+                self.note_editor = Some(match note {
+                    Some(note) => NoteEditor { commit, lines: note.lines().map(str::to_string).collect(), existing: true },
+                    None => NoteEditor { commit, lines: vec![String::new()], existing: false },
+                });
+            },
+            Message::NoteLineChanged(index, line) => {
+                if let Some(current) = self.note_editor.as_mut().and_then(|editor| editor.lines.get_mut(index)) {
+                    *current = line;
+                }
+            },
+            Message::InsertNoteLine(index) => {
+                if let Some(editor) = &mut self.note_editor {
+                    editor.lines.insert(index.min(editor.lines.len()), String::new());
+                }
+            },
+            Message::RemoveNoteLine(index) => {
+                if let Some(editor) = &mut self.note_editor {
+                    if index < editor.lines.len() {
+                        editor.lines.remove(index);
+                    }
+                }
+            },
+            Message::SaveNote | Message::RemoveNote => {
+                if let Some(editor) = self.note_editor.take() {
+                    let text = match message {
+                        Message::SaveNote => editor.lines.join("\n"),
+                        _ => String::new(),
+                    };
+                    set_note(&self.repository, &editor.commit, &text)?;
+                    let note = get_note(&self.repository, &editor.commit);
+                    let notification = match note {
+                        Some(_) => format!("Saved the note of {}", self.state.borrow().display.abbreviate(&editor.commit)),
+                        None => format!("Removed the note of {}", self.state.borrow().display.abbreviate(&editor.commit)),
+                    };
+                    if let Some(details) = self.commit_details.as_mut().filter(|details| details.id == editor.commit) {
+                        details.note = note;
+                    }
+                    self.state.borrow_mut().notes = get_noted_commits(&self.repository);
+                    let _ = self.notifications.send(Notification::Info(notification));
+                }
+            },
+            Message::CancelNote => {
+                self.note_editor = None;
+            },
+            Message::ToggleQuickSwitcher => {
+                self.quick_switcher = match self.quick_switcher {
+                    Some(_) => None,
+                    None => Some(QuickSwitcher::open(&self.repository, &self.all_commits)?),
+                };
+            },
+            Message::QuickSwitchQueryChanged(query) => {
+                if let Some(switcher) = &mut self.quick_switcher {
+                    switcher.query = query;
+                    switcher.selected = 0;
+                }
+            },
+            Message::QuickSwitchMove(step) => {
+                if let Some(switcher) = &mut self.quick_switcher {
+                    switcher.move_selection(step);
+                }
+            },
+            Message::QuickSwitchPick(index) => {
+                if let Some(switcher) = &mut self.quick_switcher {
+                    switcher.selected = index;
+                }
+                self.update(Message::QuickSwitchSubmit)?;
+            },
+            Message::QuickSwitchSubmit => {
+                if let Some(item) = self.quick_switcher.as_ref().and_then(|switcher| switcher.selected_item()) {
+                    self.quick_switcher = None;
+                    match item {
+                        QuickSwitchItem::Branch(branch) => self.update(Message::CheckoutReference(branch))?,
+                        QuickSwitchItem::Commit(commit, _) => self.update(Message::JumpToCommit(commit))?,
+                    }
+                }
+            },
+            Message::ToggleFilterPanel => {
+                self.filter_panel = !self.filter_panel;
+                if self.filter_panel {
+                    let mut authors: Vec<String> = self.all_commits.values().map(|commit| commit.author.clone()).collect();
+                    authors.sort();
+                    authors.dedup();
+                    self.history_filter.authors = authors;
+                }
+            },
+            Message::FilterAuthorSelected(author) => {
+                self.history_filter.author = Some(author);
+                self.apply_filters();
+            },
+            Message::FilterSinceChanged(since) => {
+                self.history_filter.since = since;
+                self.apply_filters();
+            },
+            Message::FilterUntilChanged(until) => {
+                self.history_filter.until = until;
+                self.apply_filters();
+            },
+            Message::ClearFilters => {
+                self.history_filter.author = None;
+                self.history_filter.since.clear();
+                self.history_filter.until.clear();
+                self.apply_filters();
+            },
+            Message::CancelTreeLoad => {
+                if self.loading.take().is_some() {
+                    let _ = self.notifications.send(Notification::Info(String::from("Loading commits cancelled")));
+                }
+            },
+            Message::SelectCommit(commit) => {
+                let change = {
+                    let state = &mut *self.state.borrow_mut();
+                    state.selection.set(Some(commit), &state.commits);
+                    state.selection.change()
+                };
+                self.update(Message::SelectionChanged(change))?;
+            },
+            Message::SelectionChanged(change) => {
+                self.follow_selection(change);
+            },
+            // The history only moves once the commit is known to be shown, since selecting it visits it and a
+            // visit anywhere else would drop the commits ahead
+            Message::SelectionBack | Message::SelectionForward => {
+                if let Some((position, commit)) = self.selection_history.peek(matches!(message, Message::SelectionForward)) {
+                    if !self.state.borrow().node_locations.contains_key(&commit) {
+                        return Err(Error::from(HIDDEN_COMMIT));
+                    }
+                    self.selection_history.go_to(position);
+                    self.update(Message::JumpToCommit(commit))?;
+                }
+            },
+            Message::JumpToCommit(commit) => {
+                let location = self.state.borrow().node_locations.get(&commit).copied()
+                    .ok_or(Error::from(HIDDEN_COMMIT))?;
+                self.update(Message::SelectCommit(commit))?;
+                self.state.borrow_mut().viewport.glide_to(location);
+            },
+            Message::SelectReference(commit, reference) => {
+                self.update(Message::SelectCommit(commit.clone()))?;
+                let state = &mut *self.state.borrow_mut();
+                let label = state.commits.get(&commit).and_then(|node| node.references.iter().find(|label| label.name == reference));
+                state.selection.reference = label.cloned();
+            },
+            Message::CheckoutReference(reference) => {
+                let id = self.repository.revparse_single(&reference)?.peel_to_commit()?.id();
+                // Anything but a local branch leaves HEAD detached, like `git checkout` does
+                let branch = self.repository.find_branch(&reference, BranchType::Local).is_ok().then(|| reference.clone());
+                self.checkout(branch, id, reference)?;
+            },
+            Message::CompareReference(reference) => {
+                self.update(Message::OpenCompareDialog)?;
+                self.update(Message::CompareLeftSelected(reference))?;
+            },
+            Message::DeleteReference(reference) => {
+                let before = take_snapshot(&self.repository)?;
+                delete_reference(&self.repository, &reference)?;
+                self.journal.record(&self.repository, format!("deleting {}", reference), before)?;
+                let _ = self.notifications.send(Notification::Info(format!("Deleted {}", reference)));
+                self.update(Message::RefreshTree)?;
+            },
+            Message::UnselectCommit => {
+                {
+                    let state = &mut *self.state.borrow_mut();
+                    state.selection.set(None, &state.commits);
+                }
+                self.update(Message::SelectionChanged(SelectionChange::Cleared))?;
+            },
+            Message::OpenCommitDetails(commit) => {
+                self.update(Message::SelectCommit(commit.clone()))?;
+                self.load_commit_details(commit);
+            },
+            Message::RangeDiffProgressed => {
+                let Some(result) = self.range_job.as_ref().and_then(|job| job.take_result()) else {
+                    return Ok(());
+                };
+                self.range_job = None;
+                let range = result?;
+                self.state.borrow_mut().selection.set_range(&range);
+                self.range_diff = Some(range);
+                self.file_list = Default::default();
+            },
+            Message::SignatureChecked => {
+                let Some(result) = self.signature_job.as_ref().and_then(|(_, job)| job.take_result()) else {
+                    return Ok(());
+                };
+                let (commit, _) = self.signature_job.take().unwrap();
+                let status = result.unwrap_or_else(|e| SignatureStatus::Unverified(e.to_string()));
+                if let Some(node) = self.state.borrow_mut().commits.get_mut(&commit) {
+                    node.signature = Some(status.clone());
+                }
+                // Copies the graph only while a load still shares it
+                if self.all_commits.contains_key(&commit) {
+                    if let Some(loaded) = Arc::make_mut(&mut self.all_commits).get_mut(&commit) {
+                        loaded.signature = Some(status);
+                    }
+                }
+            },
+            Message::CommitDetailsProgressed => {
+                let Some(result) = self.details_job.as_ref().and_then(|job| job.take_result()) else {
+                    return Ok(());
+                };
+                self.details_job = None;
+                self.commit_details = Some(result?);
+            },
+            Message::DownloadLfsObjects => {
+                if let Some(details) = self.commit_details.as_ref().filter(|_| self.lfs_download.is_none()) {
+                    let head = self.repository.head().ok().and_then(|head| head.target());
+                    let check_out = head.is_some_and(|head| head.to_string() == details.id);
+                    self.lfs_download = Some(LfsDownload::start(self.repository.path().to_path_buf(), details.id.clone(), details.lfs_missing.clone(), check_out)?);
+                }
+            },
+            Message::LfsDownloadProgressed => {
+                let Some(result) = self.lfs_download.as_ref().and_then(|download| download.take_result()) else {
+                    return Ok(());
+                };
+                let commit = self.lfs_download.take().map(|download| download.commit).unwrap_or_default();
+                match result {
+                    Ok(()) => {
+                        let _ = self.notifications.send(Notification::Info(format!("Downloaded the LFS objects of {}", self.abbreviate(&commit))));
+                        if self.commit_details.as_ref().is_some_and(|details| details.id == commit) {
+                            self.load_commit_details(commit);
+                        }
+                    },
+                    Err(e) => {
+                        let _ = self.notifications.send(Notification::Failure(format!("Downloading LFS objects failed: {}", e.message())));
+                    },
+                }
+            },
+            Message::DiffSettingsChanged(settings) => {
+                self.diff_settings = settings;
+                if let Some(id) = self.commit_details.as_ref().map(|details| details.id.clone()) {
+                    self.load_commit_details(id);
+                }
+                if let Some(range) = &self.range_diff {
+                    self.load_range_diff(range.from.clone(), range.to.clone());
+                }
+            },
+            Message::SelectDiffFile(path) => {
+                self.file_list.focused = path;
+            },
+            Message::ToggleDiffDirectory(directory) => {
+                if !self.file_list.expanded.remove(&directory) {
+                    self.file_list.expanded.insert(directory);
+                }
+            },
+            Message::ImageCompareModeSelected(mode) => {
+                self.image_comparison.mode = mode;
+            },
+            Message::ImageComparePositionChanged(position) => {
+                self.image_comparison.position = position;
+            },
+            Message::CloseCommitDetails => {
+                self.commit_details = None;
+                self.details_job = None;
+            },
+            Message::SelectRangeEnd(commit) => {
+                let selected = self.state.borrow().selection.commit.clone();
+                match selected {
+                    Some(selected) if selected != commit => self.update(Message::SelectionChanged(SelectionChange::Range(selected, commit)))?,
+                    _ => self.update(Message::SelectCommit(commit))?,
+                }
+            },
+            Message::CheckoutSelected => {
+                let selected = self.state.borrow().selection.commit.clone();
+                if let Some(selected) = selected {
+                    self.update(Message::SwitchToCommit(selected))?;
+                }
+            },
+            // The selection ring grows in by itself as frames are drawn, but a moving view has to be moved along
+            Message::AnimationFrame => {
+                self.state.borrow_mut().viewport.step();
+            },
+            Message::Zoom(factor) => {
+                let viewport = &mut self.state.borrow_mut().viewport;
+                viewport.zoom = (viewport.zoom * factor).clamp(0.001, 4.0);
+            },
+            Message::OpenUrl(url) => {
+                open_url(&url)?;
+            },
+            Message::OpenTerminal => {
+                open_terminal(&self.repository)?;
+            },
+            Message::SwitchToCommit(commit) => {
+                let (branch, name) = {
+                    let state = self.state.borrow();
+                    let commit_node = state.commits.get(&commit).ok_or(Error::from("the commit is no longer loaded"))?;
+                    let branch = commit_node.branches().next().cloned();
+                    (branch.clone(), branch.unwrap_or(state.display.abbreviate(&commit).to_string()))
+                };
+                let id = match &branch {
+                    Some(branch) => self.repository.refname_to_id(&format!("refs/heads/{}", branch))?,
+                    None => Oid::from_str(&commit)?,
+                };
+                self.checkout(branch, id, name)?;
+            },
+            Message::DetachedBranchNameChanged(name) => {
+                if let Some(detached) = &mut self.detached {
+                    detached.branch_name = name;
+                }
+            },
+            Message::CreateBranchAtHead => {
+                if let Some(detached) = &self.detached {
+                    let name = detached.branch_name.trim().to_string();
+                    if name.is_empty() {
+                        return Err(Error::from("enter a name for the new branch"));
+                    }
+                    let before = take_snapshot(&self.repository)?;
+                    create_branch_at_head(&self.repository, &name)?;
+                    self.journal.record(&self.repository, format!("creating branch {}", name), before)?;
+                    let _ = self.notifications.send(Notification::Info(format!("Created branch {} and switched to it", name)));
+                    self.update(Message::RefreshTree)?;
+                }
+            },
+            Message::ReturnToBranch(branch) => {
+                let id = self.repository.refname_to_id(&format!("refs/heads/{}", branch))?;
+                self.checkout(Some(branch.clone()), id, branch)?;
+            },
+            Message::Undo => {
+                let description = self.journal.undo(&self.repository)?;
+                let _ = self.notifications.send(Notification::Info(format!("Undid {}", description)));
+                self.update(Message::RefreshTree)?;
+            },
+            Message::OpenTagDialog => {
+                self.open_tag_dialog(Vec::new())?;
+            },
+            Message::CloseTagDialog => {
+                self.tag_dialog = None;
+            },
+            Message::TagVersionChanged(version) => {
+                if let Some(dialog) = &mut self.tag_dialog {
+                    dialog.version = version;
+                }
+            },
+            Message::TagMessageChanged(message) => {
+                if let Some(dialog) = &mut self.tag_dialog {
+                    dialog.message = message;
+                }
+            },
+            Message::TagTemplateChanged(template) => {
+                if let Some(dialog) = &mut self.tag_dialog {
+                    dialog.name_template = template;
+                }
+            },
+            Message::TagBranchToggled(index, selected) => {
+                if let Some((_, toggled)) = self.tag_dialog.as_mut().and_then(|dialog| dialog.branches.get_mut(index)) {
+                    *toggled = selected;
+                }
+            },
+            Message::TagRepositoryToggled(index, selected) => {
+                if let Some((_, toggled)) = self.tag_dialog.as_mut().and_then(|dialog| dialog.repositories.get_mut(index)) {
+                    *toggled = selected;
+                }
+            },
+            Message::CreateTags => {
+                if let Some(dialog) = &mut self.tag_dialog {
+                    let before = take_snapshot(&self.repository)?;
+                    let mut results = Vec::new();
+                    let name = repository_name(self.repository.workdir().unwrap_or(self.repository.path()));
+                    for (branch, _) in dialog.branches.iter().filter(|(_, selected)| *selected) {
+                        let tag_name = dialog.tag_name(branch, &name);
+                        let result = create_tag_at_branch(&self.repository, branch, &tag_name, &dialog.message)
+                            .map(|_| tag_name)
+                            .map_err(|e| e.message().to_string());
+                        results.push((branch.clone(), result));
+                    }
+                    // Not journaled, undo only covers this repository
+                    for (path, _) in dialog.repositories.iter().filter(|(_, selected)| *selected) {
+                        let name = repository_name(path);
+                        let result = Repository::open(path).and_then(|repository| {
+                            let branch = get_head_branch(&repository)?;
+                            let tag_name = dialog.tag_name(&branch, &name);
+                            create_tag_at_branch(&repository, &branch, &tag_name, &dialog.message).map(|_| tag_name)
+                        });
+                        results.push((name, result.map_err(|e| e.message().to_string())));
+                    }
+                    let failed = results.iter().filter(|(_, result)| result.is_err()).count();
+                    if failed < results.len() {
+                        self.journal.record(&self.repository, format!("creating {} tags", results.len() - failed), before)?;
+                    }
+                    let notification = if failed == 0 {
+                        Notification::Info(format!("Created {} tags", results.len()))
+                    } else {
+                        Notification::Failure(format!("{} of {} tags failed", failed, results.len()))
+                    };
+                    let _ = self.notifications.send(notification);
+                    dialog.results = results;
+                }
+                self.update(Message::RefreshTree)?;
+            },
+            Message::OpenCompareDialog => {
+                let references = get_reference_names(&self.repository)?;
+                self.compare_dialog = Some(CompareDialog { references, left: None, right: None, comparison: None });
+            },
+            Message::CloseCompareDialog => {
+                self.compare_dialog = None;
+            },
+            Message::CompareLeftSelected(reference) => {
+                if let Some(dialog) = &mut self.compare_dialog {
+                    dialog.left = Some(reference);
+                    dialog.update_comparison(&self.repository)?;
+                }
+            },
+            Message::CompareRightSelected(reference) => {
+                if let Some(dialog) = &mut self.compare_dialog {
+                    dialog.right = Some(reference);
+                    dialog.update_comparison(&self.repository)?;
+                }
+            },
+            Message::OpenCommitComposer => {
+                let sign = is_signing_enabled(&self.repository);
+                self.commit_composer = Some(CommitComposer::new(MessageConfig::load(&self.repository), sign));
+            },
+            Message::CloseCommitComposer => {
+                self.commit_composer = None;
+            },
+            Message::CommitSubjectChanged(subject) => {
+                if let Some(composer) = &mut self.commit_composer {
+                    composer.subject = subject;
+                }
+            },
+            Message::CommitBodyLineChanged(index, line) => {
+                if let Some(current) = self.commit_composer.as_mut().and_then(|composer| composer.body.get_mut(index)) {
+                    *current = line;
+                }
+            },
+            Message::InsertCommitBodyLine(index) => {
+                if let Some(composer) = &mut self.commit_composer {
+                    composer.body.insert(index.min(composer.body.len()), String::new());
+                }
+            },
+            Message::RemoveCommitBodyLine(index) => {
+                if let Some(composer) = &mut self.commit_composer {
+                    if index < composer.body.len() {
+                        composer.body.remove(index);
+                    }
+                }
+            },
+            Message::CommitTypeSelected(kind) => {
+                if let Some(composer) = &mut self.commit_composer {
+                    composer.kind = Some(kind);
+                    composer.subject = set_conventional_prefix(&composer.subject, kind, &composer.scope);
+                }
+            },
+            Message::CommitScopeChanged(scope) => {
+                if let Some(composer) = &mut self.commit_composer {
+                    composer.scope = scope;
+                    if let Some(kind) = composer.kind {
+                        composer.subject = set_conventional_prefix(&composer.subject, kind, &composer.scope);
+                    }
+                }
+            },
+            Message::CommitAmendToggled(amend) => {
+                if let Some(composer) = &mut self.commit_composer {
+                    composer.amend = amend;
+                    if amend && composer.message().is_empty() {
+                        if let Ok(head) = self.repository.head().and_then(|h| h.peel_to_commit()) {
+                            composer.set_message(head.message().unwrap_or_default().trim_end());
+                        }
+                    }
+                }
+            },
+            Message::CommitSignToggled(sign) => {
+                if let Some(composer) = &mut self.commit_composer {
+                    composer.sign = sign;
+                }
+            },
+            Message::CreateCommit => {
+                if let Some(composer) = &mut self.commit_composer {
+                    let mut message = composer.message();
+                    composer.hook_outputs.clear();
+                    if !composer.skip_hooks {
+                        let (outputs, hook_message) = run_commit_hooks(&self.repository, &message)?;
+                        composer.hook_outputs = outputs;
+                        if let Some(failed) = composer.hook_outputs.iter().find(|output| !output.success) {
+                            let result = Err(format!("the {} hook failed", failed.name));
+                            let _ = self.notifications.send(Notification::from_result(&result, "Commit blocked"));
+                            composer.result = Some(result);
+                            return Ok(());
+                        }
+                        message = clean_message(&hook_message);
+                    }
+
+                    let before = take_snapshot(&self.repository)?;
+                    let result = create_commit(&self.repository, &message, composer.amend, composer.sign)
+                        .map(|id| id.to_string())
+                        .map_err(|e| e.message().to_string());
+                    let notification = result.clone().map(|id| format!("Created commit {}", self.state.borrow().display.abbreviate(&id)));
+                    let _ = self.notifications.send(Notification::from_result(&notification, "Commit failed"));
+                    composer.result = Some(result);
+                    if matches!(composer.result, Some(Ok(_))) {
+                        self.journal.record_keeping_changes(&self.repository, String::from(if composer.amend { "amending the commit" } else { "commit" }), before)?;
+                        composer.reset();
+                    }
+                }
+                self.update(Message::RefreshTree)?;
+            },
+            Message::CreateFixupCommit(target, squash) => {
+                let message = fixup_message(&self.repository, &target, squash)?;
+                if !stage_tracked_changes(&self.repository)? {
+                    return Err(Error::from("there are no changes to commit"));
+                }
+                let (outputs, message) = run_commit_hooks(&self.repository, &message)?;
+                if let Some(failed) = outputs.iter().find(|output| !output.success) {
+                    return Err(Error::from(format!("the {} hook failed: {}", failed.name, failed.output)));
+                }
+
+                let before = take_snapshot(&self.repository)?;
+                let id = create_commit(&self.repository, &clean_message(&message), false, is_signing_enabled(&self.repository))?;
+                self.journal.record_keeping_changes(&self.repository, String::from(if squash { "squash commit" } else { "fixup commit" }), before)?;
+                let _ = self.notifications.send(Notification::Info(format!("Created {} for {}", self.abbreviate(&id.to_string()), self.abbreviate(&target))));
+                self.refresh_status_panel()?;
+                self.update(Message::RefreshTree)?;
+            },
+            Message::Autosquash => {
+                let plan = plan_autosquash(&self.repository)?;
+                let before = take_snapshot(&self.repository)?;
+                apply_autosquash_plan(&self.repository, &plan)?;
+                self.journal.record(&self.repository, format!("autosquash of {}", plan.branch), before)?;
+                let _ = self.notifications.send(Notification::Info(format!("Folded {} fixup commits into {}", plan.folded(), plan.branch)));
+                self.update(Message::RefreshTree)?;
+            },
+            Message::CommitSkipHooksToggled(skip) => {
+                if let Some(composer) = &mut self.commit_composer {
+                    composer.skip_hooks = skip;
+                }
+            },
+            Message::OpenWorktreePanel => {
+                let worktrees = get_worktrees(&self.repository)?;
+                self.worktree_panel = Some(WorktreePanel { worktrees, new_name: String::new(), new_path: String::new(), result: None });
+            },
+            Message::CloseWorktreePanel => {
+                self.worktree_panel = None;
+            },
+            Message::WorktreeNameChanged(name) => {
+                if let Some(panel) = &mut self.worktree_panel {
+                    panel.new_name = name;
+                }
+            },
+            Message::WorktreePathChanged(path) => {
+                if let Some(panel) = &mut self.worktree_panel {
+                    panel.new_path = path;
+                }
+            },
+            Message::CreateWorktree => {
+                let state = self.state.borrow();
+                if let (Some(panel), Some(selected)) = (&mut self.worktree_panel, &state.selection.commit) {
+                    // Check out the selected branch itself when the commit is a local branch tip
+                    let target = state.commits.get(selected)
+                        .and_then(|c| c.branches().next().cloned())
+                        .unwrap_or(selected.clone());
+                    let result = create_worktree(&self.repository, &panel.new_name, PathBuf::from(&panel.new_path).as_path(), &target);
+                    let result = result.map(|_| format!("Created worktree {}", panel.new_name)).map_err(|e| e.message().to_string());
+                    let _ = self.notifications.send(Notification::from_result(&result, "Worktree creation failed"));
+                    panel.result = Some(result);
+                    panel.worktrees = get_worktrees(&self.repository)?;
+                }
+            },
+            Message::OpenWorktree(path) => {
+                // Each instance opens the repository in its working directory
+                let result = std::env::current_exe().and_then(|exe| Command::new(exe).current_dir(&path).spawn());
+                if let Some(panel) = &mut self.worktree_panel {
+                    panel.result = Some(result.map(|_| format!("Opened {}", path.display())).map_err(|e| e.to_string()));
+                }
+            },
+            Message::OpenExportDialog => {
+                self.export_dialog = Some(ExportDialog { path: String::new(), visible_only: false, result: None });
+            },
+            Message::CloseExportDialog => {
+                self.export_dialog = None;
+            },
+            Message::ExportPathChanged(path) => {
+                if let Some(dialog) = &mut self.export_dialog {
+                    dialog.path = path;
+                }
+            },
+            Message::ExportVisibleToggled(visible_only) => {
+                if let Some(dialog) = &mut self.export_dialog {
+                    dialog.visible_only = visible_only;
+                }
+            },
+            Message::ExportGraph => {
+                if let Some(dialog) = &mut self.export_dialog {
+                    let state = self.state.borrow();
+                    let area = if dialog.visible_only { Some(state.viewport.visible_area()) } else { None };
+                    let result = export_graph(Path::new(&dialog.path), &state.commits, &state.node_locations, &state.display, state.orientation, state.size.node_radius, area);
+                    let result = result.map(|_| format!("Exported graph to {}", dialog.path)).map_err(|e| e.message());
+                    let _ = self.notifications.send(Notification::from_result(&result, "Export failed"));
+                    dialog.result = Some(result);
+                }
+            },
+            Message::OpenArchiveDialog(commit) => {
+                let name = format!("{}-{}", self.name(), self.state.borrow().display.abbreviate(&commit));
+                self.archive_dialog = Some(ArchiveDialog { commit, path: format!("{}.tar.gz", name), prefix: format!("{}/", name), result: None });
+            },
+            Message::CloseArchiveDialog => {
+                self.archive_dialog = None;
+            },
+            Message::ArchivePathChanged(path) => {
+                if let Some(dialog) = &mut self.archive_dialog {
+                    dialog.path = path;
+                }
+            },
+            Message::ArchivePrefixChanged(prefix) => {
+                if let Some(dialog) = &mut self.archive_dialog {
+                    dialog.prefix = prefix;
+                }
+            },
+            Message::ArchiveCommit => {
+                if let Some(dialog) = &mut self.archive_dialog {
+                    let result = archive_commit(&self.repository, &dialog.commit, Path::new(&dialog.path), &dialog.prefix);
+                    let result = result.map(|count| format!("Wrote {} files to {}", count, dialog.path)).map_err(|e| e.message());
+                    let _ = self.notifications.send(Notification::from_result(&result, "Archive failed"));
+                    dialog.result = Some(result);
+                }
+            },
+            Message::DropReference(branch, target) => {
+                if self.repository.find_branch(&branch, BranchType::Local).is_err() {
+                    return Err(Error::from(format!("{} is not a local branch, only those can be rebased or merged", branch)));
+                }
+                let target_branch = self.state.borrow().commits.get(&target)
+                    .and_then(|c| c.branches().find(|other| **other != branch).cloned());
+                self.drop_menu = Some(DropMenu { branch, target, target_branch });
+            },
+            Message::CloseDropMenu => {
+                self.drop_menu = None;
+            },
+            Message::RebaseDroppedBranch => {
+                if let Some(menu) = self.drop_menu.take() {
+                    let before = take_snapshot(&self.repository)?;
+                    rebase_branch(&self.repository, &menu.branch, &menu.target)?;
+                    let onto = menu.target_branch.unwrap_or(self.state.borrow().display.abbreviate(&menu.target).to_string());
+                    self.journal.record(&self.repository, format!("rebase of {} onto {}", menu.branch, onto), before)?;
+                    let _ = self.notifications.send(Notification::Info(format!("Rebased {} onto {}", menu.branch, onto)));
+                    self.update(Message::RefreshTree)?;
+                }
+            },
+            Message::MergeDroppedBranch => {
+                if let Some(DropMenu { branch, target_branch: Some(target_branch), .. }) = self.drop_menu.take() {
+                    let before = take_snapshot(&self.repository)?;
+                    merge_branch(&self.repository, &branch, &target_branch)?;
+                    self.journal.record(&self.repository, format!("merge of {} into {}", branch, target_branch), before)?;
+                    let _ = self.notifications.send(Notification::Info(format!("Merged {} into {}", branch, target_branch)));
+                    self.update(Message::RefreshTree)?;
+                }
+            },
+            Message::DropCommit(commit, target) => {
+                let plan = plan_commit_move(&self.repository, &commit, &target)?;
+                self.reorder = Some(ReorderConfirmation { commit, target, plan });
+            },
+            Message::ApplyReorder => {
+                if let Some(confirmation) = self.reorder.take() {
+                    let before = take_snapshot(&self.repository)?;
+                    apply_reorder_plan(&self.repository, &confirmation.plan)?;
+                    self.journal.record(&self.repository, format!("reordering {}", confirmation.plan.branch), before)?;
+                    let _ = self.notifications.send(Notification::Info(format!("Reordered {}", confirmation.plan.branch)));
+                    self.update(Message::RefreshTree)?;
+                }
+            },
+            Message::CancelReorder => {
+                self.reorder = None;
+            },
+            Message::ToggleStatusPanel => {
+                self.status_panel = match self.status_panel {
+                    Some(_) => None,
+                    None => Some(StatusPanel::open(&self.repository)?),
+                };
+            },
+            Message::ToggleStatisticsPanel => {
+                if self.statistics.is_some() || self.statistics_job.is_some() {
+                    self.statistics = None;
+                    self.statistics_job = None;
+                } else {
+                    // Walks the whole history and diffs hundreds of commits, so it gets a thread of its own rather
+                    // than holding up the details and diffs of selected commits queued on the worker
+                    let path = self.repository.path().to_path_buf();
+                    self.statistics_job = Some(JobHandle::spawn("statistics", move |cancel| {
+                        Ok(compute_stats(&Repository::open(path)?, &|| cancel.is_cancelled())?)
+                    })?);
+                }
+            },
+            Message::StatisticsProgressed => {
+                let Some(result) = self.statistics_job.as_ref().and_then(|job| job.take_result()) else {
+                    return Ok(());
+                };
+                self.statistics_job = None;
+                self.statistics = Some(result?);
+            },
+            Message::ToggleHealthPanel => {
+                self.health = match self.health {
+                    Some(_) => None,
+                    None => Some(check_health(&self.repository)?),
+                };
+            },
+            Message::ContinueOperation | Message::AbortOperation => {
+                let Some(operation) = self.interrupted.as_ref().map(|interrupted| interrupted.operation) else {
+                    return Ok(());
+                };
+                let (result, done) = match message {
+                    Message::ContinueOperation => (continue_operation(&self.repository, operation), "Finished"),
+                    _ => (abort_operation(&self.repository, operation), "Aborted"),
+                };
+                match result {
+                    Ok(()) => {
+                        let _ = self.notifications.send(Notification::Info(format!("{} the {}", done, operation)));
+                        self.interrupted = None;
+                        self.update(Message::RefreshTree)?;
+                    },
+                    // Still left halfway, maybe with other paths unmerged now
+                    Err(e) => self.interrupted = Some(InterruptedOperation { operation, unmerged: unmerged_paths(&self.repository)?, error: Some(e.message()) }),
+                }
+            },
+            Message::DismissInterruptedOperation => {
+                self.interrupted = None;
+            },
+            Message::ToggleMaintenancePanel => {
+                self.maintenance = match self.maintenance {
+                    // Closing leaves a running task to finish on its own
+                    Some(_) => None,
+                    None => Some(MaintenancePanel { size: repository_size(&self.repository), job: None, results: Vec::new() }),
+                };
+            },
+            Message::RunMaintenance(task) => {
+                if let Some(panel) = self.maintenance.as_mut().filter(|panel| panel.job.is_none()) {
+                    panel.job = Some(MaintenanceJob::start(self.repository.path().to_path_buf(), task)?);
+                }
+            },
+            Message::MaintenanceProgressed => {
+                let Some(panel) = &mut self.maintenance else {
+                    return Ok(());
+                };
+                let Some(result) = panel.job.as_ref().and_then(|job| job.take_result()) else {
+                    return Ok(());
+                };
+                let task = panel.job.take().map(|job| job.task);
+                match result {
+                    Ok(report) => {
+                        panel.size = report.after;
+                        panel.results.push(Ok(format!("{}: {} before, {} after", report.task, format_size(report.before), format_size(report.after))));
+                        // Pruning takes away remote branches
+                        if task == Some(MaintenanceTask::PruneRemoteBranches) {
+                            self.update(Message::RefreshTree)?;
+                        }
+                    },
+                    Err(e) => panel.results.push(Err(e.message())),
+                }
+            },
+            Message::CheckHealth => {
+                self.health = Some(check_health(&self.repository)?);
+            },
+            Message::ApplyHealthFix(index) => {
+                let fix = self.health.as_ref().and_then(|issues| issues.get(index)).and_then(|issue| issue.fix.clone());
+                if let Some(fix) = fix {
+                    apply_fix(&self.repository, &fix)?;
+                    let _ = self.notifications.send(Notification::Info(format!("{} done", fix.label())));
+                    self.health = Some(check_health(&self.repository)?);
+                }
+            },
+            Message::ToggleReleaseTimeline => {
+                self.release_timeline = match self.release_timeline {
+                    Some(_) => None,
+                    None => Some(ReleaseTimeline { releases: get_releases(&self.repository)?, path: String::new(), result: None }),
+                };
+            },
+            Message::ChangelogPathChanged(path) => {
+                if let Some(timeline) = &mut self.release_timeline {
+                    timeline.path = path;
+                }
+            },
+            Message::ExportChangelog => {
+                if let Some(timeline) = self.release_timeline.as_mut().filter(|timeline| !timeline.path.is_empty()) {
+                    let result = export_changelog(Path::new(&timeline.path), &timeline.releases, &self.state.borrow().display);
+                    let result = result.map(|_| format!("Exported the changelog to {}", timeline.path)).map_err(|e| e.message());
+                    let _ = self.notifications.send(Notification::from_result(&result, "Export failed"));
+                    timeline.result = Some(result);
+                }
+            },
+            Message::SelectStatusFile(path, staged) => {
+                if let Some(panel) = &mut self.status_panel {
+                    panel.select_file(&self.repository, &path, staged)?;
+                }
+            },
+            Message::StageFile(path) => {
+                stage_file(&self.repository, &path)?;
+                self.refresh_status_panel()?;
+            },
+            Message::UnstageFile(path) => {
+                unstage_file(&self.repository, &path)?;
+                self.refresh_status_panel()?;
+            },
+            Message::ApplyHunk(hunk) => {
+                if let Some(StatusPanel { diff: Some(diff), selected_lines, .. }) = &mut self.status_panel {
+                    let selection: Vec<Vec<bool>> = selected_lines.iter().enumerate()
+                        .map(|(i, lines)| vec![i == hunk; lines.len()])
+                        .collect();
+                    apply_selection(&self.repository, diff, &selection)?;
+                    self.refresh_status_panel()?;
+                }
+            },
+            Message::DiffLineToggled(hunk, line, selected) => {
+                if let Some(selection) = self.status_panel.as_mut().and_then(|panel| panel.selected_lines.get_mut(hunk)).and_then(|lines| lines.get_mut(line)) {
+                    *selection = selected;
+                }
+            },
+            Message::DiscardFile(path) => {
+                let untracked = self.status_panel.as_ref()
+                    .and_then(|panel| panel.entries.iter().find(|entry| entry.path == path))
+                    .is_some_and(|entry| entry.unstaged == Some('?'));
+                self.discard = Some(DiscardConfirmation { path, untracked });
+            },
+            Message::ConfirmDiscard => {
+                if let Some(confirmation) = self.discard.take() {
+                    discard_file(&self.repository, &confirmation.path)?;
+                    let action = if confirmation.untracked { "Deleted" } else { "Discarded changes to" };
+                    let _ = self.notifications.send(Notification::Info(format!("{} {}", action, confirmation.path)));
+                    self.refresh_status_panel()?;
+                }
+            },
+            Message::CancelDiscard => {
+                self.discard = None;
+            },
+            Message::RestoreFile(commit, path) => {
+                self.restore = Some(RestoreConfirmation { commit, path, index: false });
+            },
+            Message::RestoreToIndexToggled(index) => {
+                if let Some(confirmation) = &mut self.restore {
+                    confirmation.index = index;
+                }
+            },
+            Message::ConfirmRestore => {
+                if let Some(confirmation) = self.restore.take() {
+                    restore_file(&self.repository, &confirmation.commit, &confirmation.path, confirmation.index)?;
+                    let _ = self.notifications.send(Notification::Info(format!("Restored {} from {}", confirmation.path, self.abbreviate(&confirmation.commit))));
+                    self.refresh_status_panel()?;
+                }
+            },
+            Message::CancelRestore => {
+                self.restore = None;
+            },
+            Message::OpenDifftool => {
+                if let Some(diff) = self.status_panel.as_ref().and_then(|panel| panel.diff.as_ref()) {
+                    let run = prepare_difftool(&self.repository, &diff.path, diff.staged)?;
+                    let _ = self.notifications.send(Notification::Info(format!("Opened {} on {}", run.name(), diff.path)));
+                    self.external_tools.push(ExternalTool::start(run)?);
+                }
+            },
+            Message::OpenMergetool(path) => {
+                let run = prepare_mergetool(&self.repository, &path)?;
+                let _ = self.notifications.send(Notification::Info(format!("Opened {} on {}", run.name(), path)));
+                self.external_tools.push(ExternalTool::start(run)?);
+            },
+            Message::ExternalToolProgressed => {
+                let mut results = Vec::new();
+                self.external_tools.retain(|tool| match tool.take_result() {
+                    Some(result) => {
+                        results.push(result);
+                        false
+                    },
+                    None => true,
+                });
+                if results.is_empty() {
+                    return Ok(());
+                }
+
+                // Both kinds of tool can change the working tree, and a merge stages the file
+                self.refresh_status_panel()?;
+                for result in results {
+                    let _ = self.notifications.send(Notification::Info(result?));
+                }
+            },
+            Message::OpenIgnoreMenu(path) => {
+                let patterns = suggest_patterns(&path);
+                self.ignore_menu = Some(IgnoreMenu { path, patterns });
+            },
+            Message::CloseIgnoreMenu => {
+                self.ignore_menu = None;
+            },
+            Message::IgnorePattern(pattern) => {
+                self.ignore_menu = None;
+                append_to_gitignore(&self.repository, &pattern)?;
+                let _ = self.notifications.send(Notification::Info(format!("Added {} to .gitignore", pattern)));
+                if let Some(editor) = &mut self.gitignore_editor {
+                    editor.lines = read_gitignore(&self.repository)?.lines().map(String::from).collect();
+                }
+                self.update_ignore_preview()?;
+                self.refresh_status_panel()?;
+            },
+            Message::OpenGitignoreEditor => {
+                let lines = read_gitignore(&self.repository)?.lines().map(String::from).collect();
+                self.gitignore_editor = Some(GitignoreEditor { lines, preview: Vec::new(), result: None });
+                self.update_ignore_preview()?;
+            },
+            Message::CloseGitignoreEditor => {
+                self.gitignore_editor = None;
+            },
+            Message::GitignoreLineChanged(index, line) => {
+                if let Some(editor) = &mut self.gitignore_editor {
+                    if let Some(current) = editor.lines.get_mut(index) {
+                        *current = line;
+                    }
+                }
+                self.update_ignore_preview()?;
+            },
+            Message::AddGitignoreLine => {
+                if let Some(editor) = &mut self.gitignore_editor {
+                    editor.lines.push(String::new());
+                }
+            },
+            Message::RemoveGitignoreLine(index) => {
+                if let Some(editor) = &mut self.gitignore_editor {
+                    if index < editor.lines.len() {
+                        editor.lines.remove(index);
+                    }
+                }
+                self.update_ignore_preview()?;
+            },
+            Message::SaveGitignore => {
+                if let Some(editor) = &mut self.gitignore_editor {
+                    let mut contents = editor.lines.join("\n");
+                    contents.push('\n');
+                    let result = write_gitignore(&self.repository, &contents)
+                        .map(|_| String::from("Saved .gitignore"))
+                        .map_err(|e| e.message());
+                    let _ = self.notifications.send(Notification::from_result(&result, "Saving .gitignore failed"));
+                    editor.result = Some(result);
+                }
+                self.update_ignore_preview()?;
+                self.refresh_status_panel()?;
+            },
+            Message::ApplySelectedLines => {
+                if let Some(StatusPanel { diff: Some(diff), selected_lines, .. }) = &mut self.status_panel {
+                    apply_selection(&self.repository, diff, selected_lines)?;
+                    self.refresh_status_panel()?;
+                }
+            },
+            Message::SelectTab(_) | Message::CloseTab(_) | Message::OpenPathChanged(_) | Message::OpenRepository |
+            Message::DismissError(_) | Message::ToggleErrorDetails(_) | Message::LoadTick | Message::AutoFetchTick | Message::AutoFetchSelected(_) |
+            Message::OpenSettings | Message::CloseSettings | Message::ToggleNotificationDrawer | Message::ToggleDebugConsole | Message::ClearDebugConsole | Message::ClearNotifications | Message::UiScaleSelected(_) | Message::HighContrastToggled(_) | Message::RepositoryStateToggled(_) | Message::PerformanceOverlayToggled(_) | Message::ControlServerToggled(_) | Message::ControlTick | Message::GraphSizeChanged(_) | Message::GraphSizeSettled |
+            Message::KeyPressed(_) | Message::SaveBookmark | Message::RemoveBookmark(_) | Message::CloseRequested(_) | Message::WindowFocused(_) | Message::Window(..) | Message::OpenRepositoryWindow | Message::OpenDotWindow | Message::OpenCommitWindow(_) | Message::CommitDoubleClicked(_) | Message::CommitMiddleClicked(_) | Message::CopyCommitId(_) |
+            Message::DoubleClickSelected(_) | Message::MiddleClickSelected(_) | Message::RecordKeyBinding(_) | Message::ClearKeyBinding(_) |
+            Message::OpenInitDialog | Message::CloseInitDialog | Message::InitPathChanged(_) | Message::InitBranchChanged(_) | Message::InitialCommitToggled(_) | Message::InitRepository |
+            Message::OpenCloneDialog | Message::CloseCloneDialog | Message::CloneUrlChanged(_) | Message::CloneDestinationChanged(_) | Message::CloneDepthChanged(_) |
+            Message::CloneSingleBranchToggled(_) | Message::CloneBranchChanged(_) | Message::StartClone | Message::CancelClone => (),
+        }
+
+        Ok(())
+    }
+
+    // From the whole history rather than what the filters leave shown
+    fn commit_children(&self, id: &str) -> Vec<String> {
+        self.all_commits.get(id).map(|commit| commit.children.clone()).unwrap_or_default()
+    }
+
+    // Name, branch, and how much history and how many references there are, for the top of the tab
+    fn summary(&self) -> String {
+        let count = |kind| self.references.iter().filter(|(tip, _)| tip.kind == kind).count();
+        let fetched = match self.last_fetch.and_then(to_date) {
+            Some(date) => format!("fetched {}", format_relative(date)),
+            None => String::from("never fetched"),
+        };
+        format!("{} on {} - {} commits, {} branches, {} tags - {}", self.name(), self.head, self.all_commits.len(), count(ReferenceKind::Branch), count(ReferenceKind::Tag), fetched)
+    }
+
+    // What bookmarks are kept for, the same for every worktree path the repository is opened from
+    pub fn git_directory(&self) -> &Path {
+        self.repository.path()
+    }
+
+    pub fn set_bookmarks(&mut self, bookmarks: Vec<Bookmark>) {
+        self.state.borrow_mut().bookmarks = bookmarks;
+    }
+
+    // The bookmark the editor was saved with, which closes it
+    pub fn take_bookmark(&mut self) -> Option<Bookmark> {
+        let editor = self.bookmark_editor.take()?;
+        // Tabs and line breaks would split the line the settings file keeps it on
+        let label: String = editor.label.trim().chars().map(|c| if c.is_control() { ' ' } else { c }).collect();
+        let label = if label.is_empty() { self.abbreviate(&editor.commit) } else { label };
+        Some(Bookmark { repository: self.git_directory().to_path_buf(), commit: editor.commit, label, color: editor.color })
+    }
+
+    pub fn close_bookmark_editor(&mut self) {
+        self.bookmark_editor = None;
+    }
+
+    pub fn is_quick_switching(&self) -> bool {
+        self.quick_switcher.is_some()
+    }
+
+    pub fn abbreviate(&self, id: &str) -> String {
+        self.state.borrow().display.abbreviate(id).to_string()
+    }
+
+    pub fn set_details_window(&mut self, open: bool) {
+        self.details_window = open;
+    }
+
+    // All a commit window shows, taking up the whole of it
+    pub fn view_commit_window(&self) -> Element<'_, Message> {
+        let details = match &self.commit_details {
+            Some(details) => view_commit_details(details, &self.commit_children(&details.id), &self.state.borrow().display, true, self.diff_settings, &self.file_list, self.image_comparison),
+            None => Column::new().height(Length::Fill).into(),
+        };
+        // Restoring, bookmarking and notes started from the details are asked about here as well as next to the graph
+        let mut children = Vec::new();
+        if let Some(confirmation) = &self.restore {
+            children.push(view_restore_confirmation(confirmation, &self.state.borrow().display));
+        }
+        if let Some(editor) = &self.bookmark_editor {
+            children.push(view_bookmark_editor(editor, &self.state.borrow().display));
+        }
+        if let Some(editor) = &self.note_editor {
+            children.push(view_note_editor(editor, &self.state.borrow().display));
+        }
+        children.push(details);
+        Column::with_children(children).into()
+    }
+
+    pub fn view(&self, high_contrast: bool, scale: f32, overlay: bool) -> Element<'_, Message> {
+        if let Some(interrupted) = &self.interrupted {
+            return view_interrupted_operation(interrupted);
+        }
+        let colors = if high_contrast { &GraphColors::HIGH_CONTRAST } else { &GraphColors::NORMAL };
+        Column::with_children({
+            let mut children: Vec<Element<Message>> = Vec::new();
+
+            children.push(
+                Row::with_children({
+                    vec![
+                        text(self.summary()).size(16).into(),
+                        button("Tag branches").on_press(Message::OpenTagDialog).into(),
+                        button("Compare").on_press(Message::OpenCompareDialog).into(),
+                        button("Commit").on_press(Message::OpenCommitComposer).into(),
+                        button("Worktrees").on_press(Message::OpenWorktreePanel).into(),
+                        button("Export graph").on_press(Message::OpenExportDialog).into(),
+                        button("Branches").on_press(Message::ToggleReferenceSidebar).into(),
+                        button("Bookmarks").on_press(Message::ToggleBookmarkList).into(),
+                        button("Upstreams").on_press(Message::OpenTrackingDialog).into(),
+                        button("Remote refs").on_press(Message::OpenRemoteBrowser).into(),
+                        button("Refspecs").on_press(Message::OpenRefspecEditor).into(),
+                        if self.fetching.is_some() { button("Fetching...").into() } else { button("Fetch").on_press(Message::Fetch).into() },
+                        button("Push").on_press(Message::Push).into(),
+                        button("Filter").on_press(Message::ToggleFilterPanel).into(),
+                        button("Status").on_press(Message::ToggleStatusPanel).into(),
+                        button("Statistics").on_press(Message::ToggleStatisticsPanel).into(),
+                        button("Health").on_press(Message::ToggleHealthPanel).into(),
+                        button("Maintenance").on_press(Message::ToggleMaintenancePanel).into(),
+                        button("Releases").on_press(Message::ToggleReleaseTimeline).into(),
+                        button("Terminal").on_press(Message::OpenTerminal).into(),
+                        match self.journal.last() {
+                            Some(entry) => button(text(format!("Undo {}", entry.description))).on_press(Message::Undo).into(),
+                            None => button("Undo").into(),
+                        },
+                    ]
+                })
+                .align_items(Alignment::Center)
+                .spacing(10)
+                .into());
+
+            children.push(Row::with_children({
+                let mut children = Vec::new();
+
+                children.push(Row::with_children({
+                    let state = self.state.borrow();
+                    if let Some(selected) = &state.selection.commit {
+                        let mut children: Vec<Element<Message>> = Vec::new();
+                        if let Some(ReferenceLabel { name: reference, kind }) = &state.selection.reference {
+                            let kind_name = match kind {
+                                ReferenceKind::Branch => "Branch",
+                                ReferenceKind::Remote => "Remote branch",
+                                ReferenceKind::Tag => "Tag",
+                            };
+                            children.push(text(format!("{}: {}", kind_name, reference)).size(20).into());
+                            children.push(button("Checkout").on_press(Message::CheckoutReference(reference.clone())).into());
+                            children.push(button("Compare").on_press(Message::CompareReference(reference.clone())).into());
+                            // Remote branches go away by pushing the deletion, which is left to the command line
+                            if *kind != ReferenceKind::Remote {
+                                children.push(button("Delete").on_press(Message::DeleteReference(reference.clone())).into());
+                            }
+                        }
+                        children.push(text(format!("ID: {}", &selected)).size(20).into());
+                        children.push(button("Checkout").on_press(Message::SwitchToCommit(selected.clone())).into());
+                        children.push(button("Archive").on_press(Message::OpenArchiveDialog(selected.clone())).into());
+                        children.push(button("Details").on_press(Message::OpenCommitDetails(selected.clone())).into());
+                        // The neighbours on the graph as it is shown, which are the ones that can be jumped to
+                        if let Some(commit) = state.commits.get(selected) {
+                            if !commit.parents.is_empty() {
+                                children.push(view_commit_links("Parents:", &commit.parents, &state.display));
+                            }
+                            if !commit.children.is_empty() {
+                                children.push(view_commit_links("Children:", &commit.children, &state.display));
+                            }
+                        }
+                        if let Some(forge) = &self.forge {
+                            children.push(button(text(format!("Open on {}", forge.name()))).on_press(Message::OpenUrl(forge.commit_url(selected))).into());
+                        }
+                        if let Some(commit) = state.commits.get(selected) {
+                            children.push(text(format!("{} <{}>", commit.author, commit.email)).size(20).into());
+                            children.push(text(state.display.format_time(commit.time)).size(20).into());
+                        }
+                        let signature = state.commits.get(selected).and_then(|c| c.signature.as_ref());
+                        match signature {
+                            Some(SignatureStatus::Verified(signer)) => children.push(text(format!("Signature verified: {}", signer)).size(20).style(VERIFIED_COLOR).into()),
+                            Some(SignatureStatus::Untrusted(signer)) => children.push(text(format!("Good signature from {}", signer)).size(20).style(UNTRUSTED_COLOR).into()),
+                            Some(SignatureStatus::Unverified(reason)) => children.push(text(format!("Signature not verified: {}", reason)).size(20).style(UNVERIFIED_COLOR).into()),
+                            Some(SignatureStatus::Unchecked) | None => (),
+                        }
+                        children
+                    } else {
+                        Vec::new()
+                    }
+                })
+                .height(30)
+                .align_items(Alignment::Start)
+                .spacing(10)
+                .into());
+
+                children
+            }).into());
+
+            let message = self.state.borrow().selection.commit.as_ref()
+                .and_then(|selected| Oid::from_str(selected).ok())
+                .and_then(|id| self.repository.find_commit(id).ok())
+                .and_then(|commit| commit.message().map(String::from));
+            // Always present, like the dialogs below, so selecting a commit doesn't reset the canvas
+            children.push(Column::with_children(message.map(|message| view_commit_message(&message, self.forge.as_ref()))).into());
+
+            // Dialogs get their own container and side panels come after the canvas, so the canvas keeps its widget state when they open
+            children.push(Column::with_children({
+                let mut children = Vec::new();
+                if let Some(detached) = &self.detached {
+                    children.push(view_detached_banner(detached, &self.state.borrow().display));
+                }
+                if let Some(loading) = &self.loading {
+                    children.push(view_tree_load(loading));
+                }
+                if let Some(fetching) = &self.fetching {
+                    children.push(view_fetch_progress(fetching));
+                }
+                if let Some(push) = &self.pushing {
+                    children.push(view_push_progress(push));
+                }
+                if let Some(menu) = &self.drop_menu {
+                    children.push(view_drop_menu(menu, &self.state.borrow().display));
+                }
+                if let Some(confirmation) = &self.reorder {
+                    children.push(view_reorder_confirmation(confirmation, &self.state.borrow().display));
+                }
+                if let Some(confirmation) = &self.discard {
+                    children.push(view_discard_confirmation(confirmation));
+                }
+                if let Some(confirmation) = &self.restore {
+                    children.push(view_restore_confirmation(confirmation, &self.state.borrow().display));
+                }
+                if let Some(menu) = &self.ignore_menu {
+                    children.push(view_ignore_menu(menu));
+                }
+                if let Some(editor) = &self.gitignore_editor {
+                    children.push(view_gitignore_editor(editor));
+                }
+                if let Some(editor) = &self.bookmark_editor {
+                    children.push(view_bookmark_editor(editor, &self.state.borrow().display));
+                }
+                if let Some(editor) = &self.note_editor {
+                    children.push(view_note_editor(editor, &self.state.borrow().display));
+                }
+                if let Some(switcher) = &self.quick_switcher {
+                    children.push(view_quick_switcher(switcher, &self.state.borrow().display));
+                }
+                if self.filter_panel {
+                    children.push(view_history_filter(&self.history_filter));
+                }
+                if let Some(dialog) = &self.push_dialog {
+                    children.push(view_push_dialog(dialog));
+                }
+                if let Some(dialog) = &self.tracking_dialog {
+                    children.push(view_tracking_dialog(dialog));
+                }
+                if let Some(browser) = &self.remote_browser {
+                    children.push(view_remote_browser(browser));
+                }
+                if let Some(editor) = &self.refspec_editor {
+                    children.push(view_refspec_editor(editor));
+                }
+                if let Some(dialog) = &self.tag_dialog {
+                    children.push(view_tag_dialog(dialog));
+                }
+                if let Some(dialog) = &self.compare_dialog {
+                    children.push(view_compare_dialog(dialog));
+                }
+                if let Some(composer) = &self.commit_composer {
+                    children.push(view_commit_composer(composer, &self.state.borrow().display));
+                }
+                if let Some(dialog) = &self.export_dialog {
+                    children.push(view_export_dialog(dialog));
+                }
+                if let Some(dialog) = &self.archive_dialog {
+                    children.push(view_archive_dialog(dialog, &self.state.borrow().display));
+                }
+                if let Some(panel) = &self.worktree_panel {
+                    children.push(view_worktree_panel(panel, self.state.borrow().selection.commit.is_some()));
+                }
+                children
+            }).into());
+
+            // How the graph is drawn, right above it
+            children.push(Row::with_children(vec![
+                pick_list(&LayoutMode::ALL[..], Some(self.state.borrow().layout), Message::LayoutSelected).text_size(14).into(),
+                pick_list(&Orientation::ALL[..], Some(self.state.borrow().orientation), Message::OrientationSelected).text_size(14).into(),
+                checkbox("First parent only", self.first_parent).on_toggle(Message::FirstParentToggled).text_size(14).into(),
+                checkbox("Collapse chains", self.collapse_chains).on_toggle(Message::CollapseChainsToggled).text_size(14).into(),
+                checkbox("High contrast", high_contrast).on_toggle(Message::HighContrastToggled).text_size(14).into(),
+                button(if self.legend { "Hide legend" } else { "Legend" }).on_press(Message::ToggleLegend).into(),
+                if self.selection_history.can_go_back() { button("Back").on_press(Message::SelectionBack).into() } else { button("Back").into() },
+                if self.selection_history.can_go_forward() { button("Forward").on_press(Message::SelectionForward).into() } else { button("Forward").into() },
+            ])
+            .align_items(Alignment::Center)
+            .spacing(10)
+            .padding([0, 5])
+            .width(Length::Fill)
+            .into());
+
+            children.push(Row::with_children({
+                let mut children: Vec<Element<Message>> = Vec::new();
+
+                children.push(Canvas::new(TreeRenderer { state: Rc::clone(&self.state), colors, scale, overlay })
+                    .width(Length::Fill)
+                    .height(Length::Fill)
+                    .into());
+
+                if self.legend {
+                    children.push(view_graph_legend(colors));
+                }
+
+                if let Some(range) = &self.range_diff {
+                    children.push(view_range_diff(range, &self.state.borrow().display, self.diff_settings, &self.file_list));
+                } else if self.range_job.is_some() {
+                    children.push(text("Diffing the range...").size(16).into());
+                }
+
+                if let Some(details) = self.commit_details.as_ref().filter(|_| !self.details_window) {
+                    children.push(view_commit_details(details, &self.commit_children(&details.id), &self.state.borrow().display, false, self.diff_settings, &self.file_list, self.image_comparison));
+                }
+
+                if let Some(comparison) = self.compare_dialog.as_ref().and_then(|d| d.comparison.as_ref()) {
+                    children.push(view_comparison(comparison, &self.state.borrow().display, &self.file_list));
+                }
+
+                if self.reference_sidebar {
+                    children.push(view_reference_sidebar(&self.references, self.reference_groups, &self.expanded_folders, self.renaming.as_ref()));
+                }
+
+                if let Some(panel) = &self.status_panel {
+                    children.push(view_status_panel(panel, self.image_comparison));
+                }
+
+                if let Some(stats) = &self.statistics {
+                    children.push(view_statistics_panel(stats));
+                } else if self.statistics_job.is_some() {
+                    children.push(text("Counting commits...").size(16).into());
+                }
+
+                if let Some(issues) = &self.health {
+                    children.push(view_health_panel(issues));
+                }
+
+                if self.bookmark_list {
+                    children.push(view_bookmark_list(&self.state.borrow().bookmarks, &self.state.borrow().display));
+                }
+
+                if let Some(panel) = &self.maintenance {
+                    children.push(view_maintenance_panel(panel));
+                }
+
+                if let Some(timeline) = &self.release_timeline {
+                    children.push(view_release_timeline(timeline, &self.state.borrow().display));
+                }
+
+                children
+            })
+            .width(Length::Fill)
+            .height(Length::Fill)
+            .into());
+
+            children
+        })
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .align_items(Alignment::Center)
+        .into()
+    }
+}
@@ -0,0 +1,931 @@
+use std::collections::{BTreeMap, VecDeque};
+use std::time::{Duration, Instant};
+use std::{cell::{Cell, RefCell}, rc::Rc};
+
+use iced::advanced::mouse::Cursor;
+use iced::alignment::{Horizontal, Vertical};
+use iced::event::Status;
+use iced::keyboard::key::Named;
+use iced::mouse::{Button, Interaction, ScrollDelta};
+use iced::widget::canvas::{Program, Geometry, Frame, Path, Style, Text, Stroke, Event};
+use iced::{Rectangle, Theme, Color, keyboard, mouse, Renderer, Pixels, Point, Size, Vector};
+
+use crate::backend::ReferenceKind;
+use crate::config::Orientation;
+use crate::signing::SignatureStatus;
+
+use super::tab::SharedState;
+use super::{Message, VERIFIED_COLOR, UNTRUSTED_COLOR, UNVERIFIED_COLOR, bookmark_color};
+
+pub struct TreeRenderer {
+    pub state: Rc<RefCell<SharedState>>,
+    pub colors: &'static GraphColors,
+    // The UI scale, which text on the canvas is enlarged by on top of its own size
+    pub scale: f32,
+    // Whether the performance overlay is drawn over the graph
+    pub overlay: bool,
+}
+
+// Measured as the graph is laid out and drawn, for the performance overlay. Drawing only has the shared state to read,
+// so what it measures is kept in cells
+#[derive(Default)]
+pub struct PerformanceStats {
+    // When the frames of the last second were drawn
+    frames: RefCell<VecDeque<Instant>>,
+    draw_time: Cell<Duration>,
+    drawn_nodes: Cell<usize>,
+    pub layout_time: Duration,
+}
+
+impl PerformanceStats {
+    fn record_frame(&self, started: Instant, drawn_nodes: usize) {
+        let mut frames = self.frames.borrow_mut();
+        frames.push_back(started);
+        while frames.front().is_some_and(|frame| started.duration_since(*frame) > Duration::from_secs(1)) {
+            frames.pop_front();
+        }
+        self.draw_time.set(started.elapsed());
+        self.drawn_nodes.set(drawn_nodes);
+    }
+
+    // Of the frame drawn before, since the one being drawn isn't done yet
+    fn summary(&self, total_nodes: usize) -> Vec<String> {
+        vec![
+            format!("{} fps", self.frames.borrow().len()),
+            format!("draw {:.1} ms", self.draw_time.get().as_secs_f64() * 1000.0),
+            format!("nodes {} / {}", self.drawn_nodes.get(), total_nodes),
+            format!("layout {:.1} ms", self.layout_time.as_secs_f64() * 1000.0),
+        ]
+    }
+}
+
+pub struct GraphColors {
+    node: Color,
+    dimmed_node: Color,
+    // Commits related to the selected one, and the edges between them
+    related: Color,
+    collapsed: Color,
+    edge: Color,
+    dimmed_edge: Color,
+    node_text: Color,
+    // Reference labels, by the kind of reference
+    branch: Color,
+    remote: Color,
+    tag: Color,
+    // Hidden commit counts and the time axis
+    muted: Color,
+    // Ring around the selected commit
+    selected: Color,
+}
+
+impl GraphColors {
+    pub const NORMAL: GraphColors = GraphColors {
+        node: Color::from_rgb(0.35, 0.35, 0.35),
+        dimmed_node: Color::from_rgba(0.35, 0.35, 0.35, 0.25),
+        related: Color::from_rgb(0.2, 0.4, 0.65),
+        collapsed: Color::from_rgb(0.5, 0.45, 0.3),
+        edge: Color::BLACK,
+        dimmed_edge: Color::from_rgba(0.0, 0.0, 0.0, 0.25),
+        node_text: Color::from_rgb(0.8, 0.8, 0.8),
+        branch: Color::from_rgb(0.1, 0.4, 0.15),
+        remote: Color::from_rgb(0.4, 0.25, 0.55),
+        tag: Color::from_rgb(0.6, 0.35, 0.0),
+        muted: Color::from_rgb(0.4, 0.4, 0.4),
+        selected: Color::from_rgb(0.95, 0.55, 0.1),
+    };
+
+    // For the high contrast theme's black background
+    pub const HIGH_CONTRAST: GraphColors = GraphColors {
+        node: Color::WHITE,
+        dimmed_node: Color::from_rgba(1.0, 1.0, 1.0, 0.35),
+        related: Color::from_rgb(1.0, 0.85, 0.0),
+        collapsed: Color::from_rgb(1.0, 0.55, 0.0),
+        edge: Color::WHITE,
+        dimmed_edge: Color::from_rgba(1.0, 1.0, 1.0, 0.35),
+        node_text: Color::BLACK,
+        branch: Color::from_rgb(0.4, 1.0, 0.4),
+        remote: Color::from_rgb(0.85, 0.65, 1.0),
+        tag: Color::from_rgb(1.0, 0.8, 0.3),
+        muted: Color::from_rgb(0.85, 0.85, 0.85),
+        selected: Color::from_rgb(0.0, 1.0, 1.0),
+    };
+
+    // What each color on the graph means, as the symbol to show it with, its color and a description
+    pub fn legend(&self) -> Vec<(&'static str, Color, &'static str)> {
+        vec![
+            ("●", self.node, "Commit"),
+            ("○", self.selected, "Selected commit"),
+            ("●", self.related, "Ancestor or descendant of the selection"),
+            ("●", self.dimmed_node, "Unrelated to the selection"),
+            ("●", self.collapsed, "Collapsed chain or merge, click to expand"),
+            ("Aa", self.branch, "Local branch"),
+            ("Aa", self.remote, "Remote branch"),
+            ("Aa", self.tag, "Tag"),
+            ("━", self.edge, "First parent"),
+            ("—", self.edge, "Parent merged in"),
+            ("—", self.related, "Parent within the selected history"),
+            ("n", self.muted, "Commits the history filter hides along an edge"),
+            ("•", VERIFIED_COLOR, "Signature verified"),
+            ("•", UNTRUSTED_COLOR, "Good signature from a key that isn't trusted"),
+            ("•", UNVERIFIED_COLOR, "Signature not verified"),
+            ("•", UNCHECKED_COLOR, "Signed, not checked yet"),
+            ("▪", NOTE_COLOR, "Has a note"),
+        ]
+    }
+}
+
+// Badge of a signature that is only checked once its commit is selected
+const UNCHECKED_COLOR: Color = Color::from_rgb(0.6, 0.6, 0.6);
+// Badge of a commit annotated with git notes
+const NOTE_COLOR: Color = Color::from_rgb(0.95, 0.8, 0.3);
+
+// Below this zoom nodes are drawn as plain dots, since thousands of labels make drawing too slow
+const LABEL_ZOOM: f32 = 0.3;
+// From this zoom on there is room for commit messages and references
+const DETAIL_ZOOM: f32 = 1.0;
+// How much bigger the selected node is drawn
+const SELECTED_GROWTH: f32 = 0.15;
+// Room between the selected node and the ring around it, in node radii
+const RING_GAP: f32 = 0.15;
+// Longest commit message that fits inside a node at the detail zoom
+const SUMMARY_LENGTH: usize = 14;
+
+// How far the mouse can wobble during a click before it counts as a drag, in screen pixels
+const CLICK_SLACK: f32 = 5.0;
+// Longest time between two clicks on the same node that still counts as a double click
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+// Share of its speed a released pan still has after gliding for a second
+const PAN_FRICTION: f32 = 0.04;
+// Gliding stops once slower than this, in screen pixels per second
+const PAN_STOP_SPEED: f32 = 10.0;
+// A drag held still for longer than this before letting go doesn't glide
+const PAN_RELEASE_WINDOW: Duration = Duration::from_millis(80);
+// How long the view takes to glide over to a commit that was jumped to
+const GLIDE_DURATION: Duration = Duration::from_millis(300);
+// How far one notch of the wheel pans, in screen pixels
+const SCROLL_LINE: f32 = 40.0;
+// Labels that would come out smaller than this on screen are left out instead of drawn as unreadable specks
+const MIN_TEXT_SIZE: f32 = 6.0;
+// Least room between two dates on the time axis, in screen pixels
+const TICK_SPACING: f32 = 120.0;
+// How close the mouse has to be to an edge to hover it, in screen pixels
+const EDGE_HOVER: f32 = 6.0;
+
+// Lives in the tab's shared state rather than the canvas state so every tab keeps its own view
+pub struct Viewport {
+    pub offset: Vector,
+    pub zoom: f32,
+    // Size of the canvas the last time it received an event
+    pub size: Size,
+    // Graph units per second the view keeps moving at after a pan is let go, and when it last moved
+    velocity: Vector,
+    last_step: Option<Instant>,
+    // Where the view is gliding from and to after jumping to a commit, and since when
+    glide: Option<(Vector, Vector, Instant)>,
+}
+
+impl Default for Viewport {
+    fn default() -> Self {
+        Self { offset: Default::default(), zoom: 1.0, size: Size::ZERO, velocity: Vector::ZERO, last_step: None, glide: None }
+    }
+}
+
+impl Viewport {
+    // Part of the graph currently on screen, in graph coordinates
+    pub fn visible_area(&self) -> Rectangle {
+        let width = self.size.width / self.zoom;
+        let height = self.size.height / self.zoom;
+        Rectangle::new(Point::new(-width / 2.0 - self.offset.x, -height / 2.0 - self.offset.y), Size::new(width, height))
+    }
+
+    pub fn is_moving(&self) -> bool {
+        self.last_step.is_some() || self.glide.is_some()
+    }
+
+    fn start_coasting(&mut self, velocity: Vector) {
+        self.stop();
+        self.velocity = velocity;
+        self.last_step = Some(Instant::now());
+    }
+
+    // Brings `location` to the middle of the canvas over a short moment, rather than all at once so it's clear which
+    // way the view went
+    pub fn glide_to(&mut self, location: Point) {
+        self.stop();
+        self.glide = Some((self.offset, Vector::new(-location.x, -location.y), Instant::now()));
+    }
+
+    pub fn stop(&mut self) {
+        self.velocity = Vector::ZERO;
+        self.last_step = None;
+        self.glide = None;
+    }
+
+    // Moves the view on by however long it has been since the last frame, slowing a released pan down as it goes
+    pub fn step(&mut self) {
+        if let Some((from, to, started)) = self.glide {
+            let progress = (started.elapsed().as_secs_f32() / GLIDE_DURATION.as_secs_f32()).min(1.0);
+            // Eased in and out
+            let eased = progress * progress * (3.0 - 2.0 * progress);
+            self.offset = from + (to - from) * eased;
+            if progress >= 1.0 {
+                self.glide = None;
+            }
+            return;
+        }
+
+        let Some(last_step) = self.last_step else { return };
+        let now = Instant::now();
+        // Frames stop coming while another tab is shown, which shouldn't send the view flying once it's back
+        let elapsed = (now - last_step).as_secs_f32().min(0.1);
+        self.offset = self.offset + self.velocity * elapsed;
+        self.velocity = self.velocity * PAN_FRICTION.powf(elapsed);
+        self.last_step = Some(now);
+
+        let speed = (self.velocity.x * self.velocity.x + self.velocity.y * self.velocity.y).sqrt() * self.zoom;
+        if speed < PAN_STOP_SPEED {
+            self.stop();
+        }
+    }
+}
+
+fn adjust_position_for_view(position: &Point, bounds: &Rectangle, viewport: &Viewport) -> Point {
+    let x = viewport.zoom * (position.x + viewport.offset.x) + bounds.width / 2.0;
+    let y = viewport.zoom * (position.y + viewport.offset.y) + bounds.height / 2.0;
+    Point::new(x, y)
+}
+
+// Rough extent of a reference label around where it is drawn, since the canvas can't measure text
+fn is_over_label(mouse: Point, position: Point, centered: bool, label: &str, zoom: f32) -> bool {
+    let width = label.chars().count() as f32 * 8.0 * zoom;
+    let left = if centered { position.x - width / 2.0 } else { position.x };
+    mouse.x >= left && mouse.x <= left + width && (mouse.y - position.y).abs() <= 10.0 * zoom
+}
+
+#[derive(Default)]
+pub struct TreeState {
+    mouse_location: Point,
+    dragging: bool,
+    // Commit and name of the reference label being dragged onto another commit
+    dragged_reference: Option<(String, String)>,
+    // Commit the button went down on, and whether it has been moved far enough to count as dragging it
+    pressed_commit: Option<String>,
+    moving_commit: bool,
+    offset_start: Vector,
+    dragging_start: Point,
+    modifiers: keyboard::Modifiers,
+    // Node clicked last and when, to tell double clicks apart
+    last_click: Option<(String, Instant)>,
+    // Held down, the left button pans even over nodes
+    space_held: bool,
+    // A press on empty space only unselects when it wasn't the start of a pan
+    pressed_empty: bool,
+    // Node the middle button went down on, clicked if the button comes up before the view was panned
+    middle_pressed: Option<String>,
+    // How fast the mouse has been panning lately in screen pixels per second, and when it last moved
+    pan_velocity: Vector,
+    last_move: Option<Instant>,
+    // Child, parent and which of the child's parents it is, for the edge under the mouse
+    hovered_edge: Option<(String, String, usize)>,
+}
+
+impl TreeState {
+    fn start_pan(&mut self, viewport: &mut Viewport) {
+        viewport.stop();
+        self.dragging = true;
+        self.dragging_start = self.mouse_location;
+        self.offset_start = viewport.offset;
+        self.pan_velocity = Vector::ZERO;
+        self.last_move = None;
+    }
+
+    // Lets the view glide on at the speed it was being panned at, unless the mouse had already come to a stop
+    fn end_pan(&mut self, viewport: &mut Viewport) {
+        self.dragging = false;
+        if self.last_move.take().is_some_and(|at| at.elapsed() < PAN_RELEASE_WINDOW) {
+            viewport.start_coasting(self.pan_velocity * (1.0 / viewport.zoom));
+        }
+    }
+
+    fn moved_since_press(&self) -> bool {
+        self.mouse_location.distance(self.dragging_start) > CLICK_SLACK
+    }
+}
+
+fn node_at<'a>(mouse: Point, locations: &'a BTreeMap<String, Point>, radius: f32, bounds: &Rectangle, viewport: &Viewport) -> Option<&'a String> {
+    locations.iter()
+        .find(|(_, location)| mouse.distance(adjust_position_for_view(location, bounds, viewport)) < radius * viewport.zoom)
+        .map(|(id, _)| id)
+}
+
+fn distance_to_segment(point: Point, from: Point, to: Point) -> f32 {
+    let segment = to - from;
+    let length = segment.x * segment.x + segment.y * segment.y;
+    if length == 0.0 {
+        return point.distance(from);
+    }
+    let along = (((point.x - from.x) * segment.x + (point.y - from.y) * segment.y) / length).clamp(0.0, 1.0);
+    point.distance(from + segment * along)
+}
+
+// Where an edge goes on screen, from the child through the bends its layout gave it to the parent
+fn edge_points(shared_state: &SharedState, child: &str, parent: &str, location: Point, parent_location: Point, bounds: &Rectangle) -> Vec<Point> {
+    let viewport = &shared_state.viewport;
+    let (from, to) = shared_state.orientation.edge(location, parent_location, shared_state.size.node_radius * viewport.zoom);
+    let route = shared_state.edge_routes.get(&(child.to_string(), parent.to_string())).map(Vec::as_slice).unwrap_or_default();
+    let mut points = Vec::with_capacity(route.len() + 2);
+    points.push(from);
+    points.extend(route.iter().map(|bend| adjust_position_for_view(bend, bounds, viewport)));
+    points.push(to);
+    points
+}
+
+// The closest edge within reach of the mouse, if any
+fn edge_at(mouse: Point, shared_state: &SharedState, bounds: &Rectangle) -> Option<(String, String, usize)> {
+    let viewport = &shared_state.viewport;
+    let locations = &shared_state.node_locations;
+    let mut closest: Option<(f32, (String, String, usize))> = None;
+    for (id, commit) in shared_state.commits.iter() {
+        let location = match locations.get(id) {
+            Some(location) => adjust_position_for_view(location, bounds, viewport),
+            None => continue,
+        };
+        for (index, parent) in commit.parents.iter().enumerate() {
+            let parent_location = match locations.get(parent) {
+                Some(location) => adjust_position_for_view(location, bounds, viewport),
+                None => continue,
+            };
+            let distance = edge_points(shared_state, id, parent, location, parent_location, bounds).windows(2)
+                .map(|segment| distance_to_segment(mouse, segment[0], segment[1]))
+                .fold(f32::INFINITY, f32::min);
+            if distance < EDGE_HOVER && closest.as_ref().is_none_or(|(closest, _)| distance < *closest) {
+                closest = Some((distance, (id.clone(), parent.clone(), index)));
+            }
+        }
+    }
+    closest.map(|(_, edge)| edge)
+}
+
+impl TreeRenderer {
+    fn fill_label(&self, frame: &mut Frame, text: Text) {
+        if text.size.0 * self.scale >= MIN_TEXT_SIZE {
+            frame.fill_text(text);
+        }
+    }
+}
+
+impl Program<Message> for TreeRenderer {
+    type State = TreeState;
+
+    fn update(&self, state: &mut Self::State, event: Event, bounds: Rectangle, _cursor: Cursor) -> (Status, Option<Message>) {
+        let shared_state = &mut *self.state.borrow_mut();
+        let viewport = &mut shared_state.viewport;
+        viewport.size = bounds.size();
+        let radius = shared_state.size.node_radius;
+
+        match event {
+            Event::Mouse(mouse::Event::ButtonPressed(button)) => {
+                // Any click catches a gliding view
+                viewport.stop();
+                if button == Button::Left && state.space_held {
+                    state.start_pan(viewport);
+                    (Status::Captured, None)
+                } else if button == Button::Left {
+                    if state.mouse_location.y > 0.0 {
+                        // Labels are only drawn, and so can only be picked up, when zoomed in
+                        if viewport.zoom >= DETAIL_ZOOM {
+                            for (id, location) in shared_state.node_locations.iter() {
+                                let references = match shared_state.commits.get(id) {
+                                    Some(commit) => &commit.references,
+                                    None => continue,
+                                };
+                                let location = adjust_position_for_view(location, &bounds, viewport);
+                                for (i, reference) in references.iter().enumerate() {
+                                    let (position, centered) = shared_state.orientation.reference_label(location, radius * viewport.zoom, i, references.len());
+                                    if is_over_label(state.mouse_location, position, centered, &reference.name, viewport.zoom) {
+                                        state.dragged_reference = Some((id.clone(), reference.name.clone()));
+                                        state.dragging_start = state.mouse_location;
+                                        return (Status::Captured, None)
+                                    }
+                                }
+                            }
+                        }
+
+                        if let Some(id) = node_at(state.mouse_location, &shared_state.node_locations, shared_state.size.node_radius, &bounds, viewport) {
+                            state.pressed_commit = Some(id.clone());
+                            state.dragging_start = state.mouse_location;
+                            // The first click of a double click has already selected the node
+                            let double = state.last_click.take().is_some_and(|(last, at)| last == *id && at.elapsed() < DOUBLE_CLICK);
+                            if !double {
+                                state.last_click = Some((id.clone(), Instant::now()));
+                            }
+                            let message = if shared_state.collapsed_chains.contains_key(id) {
+                                Message::ExpandChain(id.clone())
+                            } else if shared_state.folded_merges.contains_key(id) {
+                                Message::ExpandMerge(id.clone())
+                            } else if state.modifiers.control() {
+                                Message::SelectRangeEnd(id.clone())
+                            } else if double {
+                                Message::CommitDoubleClicked(id.clone())
+                            } else {
+                                Message::SelectCommit(id.clone())
+                            };
+                            return (Status::Captured, Some(message))
+                        }
+
+                        state.start_pan(viewport);
+                        state.pressed_empty = true;
+
+                        (Status::Captured, None)
+                    } else {
+                        (Status::Captured, None)
+                    }
+                } else if button == Button::Middle {
+                    state.middle_pressed = node_at(state.mouse_location, &shared_state.node_locations, shared_state.size.node_radius, &bounds, viewport).cloned();
+                    state.start_pan(viewport);
+                    (Status::Captured, None)
+                } else {
+                    (Status::Ignored, None)
+                }
+            },
+            Event::Mouse(mouse::Event::ButtonReleased(button)) => {
+                if button == Button::Left {
+                    if state.dragging {
+                        state.end_pan(viewport);
+                    }
+                    if std::mem::take(&mut state.pressed_empty) && !state.moved_since_press() {
+                        return (Status::Captured, Some(Message::UnselectCommit))
+                    }
+
+                    if let Some(source) = state.pressed_commit.take().filter(|_| state.moving_commit) {
+                        state.moving_commit = false;
+                        for (id, location) in shared_state.node_locations.iter().filter(|(id, _)| **id != source) {
+                            let location = adjust_position_for_view(location, &bounds, viewport);
+                            if state.mouse_location.distance(location) < radius * viewport.zoom {
+                                return (Status::Captured, Some(Message::DropCommit(source, id.clone())))
+                            }
+                        }
+                    }
+                    state.moving_commit = false;
+
+                    if let Some((source, reference)) = state.dragged_reference.take() {
+                        // Let go where it was picked up, the label was only clicked
+                        if !state.moved_since_press() {
+                            return (Status::Captured, Some(Message::SelectReference(source, reference)))
+                        }
+                        for (id, location) in shared_state.node_locations.iter().filter(|(id, _)| **id != source) {
+                            let location = adjust_position_for_view(location, &bounds, viewport);
+                            if state.mouse_location.distance(location) < radius * viewport.zoom {
+                                return (Status::Captured, Some(Message::DropReference(reference, id.clone())))
+                            }
+                        }
+                    }
+
+                    (Status::Captured, None)
+                } else if button == Button::Middle {
+                    state.end_pan(viewport);
+                    match state.middle_pressed.take().filter(|_| !state.moved_since_press()) {
+                        Some(id) => (Status::Captured, Some(Message::CommitMiddleClicked(id))),
+                        None => (Status::Captured, None),
+                    }
+                } else {
+                    (Status::Ignored, None)
+                }
+            },
+            Event::Mouse(mouse::Event::CursorMoved { position: location }) => {
+                let previous = state.mouse_location;
+                state.mouse_location = Point::new(location.x - bounds.x, location.y - bounds.y);
+
+                if state.dragging {
+                    // Smoothed over the last few moves, since a single one can be jumpy
+                    let now = Instant::now();
+                    if let Some(elapsed) = state.last_move.map(|at| (now - at).as_secs_f32()).filter(|elapsed| *elapsed > 0.0) {
+                        let current = (state.mouse_location - previous) * (1.0 / elapsed);
+                        state.pan_velocity = state.pan_velocity * 0.5 + current * 0.5;
+                    }
+                    state.last_move = Some(now);
+                    viewport.offset = state.offset_start + (state.mouse_location - state.dragging_start) * (1.0 / viewport.zoom);
+                }
+                // A little slack so a click that wobbles doesn't start moving the commit
+                if state.pressed_commit.is_some() && state.moved_since_press() {
+                    state.moving_commit = true;
+                }
+                // Nodes are in front of the edges
+                state.hovered_edge = if state.dragging || node_at(state.mouse_location, &shared_state.node_locations, shared_state.size.node_radius, &bounds, &shared_state.viewport).is_some() {
+                    None
+                } else {
+                    edge_at(state.mouse_location, shared_state, &bounds)
+                };
+
+                (Status::Captured, None)
+            },
+            Event::Mouse(mouse::Event::WheelScrolled { delta }) => {
+                if !state.dragging {
+                    viewport.stop();
+                    // Touchpads scroll by pixels rather than by notches
+                    let (x, y) = match delta {
+                        ScrollDelta::Lines { x, y } => (x * SCROLL_LINE, y * SCROLL_LINE),
+                        ScrollDelta::Pixels { x, y } => (x, y),
+                    };
+
+                    if state.modifiers.control() {
+                        // Mouse location in terms of graph coordinates
+                        let mouse_location_x = (state.mouse_location.x - bounds.width / 2.0) / viewport.zoom - viewport.offset.x;
+                        let mouse_location_y = (state.mouse_location.y - bounds.height / 2.0) / viewport.zoom - viewport.offset.y;
+
+                        let previous_pos = Point::new(mouse_location_x, mouse_location_y);
+                        // Previous position of mouse in screen coordinates
+                        let previous_pos = adjust_position_for_view(&previous_pos, &bounds, viewport);
+
+                        viewport.zoom += y / SCROLL_LINE * 0.15 * viewport.zoom;
+                        viewport.zoom = viewport.zoom.clamp(0.001, 4.0);
+
+                        let new_pos = Point::new(mouse_location_x, mouse_location_y);
+                        // Current position of mouse in screen coordinates
+                        let new_pos = adjust_position_for_view(&new_pos, &bounds, viewport);
+
+                        // Mouse distance moved in graph coordinates
+                        let moved_x = (new_pos.x - previous_pos.x) / viewport.zoom;
+                        viewport.offset.x -= moved_x;
+                        let moved_y = (new_pos.y - previous_pos.y) / viewport.zoom;
+                        viewport.offset.y -= moved_y;
+                    } else if state.modifiers.shift() {
+                        // Mice without a horizontal wheel pan sideways with Shift held
+                        viewport.offset.x += (x + y) / viewport.zoom;
+                    } else {
+                        viewport.offset.x += x / viewport.zoom;
+                        viewport.offset.y += y / viewport.zoom;
+                    }
+                }
+                (Status::Captured, None)
+            },
+            Event::Keyboard(keyboard::Event::ModifiersChanged(modifiers)) => {
+                state.modifiers = modifiers;
+                (Status::Ignored, None)
+            },
+            // Left ignored so a space typed into a text field still gets there
+            Event::Keyboard(keyboard::Event::KeyPressed { key: keyboard::Key::Named(Named::Space), .. }) => {
+                state.space_held = true;
+                (Status::Ignored, None)
+            },
+            Event::Keyboard(keyboard::Event::KeyReleased { key: keyboard::Key::Named(Named::Space), .. }) => {
+                state.space_held = false;
+                (Status::Ignored, None)
+            },
+            _ => (Status::Ignored, None),
+        }
+    }
+
+    fn mouse_interaction(&self, state: &Self::State, bounds: Rectangle, _cursor: Cursor) -> Interaction {
+        let shared_state = &*self.state.borrow();
+        let viewport = &shared_state.viewport;
+
+        if state.dragging || state.dragged_reference.is_some() || state.moving_commit {
+            Interaction::Grabbing
+        } else if state.space_held {
+            Interaction::Grab
+        } else {
+            for location in shared_state.node_locations.values() {
+                let location = adjust_position_for_view(location, &bounds, viewport);
+
+                if state.mouse_location.distance(location) < shared_state.size.node_radius * viewport.zoom {
+                    return Interaction::Pointer
+                }
+            }
+
+            if state.mouse_location.y > 0.0 {
+                Interaction::Pointer
+            } else {
+                Interaction::Idle
+            }
+        }
+    }
+
+    fn draw(&self, state: &TreeState, renderer: &Renderer, _theme: &Theme, bounds: Rectangle, _cursor: mouse::Cursor) -> Vec<Geometry> {
+        let started = Instant::now();
+        let shared_state = &*self.state.borrow();
+        let commits = &shared_state.commits;
+        let related = &shared_state.selection.related_commits;
+        let viewport = &shared_state.viewport;
+        let colors = self.colors;
+        let selection = &shared_state.selection;
+        let size = shared_state.size;
+        let radius = size.node_radius;
+        // Eased so the highlight slows down as it settles
+        let highlight = 1.0 - (1.0 - selection.highlight()).powi(2);
+
+        let mut frame = Frame::new(renderer, bounds.size());
+
+        let arrival = &shared_state.arrival;
+        let mut drawn_nodes = 0;
+        for (id, commit) in commits.iter() {
+            let location = match shared_state.node_locations.get(id) {
+                Some(location) => arrival.location(id, *location),
+                None => continue,
+            };
+            let location = adjust_position_for_view(&location, &bounds, viewport);
+
+            if location.x > bounds.x + bounds.width || location.x < bounds.x ||
+                location.y > bounds.y + bounds.height || location.y < bounds.y {
+                continue
+            }
+            drawn_nodes += 1;
+
+            let is_dimmed = !related.is_empty() && !related.contains(id);
+            let node_color = if is_dimmed {
+                colors.dimmed_node
+            } else if related.contains(id) {
+                colors.related
+            } else if shared_state.collapsed_chains.contains_key(id) || shared_state.folded_merges.contains_key(id) {
+                colors.collapsed
+            } else {
+                colors.node
+            };
+
+            // The selected node grows while the one selected before shrinks back
+            let selected = selection.commit.as_ref() == Some(id);
+            let growth = if selected {
+                highlight
+            } else if selection.previous.as_ref() == Some(id) {
+                1.0 - highlight
+            } else {
+                0.0
+            };
+            let drawn_radius = (radius * viewport.zoom * (1.0 + SELECTED_GROWTH * growth)).max(1.5);
+            frame.fill(&Path::circle(location, drawn_radius), node_color);
+            if selected {
+                frame.stroke(&Path::circle(location, drawn_radius + (radius * RING_GAP * viewport.zoom).max(2.0)), Stroke {
+                    width: if viewport.zoom < LABEL_ZOOM { 2.0 } else { 3.0 },
+                    style: Style::Solid(Color { a: highlight, ..colors.selected }),
+                    ..Default::default()
+                });
+            }
+            let bookmark = shared_state.bookmarks.iter().find(|bookmark| bookmark.commit == *id);
+            if let Some(bookmark) = bookmark {
+                frame.stroke(&Path::circle(location, drawn_radius + (radius * RING_GAP * 2.5 * viewport.zoom).max(4.0)), Stroke {
+                    width: 2.0,
+                    style: Style::Solid(bookmark_color(bookmark.color)),
+                    ..Default::default()
+                });
+            }
+
+            for (index, parent) in commit.parents.iter().enumerate() {
+                let parent_location = match shared_state.node_locations.get(parent) {
+                    Some(location) => arrival.location(parent, *location),
+                    None => continue,
+                };
+                let parent_location = adjust_position_for_view(&parent_location, &bounds, viewport);
+                let points = edge_points(shared_state, id, parent, location, parent_location, &bounds);
+                let path = Path::new(|builder| {
+                    builder.move_to(points[0]);
+                    for point in &points[1..] {
+                        builder.line_to(*point);
+                    }
+                });
+                // Edges to commits that just came in fade in as they move into place
+                let fade = if arrival.is_new(id) || arrival.is_new(parent) { arrival.progress() } else { 1.0 };
+                let hovered = state.hovered_edge.as_ref().is_some_and(|(child, hovered, _)| child == id && hovered == parent);
+                let edge_color = if hovered {
+                    colors.selected
+                } else if related.contains(id) && related.contains(parent) {
+                    colors.related
+                } else if !related.is_empty() {
+                    colors.dimmed_edge
+                } else {
+                    colors.edge
+                };
+                // First parents are drawn thicker, so the mainline stands out from the branches merged into it
+                let width = match (viewport.zoom < LABEL_ZOOM, index == 0) {
+                    (true, true) => 2.0,
+                    (true, false) => 1.0,
+                    (false, true) => 3.0,
+                    (false, false) => 1.5,
+                };
+                frame.stroke(&path, Stroke {
+                    width: if hovered { width + 2.0 } else { width },
+                    style: Style::Solid(Color { a: edge_color.a * fade, ..edge_color }),
+                    ..Default::default()
+                });
+
+                if let Some(hidden) = shared_state.hidden_edges.get(&(id.clone(), parent.clone())).filter(|_| viewport.zoom >= LABEL_ZOOM) {
+                    let text = Text {
+                        content: format!("{} hidden commits", hidden),
+                        position: Point::new((location.x + parent_location.x) / 2.0, (location.y + parent_location.y) / 2.0 - 10.0 * viewport.zoom),
+                        size: Pixels(12.0 * size.text_scale * viewport.zoom),
+                        color: colors.muted,
+                        horizontal_alignment: Horizontal::Center,
+                        vertical_alignment: Vertical::Center,
+                        ..Default::default()
+                    };
+
+                    self.fill_label(&mut frame, text);
+                }
+            }
+
+            if viewport.zoom < LABEL_ZOOM {
+                continue
+            }
+
+            let chain = shared_state.collapsed_chains.get(id);
+            let detailed = viewport.zoom >= DETAIL_ZOOM && chain.is_none();
+            let text = Text {
+                content: match chain {
+                    Some(length) => format!("{} commits", length),
+                    None => shared_state.display.abbreviate(id).to_string(),
+                },
+                position: if detailed { location - Vector::new(0.0, radius * 0.2 * viewport.zoom) } else { location },
+                size: Pixels(15.0 * size.text_scale * viewport.zoom),
+                color: colors.node_text,
+                horizontal_alignment: Horizontal::Center,
+                vertical_alignment: Vertical::Center,
+                ..Default::default()
+            };
+
+            self.fill_label(&mut frame, text);
+
+            if detailed {
+                let summary = if commit.summary.chars().count() > SUMMARY_LENGTH {
+                    format!("{}...", commit.summary.chars().take(SUMMARY_LENGTH - 3).collect::<String>())
+                } else {
+                    commit.summary.clone()
+                };
+                let text = Text {
+                    content: summary,
+                    position: location + Vector::new(0.0, radius * 0.2 * viewport.zoom),
+                    size: Pixels(11.0 * size.text_scale * viewport.zoom),
+                    color: colors.node_text,
+                    horizontal_alignment: Horizontal::Center,
+                    vertical_alignment: Vertical::Center,
+                    ..Default::default()
+                };
+
+                self.fill_label(&mut frame, text);
+            }
+
+            if let Some(merged) = shared_state.folded_merges.get(id) {
+                // On the side no edges or references leave from
+                let (offset, alignment) = match shared_state.orientation {
+                    Orientation::Horizontal => (Vector::new(0.0, radius * 1.2), Horizontal::Center),
+                    Orientation::Vertical => (Vector::new(-radius * 1.2, 0.0), Horizontal::Right),
+                };
+                self.fill_label(&mut frame, Text {
+                    content: format!("+{} merged", merged),
+                    position: location + offset * viewport.zoom,
+                    size: Pixels(12.0 * size.text_scale * viewport.zoom),
+                    color: colors.muted,
+                    horizontal_alignment: alignment,
+                    vertical_alignment: Vertical::Center,
+                    ..Default::default()
+                });
+            }
+
+            if let Some(signature) = &commit.signature {
+                let badge_color = match signature {
+                    SignatureStatus::Verified(_) => VERIFIED_COLOR,
+                    SignatureStatus::Untrusted(_) => UNTRUSTED_COLOR,
+                    SignatureStatus::Unverified(_) => UNVERIFIED_COLOR,
+                    SignatureStatus::Unchecked => UNCHECKED_COLOR,
+                };
+                let badge_location = location + Vector::new(radius * 0.7, -radius * 0.7) * viewport.zoom;
+                frame.fill(&Path::circle(badge_location, radius * 0.2 * viewport.zoom), badge_color);
+            }
+
+            // Across from the signature badge
+            if shared_state.notes.contains(id) {
+                let side = radius * 0.35 * viewport.zoom;
+                let badge_location = location + Vector::new(-radius * 0.7, -radius * 0.7) * viewport.zoom - Vector::new(side / 2.0, side / 2.0);
+                frame.fill(&Path::rectangle(badge_location, Size::new(side, side)), NOTE_COLOR);
+            }
+
+            // After the references, as if it were one more
+            if let Some(bookmark) = bookmark.filter(|_| detailed) {
+                let count = commit.references.len();
+                let (position, centered) = shared_state.orientation.reference_label(location, radius * viewport.zoom, count, count + 1);
+                self.fill_label(&mut frame, Text {
+                    content: bookmark.label.clone(),
+                    position,
+                    size: Pixels(15.0 * size.text_scale * viewport.zoom),
+                    color: bookmark_color(bookmark.color),
+                    horizontal_alignment: if centered { Horizontal::Center } else { Horizontal::Left },
+                    vertical_alignment: Vertical::Center,
+                    ..Default::default()
+                });
+            }
+
+            for (i, reference) in commit.references.iter().enumerate().filter(|_| detailed) {
+                let (position, centered) = shared_state.orientation.reference_label(location, radius * viewport.zoom, i, commit.references.len());
+                let clicked = selected && selection.reference.as_ref().is_some_and(|label| label == reference);
+                let text = Text {
+                    content: reference.name.clone(),
+                    position,
+                    size: Pixels(15.0 * size.text_scale * viewport.zoom),
+                    color: if clicked {
+                        colors.selected
+                    } else {
+                        match reference.kind {
+                            ReferenceKind::Branch => colors.branch,
+                            ReferenceKind::Remote => colors.remote,
+                            ReferenceKind::Tag => colors.tag,
+                        }
+                    },
+                    horizontal_alignment: if centered { Horizontal::Center } else { Horizontal::Left },
+                    vertical_alignment: Vertical::Center,
+                    ..Default::default()
+                };
+
+                self.fill_label(&mut frame, text);
+            }
+        }
+
+        if state.moving_commit {
+            frame.fill(&Path::circle(state.mouse_location, radius * 0.5 * viewport.zoom), Color { a: 0.6, ..colors.related });
+        }
+
+        // Left out once the graph has changed under an edge hovered before
+        let hovered_edge = state.hovered_edge.as_ref()
+            .filter(|(child, parent, index)| commits.get(child).is_some_and(|commit| commit.parents.get(*index) == Some(parent)));
+        if let Some((_, _, index)) = hovered_edge {
+            frame.fill_text(Text {
+                content: if *index == 0 { String::from("First parent") } else { format!("Parent {} (merged in)", index + 1) },
+                position: state.mouse_location + Vector::new(12.0, -12.0),
+                size: Pixels(13.0),
+                color: colors.selected,
+                horizontal_alignment: Horizontal::Left,
+                vertical_alignment: Vertical::Center,
+                ..Default::default()
+            });
+        }
+
+        if let Some((_, reference)) = state.dragged_reference.as_ref().filter(|_| state.moved_since_press()) {
+            self.fill_label(&mut frame, Text {
+                content: reference.clone(),
+                position: state.mouse_location,
+                size: Pixels(15.0 * size.text_scale * viewport.zoom),
+                color: colors.related,
+                horizontal_alignment: Horizontal::Center,
+                vertical_alignment: Vertical::Center,
+                ..Default::default()
+            });
+        }
+
+        if !shared_state.time_axis.is_empty() {
+            let vertical = shared_state.orientation == Orientation::Vertical;
+            let ruler = 15.0;
+            let length = if vertical { bounds.height } else { bounds.width };
+            // Maps a distance along the ruler and a distance from it to a point on the canvas
+            let place = |along: f32, across: f32| if vertical { Point::new(across, along) } else { Point::new(along, across) };
+            let stroke = || Stroke {
+                width: 1.0,
+                style: Style::Solid(colors.muted),
+                ..Default::default()
+            };
+
+            frame.stroke(&Path::line(place(0.0, ruler), place(length, ruler)), stroke());
+
+            // Skip columns so neighbouring dates never overlap, whatever the zoom
+            let spacing = if vertical { TICK_SPACING / 4.0 } else { TICK_SPACING };
+            let step = ((spacing / (size.column_width() * viewport.zoom)).ceil() as usize).max(1);
+            for (position, time) in shared_state.time_axis.iter().step_by(step) {
+                let graph_position = if vertical { Point::new(0.0, *position) } else { Point::new(*position, 0.0) };
+                let screen_position = adjust_position_for_view(&graph_position, &bounds, viewport);
+                let along = if vertical { screen_position.y } else { screen_position.x };
+                if along < 0.0 || along > length {
+                    continue
+                }
+
+                frame.stroke(&Path::line(place(along, ruler - 5.0), place(along, ruler + 5.0)), stroke());
+                frame.fill_text(Text {
+                    content: shared_state.display.format_day(*time),
+                    position: if vertical { place(along, ruler + 10.0) } else { place(along, ruler + 15.0) },
+                    size: Pixels(12.0),
+                    color: colors.muted,
+                    horizontal_alignment: if vertical { Horizontal::Left } else { Horizontal::Center },
+                    vertical_alignment: Vertical::Center,
+                    ..Default::default()
+                });
+            }
+        }
+
+        // In the corner the time axis leaves free, above everything else
+        if self.overlay {
+            let lines = shared_state.performance.summary(commits.len());
+            let line_height = 16.0;
+            let width = 150.0;
+            let corner = Point::new(bounds.width - width - 10.0, 30.0);
+            frame.fill_rectangle(corner, Size::new(width, line_height * lines.len() as f32 + 10.0), Color { a: 0.75, ..Color::BLACK });
+            for (i, line) in lines.into_iter().enumerate() {
+                frame.fill_text(Text {
+                    content: line,
+                    position: corner + Vector::new(8.0, 5.0 + line_height * i as f32),
+                    size: Pixels(14.0),
+                    color: Color::WHITE,
+                    font: iced::Font::MONOSPACE,
+                    ..Default::default()
+                });
+            }
+        }
+
+        shared_state.performance.record_frame(started, drawn_nodes);
+        vec![frame.into_geometry()]
+    }
+}
@@ -0,0 +1,148 @@
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use git2::Repository;
+
+use crate::crash::catch_panic;
+use crate::error::Error;
+
+// Shared between a job and whoever is waiting for it, for stopping the job early
+#[derive(Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    // Long jobs are expected to check this between steps
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+// A job that panics fails like any other, so the thread it ran on keeps going and whoever waits for it gets an error
+fn run_caught<T>(job: impl FnOnce() -> Result<T, Error>) -> Result<T, Error> {
+    catch_panic(job).unwrap_or_else(|| Err(Error::from("the job panicked")))
+}
+
+type Job = Box<dyn FnOnce(&Result<Repository, git2::Error>, &CancelToken) + Send>;
+
+// Runs blocking git operations on a thread of its own, one after another. A Repository can't be sent to another
+// thread, so the worker opens its own and jobs are sent to it instead
+pub struct GitWorker {
+    jobs: Sender<(Job, CancelToken)>,
+}
+
+// A job sent to the worker. Dropping it cancels the job, since there is nobody left to read the result
+pub struct JobHandle<T> {
+    result: Arc<Mutex<Option<Result<T, Error>>>>,
+    cancel: CancelToken,
+}
+
+impl<T: Send + 'static> JobHandle<T> {
+    // Runs the job on a thread of its own rather than the worker, for jobs that can take arbitrarily long, like going
+    // over the network or waiting on a tool, and would hold up everything queued behind them
+    pub fn spawn(name: &str, job: impl FnOnce(&CancelToken) -> Result<T, Error> + Send + 'static) -> Result<JobHandle<T>, Error> {
+        let result = Arc::new(Mutex::new(None));
+        let cancel = CancelToken::default();
+
+        let (job_result, job_cancel) = (Arc::clone(&result), cancel.clone());
+        thread::Builder::new().name(name.to_string()).spawn(move || {
+            let outcome = run_caught(|| job(&job_cancel));
+            *job_result.lock().unwrap() = Some(outcome);
+        })?;
+
+        Ok(JobHandle { result, cancel })
+    }
+}
+
+impl<T> JobHandle<T> {
+    // Only there once the job is done
+    pub fn take_result(&self) -> Option<Result<T, Error>> {
+        self.result.lock().unwrap().take()
+    }
+
+    pub fn cancel(&self) {
+        self.cancel.cancel();
+    }
+}
+
+impl<T> Drop for JobHandle<T> {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+impl GitWorker {
+    // The thread stops once the worker is dropped, after the job it is running
+    pub fn start(path: PathBuf) -> Result<GitWorker, Error> {
+        let (jobs, queue) = mpsc::channel::<(Job, CancelToken)>();
+        thread::Builder::new().name(String::from("git worker")).spawn(move || {
+            // Every job is told if the repository couldn't be opened, instead of the worker failing on its own
+            let repository = Repository::open(path);
+            for (job, cancel) in queue {
+                job(&repository, &cancel);
+            }
+        })?;
+        Ok(GitWorker { jobs })
+    }
+
+    // Jobs cancelled while waiting in the queue are skipped
+    pub fn submit<T: Send + 'static>(&self, job: impl FnOnce(&Repository, &CancelToken) -> Result<T, Error> + Send + 'static) -> JobHandle<T> {
+        let result = Arc::new(Mutex::new(None));
+        let cancel = CancelToken::default();
+
+        let job_result = Arc::clone(&result);
+        let job: Job = Box::new(move |repository, cancel| {
+            let outcome = match repository {
+                _ if cancel.is_cancelled() => Err(Error::from("the job was cancelled")),
+                Ok(repository) => run_caught(|| job(repository, cancel)),
+                Err(e) => Err(Error::from(git2::Error::new(e.code(), e.class(), e.message()))),
+            };
+            *job_result.lock().unwrap() = Some(outcome);
+        });
+        // Sending only fails once the thread has gone
+        if self.jobs.send((job, cancel.clone())).is_err() {
+            *result.lock().unwrap() = Some(Err(Error::from("the git worker has stopped")));
+        }
+
+        JobHandle { result, cancel }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+
+    fn wait<T>(handle: &JobHandle<T>) -> Result<T, Error> {
+        let started = Instant::now();
+        loop {
+            if let Some(result) = handle.take_result() {
+                return result;
+            }
+            assert!(started.elapsed() < Duration::from_secs(10), "the job never finished");
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    #[test]
+    fn spawned_job_that_panics_fails() {
+        let handle = JobHandle::<()>::spawn("panicking job", |_| panic!("on purpose")).unwrap();
+        assert_eq!(wait(&handle).unwrap_err().message(), "the job panicked");
+    }
+
+    #[test]
+    fn worker_keeps_running_after_a_job_panics() {
+        let worker = GitWorker::start(PathBuf::from(env!("CARGO_MANIFEST_DIR"))).unwrap();
+        let panicked = worker.submit::<()>(|_, _| panic!("on purpose"));
+        let next = worker.submit(|repository, _| Ok(repository.is_bare()));
+        assert_eq!(wait(&panicked).unwrap_err().message(), "the job panicked");
+        assert!(!wait(&next).unwrap());
+    }
+}
@@ -0,0 +1,188 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use git2::{Repository, RepositoryState};
+
+use crate::error::Error;
+use crate::maintenance::{common_directory, run_git};
+
+// Git packs the loose objects on its own past this many, when `gc.auto` is left at its default
+const LOOSE_OBJECT_LIMIT: usize = 6700;
+
+// A lock this old is taken to be left behind by a git that crashed, not held by one still running
+const STALE_LOCK_AGE: Duration = Duration::from_secs(10 * 60);
+
+// Only fixes that can't lose any work are offered
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HealthFix {
+    RemoveLock(PathBuf),
+    PackObjects,
+}
+
+impl HealthFix {
+    pub fn label(&self) -> &'static str {
+        match self {
+            HealthFix::RemoveLock(_) => "Remove lock",
+            HealthFix::PackObjects => "Run git gc",
+        }
+    }
+}
+
+pub struct HealthIssue {
+    pub problem: String,
+    pub suggestion: String,
+    pub fix: Option<HealthFix>,
+}
+
+// The operation the state files in the repository say is halfway done, named like its git command
+pub fn operation_name(state: RepositoryState) -> Option<&'static str> {
+    match state {
+        RepositoryState::Clean => None,
+        RepositoryState::Merge => Some("merge"),
+        RepositoryState::Revert | RepositoryState::RevertSequence => Some("revert"),
+        RepositoryState::CherryPick | RepositoryState::CherryPickSequence => Some("cherry-pick"),
+        RepositoryState::Bisect => Some("bisect"),
+        RepositoryState::Rebase | RepositoryState::RebaseInteractive | RepositoryState::RebaseMerge => Some("rebase"),
+        RepositoryState::ApplyMailbox | RepositoryState::ApplyMailboxOrRebase => Some("am"),
+    }
+}
+
+fn find_locks(directory: &Path, recurse: bool, locks: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(directory) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            if recurse {
+                find_locks(&path, true, locks);
+            }
+        } else if path.extension().is_some_and(|extension| extension == "lock") {
+            locks.push(path);
+        }
+    }
+}
+
+fn is_stale(lock: &Path) -> bool {
+    let modified = lock.metadata().and_then(|metadata| metadata.modified());
+    modified.is_ok_and(|modified| SystemTime::now().duration_since(modified).is_ok_and(|age| age >= STALE_LOCK_AGE))
+}
+
+// Locks on the index, HEAD and config sit at the top of the git directory and the ones on refs under `refs`
+fn stale_locks(repository: &Repository) -> Vec<PathBuf> {
+    let common = common_directory(repository);
+    let mut locks = Vec::new();
+    find_locks(repository.path(), false, &mut locks);
+    if common != repository.path() {
+        find_locks(&common, false, &mut locks);
+    }
+    find_locks(&common.join("refs"), true, &mut locks);
+    locks.retain(|lock| is_stale(lock));
+    locks
+}
+
+// Loose objects are kept in directories named after the first two hex digits of their ID
+fn count_loose_objects(repository: &Repository) -> usize {
+    let Ok(entries) = fs::read_dir(common_directory(repository).join("objects")) else {
+        return 0;
+    };
+    entries.flatten()
+        .filter(|entry| entry.file_name().to_str().is_some_and(|name| name.len() == 2 && name.chars().all(|c| c.is_ascii_hexdigit())))
+        .filter_map(|entry| fs::read_dir(entry.path()).ok())
+        .map(|objects| objects.count())
+        .sum()
+}
+
+pub fn unmerged_paths(repository: &Repository) -> Result<Vec<String>, Error> {
+    let index = repository.index()?;
+    if !index.has_conflicts() {
+        return Ok(Vec::new());
+    }
+    let paths = index.conflicts()?.flatten()
+        .filter_map(|conflict| conflict.our.or(conflict.their).or(conflict.ancestor))
+        .map(|entry| String::from_utf8_lossy(&entry.path).to_string())
+        .collect();
+    Ok(paths)
+}
+
+pub fn check_health(repository: &Repository) -> Result<Vec<HealthIssue>, Error> {
+    let mut issues = Vec::new();
+
+    if repository.head_detached()? {
+        issues.push(HealthIssue {
+            problem: String::from("HEAD is detached"),
+            suggestion: String::from("Commits made now are on no branch. Create a branch at HEAD or check out an existing one"),
+            fix: None,
+        });
+    }
+
+    let paths = unmerged_paths(repository)?;
+    if !paths.is_empty() {
+        issues.push(HealthIssue {
+            problem: format!("{} unmerged paths: {}", paths.len(), paths.join(", ")),
+            suggestion: String::from("Resolve the conflicts and stage the files from the Status panel"),
+            fix: None,
+        });
+    }
+
+    if let Some(operation) = operation_name(repository.state()) {
+        let abort = match operation {
+            "bisect" => String::from("git bisect reset"),
+            operation => format!("git {} --abort", operation),
+        };
+        issues.push(HealthIssue {
+            problem: format!("A {} is in progress", operation),
+            suggestion: format!("Finish it, or go back to where it started with `{}`", abort),
+            fix: None,
+        });
+    }
+
+    for lock in stale_locks(repository) {
+        let name = lock.strip_prefix(repository.path()).unwrap_or(&lock).display().to_string();
+        issues.push(HealthIssue {
+            problem: format!("Stale lock file {}", name),
+            suggestion: String::from("Left behind by a git that didn't finish, it keeps any other from writing there"),
+            fix: Some(HealthFix::RemoveLock(lock)),
+        });
+    }
+
+    let loose = count_loose_objects(repository);
+    if loose > LOOSE_OBJECT_LIMIT {
+        issues.push(HealthIssue {
+            problem: format!("{} loose objects", loose),
+            suggestion: String::from("Packing them makes the repository smaller and faster to read"),
+            fix: Some(HealthFix::PackObjects),
+        });
+    }
+
+    Ok(issues)
+}
+
+// A bisect goes on by marking commits instead, so it has nothing to continue
+pub fn continue_operation(repository: &Repository, operation: &str) -> Result<(), Error> {
+    run_git(repository, &[operation, "--continue"])
+}
+
+pub fn abort_operation(repository: &Repository, operation: &str) -> Result<(), Error> {
+    match operation {
+        "bisect" => run_git(repository, &["bisect", "reset"]),
+        operation => run_git(repository, &[operation, "--abort"]),
+    }
+}
+
+pub fn apply_fix(repository: &Repository, fix: &HealthFix) -> Result<(), Error> {
+    match fix {
+        HealthFix::RemoveLock(lock) => {
+            // It might have been taken again since the check
+            if !is_stale(lock) {
+                return Err(Error::from(format!("{} is in use", lock.display())));
+            }
+            fs::remove_file(lock)?;
+        },
+        HealthFix::PackObjects => {
+            run_git(repository, &["gc", "--quiet"])?;
+        },
+    }
+    Ok(())
+}
@@ -0,0 +1,82 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use git2::Repository;
+
+use crate::error::Error;
+
+pub struct HookOutput {
+    pub name: String,
+    pub success: bool,
+    // Standard output followed by standard error
+    pub output: String,
+}
+
+// `core.hooksPath` when set, relative to the working tree like git takes it, otherwise the hooks directory of the repository
+fn hooks_directory(repository: &Repository) -> PathBuf {
+    let configured = repository.config().ok().and_then(|config| config.get_path("core.hooksPath").ok());
+    match (configured, repository.workdir()) {
+        (Some(path), Some(workdir)) if path.is_relative() => workdir.join(path),
+        (Some(path), _) => path,
+        (None, _) => repository.path().join("hooks"),
+    }
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    path.metadata().is_ok_and(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.is_file()
+}
+
+// Runs the hook the way git does, from the top of the working tree; None when the repository has no such hook
+fn run_hook(repository: &Repository, name: &str, args: &[&Path]) -> Result<Option<HookOutput>, Error> {
+    let hook = hooks_directory(repository).join(name);
+    if !is_executable(&hook) {
+        return Ok(None);
+    }
+
+    let index = repository.path().join("index");
+    let output = Command::new(&hook)
+        .args(args)
+        .current_dir(repository.workdir().unwrap_or(repository.path()))
+        .env("GIT_DIR", repository.path())
+        .env("GIT_INDEX_FILE", index)
+        // Nothing can be typed into a hook run from the GUI
+        .env("GIT_EDITOR", ":")
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| Error::from(format!("couldn't run the {} hook: {}", name, e)))?;
+
+    let mut text = String::from_utf8_lossy(&output.stdout).to_string();
+    text.push_str(&String::from_utf8_lossy(&output.stderr));
+    Ok(Some(HookOutput { name: name.to_string(), success: output.status.success(), output: text.trim_end().to_string() }))
+}
+
+// Runs `pre-commit` and then `commit-msg`, stopping at the first that fails. Returns what the hooks printed
+// and the message, which `commit-msg` is allowed to rewrite
+pub fn run_commit_hooks(repository: &Repository, message: &str) -> Result<(Vec<HookOutput>, String), Error> {
+    let mut outputs = Vec::new();
+
+    if let Some(output) = run_hook(repository, "pre-commit", &[])? {
+        let success = output.success;
+        outputs.push(output);
+        if !success {
+            return Ok((outputs, message.to_string()));
+        }
+    }
+
+    let message_file = repository.path().join("COMMIT_EDITMSG");
+    fs::write(&message_file, format!("{}\n", message.trim_end()))?;
+    if let Some(output) = run_hook(repository, "commit-msg", &[&message_file])? {
+        outputs.push(output);
+    }
+    let message = fs::read_to_string(&message_file)?;
+
+    Ok((outputs, message))
+}
@@ -0,0 +1,65 @@
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+use git2::Repository;
+
+use crate::error::Error;
+
+fn gitignore_path(repository: &Repository) -> Result<PathBuf, Error> {
+    let workdir = repository.workdir().ok_or(Error::from("the repository has no working directory"))?;
+    Ok(workdir.join(".gitignore"))
+}
+
+// Contents of the top level .gitignore, empty when there is none yet
+pub fn read_gitignore(repository: &Repository) -> Result<String, Error> {
+    match fs::read_to_string(gitignore_path(repository)?) {
+        Ok(contents) => Ok(contents),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(String::new()),
+        Err(e) => Err(Error::from(e)),
+    }
+}
+
+pub fn write_gitignore(repository: &Repository, contents: &str) -> Result<(), Error> {
+    fs::write(gitignore_path(repository)?, contents)?;
+    Ok(())
+}
+
+pub fn append_to_gitignore(repository: &Repository, pattern: &str) -> Result<(), Error> {
+    let mut contents = read_gitignore(repository)?;
+    if !contents.is_empty() && !contents.ends_with('\n') {
+        contents.push('\n');
+    }
+    contents.push_str(pattern);
+    contents.push('\n');
+    write_gitignore(repository, &contents)
+}
+
+// Ways to ignore an untracked file: the file itself, every file with its extension and every directory it is in
+pub fn suggest_patterns(path: &str) -> Vec<String> {
+    let mut patterns = vec![format!("/{}", path)];
+
+    let file = Path::new(path);
+    if let Some(extension) = file.extension().and_then(|e| e.to_str()) {
+        patterns.push(format!("*.{}", extension));
+    }
+    let mut directory = file.parent();
+    while let Some(parent) = directory.filter(|d| !d.as_os_str().is_empty()) {
+        patterns.push(format!("/{}/", parent.to_string_lossy()));
+        directory = parent.parent();
+    }
+
+    patterns
+}
+
+// Which of the untracked files the given .gitignore contents would hide. The rules are tried as libgit2's
+// in-memory ignore rules, on top of the ones on disk, which none of the untracked files match already
+pub fn preview_ignored(repository: &Repository, contents: &str, untracked: &[String]) -> Result<Vec<String>, git2::Error> {
+    repository.add_ignore_rule(contents)?;
+    let ignored = untracked.iter()
+        .map(|path| repository.status_should_ignore(Path::new(path)).map(|ignored| (path, ignored)))
+        .collect::<Result<Vec<_>, _>>();
+    repository.clear_ignore_rules()?;
+
+    Ok(ignored?.into_iter().filter(|(_, ignored)| *ignored).map(|(path, _)| path.clone()).collect())
+}
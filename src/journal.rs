@@ -0,0 +1,135 @@
+use std::collections::HashMap;
+
+use git2::build::CheckoutBuilder;
+use git2::{Oid, Repository, StatusOptions};
+use tracing::instrument;
+
+// Older operations are forgotten once the journal is this long
+const JOURNAL_LENGTH: usize = 50;
+
+#[derive(PartialEq)]
+enum HeadState {
+    Branch(String),
+    Detached(Oid),
+    // Nothing committed yet
+    Unborn(String),
+}
+
+// What HEAD, the local branches and the tags pointed at before or after an operation
+pub struct RefSnapshot {
+    head: HeadState,
+    references: HashMap<String, Oid>,
+}
+
+pub struct JournalEntry {
+    pub description: String,
+    before: RefSnapshot,
+    after: RefSnapshot,
+    // Undone like `git reset --soft`, leaving the index and working tree alone, rather than checking out the old HEAD
+    keep_changes: bool,
+}
+
+#[derive(Default)]
+pub struct Journal {
+    entries: Vec<JournalEntry>,
+}
+
+impl Journal {
+    // Recorded after the operation succeeded, with the snapshot taken before it ran
+    pub fn record(&mut self, repository: &Repository, description: String, before: RefSnapshot) -> Result<(), git2::Error> {
+        self.push(repository, description, before, false)
+    }
+
+    // For commits, whose changes should come back as staged rather than be dropped when undone
+    pub fn record_keeping_changes(&mut self, repository: &Repository, description: String, before: RefSnapshot) -> Result<(), git2::Error> {
+        self.push(repository, description, before, true)
+    }
+
+    fn push(&mut self, repository: &Repository, description: String, before: RefSnapshot, keep_changes: bool) -> Result<(), git2::Error> {
+        let after = take_snapshot(repository)?;
+        if self.entries.len() == JOURNAL_LENGTH {
+            self.entries.remove(0);
+        }
+        self.entries.push(JournalEntry { description, before, after, keep_changes });
+        Ok(())
+    }
+
+    pub fn last(&self) -> Option<&JournalEntry> {
+        self.entries.last()
+    }
+
+    // Puts the references back the way they were before the last operation
+    pub fn undo(&mut self, repository: &Repository) -> Result<String, git2::Error> {
+        let entry = self.entries.last().ok_or_else(|| git2::Error::from_str("there is nothing to undo"))?;
+        restore_snapshot(repository, entry)?;
+        Ok(self.entries.pop().map(|entry| entry.description).unwrap_or_default())
+    }
+}
+
+#[instrument(skip(repository), err)]
+pub fn take_snapshot(repository: &Repository) -> Result<RefSnapshot, git2::Error> {
+    let head_reference = repository.find_reference("HEAD")?;
+    let head = match head_reference.symbolic_target() {
+        Some(target) if repository.refname_to_id(target).is_ok() => HeadState::Branch(target.to_string()),
+        Some(target) => HeadState::Unborn(target.to_string()),
+        None => HeadState::Detached(head_reference.target().ok_or_else(|| git2::Error::from_str("HEAD points nowhere"))?),
+    };
+
+    let mut references = HashMap::new();
+    for glob in ["refs/heads/*", "refs/tags/*"] {
+        for reference in repository.references_glob(glob)? {
+            let reference = reference?;
+            if let (Some(name), Some(target)) = (reference.name(), reference.target()) {
+                references.insert(name.to_string(), target);
+            }
+        }
+    }
+
+    Ok(RefSnapshot { head, references })
+}
+
+// Only the references the operation changed are put back, and only while they still point where the operation left
+// them, so a commit or branch made outside gitui since then isn't thrown away
+fn restore_snapshot(repository: &Repository, entry: &JournalEntry) -> Result<(), git2::Error> {
+    // The working tree is reset to the restored HEAD, which must not throw away uncommitted work
+    let mut options = StatusOptions::new();
+    options.include_untracked(false);
+    if !entry.keep_changes && !repository.statuses(Some(&mut options))?.is_empty() {
+        return Err(git2::Error::from_str("commit or discard the uncommitted changes before undoing"));
+    }
+
+    let (before, after) = (&entry.before, &entry.after);
+    let mut changed: Vec<&String> = before.references.keys().chain(after.references.keys()).filter(|name| before.references.get(*name) != after.references.get(*name)).collect();
+    changed.sort();
+    changed.dedup();
+
+    let current = take_snapshot(repository)?;
+    let moved: Vec<&str> = changed.iter().filter(|name| current.references.get(**name) != after.references.get(**name)).map(|name| name.as_str()).collect();
+    if !moved.is_empty() {
+        return Err(git2::Error::from_str(&format!("{} changed since the {}, so it can't be undone", moved.join(", "), entry.description)));
+    }
+    let head_changed = before.head != after.head;
+    if head_changed && current.head != after.head {
+        return Err(git2::Error::from_str(&format!("HEAD moved since the {}, so it can't be undone", entry.description)));
+    }
+
+    for name in changed {
+        match before.references.get(name) {
+            Some(target) => {
+                repository.reference(name, *target, true, "undo")?;
+            },
+            None => repository.find_reference(name)?.delete()?,
+        }
+    }
+
+    if head_changed {
+        match &before.head {
+            HeadState::Branch(name) | HeadState::Unborn(name) => repository.set_head(name)?,
+            HeadState::Detached(id) => repository.set_head_detached(*id)?,
+        }
+    }
+    if !entry.keep_changes && !matches!(before.head, HeadState::Unborn(_)) {
+        repository.checkout_head(Some(CheckoutBuilder::new().force()))?;
+    }
+    Ok(())
+}
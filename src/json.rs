@@ -0,0 +1,241 @@
+use std::fmt::Write;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<JsonValue>),
+    Object(Vec<(String, JsonValue)>),
+}
+
+impl JsonValue {
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        match self {
+            JsonValue::Object(fields) => fields.iter().find(|(name, _)| name == key).map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonValue::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    pub fn as_f32(&self) -> Option<f32> {
+        match self {
+            JsonValue::Number(value) => Some(*value as f32),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            JsonValue::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> &[JsonValue] {
+        match self {
+            JsonValue::Array(items) => items,
+            _ => &[],
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        match self {
+            JsonValue::Null => String::from("null"),
+            JsonValue::Bool(value) => value.to_string(),
+            JsonValue::Number(value) => value.to_string(),
+            JsonValue::String(value) => format!("\"{}\"", escape_json(value)),
+            JsonValue::Array(items) => format!("[{}]", items.iter().map(JsonValue::to_json).collect::<Vec<_>>().join(", ")),
+            JsonValue::Object(fields) => {
+                let fields: Vec<String> = fields.iter().map(|(key, value)| format!("\"{}\": {}", escape_json(key), value.to_json())).collect();
+                format!("{{{}}}", fields.join(", "))
+            },
+        }
+    }
+}
+
+// Arrays and objects nested deeper than this are refused, since every level takes a frame of the parsing thread's
+// stack and other tools can send anything
+const MAX_DEPTH: usize = 64;
+
+// Just enough JSON for the files this app writes, which might still be edited by hand, and for what other tools send it
+struct JsonParser<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    // Arrays and objects the parser is inside of
+    depth: usize,
+}
+
+impl JsonParser<'_> {
+    fn skip_whitespace(&mut self) {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+    }
+
+    fn expect(&mut self, expected: char) -> Option<()> {
+        self.skip_whitespace();
+        self.chars.next_if_eq(&expected).map(|_| ())
+    }
+
+    // Exactly four hex digits, as after `\u`
+    fn parse_code_unit(&mut self) -> Option<u32> {
+        let code: String = (0..4).map(|_| self.chars.next_if(char::is_ascii_hexdigit)).collect::<Option<_>>()?;
+        u32::from_str_radix(&code, 16).ok()
+    }
+
+    // Characters outside the basic plane come as a surrogate pair of escapes, and half of a pair is no character
+    fn parse_escaped_char(&mut self) -> Option<char> {
+        let high = self.parse_code_unit()?;
+        if !(0xD800..0xDC00).contains(&high) {
+            return char::from_u32(high);
+        }
+        self.chars.next_if_eq(&'\\')?;
+        self.chars.next_if_eq(&'u')?;
+        let low = self.parse_code_unit().filter(|low| (0xDC00..0xE000).contains(low))?;
+        char::from_u32(0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00))
+    }
+
+    fn parse_value(&mut self) -> Option<JsonValue> {
+        self.skip_whitespace();
+        let nested = matches!(self.chars.peek(), Some('{' | '['));
+        if nested {
+            if self.depth == MAX_DEPTH {
+                return None;
+            }
+            self.depth += 1;
+        }
+        let value = self.parse_unchecked_value();
+        if nested {
+            self.depth -= 1;
+        }
+        value
+    }
+
+    fn parse_unchecked_value(&mut self) -> Option<JsonValue> {
+        match *self.chars.peek()? {
+            '{' => {
+                self.chars.next();
+                let mut fields = Vec::new();
+                if self.expect('}').is_some() {
+                    return Some(JsonValue::Object(fields));
+                }
+                loop {
+                    self.skip_whitespace();
+                    let JsonValue::String(key) = self.parse_value()? else {
+                        return None;
+                    };
+                    self.expect(':')?;
+                    fields.push((key, self.parse_value()?));
+                    if self.expect(',').is_none() {
+                        self.expect('}')?;
+                        return Some(JsonValue::Object(fields));
+                    }
+                }
+            },
+            '[' => {
+                self.chars.next();
+                let mut items = Vec::new();
+                if self.expect(']').is_some() {
+                    return Some(JsonValue::Array(items));
+                }
+                loop {
+                    items.push(self.parse_value()?);
+                    if self.expect(',').is_none() {
+                        self.expect(']')?;
+                        return Some(JsonValue::Array(items));
+                    }
+                }
+            },
+            '"' => {
+                self.chars.next();
+                let mut value = String::new();
+                loop {
+                    match self.chars.next()? {
+                        '"' => return Some(JsonValue::String(value)),
+                        '\\' => match self.chars.next()? {
+                            'n' => value.push('\n'),
+                            'r' => value.push('\r'),
+                            't' => value.push('\t'),
+                            'b' => value.push('\u{8}'),
+                            'f' => value.push('\u{c}'),
+                            'u' => value.push(self.parse_escaped_char()?),
+                            c => value.push(c),
+                        },
+                        c => value.push(c),
+                    }
+                }
+            },
+            c if c == '-' || c.is_ascii_digit() => {
+                let mut number = String::new();
+                while let Some(c) = self.chars.next_if(|c| c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E')) {
+                    number.push(c);
+                }
+                number.parse().ok().map(JsonValue::Number)
+            },
+            _ => {
+                let mut word = String::new();
+                while let Some(c) = self.chars.next_if(|c| c.is_ascii_alphabetic()) {
+                    word.push(c);
+                }
+                match word.as_str() {
+                    "true" => Some(JsonValue::Bool(true)),
+                    "false" => Some(JsonValue::Bool(false)),
+                    "null" => Some(JsonValue::Null),
+                    _ => None,
+                }
+            },
+        }
+    }
+}
+
+pub fn parse_json(text: &str) -> Option<JsonValue> {
+    let mut parser = JsonParser { chars: text.chars().peekable(), depth: 0 };
+    let value = parser.parse_value()?;
+    parser.skip_whitespace();
+    parser.chars.peek().is_none().then_some(value)
+}
+
+pub fn escape_json(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => { let _ = write!(escaped, "\\u{:04x}", c as u32); },
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{JsonValue, MAX_DEPTH, parse_json};
+
+    #[test]
+    fn deep_nesting_is_refused() {
+        let nested = |depth: usize| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(parse_json(&nested(MAX_DEPTH)).is_some());
+        assert!(parse_json(&nested(MAX_DEPTH + 1)).is_none());
+        // Far deeper than a thread's stack would take one frame per level for
+        assert!(parse_json(&"[".repeat(200_000)).is_none());
+        assert!(parse_json(&"{\"a\": ".repeat(200_000)).is_none());
+    }
+
+    #[test]
+    fn surrogate_pairs_make_one_character() {
+        assert_eq!(parse_json("\"\\ud83d\\ude00 \\u00e9\""), Some(JsonValue::String(String::from("\u{1f600} \u{e9}"))));
+        // Half of a pair, or an escape that is cut short
+        for invalid in ["\"\\ud83d\"", "\"\\ude00\"", "\"\\ud83d\\u0041\"", "\"\\u12\"", "\"\\u12zz\""] {
+            assert_eq!(parse_json(invalid), None, "{}", invalid);
+        }
+    }
+}
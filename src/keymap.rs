@@ -0,0 +1,158 @@
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Refresh,
+    // The selected commit
+    Checkout,
+    // Opens the history filter
+    Search,
+    ZoomIn,
+    ZoomOut,
+    // Opens the commit composer
+    Commit,
+    // Through the commits selected before
+    Back,
+    Forward,
+    // Opens the overlay for finding a branch or commit by typing
+    QuickSwitch,
+    DebugConsole,
+}
+
+impl Action {
+    pub const ALL: [Action; 10] = [Action::Refresh, Action::Checkout, Action::Search, Action::ZoomIn, Action::ZoomOut, Action::Commit, Action::Back, Action::Forward, Action::QuickSwitch, Action::DebugConsole];
+
+    // Name in the settings file, as `key.<name> = <chord>`
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Refresh => "refresh",
+            Action::Checkout => "checkout",
+            Action::Search => "search",
+            Action::ZoomIn => "zoom_in",
+            Action::ZoomOut => "zoom_out",
+            Action::Commit => "commit",
+            Action::Back => "back",
+            Action::Forward => "forward",
+            Action::QuickSwitch => "quick_switch",
+            Action::DebugConsole => "debug_console",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        Action::ALL.into_iter().find(|action| action.name() == name)
+    }
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Action::Refresh => write!(f, "Refresh"),
+            Action::Checkout => write!(f, "Check out selected commit"),
+            Action::Search => write!(f, "Search history"),
+            Action::ZoomIn => write!(f, "Zoom in"),
+            Action::ZoomOut => write!(f, "Zoom out"),
+            Action::Commit => write!(f, "Commit"),
+            Action::Back => write!(f, "Previous selection"),
+            Action::Forward => write!(f, "Next selection"),
+            Action::QuickSwitch => write!(f, "Quick switcher"),
+            Action::DebugConsole => write!(f, "Debug console"),
+        }
+    }
+}
+
+// A key with the modifiers held down, written like `Ctrl+Shift+F`. Keys keep the names iced 0.10 gave them, such as
+// `F5`, `Enter`, `Equals` or `Key1`, which the bindings in settings files were saved with
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyChord {
+    pub control: bool,
+    pub alt: bool,
+    pub shift: bool,
+    pub logo: bool,
+    pub key: String,
+}
+
+impl KeyChord {
+    pub fn parse(chord: &str) -> Option<KeyChord> {
+        let mut parts: Vec<&str> = chord.split('+').map(str::trim).collect();
+        let key = parts.pop().filter(|key| !key.is_empty())?.to_string();
+
+        let mut result = KeyChord { control: false, alt: false, shift: false, logo: false, key };
+        for modifier in parts {
+            match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => result.control = true,
+                "alt" => result.alt = true,
+                "shift" => result.shift = true,
+                "super" | "logo" | "cmd" => result.logo = true,
+                _ => return None,
+            }
+        }
+        Some(result)
+    }
+
+    // Keys are told apart regardless of case, since `f5` in the settings file means the same as `F5`
+    fn matches(&self, other: &KeyChord) -> bool {
+        (self.control, self.alt, self.shift, self.logo) == (other.control, other.alt, other.shift, other.logo)
+            && self.key.eq_ignore_ascii_case(&other.key)
+    }
+}
+
+impl fmt::Display for KeyChord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (held, name) in [(self.control, "Ctrl+"), (self.alt, "Alt+"), (self.shift, "Shift+"), (self.logo, "Super+")] {
+            if held {
+                write!(f, "{}", name)?;
+            }
+        }
+        write!(f, "{}", self.key)
+    }
+}
+
+pub struct Keymap {
+    // Actions without a chord have no shortcut
+    bindings: Vec<(Action, Option<KeyChord>)>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let default = |action| match action {
+            Action::Refresh => "F5",
+            Action::Checkout => "Ctrl+Shift+C",
+            Action::Search => "Ctrl+F",
+            Action::ZoomIn => "Ctrl+Equals",
+            Action::ZoomOut => "Ctrl+Minus",
+            Action::Commit => "Ctrl+K",
+            Action::Back => "Alt+Left",
+            Action::Forward => "Alt+Right",
+            Action::QuickSwitch => "Ctrl+P",
+            Action::DebugConsole => "Ctrl+Shift+D",
+        };
+        Self { bindings: Action::ALL.into_iter().map(|action| (action, KeyChord::parse(default(action)))).collect() }
+    }
+}
+
+impl Keymap {
+    pub fn get(&self, action: Action) -> Option<&KeyChord> {
+        self.bindings.iter().find(|(bound, _)| *bound == action).and_then(|(_, chord)| chord.as_ref())
+    }
+
+    // A chord does one thing only, so binding it takes it away from whatever action had it before
+    pub fn set(&mut self, action: Action, chord: Option<KeyChord>) {
+        for (bound, existing) in self.bindings.iter_mut() {
+            if *bound == action {
+                *existing = chord.clone();
+            } else if existing.as_ref().zip(chord.as_ref()).is_some_and(|(existing, chord)| existing.matches(chord)) {
+                *existing = None;
+            }
+        }
+    }
+
+    pub fn lookup(&self, chord: &KeyChord) -> Option<Action> {
+        self.bindings.iter()
+            .find(|(_, bound)| bound.as_ref().is_some_and(|bound| bound.matches(chord)))
+            .map(|(action, _)| *action)
+    }
+
+    pub fn bindings(&self) -> impl Iterator<Item = (Action, Option<&KeyChord>)> {
+        self.bindings.iter().map(|(action, chord)| (*action, chord.as_ref()))
+    }
+}
@@ -0,0 +1,90 @@
+use git2::{Oid, Repository};
+
+use crate::error::Error;
+use crate::maintenance::{common_directory, format_size, run_git};
+
+// Pointer files are small text files starting with the spec they follow, anything larger is real content
+const POINTER_SIZE_LIMIT: usize = 1024;
+const POINTER_VERSION: &str = "version https://git-lfs.github.com/spec/v1";
+
+// What git keeps in place of a file stored with Git LFS
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LfsPointer {
+    // SHA-256 of the content, without the `sha256:` prefix
+    pub oid: String,
+    pub size: u64,
+}
+
+impl LfsPointer {
+    // Downloaded objects are kept under `lfs/objects/<first two digits>/<next two>/<oid>` of the git directory
+    pub fn is_downloaded(&self, repository: &Repository) -> bool {
+        self.oid.len() > 4 && common_directory(repository).join("lfs").join("objects").join(&self.oid[0..2]).join(&self.oid[2..4]).join(&self.oid).is_file()
+    }
+}
+
+pub fn parse_pointer(content: &[u8]) -> Option<LfsPointer> {
+    if content.len() > POINTER_SIZE_LIMIT {
+        return None;
+    }
+    let content = std::str::from_utf8(content).ok()?;
+    let mut lines = content.lines();
+    if lines.next()? != POINTER_VERSION {
+        return None;
+    }
+
+    let (mut oid, mut size) = (None, None);
+    for line in lines {
+        match line.split_once(' ') {
+            Some(("oid", value)) => oid = value.strip_prefix("sha256:").map(str::to_string),
+            Some(("size", value)) => size = value.parse().ok(),
+            _ => (),
+        }
+    }
+    Some(LfsPointer { oid: oid?, size: size? })
+}
+
+// None for a file that isn't there on that side of a diff, or isn't a pointer
+pub fn blob_pointer(repository: &Repository, id: Oid) -> Option<LfsPointer> {
+    if id.is_zero() {
+        return None;
+    }
+    let blob = repository.find_blob(id).ok()?;
+    parse_pointer(blob.content())
+}
+
+pub fn describe_pointer(pointer: &LfsPointer) -> String {
+    format!("LFS object ({})", format_size(pointer.size))
+}
+
+// Shown in a diff instead of the pointer text, when either side is a pointer. `exists` says whether there is a
+// file at all on that side, which isn't a pointer when it was just moved into or out of LFS
+pub fn describe_change(old: Option<&LfsPointer>, old_exists: bool, new: Option<&LfsPointer>, new_exists: bool) -> Option<String> {
+    if old.is_none() && new.is_none() {
+        return None;
+    }
+    let side = |pointer: Option<&LfsPointer>, exists: bool| match pointer {
+        Some(pointer) => describe_pointer(pointer),
+        None if exists => String::from("file stored in git"),
+        None => String::from("nothing"),
+    };
+    Some(match (old_exists, new_exists) {
+        (false, _) => side(new, new_exists),
+        (_, false) => format!("{} deleted", side(old, old_exists)),
+        _ => format!("{} -> {}", side(old, old_exists), side(new, new_exists)),
+    })
+}
+
+// Downloads the objects of the given paths at the commit into the local LFS store, leaving the working tree alone
+pub fn fetch_objects(repository: &Repository, commit: &str, paths: &[String]) -> Result<(), Error> {
+    let remotes = repository.remotes()?;
+    let remote = match remotes.iter().flatten().find(|remote| *remote == "origin") {
+        Some(remote) => remote,
+        None => remotes.iter().flatten().next().ok_or(Error::from("there is no remote to fetch LFS objects from"))?,
+    };
+    run_git(repository, &["lfs", "fetch", &format!("--include={}", paths.join(",")), remote, commit])
+}
+
+// Downloads the objects of the given paths if needed and puts their content in the working tree in place of the pointers
+pub fn check_out_objects(repository: &Repository, paths: &[String]) -> Result<(), Error> {
+    run_git(repository, &["lfs", "pull", &format!("--include={}", paths.join(","))])
+}
@@ -0,0 +1,33 @@
+pub mod gui;
+pub mod backend;
+pub mod signing;
+pub mod config;
+pub mod export;
+pub mod archive;
+pub mod error;
+pub mod rebase;
+pub mod status;
+pub mod ignore;
+pub mod message;
+pub mod hooks;
+pub mod journal;
+pub mod remote;
+pub mod links;
+pub mod tools;
+pub mod stats;
+pub mod changelog;
+pub mod settings;
+pub mod keymap;
+pub mod session;
+pub mod cli;
+pub mod cache;
+pub mod commitgraph;
+pub mod health;
+pub mod maintenance;
+pub mod lfs;
+pub mod preview;
+pub mod uistate;
+pub mod json;
+pub mod logging;
+pub mod crash;
+pub mod dot;
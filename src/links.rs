@@ -0,0 +1,139 @@
+use std::process::Command;
+use std::thread;
+
+use git2::Repository;
+
+use crate::error::Error;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum ForgeKind {
+    GitHub,
+    GitLab,
+}
+
+// The web side of a hosted repository, worked out from its remote URL
+pub struct Forge {
+    pub kind: ForgeKind,
+    // Like `https://github.com/owner/repo`
+    pub base: String,
+}
+
+impl Forge {
+    pub fn name(&self) -> &'static str {
+        match self.kind {
+            ForgeKind::GitHub => "GitHub",
+            ForgeKind::GitLab => "GitLab",
+        }
+    }
+
+    pub fn commit_url(&self, id: &str) -> String {
+        match self.kind {
+            ForgeKind::GitHub => format!("{}/commit/{}", self.base, id),
+            ForgeKind::GitLab => format!("{}/-/commit/{}", self.base, id),
+        }
+    }
+
+    // GitHub sends issue links for pull requests on to the pull request
+    pub fn issue_url(&self, number: &str) -> String {
+        match self.kind {
+            ForgeKind::GitHub => format!("{}/issues/{}", self.base, number),
+            ForgeKind::GitLab => format!("{}/-/issues/{}", self.base, number),
+        }
+    }
+}
+
+// From `origin`, or the only remote when it is named otherwise
+pub fn get_forge(repository: &Repository) -> Option<Forge> {
+    let remote = match repository.find_remote("origin") {
+        Ok(remote) => remote,
+        Err(_) => {
+            let names = repository.remotes().ok()?;
+            match names.len() {
+                1 => repository.find_remote(names.get(0)?).ok()?,
+                _ => return None,
+            }
+        },
+    };
+    parse_remote_url(remote.url()?)
+}
+
+// Takes `https://host/path`, `ssh://user@host/path` and scp-like `user@host:path`
+fn parse_remote_url(url: &str) -> Option<Forge> {
+    let (host, path) = match url.split_once("://") {
+        Some((_, rest)) => rest.split_once('/')?,
+        None => url.split_once(':')?,
+    };
+    let host = host.rsplit('@').next()?;
+    // A port only matters to the transport, the web side is on the usual one
+    let host = host.split(':').next()?;
+    let path = path.trim_end_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path);
+
+    let kind = if host.contains("github") {
+        ForgeKind::GitHub
+    } else if host.contains("gitlab") {
+        ForgeKind::GitLab
+    } else {
+        return None;
+    };
+    (!path.is_empty()).then(|| Forge { kind, base: format!("https://{}/{}", host, path) })
+}
+
+pub enum MessagePart {
+    Text(String),
+    Link { label: String, url: String },
+}
+
+// Splits a line of a commit message into plain text, URLs and `#123` references, which only become links when
+// the forge to send them to is known
+pub fn link_message_line(line: &str, forge: Option<&Forge>) -> Vec<MessagePart> {
+    let mut parts = Vec::new();
+    let mut pending = String::new();
+
+    for word in line.split_inclusive(' ') {
+        let core = word.trim_end_matches(' ').trim_start_matches(['(', '[', '<']);
+        let core = core.trim_end_matches(['.', ',', ';', ':', '!', '?', ')', ']', '>', '"', '\'']);
+        let url = if core.starts_with("https://") || core.starts_with("http://") {
+            Some(core.to_string())
+        } else {
+            core.strip_prefix('#')
+                .filter(|number| !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()))
+                .and_then(|number| forge.map(|forge| forge.issue_url(number)))
+        };
+
+        match url {
+            Some(url) if !core.is_empty() => {
+                let start = word.find(core).unwrap_or(0);
+                pending.push_str(&word[..start]);
+                if !pending.is_empty() {
+                    parts.push(MessagePart::Text(std::mem::take(&mut pending)));
+                }
+                parts.push(MessagePart::Link { label: core.to_string(), url });
+                pending.push_str(&word[start + core.len()..]);
+            },
+            _ => pending.push_str(word),
+        }
+    }
+
+    if !pending.is_empty() {
+        parts.push(MessagePart::Text(pending));
+    }
+    parts
+}
+
+// Hands the URL to the desktop's browser
+pub fn open_url(url: &str) -> Result<(), Error> {
+    let mut command = if cfg!(target_os = "windows") {
+        let mut command = Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    } else if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else {
+        Command::new("xdg-open")
+    };
+    let mut child = command.arg(url).spawn().map_err(|e| Error::from(format!("couldn't open {}: {}", url, e)))?;
+    // Reaped in the background, the opener usually exits as soon as it handed the URL on
+    thread::spawn(move || child.wait());
+    Ok(())
+}
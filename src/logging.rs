@@ -0,0 +1,122 @@
+use std::collections::VecDeque;
+use std::fmt::{self, Write};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Local};
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+use tracing_subscriber::registry::LookupSpan;
+use tracing_subscriber::util::SubscriberInitExt;
+
+// Lines the debug console keeps, older ones are dropped
+const LOG_CAPACITY: usize = 1000;
+
+static LOG: Mutex<VecDeque<LogLine>> = Mutex::new(VecDeque::new());
+
+#[derive(Debug, Clone)]
+pub struct LogLine {
+    pub at: DateTime<Local>,
+    pub level: Level,
+    pub message: String,
+    // How long it took, for the line logged when an operation finishes
+    pub duration: Option<Duration>,
+}
+
+// The message of an event or span followed by its other fields as `name=value`
+#[derive(Default)]
+struct FieldText(String);
+
+impl FieldText {
+    fn push(&mut self, field: &Field, value: fmt::Arguments) {
+        if !self.0.is_empty() {
+            self.0.push(' ');
+        }
+        if field.name() != "message" {
+            let _ = write!(self.0, "{}=", field.name());
+        }
+        let _ = self.0.write_fmt(value);
+    }
+}
+
+impl Visit for FieldText {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.push(field, format_args!("{}", value));
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.push(field, format_args!("{:?}", value));
+    }
+}
+
+struct SpanTiming {
+    started: Instant,
+    fields: String,
+}
+
+// Keeps what is logged for the debug console, with how long every span took once it closes
+struct ConsoleLayer;
+
+fn push_line(level: Level, message: String, duration: Option<Duration>) {
+    let mut log = LOG.lock().unwrap();
+    if log.len() == LOG_CAPACITY {
+        log.pop_front();
+    }
+    log.push_back(LogLine { at: Local::now(), level, message, duration });
+}
+
+impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for ConsoleLayer {
+    fn on_new_span(&self, attributes: &Attributes<'_>, id: &Id, context: Context<'_, S>) {
+        let mut fields = FieldText::default();
+        attributes.record(&mut fields);
+        if let Some(span) = context.span(id) {
+            span.extensions_mut().insert(SpanTiming { started: Instant::now(), fields: fields.0 });
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, context: Context<'_, S>) {
+        let mut fields = FieldText::default();
+        event.record(&mut fields);
+        let message = match context.event_span(event) {
+            Some(span) => format!("{}: {}", span.name(), fields.0),
+            None => fields.0,
+        };
+        push_line(*event.metadata().level(), message, None);
+    }
+
+    fn on_close(&self, id: Id, context: Context<'_, S>) {
+        let Some(span) = context.span(&id) else {
+            return;
+        };
+        let Some(timing) = span.extensions_mut().remove::<SpanTiming>() else {
+            return;
+        };
+        let message = format!("{} {}", span.name(), timing.fields).trim_end().to_string();
+        push_line(*span.metadata().level(), message, Some(timing.started.elapsed()));
+    }
+}
+
+// Only what this app logs ends up here, since iced and its renderers log through the `log` crate instead
+pub fn init() {
+    let _ = tracing_subscriber::registry().with(ConsoleLayer).try_init();
+}
+
+// Oldest first
+pub fn recent_lines() -> Vec<LogLine> {
+    LOG.lock().unwrap().iter().cloned().collect()
+}
+
+// The last `count` lines, or none when the log is in use. Called from the panic hook, which might run while the
+// panicking thread is holding the log
+pub fn try_last_lines(count: usize) -> Vec<LogLine> {
+    match LOG.try_lock() {
+        Ok(log) => log.iter().skip(log.len().saturating_sub(count)).cloned().collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+pub fn clear_log() {
+    LOG.lock().unwrap().clear();
+}
@@ -1,11 +1,36 @@
-mod gui;
-pub mod backend;
+use std::env;
+use std::path::PathBuf;
+use std::process;
 
-//use std::env;
-
-use gui::GitUI;
+use gitui::gui::{self, GitUI};
+use gitui::{cli, crash, logging};
 
 pub fn main() {
     //env::set_current_dir("/home/main/testrepo").unwrap();
-    GitUI::start()
+    logging::init();
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "--export") {
+        if let Err(e) = cli::parse_export_args(&args).and_then(|args| cli::export_layout(&args)) {
+            eprintln!("gitui: {}\n{}", e, cli::USAGE);
+            process::exit(1);
+        }
+        return;
+    }
+    // Started by the panic hook of a gitui that crashed
+    if let [flag, report] = args.as_slice() {
+        if flag == "--crash-report" {
+            gui::show_crash_report(PathBuf::from(report));
+            return;
+        }
+    }
+    match cli::parse_window_args(&args) {
+        Ok(dot) => {
+            crash::install_panic_hook();
+            GitUI::start(dot)
+        },
+        Err(e) => {
+            eprintln!("gitui: {}\n{}", e, cli::WINDOW_USAGE);
+            process::exit(1);
+        },
+    }
 }
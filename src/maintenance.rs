@@ -0,0 +1,114 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use git2::Repository;
+use tracing::instrument;
+
+use crate::error::Error;
+
+// Handed to git itself, which knows how to do these safely while other gits might be running
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MaintenanceTask {
+    // Past `gc.reflogExpire`, 90 days unless configured otherwise
+    ExpireReflogs,
+    // Remote branches that were deleted on their remote
+    PruneRemoteBranches,
+    Repack,
+    CollectGarbage,
+}
+
+impl MaintenanceTask {
+    pub const ALL: [MaintenanceTask; 4] = [MaintenanceTask::ExpireReflogs, MaintenanceTask::PruneRemoteBranches, MaintenanceTask::Repack, MaintenanceTask::CollectGarbage];
+}
+
+impl fmt::Display for MaintenanceTask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MaintenanceTask::ExpireReflogs => write!(f, "Expire old reflog entries"),
+            MaintenanceTask::PruneRemoteBranches => write!(f, "Prune deleted remote branches"),
+            MaintenanceTask::Repack => write!(f, "Repack objects"),
+            MaintenanceTask::CollectGarbage => write!(f, "Collect garbage"),
+        }
+    }
+}
+
+pub struct MaintenanceReport {
+    pub task: MaintenanceTask,
+    // Size of the git directory in bytes
+    pub before: u64,
+    pub after: u64,
+}
+
+// Where refs and objects live, which for a linked worktree is the main repository's directory
+pub fn common_directory(repository: &Repository) -> PathBuf {
+    match fs::read_to_string(repository.path().join("commondir")) {
+        Ok(common) => repository.path().join(common.trim()),
+        Err(_) => repository.path().to_path_buf(),
+    }
+}
+
+fn directory_size(directory: &Path) -> u64 {
+    let Ok(entries) = fs::read_dir(directory) else {
+        return 0;
+    };
+    entries.flatten()
+        .filter_map(|entry| Some((entry.path(), entry.metadata().ok()?)))
+        .map(|(path, metadata)| if metadata.is_dir() { directory_size(&path) } else { metadata.len() })
+        .sum()
+}
+
+pub fn repository_size(repository: &Repository) -> u64 {
+    directory_size(&common_directory(repository))
+}
+
+pub fn format_size(bytes: u64) -> String {
+    if bytes < 1024 {
+        return format!("{} B", bytes);
+    }
+    let mut size = bytes as f64 / 1024.0;
+    for unit in ["KiB", "MiB"] {
+        if size < 1024.0 {
+            return format!("{:.1} {}", size, unit);
+        }
+        size /= 1024.0;
+    }
+    format!("{:.1} GiB", size)
+}
+
+// For what libgit2 can't do, run from the top of the working tree with nothing to type into an editor
+#[instrument(skip(repository), err)]
+pub fn run_git(repository: &Repository, args: &[&str]) -> Result<(), Error> {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(repository.workdir().unwrap_or(repository.path()))
+        .env("GIT_DIR", repository.path())
+        .env("GIT_EDITOR", ":")
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| Error::from(format!("couldn't run git {}: {}", args[0], e)))?;
+    if !output.status.success() {
+        let mut message = String::from_utf8_lossy(&output.stdout).to_string();
+        message.push_str(&String::from_utf8_lossy(&output.stderr));
+        return Err(Error::from(format!("git {} failed: {}", args.join(" "), message.trim_end())));
+    }
+    Ok(())
+}
+
+pub fn run_task(repository: &Repository, task: MaintenanceTask) -> Result<MaintenanceReport, Error> {
+    let before = repository_size(repository);
+    match task {
+        MaintenanceTask::ExpireReflogs => run_git(repository, &["reflog", "expire", "--all"])?,
+        MaintenanceTask::PruneRemoteBranches => {
+            let remotes = repository.remotes()?;
+            let remotes: Vec<&str> = remotes.iter().flatten().collect();
+            if !remotes.is_empty() {
+                run_git(repository, &[&["remote", "prune"], &remotes[..]].concat())?;
+            }
+        },
+        MaintenanceTask::Repack => run_git(repository, &["repack", "-a", "-d", "--quiet"])?,
+        MaintenanceTask::CollectGarbage => run_git(repository, &["gc", "--quiet"])?,
+    }
+    Ok(MaintenanceReport { task, before, after: repository_size(repository) })
+}
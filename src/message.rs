@@ -0,0 +1,124 @@
+use std::fs;
+use std::path::PathBuf;
+
+use git2::Repository;
+
+const DEFAULT_SUBJECT_LENGTH: usize = 50;
+const DEFAULT_BODY_LENGTH: usize = 72;
+
+pub const CONVENTIONAL_TYPES: [&str; 11] = ["feat", "fix", "docs", "style", "refactor", "perf", "test", "build", "ci", "chore", "revert"];
+
+pub struct MessageConfig {
+    // Contents of the file `commit.template` points to
+    pub template: Option<String>,
+    pub subject_length: usize,
+    pub body_length: usize,
+}
+
+impl Default for MessageConfig {
+    fn default() -> Self {
+        Self { template: None, subject_length: DEFAULT_SUBJECT_LENGTH, body_length: DEFAULT_BODY_LENGTH }
+    }
+}
+
+impl MessageConfig {
+    // Line lengths come from `gitui.subjectLength` and `gitui.bodyLength`, zero turning the warning off
+    pub fn load(repository: &Repository) -> MessageConfig {
+        let config = match repository.config() {
+            Ok(config) => config,
+            Err(_) => return Default::default(),
+        };
+
+        let template = config.get_path("commit.template").ok().and_then(|path| {
+            // A relative path is taken from the top of the working tree, where git is usually run
+            let path = match (path.is_relative(), repository.workdir()) {
+                (true, Some(workdir)) => workdir.join(path),
+                _ => path,
+            };
+            fs::read_to_string(expand_home(path)).ok()
+        });
+        let length = |key: &str, default: usize| config.get_i64(key).ok().and_then(|length| usize::try_from(length).ok()).unwrap_or(default);
+
+        MessageConfig {
+            template,
+            subject_length: length("gitui.subjectLength", DEFAULT_SUBJECT_LENGTH),
+            body_length: length("gitui.bodyLength", DEFAULT_BODY_LENGTH),
+        }
+    }
+}
+
+// libgit2 leaves `~/` in config paths alone
+fn expand_home(path: PathBuf) -> PathBuf {
+    match (path.strip_prefix("~"), std::env::var_os("HOME")) {
+        (Ok(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => path,
+    }
+}
+
+// Drops comment lines and surrounding blank lines, like git does for a message written in the editor
+pub fn clean_message(message: &str) -> String {
+    let lines: Vec<&str> = message.lines()
+        .filter(|line| !line.starts_with('#'))
+        .map(|line| line.trim_end())
+        .collect();
+    lines.join("\n").trim_matches('\n').to_string()
+}
+
+pub fn check_message(message: &str, config: &MessageConfig) -> Vec<String> {
+    let mut warnings = Vec::new();
+    let message = clean_message(message);
+    let mut lines = message.lines();
+
+    let subject = lines.next().unwrap_or_default();
+    if config.subject_length > 0 && subject.chars().count() > config.subject_length {
+        warnings.push(format!("The subject is longer than {} characters", config.subject_length));
+    }
+    if lines.next().is_some_and(|line| !line.is_empty()) {
+        warnings.push(String::from("The subject should be followed by a blank line"));
+    }
+    if config.body_length > 0 {
+        let long_lines = lines.filter(|line| line.chars().count() > config.body_length).count();
+        if long_lines > 0 {
+            warnings.push(format!("{} body lines are longer than {} characters", long_lines, config.body_length));
+        }
+    }
+
+    warnings
+}
+
+// Length of a `type(scope)!: ` prefix at the start of the subject, if there is one; types are lowercase,
+// which keeps subjects like "Note: ..." intact
+fn conventional_prefix_length(subject: &str) -> Option<usize> {
+    let end = subject.find(": ")?;
+    let prefix = subject[..end].trim_end_matches('!');
+    let kind = match prefix.split_once('(') {
+        Some((kind, scope)) if scope.ends_with(')') => kind,
+        Some(_) => return None,
+        None => prefix,
+    };
+    (!kind.is_empty() && kind.chars().all(|c| c.is_ascii_lowercase())).then_some(end + 2)
+}
+
+// Replaces the conventional commit prefix of the subject, or adds one
+pub fn set_conventional_prefix(subject: &str, kind: &str, scope: &str) -> String {
+    let rest = &subject[conventional_prefix_length(subject).unwrap_or(0)..];
+    if scope.trim().is_empty() {
+        format!("{}: {}", kind, rest)
+    } else {
+        format!("{}({}): {}", kind, scope.trim(), rest)
+    }
+}
+
+// Columns of the ruler above the message, marking every tenth column and the configured lengths
+pub fn ruler(config: &MessageConfig) -> String {
+    let width = config.subject_length.max(config.body_length);
+    (1..=width).map(|column| {
+        if column == config.subject_length || column == config.body_length {
+            '|'
+        } else if column % 10 == 0 {
+            char::from_digit((column / 10 % 10) as u32, 10).unwrap_or('+')
+        } else {
+            '.'
+        }
+    }).collect()
+}
@@ -0,0 +1,130 @@
+use std::cell::RefCell;
+use std::fmt;
+use std::path::Path;
+
+use iced::widget::image::Handle;
+use iced::widget::svg;
+use image::{Rgba, RgbaImage};
+
+// Images larger than this aren't previewed, to keep from holding on to huge files
+const MAX_PREVIEW_SIZE: usize = 16 * 1024 * 1024;
+
+// Raster images are scaled down to fit this many pixels a side, which is plenty for comparing them side by side
+const THUMBNAIL_SIZE: u32 = 400;
+
+// Extensions of the raster images that can be decoded
+const RASTER_EXTENSIONS: [&str; 6] = ["png", "jpg", "jpeg", "gif", "bmp", "webp"];
+
+fn has_extension(path: &Path, extensions: &[&str]) -> bool {
+    path.extension().and_then(|extension| extension.to_str())
+        .is_some_and(|extension| extensions.iter().any(|image| image.eq_ignore_ascii_case(extension)))
+}
+
+pub fn is_image_path(path: &Path) -> bool {
+    has_extension(path, &RASTER_EXTENSIONS) || has_extension(path, &["svg"])
+}
+
+pub enum PreviewImage {
+    // The pixels are kept for laying the images over each other
+    Raster { handle: Handle, pixels: RgbaImage },
+    Vector(svg::Handle),
+}
+
+// None when the content is too large or doesn't decode
+pub fn load_preview(path: &Path, content: &[u8]) -> Option<PreviewImage> {
+    if content.len() > MAX_PREVIEW_SIZE {
+        return None;
+    }
+    if has_extension(path, &["svg"]) {
+        return Some(PreviewImage::Vector(svg::Handle::from_memory(content.to_vec())));
+    }
+    let mut decoded = image::load_from_memory(content).ok()?;
+    if decoded.width() > THUMBNAIL_SIZE || decoded.height() > THUMBNAIL_SIZE {
+        decoded = decoded.thumbnail(THUMBNAIL_SIZE, THUMBNAIL_SIZE);
+    }
+    let pixels = decoded.to_rgba8();
+    let handle = Handle::from_pixels(pixels.width(), pixels.height(), pixels.as_raw().clone());
+    Some(PreviewImage::Raster { handle, pixels })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageCompareMode {
+    SideBySide,
+    // The after image is uncovered from the left up to the slider
+    Swipe,
+    // The after image over the before one, as opaque as the slider says
+    OnionSkin,
+}
+
+impl ImageCompareMode {
+    pub const ALL: [ImageCompareMode; 3] = [ImageCompareMode::SideBySide, ImageCompareMode::Swipe, ImageCompareMode::OnionSkin];
+}
+
+impl fmt::Display for ImageCompareMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImageCompareMode::SideBySide => write!(f, "Side by side"),
+            ImageCompareMode::Swipe => write!(f, "Swipe"),
+            ImageCompareMode::OnionSkin => write!(f, "Onion skin"),
+        }
+    }
+}
+
+// A changed image as it was before and after, without a side where it doesn't exist
+pub struct ImagePreview {
+    pub path: String,
+    pub old: Option<PreviewImage>,
+    pub new: Option<PreviewImage>,
+    // The last image laid over, with the mode and slider in hundredths it was made for, since the view asks for it
+    // on every redraw
+    overlay: RefCell<Option<(ImageCompareMode, u32, Handle)>>,
+}
+
+fn blend(before: Rgba<u8>, after: Rgba<u8>, amount: f32) -> Rgba<u8> {
+    Rgba(std::array::from_fn(|i| (before[i] as f32 + (after[i] as f32 - before[i] as f32) * amount).round() as u8))
+}
+
+impl ImagePreview {
+    pub fn new(path: String, old: Option<PreviewImage>, new: Option<PreviewImage>) -> ImagePreview {
+        ImagePreview { path, old, new, overlay: RefCell::new(None) }
+    }
+
+    fn raster_pair(&self) -> Option<(&RgbaImage, &RgbaImage)> {
+        match (&self.old, &self.new) {
+            (Some(PreviewImage::Raster { pixels: before, .. }), Some(PreviewImage::Raster { pixels: after, .. })) => Some((before, after)),
+            _ => None,
+        }
+    }
+
+    // Only two raster images can be laid over each other
+    pub fn can_overlay(&self) -> bool {
+        self.raster_pair().is_some()
+    }
+
+    // Both images from their top left corner on one canvas as large as the larger of them, with `position` going
+    // from 0 to 1. None side by side, where there is nothing to lay over
+    pub fn overlay(&self, mode: ImageCompareMode, position: f32) -> Option<Handle> {
+        let (before, after) = self.raster_pair().filter(|_| mode != ImageCompareMode::SideBySide)?;
+        let hundredths = (position.clamp(0.0, 1.0) * 100.0).round() as u32;
+        if let Some((cached_mode, cached_hundredths, handle)) = &*self.overlay.borrow() {
+            if (*cached_mode, *cached_hundredths) == (mode, hundredths) {
+                return Some(handle.clone());
+            }
+        }
+
+        let (width, height) = (before.width().max(after.width()), before.height().max(after.height()));
+        let transparent = Rgba([0, 0, 0, 0]);
+        let pixel = |image: &RgbaImage, x, y| image.get_pixel_checked(x, y).copied().unwrap_or(transparent);
+        let amount = hundredths as f32 / 100.0;
+        let split = (width as f32 * amount).round() as u32;
+        let combined = RgbaImage::from_fn(width, height, |x, y| match mode {
+            ImageCompareMode::Swipe if x < split => pixel(after, x, y),
+            ImageCompareMode::OnionSkin => blend(pixel(before, x, y), pixel(after, x, y), amount),
+            _ => pixel(before, x, y),
+        });
+
+        let handle = Handle::from_pixels(width, height, combined.into_raw());
+        *self.overlay.borrow_mut() = Some((mode, hundredths, handle.clone()));
+        Some(handle)
+    }
+}
@@ -0,0 +1,305 @@
+use git2::build::CheckoutBuilder;
+use git2::{BranchType, Commit, ErrorCode, Oid, Reference, RebaseOptions, Repository, StatusOptions};
+use tracing::instrument;
+
+use crate::commitgraph::{CommitGraph, graph_descendant_of};
+use crate::config::get_signature;
+
+// What `git commit --fixup` and `--squash` put before the subject of the commit they are meant for
+const FIXUP_PREFIX: &str = "fixup! ";
+const SQUASH_PREFIX: &str = "squash! ";
+
+// How many commits back from HEAD fixups and the commits they are for are looked for
+const AUTOSQUASH_DEPTH: usize = 1000;
+
+// A checked out branch can only be moved when that loses nothing, since the working tree is updated to match.
+// Returns whether the branch is checked out
+fn check_can_move(repository: &Repository, reference: &Reference) -> Result<bool, git2::Error> {
+    let checked_out = repository.head().ok().and_then(|head| head.name().map(String::from)) == reference.name().map(String::from);
+    if checked_out {
+        let mut options = StatusOptions::new();
+        options.include_untracked(false);
+        if !repository.statuses(Some(&mut options))?.is_empty() {
+            return Err(git2::Error::from_str("the branch is checked out and has uncommitted changes"));
+        }
+    }
+    Ok(checked_out)
+}
+
+fn move_branch(repository: &Repository, reference: &Reference, id: Oid, checked_out: bool, reflog_message: &str) -> Result<(), git2::Error> {
+    repository.reference(reference.name().unwrap_or_default(), id, true, reflog_message)?;
+    if checked_out {
+        repository.checkout_head(Some(CheckoutBuilder::new().force()))?;
+    }
+    Ok(())
+}
+
+// Like `git rebase <onto> <branch>`, replayed in memory so a conflict leaves everything as it was
+#[instrument(skip(repository), err)]
+pub fn rebase_branch(repository: &Repository, branch: &str, onto: &str) -> Result<Oid, git2::Error> {
+    let reference = repository.find_branch(branch, BranchType::Local)?.into_reference();
+    let checked_out = check_can_move(repository, &reference)?;
+
+    let branch_commit = repository.reference_to_annotated_commit(&reference)?;
+    let onto_commit = repository.find_annotated_commit(Oid::from_str(onto)?)?;
+    let committer = get_signature(repository)?;
+
+    let mut options = RebaseOptions::new();
+    options.inmemory(true);
+    let mut rebase = repository.rebase(Some(&branch_commit), Some(&onto_commit), None, Some(&mut options))?;
+
+    let mut tip = onto_commit.id();
+    while let Some(operation) = rebase.next() {
+        let result = operation.and_then(|_| {
+            if rebase.inmemory_index()?.has_conflicts() {
+                return Err(git2::Error::from_str("rebasing would cause conflicts"));
+            }
+            rebase.commit(None, &committer, None)
+        });
+        match result {
+            Ok(id) => tip = id,
+            // The change is already upstream, so there is nothing to replay
+            Err(e) if e.code() == ErrorCode::Applied => (),
+            Err(e) => {
+                rebase.abort()?;
+                return Err(e);
+            },
+        }
+    }
+    rebase.finish(None)?;
+
+    move_branch(repository, &reference, tip, checked_out, &format!("rebase: {} onto {}", branch, onto))?;
+    Ok(tip)
+}
+
+// Like `git merge <source>` run on `target`, fast-forwarding when possible
+#[instrument(skip(repository), err)]
+pub fn merge_branch(repository: &Repository, source: &str, target: &str) -> Result<Oid, git2::Error> {
+    let reference = repository.find_branch(target, BranchType::Local)?.into_reference();
+    let ours = reference.peel_to_commit()?;
+    let theirs = repository.find_branch(source, BranchType::Local)?.into_reference().peel_to_commit()?;
+
+    let graph = CommitGraph::open(repository);
+    if ours.id() == theirs.id() || graph_descendant_of(repository, graph.as_ref(), ours.id(), theirs.id())? {
+        return Err(git2::Error::from_str("already up to date"));
+    }
+    let checked_out = check_can_move(repository, &reference)?;
+
+    let id = if graph_descendant_of(repository, graph.as_ref(), theirs.id(), ours.id())? {
+        theirs.id()
+    } else {
+        let mut index = repository.merge_commits(&ours, &theirs, None)?;
+        if index.has_conflicts() {
+            return Err(git2::Error::from_str("merging would cause conflicts"));
+        }
+        let tree = repository.find_tree(index.write_tree_to(repository)?)?;
+        let signature = get_signature(repository)?;
+        repository.commit(None, &signature, &signature, &format!("Merge branch '{}' into {}", source, target), &tree, &[&ours, &theirs])?
+    };
+
+    move_branch(repository, &reference, id, checked_out, &format!("merge {}", source))?;
+    Ok(id)
+}
+
+// Commits to replay, oldest first, on top of `base` to rewrite the checked out branch; what an interactive rebase todo list holds
+pub struct ReorderPlan {
+    pub branch: String,
+    pub base: Oid,
+    pub commits: Vec<Oid>,
+}
+
+// Plan for moving `commit` so it sits directly on top of `target`, both on the first parent history of the checked out branch
+#[instrument(skip(repository), err)]
+pub fn plan_commit_move(repository: &Repository, commit: &str, target: &str) -> Result<ReorderPlan, git2::Error> {
+    let head = repository.head()?;
+    if !head.is_branch() {
+        return Err(git2::Error::from_str("HEAD is detached, so there is no branch to reorder"));
+    }
+    let commit = Oid::from_str(commit)?;
+    let target = Oid::from_str(target)?;
+
+    // Newest first, back to whichever of the two commits is older
+    let mut history = Vec::new();
+    let mut current = head.peel_to_commit()?;
+    loop {
+        history.push(current.id());
+        if history.contains(&commit) && history.contains(&target) {
+            break
+        }
+        current = match current.parents().next() {
+            Some(parent) => parent,
+            None => return Err(git2::Error::from_str("both commits must be on the checked out branch")),
+        };
+    }
+    history.reverse();
+
+    let (base, commits) = if history[0] == target {
+        let mut commits = vec![commit];
+        commits.extend(history[1..].iter().filter(|id| **id != commit));
+        (target, commits)
+    } else {
+        let base = repository.find_commit(commit)?.parents().next()
+            .ok_or_else(|| git2::Error::from_str("the root commit can't be moved"))?
+            .id();
+        let mut commits: Vec<Oid> = history[1..].to_vec();
+        let position = commits.iter().position(|id| *id == target).map_or(commits.len(), |i| i + 1);
+        commits.insert(position, commit);
+        (base, commits)
+    };
+
+    if commits.len() + 1 == history.len() && commits[..] == history[1..] && base == history[0] {
+        return Err(git2::Error::from_str("the commit is already there"));
+    }
+
+    Ok(ReorderPlan { branch: head.shorthand().unwrap_or_default().to_string(), base, commits })
+}
+
+// Replays the plan in memory and only moves the branch once every commit applied cleanly
+#[instrument(skip_all, err)]
+pub fn apply_reorder_plan(repository: &Repository, plan: &ReorderPlan) -> Result<Oid, git2::Error> {
+    let reference = repository.find_branch(&plan.branch, BranchType::Local)?.into_reference();
+    let checked_out = check_can_move(repository, &reference)?;
+    let committer = get_signature(repository)?;
+
+    let mut tip = repository.find_commit(plan.base)?;
+    for id in &plan.commits {
+        let commit = repository.find_commit(*id)?;
+        if commit.parent_count() != 1 {
+            return Err(git2::Error::from_str("merge commits can't be reordered"));
+        }
+
+        let mut index = repository.cherrypick_commit(&commit, &tip, 0, None)?;
+        if index.has_conflicts() {
+            return Err(git2::Error::from_str(&format!("reordering would cause conflicts at {}", commit.summary().unwrap_or_default())));
+        }
+        let tree = repository.find_tree(index.write_tree_to(repository)?)?;
+        let id = repository.commit(None, &commit.author(), &committer, commit.message().unwrap_or_default(), &tree, &[&tip])?;
+        tip = repository.find_commit(id)?;
+    }
+
+    move_branch(repository, &reference, tip.id(), checked_out, &format!("rebase (reorder): {}", plan.branch))?;
+    Ok(tip.id())
+}
+
+pub fn fixup_message(repository: &Repository, target: &str, squash: bool) -> Result<String, git2::Error> {
+    let target = repository.find_commit(Oid::from_str(target)?)?;
+    Ok(format!("{}{}", if squash { SQUASH_PREFIX } else { FIXUP_PREFIX }, target.summary().unwrap_or_default()))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AutosquashStep {
+    Pick(Oid),
+    // Folded into the commit before it, keeping its message
+    Fixup(Oid),
+    // Folded into the commit before it, adding its own message after the subject to that commit's
+    Squash(Oid),
+}
+
+// What `git rebase -i --autosquash` would do with the checked out branch: fixups moved right after the commits they
+// are for, replayed from just before the oldest of those
+pub struct AutosquashPlan {
+    pub branch: String,
+    pub base: Oid,
+    pub steps: Vec<AutosquashStep>,
+}
+
+impl AutosquashPlan {
+    pub fn folded(&self) -> usize {
+        self.steps.iter().filter(|step| !matches!(step, AutosquashStep::Pick(_))).count()
+    }
+}
+
+// The subject a fixup is meant for, with any further prefixes of fixups of fixups taken off, and whether it squashes
+fn fixup_target(summary: &str) -> Option<(&str, bool)> {
+    let (mut subject, squash) = match (summary.strip_prefix(FIXUP_PREFIX), summary.strip_prefix(SQUASH_PREFIX)) {
+        (Some(subject), _) => (subject, false),
+        (_, Some(subject)) => (subject, true),
+        _ => return None,
+    };
+    while let Some(rest) = subject.strip_prefix(FIXUP_PREFIX).or_else(|| subject.strip_prefix(SQUASH_PREFIX)) {
+        subject = rest;
+    }
+    Some((subject, squash))
+}
+
+#[instrument(skip(repository), err)]
+pub fn plan_autosquash(repository: &Repository) -> Result<AutosquashPlan, git2::Error> {
+    let head = repository.head()?;
+    if !head.is_branch() {
+        return Err(git2::Error::from_str("HEAD is detached, so there is no branch to rewrite"));
+    }
+
+    // Oldest first, back to a merge, which can't be replayed
+    let mut history: Vec<Commit> = Vec::new();
+    let mut current = Some(head.peel_to_commit()?);
+    while let Some(commit) = current.filter(|commit| commit.parent_count() <= 1 && history.len() < AUTOSQUASH_DEPTH) {
+        current = commit.parents().next();
+        history.push(commit);
+    }
+    history.reverse();
+
+    // The fixups for each commit in the order they were made, against the oldest commit with the subject like git does
+    let mut fixups: Vec<Vec<AutosquashStep>> = vec![Vec::new(); history.len()];
+    let mut folded = vec![false; history.len()];
+    for (i, commit) in history.iter().enumerate() {
+        let Some((subject, squash)) = commit.summary().and_then(fixup_target) else {
+            continue;
+        };
+        if let Some(target) = history[..i].iter().position(|earlier| earlier.summary() == Some(subject)) {
+            fixups[target].push(if squash { AutosquashStep::Squash(commit.id()) } else { AutosquashStep::Fixup(commit.id()) });
+            folded[i] = true;
+        }
+    }
+
+    let first = fixups.iter().position(|fixups| !fixups.is_empty())
+        .ok_or_else(|| git2::Error::from_str("there are no fixup! or squash! commits for earlier commits of the branch"))?;
+    let base = history[first].parents().next()
+        .ok_or_else(|| git2::Error::from_str("fixups can't be folded into the root commit"))?
+        .id();
+    let mut steps = Vec::new();
+    for i in first..history.len() {
+        if !folded[i] {
+            steps.push(AutosquashStep::Pick(history[i].id()));
+            steps.append(&mut fixups[i]);
+        }
+    }
+
+    Ok(AutosquashPlan { branch: head.shorthand().unwrap_or_default().to_string(), base, steps })
+}
+
+// Replays the plan in memory like a reorder, folding each fixup into the commit just made by making it again
+#[instrument(skip_all, fields(branch = plan.branch), err)]
+pub fn apply_autosquash_plan(repository: &Repository, plan: &AutosquashPlan) -> Result<Oid, git2::Error> {
+    let reference = repository.find_branch(&plan.branch, BranchType::Local)?.into_reference();
+    let checked_out = check_can_move(repository, &reference)?;
+    let committer = get_signature(repository)?;
+
+    let mut tip = repository.find_commit(plan.base)?;
+    for step in &plan.steps {
+        let (AutosquashStep::Pick(id) | AutosquashStep::Fixup(id) | AutosquashStep::Squash(id)) = *step;
+        let commit = repository.find_commit(id)?;
+        let mut index = repository.cherrypick_commit(&commit, &tip, 0, None)?;
+        if index.has_conflicts() {
+            return Err(git2::Error::from_str(&format!("folding the fixups would cause conflicts at {}", commit.summary().unwrap_or_default())));
+        }
+        let tree = repository.find_tree(index.write_tree_to(repository)?)?;
+
+        let id = match step {
+            AutosquashStep::Pick(_) => repository.commit(None, &commit.author(), &committer, commit.message().unwrap_or_default(), &tree, &[&tip])?,
+            AutosquashStep::Fixup(_) | AutosquashStep::Squash(_) => {
+                let mut message = tip.message().unwrap_or_default().to_string();
+                let body = commit.message().unwrap_or_default().split_once('\n').map_or("", |(_, body)| body).trim();
+                if matches!(step, AutosquashStep::Squash(_)) && !body.is_empty() {
+                    message = format!("{}\n\n{}\n", message.trim_end(), body);
+                }
+                let parents: Vec<Commit> = tip.parents().collect();
+                let parents: Vec<&Commit> = parents.iter().collect();
+                repository.commit(None, &tip.author(), &committer, &message, &tree, &parents)?
+            },
+        };
+        tip = repository.find_commit(id)?;
+    }
+
+    move_branch(repository, &reference, tip.id(), checked_out, &format!("rebase (autosquash): {}", plan.branch))?;
+    Ok(tip.id())
+}
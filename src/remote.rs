@@ -0,0 +1,422 @@
+use std::cell::RefCell;
+use std::path::PathBuf;
+
+use git2::build::RepoBuilder;
+use git2::{BranchType, Config, Cred, CredentialType, Direction, ErrorCode, FetchOptions, Oid, PushOptions, Remote, RemoteCallbacks, Repository};
+use tracing::instrument;
+
+use crate::commitgraph::{CommitGraph, graph_ahead_behind};
+
+pub struct BranchTracking {
+    pub name: String,
+    // As `remote/branch`, or just the branch for a local upstream
+    pub upstream: Option<String>,
+    // Whether the upstream exists locally; a push target that was never pushed to doesn't yet
+    pub fetched: bool,
+    // Commits only on the branch and only on its upstream
+    pub ahead_behind: Option<(usize, usize)>,
+}
+
+pub fn get_branch_tracking(repository: &Repository) -> Result<Vec<BranchTracking>, git2::Error> {
+    let config = repository.config()?.snapshot()?;
+    let graph = CommitGraph::open(repository);
+
+    let mut branches = Vec::new();
+    for branch in repository.branches(Some(BranchType::Local))? {
+        let (branch, _) = branch?;
+        let name = match branch.name()? {
+            Some(name) => name.to_string(),
+            None => continue,
+        };
+
+        let remote = config.get_str(&format!("branch.{}.remote", name)).ok();
+        let merge = config.get_str(&format!("branch.{}.merge", name)).ok();
+        let upstream = match (remote, merge.map(|merge| merge.strip_prefix("refs/heads/").unwrap_or(merge))) {
+            (Some("."), Some(merge)) => Some(merge.to_string()),
+            (Some(remote), Some(merge)) => Some(format!("{}/{}", remote, merge)),
+            _ => None,
+        };
+
+        let upstream_branch = branch.upstream().ok();
+        let ahead_behind = match (branch.get().target(), upstream_branch.as_ref().and_then(|upstream| upstream.get().target())) {
+            (Some(local), Some(upstream)) => Some(graph_ahead_behind(repository, graph.as_ref(), local, upstream)?),
+            _ => None,
+        };
+
+        branches.push(BranchTracking { name, upstream, fetched: upstream_branch.is_some(), ahead_behind });
+    }
+    Ok(branches)
+}
+
+// Everything a branch can track: remote branches first, then local ones
+pub fn get_upstream_candidates(repository: &Repository) -> Result<Vec<String>, git2::Error> {
+    let mut candidates = Vec::new();
+    for kind in [BranchType::Remote, BranchType::Local] {
+        for branch in repository.branches(Some(kind))? {
+            let (branch, _) = branch?;
+            // The remote's HEAD only points at one of its other branches
+            if let Some(name) = branch.name()?.filter(|name| !name.ends_with("/HEAD")) {
+                candidates.push(name.to_string());
+            }
+        }
+    }
+    Ok(candidates)
+}
+
+pub fn get_remote_names(repository: &Repository) -> Result<Vec<String>, git2::Error> {
+    Ok(repository.remotes()?.iter().flatten().map(String::from).collect())
+}
+
+// Tracks an existing remote branch, or a local one, like `git branch --set-upstream-to`; None unsets it
+#[instrument(skip(repository), err)]
+pub fn set_upstream(repository: &Repository, branch: &str, upstream: Option<&str>) -> Result<(), git2::Error> {
+    repository.find_branch(branch, BranchType::Local)?.set_upstream(upstream)
+}
+
+// Makes the branch track a branch of the same name on the remote, which needn't exist until the first push
+#[instrument(skip(repository), err)]
+pub fn set_push_target(repository: &Repository, branch: &str, remote: &str) -> Result<(), git2::Error> {
+    repository.find_remote(remote)?;
+    let mut config = repository.config()?;
+    config.set_str(&format!("branch.{}.remote", branch), remote)?;
+    config.set_str(&format!("branch.{}.merge", branch), &format!("refs/heads/{}", branch))
+}
+
+// Credentials from the ssh agent or the configured credential helper, each tried once so a rejected one
+// fails the operation instead of being offered again forever
+pub fn remote_callbacks<'a>(repository: &Repository) -> Result<RemoteCallbacks<'a>, git2::Error> {
+    Ok(credential_callbacks(repository.config()?))
+}
+
+fn credential_callbacks<'a>(config: Config) -> RemoteCallbacks<'a> {
+    let mut tried = CredentialType::empty();
+
+    let mut callbacks = RemoteCallbacks::new();
+    callbacks.credentials(move |url, username, allowed| {
+        if allowed.contains(CredentialType::SSH_KEY) && !tried.contains(CredentialType::SSH_KEY) {
+            tried |= CredentialType::SSH_KEY;
+            return Cred::ssh_key_from_agent(username.unwrap_or("git"));
+        }
+        if allowed.contains(CredentialType::USER_PASS_PLAINTEXT) && !tried.contains(CredentialType::USER_PASS_PLAINTEXT) {
+            tried |= CredentialType::USER_PASS_PLAINTEXT;
+            return Cred::credential_helper(&config, url, username);
+        }
+        if allowed.contains(CredentialType::DEFAULT) && !tried.contains(CredentialType::DEFAULT) {
+            tried |= CredentialType::DEFAULT;
+            return Cred::default();
+        }
+        Err(git2::Error::from_str(&format!("no credentials were accepted for {}", url)))
+    });
+    callbacks
+}
+
+// Fetches every remote with its configured refspecs, returning the remote branches that moved or appeared. `progress`
+// is told the remote and the objects received of the total as they come in, and stops the fetch by returning false
+#[instrument(skip_all, err)]
+pub fn fetch_all(repository: &Repository, progress: &mut dyn FnMut(&str, usize, usize) -> bool) -> Result<Vec<String>, git2::Error> {
+    let mut updated = Vec::new();
+    for name in get_remote_names(repository)? {
+        let mut remote = repository.find_remote(&name)?;
+
+        let mut callbacks = remote_callbacks(repository)?;
+        callbacks.transfer_progress(|stats| progress(&name, stats.received_objects(), stats.total_objects()));
+        callbacks.update_tips(|reference, _, _| {
+            updated.push(reference.strip_prefix("refs/remotes/").unwrap_or(reference).to_string());
+            true
+        });
+        let mut options = FetchOptions::new();
+        options.remote_callbacks(callbacks);
+
+        remote.fetch(&[] as &[&str], Some(&mut options), None)?;
+    }
+    Ok(updated)
+}
+
+// What a remote fetches and pushes, from `remote.<name>.fetch` and `remote.<name>.push`
+pub struct RemoteRefspecs {
+    pub fetch: Vec<String>,
+    pub push: Vec<String>,
+}
+
+pub fn get_remote_refspecs(repository: &Repository, remote: &str) -> Result<RemoteRefspecs, git2::Error> {
+    let remote = repository.find_remote(remote)?;
+    Ok(RemoteRefspecs {
+        fetch: remote.fetch_refspecs()?.iter().flatten().map(String::from).collect(),
+        push: remote.push_refspecs()?.iter().flatten().map(String::from).collect(),
+    })
+}
+
+// Catches the mistakes libgit2 would only report halfway through replacing them, like a pattern on one side only
+fn check_refspec(refspec: &str) -> Result<(), git2::Error> {
+    let (source, destination) = refspec.strip_prefix('+').unwrap_or(refspec).split_once(':').unwrap_or((refspec, ""));
+    if source.is_empty() && destination.is_empty() || refspec.contains(char::is_whitespace) {
+        return Err(git2::Error::from_str(&format!("{} is not a refspec", refspec)));
+    }
+    if !destination.is_empty() && source.matches('*').count() != destination.matches('*').count() {
+        return Err(git2::Error::from_str(&format!("{} needs a * on both sides or neither", refspec)));
+    }
+    Ok(())
+}
+
+// Replaces the remote's refspecs in the repository's config. Ones set in the global config are left alone, and
+// still apply
+#[instrument(skip(repository, refspecs), err)]
+pub fn set_remote_refspecs(repository: &Repository, remote: &str, refspecs: &RemoteRefspecs) -> Result<(), git2::Error> {
+    repository.find_remote(remote)?;
+    for refspec in refspecs.fetch.iter().chain(&refspecs.push) {
+        check_refspec(refspec)?;
+    }
+
+    let mut config = repository.config()?;
+    for (direction, refspecs) in [(Direction::Fetch, &refspecs.fetch), (Direction::Push, &refspecs.push)] {
+        let key = format!("remote.{}.{}", remote, if direction == Direction::Fetch { "fetch" } else { "push" });
+        match config.remove_multivar(&key, ".*") {
+            Err(e) if e.code() != ErrorCode::NotFound => return Err(e),
+            _ => {},
+        }
+        for refspec in refspecs {
+            match direction {
+                Direction::Fetch => repository.remote_add_fetch(remote, refspec)?,
+                Direction::Push => repository.remote_add_push(remote, refspec)?,
+            }
+        }
+    }
+    Ok(())
+}
+
+// How a reference on a remote compares to the one mirroring it here
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RemoteReferenceState {
+    // Nothing mirrors it here yet
+    New,
+    UpToDate,
+    // Commits it has that the local one doesn't, counted when they were fetched before under another name
+    Ahead(Option<usize>),
+    Behind(usize),
+    Diverged(usize, usize),
+}
+
+// A reference as the remote advertises it, listed without fetching anything
+#[derive(Debug, Clone)]
+pub struct RemoteReference {
+    pub name: String,
+    pub id: Oid,
+    // Where the remote's fetch refspecs put it here, tags going to tags of the same name
+    pub local: Option<String>,
+    pub state: RemoteReferenceState,
+}
+
+// References the refspecs leave out, like pull requests, go under `refs/remotes/<remote>/`. A URL has no
+// refspecs or name, so only its tags have a place here
+fn mirrored_name(remote: &Remote, name: &str) -> Option<String> {
+    if name.starts_with("refs/tags/") {
+        return Some(name.to_string());
+    }
+    remote.refspecs()
+        .filter(|refspec| refspec.direction() == Direction::Fetch && refspec.src_matches(name))
+        .find_map(|refspec| refspec.transform(name).ok()?.as_str().map(str::to_string))
+        .or_else(|| Some(format!("refs/remotes/{}/{}", remote.name()?, name.strip_prefix("refs/").unwrap_or(name))))
+}
+
+// Lists the references of a configured remote or of a URL, like `git ls-remote`. Peeled tags and the remote's HEAD
+// are left out, since they only repeat other references
+#[instrument(skip(repository), err)]
+pub fn list_remote_references(repository: &Repository, remote: &str) -> Result<Vec<RemoteReference>, git2::Error> {
+    let mut remote = repository.find_remote(remote).or_else(|_| repository.remote_anonymous(remote))?;
+    let advertised: Vec<(String, Oid)> = {
+        let connection = remote.connect_auth(Direction::Fetch, Some(remote_callbacks(repository)?), None)?;
+        connection.list()?.iter()
+            .filter(|head| head.name() != "HEAD" && !head.name().ends_with("^{}"))
+            .map(|head| (head.name().to_string(), head.oid()))
+            .collect()
+    };
+
+    let graph = CommitGraph::open(repository);
+    let mut references = Vec::new();
+    for (name, id) in advertised {
+        let local = mirrored_name(&remote, &name);
+        let state = match local.as_deref().and_then(|local| repository.refname_to_id(local).ok()) {
+            None => RemoteReferenceState::New,
+            Some(local) if local == id => RemoteReferenceState::UpToDate,
+            // Counting needs the remote's commit, which isn't here when it was never fetched
+            Some(_) if repository.find_object(id, None).is_err() => RemoteReferenceState::Ahead(None),
+            Some(local) => match graph_ahead_behind(repository, graph.as_ref(), id, local)? {
+                (ahead, 0) => RemoteReferenceState::Ahead(Some(ahead)),
+                (0, behind) => RemoteReferenceState::Behind(behind),
+                (ahead, behind) => RemoteReferenceState::Diverged(ahead, behind),
+            },
+        };
+        references.push(RemoteReference { name, id, local, state });
+    }
+    Ok(references)
+}
+
+// The refspec fetching a listed reference into its mirror. Tags aren't forced, so one that was moved on the remote
+// fails instead of replacing the one here
+pub fn fetch_refspec(reference: &RemoteReference) -> Option<String> {
+    let local = reference.local.as_ref()?;
+    match reference.name.starts_with("refs/tags/") {
+        true => Some(format!("{}:{}", reference.name, local)),
+        false => Some(format!("+{}:{}", reference.name, local)),
+    }
+}
+
+// Fetches only `refspecs` from the remote, leaving the configured ones out, and returns the references that moved or
+// appeared. Returning false from `progress` stops the fetch
+#[instrument(skip(repository, progress), err)]
+pub fn fetch_refspecs(repository: &Repository, remote: &str, refspecs: &[String], progress: &mut dyn FnMut(usize, usize) -> bool) -> Result<Vec<String>, git2::Error> {
+    let mut remote = repository.find_remote(remote)?;
+
+    let mut updated = Vec::new();
+    let mut callbacks = remote_callbacks(repository)?;
+    callbacks.transfer_progress(|stats| progress(stats.received_objects(), stats.total_objects()));
+    callbacks.update_tips(|reference, _, _| {
+        updated.push(reference.to_string());
+        true
+    });
+    let mut options = FetchOptions::new();
+    options.remote_callbacks(callbacks);
+
+    remote.fetch(refspecs, Some(&mut options), None)?;
+    drop(options);
+    Ok(updated)
+}
+
+// Where pushing a branch goes, from its `branch.<name>.remote` and `branch.<name>.merge` config
+struct PushTarget {
+    remote: String,
+    // Reference on the remote and the remote-tracking branch mirroring it here
+    remote_reference: String,
+    tracking: String,
+}
+
+fn get_push_target(repository: &Repository, branch: &str) -> Result<PushTarget, git2::Error> {
+    let config = repository.config()?.snapshot()?;
+    let remote = config.get_str(&format!("branch.{}.remote", branch)).ok();
+    let merge = config.get_str(&format!("branch.{}.merge", branch)).ok();
+    match (remote, merge) {
+        (Some("."), _) => Err(git2::Error::from_str(&format!("{} tracks a local branch, so there is nowhere to push it", branch))),
+        (Some(remote), Some(merge)) => Ok(PushTarget {
+            remote: remote.to_string(),
+            remote_reference: merge.to_string(),
+            tracking: format!("refs/remotes/{}/{}", remote, merge.strip_prefix("refs/heads/").unwrap_or(merge)),
+        }),
+        _ => Err(git2::Error::from_str(&format!("{} has no upstream or push target, set one under Upstreams first", branch))),
+    }
+}
+
+pub enum PushCheck {
+    UpToDate,
+    // Includes branches that were never pushed
+    FastForward,
+    // Commits only on the branch and only on its last fetched upstream
+    Diverged { upstream: String, ahead: usize, behind: usize },
+}
+
+// Compares the branch with the remote-tracking branch of its push target, as of the last fetch
+#[instrument(skip(repository), err)]
+pub fn check_push(repository: &Repository, branch: &str) -> Result<PushCheck, git2::Error> {
+    let target = get_push_target(repository, branch)?;
+    let local = repository.refname_to_id(&format!("refs/heads/{}", branch))?;
+    let remote = match repository.refname_to_id(&target.tracking) {
+        Ok(remote) => remote,
+        Err(_) => return Ok(PushCheck::FastForward),
+    };
+
+    let (ahead, behind) = graph_ahead_behind(repository, CommitGraph::open(repository).as_ref(), local, remote)?;
+    Ok(match (ahead, behind) {
+        (0, 0) => PushCheck::UpToDate,
+        (_, 0) => PushCheck::FastForward,
+        (ahead, behind) => PushCheck::Diverged {
+            upstream: target.tracking.strip_prefix("refs/remotes/").unwrap_or(&target.tracking).to_string(),
+            ahead,
+            behind,
+        },
+    })
+}
+
+// Id of the remote-tracking branch the branch pushes to, for rebasing onto it
+pub fn get_push_upstream(repository: &Repository, branch: &str) -> Result<Oid, git2::Error> {
+    repository.refname_to_id(&get_push_target(repository, branch)?.tracking)
+}
+
+// Pushes the branch to its push target. Forcing is done with a lease, like `--force-with-lease`: the push is
+// only made while the remote branch is still where it was at the last fetch, so nothing unseen is overwritten.
+// `progress` is told the objects sent of the total. `cancelled` is only asked before anything is sent: libgit2 can't
+// stop a pack halfway, and once it is sent the remote updates its branch whatever happens on this end
+#[instrument(skip(repository, progress, cancelled), err)]
+pub fn push_branch(repository: &Repository, branch: &str, force: bool, progress: &mut dyn FnMut(usize, usize), cancelled: &dyn Fn() -> bool) -> Result<(), git2::Error> {
+    let target = get_push_target(repository, branch)?;
+    let mut remote = repository.find_remote(&target.remote)?;
+    let expected = repository.refname_to_id(&target.tracking).ok();
+
+    // libgit2 replaces errors returned from the callbacks with its own, so the reasons are kept here
+    let rejection = RefCell::new(None);
+    let mut callbacks = remote_callbacks(repository)?;
+    callbacks.push_negotiation(|updates| {
+        if cancelled() {
+            *rejection.borrow_mut() = Some(String::from("pushing was cancelled"));
+            return Err(git2::Error::from_str("pushing was cancelled"));
+        }
+        for update in updates.iter().filter(|update| force && update.dst_refname() == Some(target.remote_reference.as_str())) {
+            // A zero id stands for a branch the remote doesn't have
+            let current = Some(update.src()).filter(|id| !id.is_zero());
+            if current != expected {
+                let message = "the remote branch moved since it was last fetched, fetch and look at it before forcing";
+                *rejection.borrow_mut() = Some(String::from(message));
+                return Err(git2::Error::from_str(message));
+            }
+        }
+        Ok(())
+    });
+    callbacks.push_transfer_progress(|sent, total, _| progress(sent, total));
+    callbacks.push_update_reference(|reference, status| {
+        if let Some(status) = status {
+            *rejection.borrow_mut() = Some(format!("the remote rejected {}: {}", reference, status));
+        }
+        Ok(())
+    });
+
+    let mut options = PushOptions::new();
+    options.remote_callbacks(callbacks);
+    let refspec = format!("{}refs/heads/{}:{}", if force { "+" } else { "" }, branch, target.remote_reference);
+    let result = remote.push(&[refspec.as_str()], Some(&mut options));
+    drop(options);
+
+    match (rejection.into_inner(), result) {
+        (Some(rejection), _) => Err(git2::Error::from_str(&rejection)),
+        (None, result) => result,
+    }
+}
+
+pub struct CloneOptions {
+    pub url: String,
+    pub destination: PathBuf,
+    // Only this many commits of history, for a shallow clone
+    pub depth: Option<u32>,
+    // Clone and track only this branch, like `--single-branch --branch`
+    pub branch: Option<String>,
+}
+
+// Clones with `progress` told the objects received and the total; returning false from it cancels the clone
+#[instrument(skip_all, fields(url = options.url), err)]
+pub fn clone_repository(options: &CloneOptions, progress: &mut dyn FnMut(usize, usize) -> bool) -> Result<Repository, git2::Error> {
+    let mut callbacks = credential_callbacks(Config::open_default()?);
+    callbacks.transfer_progress(|stats| progress(stats.received_objects(), stats.total_objects()));
+
+    let mut fetch = FetchOptions::new();
+    fetch.remote_callbacks(callbacks);
+    if let Some(depth) = options.depth {
+        fetch.depth(i32::try_from(depth).unwrap_or(i32::MAX));
+    }
+
+    let mut builder = RepoBuilder::new();
+    builder.fetch_options(fetch);
+    if let Some(branch) = &options.branch {
+        builder.branch(branch);
+        builder.remote_create(move |repository, name, url| {
+            repository.remote_with_fetch(name, url, &format!("+refs/heads/{0}:refs/remotes/{1}/{0}", branch, name))
+        });
+    }
+    builder.clone(&options.url, &options.destination)
+}
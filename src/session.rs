@@ -0,0 +1,74 @@
+use std::fs;
+use std::path::PathBuf;
+
+use crate::error::Error;
+use crate::settings::settings_directory;
+
+// How a tab was left: its repository, where the graph was scrolled and zoomed to, and the selected commit
+pub struct TabSession {
+    pub path: PathBuf,
+    pub zoom: f32,
+    pub offset: (f32, f32),
+    pub selected: Option<String>,
+}
+
+#[derive(Default)]
+pub struct Session {
+    pub tabs: Vec<TabSession>,
+    pub active: usize,
+}
+
+impl Session {
+    // Kept next to the settings. Each `repository` line starts a tab, and the lines after it describe that tab
+    pub fn load() -> Session {
+        let mut session = Session::default();
+        let contents = match settings_directory().and_then(|directory| fs::read_to_string(directory.join("session")).ok()) {
+            Some(contents) => contents,
+            None => return session,
+        };
+
+        for line in contents.lines() {
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => continue,
+            };
+            if key == "repository" {
+                session.tabs.push(TabSession { path: PathBuf::from(value), zoom: 1.0, offset: (0.0, 0.0), selected: None });
+                continue;
+            }
+            if key == "active" {
+                session.active = value.parse().unwrap_or(0);
+                continue;
+            }
+
+            let tab = match session.tabs.last_mut() {
+                Some(tab) => tab,
+                None => continue,
+            };
+            match key {
+                "zoom" => tab.zoom = value.parse().unwrap_or(1.0),
+                "offset" => if let Some((x, y)) = value.split_once(',').and_then(|(x, y)| Some((x.trim().parse().ok()?, y.trim().parse().ok()?))) {
+                    tab.offset = (x, y);
+                },
+                "selected" => tab.selected = Some(value.to_string()),
+                _ => (),
+            }
+        }
+        session
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        let directory = settings_directory().ok_or(Error::from("there is no config directory to keep the session in"))?;
+        fs::create_dir_all(&directory)?;
+
+        let mut contents = format!("active = {}\n", self.active);
+        for tab in &self.tabs {
+            contents.push_str(&format!("repository = {}\nzoom = {}\noffset = {},{}\n", tab.path.display(), tab.zoom, tab.offset.0, tab.offset.1));
+            if let Some(selected) = &tab.selected {
+                contents.push_str(&format!("selected = {}\n", selected));
+            }
+        }
+        fs::write(directory.join("session"), contents)?;
+        Ok(())
+    }
+}
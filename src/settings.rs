@@ -0,0 +1,283 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::backend::GraphSize;
+use crate::error::Error;
+use crate::keymap::{Action, KeyChord, Keymap};
+
+// Multiplies the size of everything in the window, text, paddings and the graph alike. It comes on top of the scale
+// the system sets for the monitor, and the larger steps are for high-DPI screens where the system doesn't set one
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UiScale(pub u32);
+
+impl UiScale {
+    pub const ALL: [UiScale; 8] = [UiScale(75), UiScale(100), UiScale(125), UiScale(150), UiScale(175), UiScale(200), UiScale(250), UiScale(300)];
+
+    pub fn factor(self) -> f64 {
+        self.0 as f64 / 100.0
+    }
+}
+
+impl Default for UiScale {
+    fn default() -> Self {
+        UiScale(100)
+    }
+}
+
+impl fmt::Display for UiScale {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}%", self.0)
+    }
+}
+
+// Smallest and largest values the graph size settings take, also the ends of their sliders
+pub const NODE_RADIUS_RANGE: (f32, f32) = (10.0, 80.0);
+pub const COLUMN_SPACING_RANGE: (f32, f32) = (2.0, 5.0);
+pub const ROW_SPACING_RANGE: (f32, f32) = (1.0, 4.0);
+pub const TEXT_SCALE_RANGE: (f32, f32) = (0.5, 2.0);
+
+// Where editors connect to when the control server is turned on in the settings, unless the file names another port
+pub const DEFAULT_CONTROL_PORT: u16 = 47150;
+
+// What clicking a commit node in a way other than a plain click does
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeClickAction {
+    Nothing,
+    Select,
+    OpenDetails,
+    CopyId,
+    Checkout,
+}
+
+impl NodeClickAction {
+    pub const ALL: [NodeClickAction; 5] = [NodeClickAction::Nothing, NodeClickAction::Select, NodeClickAction::OpenDetails, NodeClickAction::CopyId, NodeClickAction::Checkout];
+
+    fn name(self) -> &'static str {
+        match self {
+            NodeClickAction::Nothing => "none",
+            NodeClickAction::Select => "select",
+            NodeClickAction::OpenDetails => "details",
+            NodeClickAction::CopyId => "copy_id",
+            NodeClickAction::Checkout => "checkout",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<NodeClickAction> {
+        NodeClickAction::ALL.into_iter().find(|action| action.name() == name)
+    }
+}
+
+impl fmt::Display for NodeClickAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            NodeClickAction::Nothing => write!(f, "Do nothing"),
+            NodeClickAction::Select => write!(f, "Select"),
+            NodeClickAction::OpenDetails => write!(f, "Open details"),
+            NodeClickAction::CopyId => write!(f, "Copy commit ID"),
+            NodeClickAction::Checkout => write!(f, "Check out"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BookmarkColor {
+    Red,
+    Orange,
+    Yellow,
+    Green,
+    Blue,
+    Purple,
+}
+
+impl BookmarkColor {
+    pub const ALL: [BookmarkColor; 6] = [BookmarkColor::Red, BookmarkColor::Orange, BookmarkColor::Yellow, BookmarkColor::Green, BookmarkColor::Blue, BookmarkColor::Purple];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            BookmarkColor::Red => "red",
+            BookmarkColor::Orange => "orange",
+            BookmarkColor::Yellow => "yellow",
+            BookmarkColor::Green => "green",
+            BookmarkColor::Blue => "blue",
+            BookmarkColor::Purple => "purple",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<BookmarkColor> {
+        BookmarkColor::ALL.into_iter().find(|color| color.name() == name)
+    }
+}
+
+impl fmt::Display for BookmarkColor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BookmarkColor::Red => write!(f, "Red"),
+            BookmarkColor::Orange => write!(f, "Orange"),
+            BookmarkColor::Yellow => write!(f, "Yellow"),
+            BookmarkColor::Green => write!(f, "Green"),
+            BookmarkColor::Blue => write!(f, "Blue"),
+            BookmarkColor::Purple => write!(f, "Purple"),
+        }
+    }
+}
+
+// A commit the user marked, in the repository with the git directory at `repository`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bookmark {
+    pub repository: PathBuf,
+    pub commit: String,
+    pub label: String,
+    pub color: BookmarkColor,
+}
+
+// Preferences of the user rather than of a repository, so they live in a file of their own instead of git config
+pub struct Settings {
+    pub ui_scale: UiScale,
+    pub high_contrast: bool,
+    pub keymap: Keymap,
+    pub double_click: NodeClickAction,
+    pub middle_click: NodeClickAction,
+    pub graph_size: GraphSize,
+    pub bookmarks: Vec<Bookmark>,
+    // Whether the viewport, filters and bookmarks of a repository are kept in its git directory instead
+    pub repository_state: bool,
+    // Frame rate, draw and layout times over the graph, for reporting slow repositories
+    pub performance_overlay: bool,
+    // Localhost port other tools can send commands to, None while that is off
+    pub control_port: Option<u16>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            ui_scale: Default::default(),
+            high_contrast: false,
+            keymap: Default::default(),
+            double_click: NodeClickAction::OpenDetails,
+            middle_click: NodeClickAction::CopyId,
+            graph_size: Default::default(),
+            bookmarks: Vec::new(),
+            repository_state: false,
+            performance_overlay: false,
+            control_port: None,
+        }
+    }
+}
+
+// `$XDG_CONFIG_HOME/gitui/settings`, falling back to `~/.config`, or `%APPDATA%\gitui\settings` on Windows
+pub fn settings_directory() -> Option<PathBuf> {
+    let base = if cfg!(windows) {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME").map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+    };
+    base.map(|base| base.join("gitui"))
+}
+
+impl Settings {
+    // Missing files and lines that don't parse leave the defaults, so a broken file never keeps the app from starting
+    pub fn load() -> Settings {
+        let mut settings = Settings::default();
+        let contents = match settings_directory().and_then(|directory| fs::read_to_string(directory.join("settings")).ok()) {
+            Some(contents) => contents,
+            None => return settings,
+        };
+
+        for line in contents.lines().filter(|line| !line.trim_start().starts_with('#')) {
+            let (key, value) = match line.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => continue,
+            };
+            match key {
+                "ui_scale" => if let Ok(percent) = value.parse::<u32>() {
+                    settings.ui_scale = UiScale(percent.clamp(50, 300));
+                },
+                "high_contrast" => if let Ok(high_contrast) = value.parse::<bool>() {
+                    settings.high_contrast = high_contrast;
+                },
+                "repository_state" => if let Ok(repository_state) = value.parse::<bool>() {
+                    settings.repository_state = repository_state;
+                },
+                "performance_overlay" => if let Ok(performance_overlay) = value.parse::<bool>() {
+                    settings.performance_overlay = performance_overlay;
+                },
+                "control_port" => settings.control_port = value.parse::<u16>().ok(),
+                "double_click" => if let Some(action) = NodeClickAction::from_name(value) {
+                    settings.double_click = action;
+                },
+                "middle_click" => if let Some(action) = NodeClickAction::from_name(value) {
+                    settings.middle_click = action;
+                },
+                "node_radius" => if let Ok(radius) = value.parse::<f32>() {
+                    settings.graph_size.node_radius = radius.clamp(NODE_RADIUS_RANGE.0, NODE_RADIUS_RANGE.1);
+                },
+                "column_spacing" => if let Ok(spacing) = value.parse::<f32>() {
+                    settings.graph_size.column_spacing = spacing.clamp(COLUMN_SPACING_RANGE.0, COLUMN_SPACING_RANGE.1);
+                },
+                "row_spacing" => if let Ok(spacing) = value.parse::<f32>() {
+                    settings.graph_size.row_spacing = spacing.clamp(ROW_SPACING_RANGE.0, ROW_SPACING_RANGE.1);
+                },
+                "text_scale" => if let Ok(scale) = value.parse::<f32>() {
+                    settings.graph_size.text_scale = scale.clamp(TEXT_SCALE_RANGE.0, TEXT_SCALE_RANGE.1);
+                },
+                // The fields are split by tabs, which neither paths nor labels have
+                "bookmark" => {
+                    let fields: Vec<&str> = value.splitn(4, '\t').collect();
+                    if let [repository, commit, color, label] = fields[..] {
+                        if let Some(color) = BookmarkColor::from_name(color) {
+                            settings.bookmarks.push(Bookmark { repository: PathBuf::from(repository), commit: commit.to_string(), label: label.to_string(), color });
+                        }
+                    }
+                },
+                key => if let Some(action) = key.strip_prefix("key.").and_then(Action::from_name) {
+                    // `none` leaves the action without a shortcut
+                    if value == "none" {
+                        settings.keymap.set(action, None);
+                    } else if let Some(chord) = KeyChord::parse(value) {
+                        settings.keymap.set(action, Some(chord));
+                    }
+                },
+            }
+        }
+        settings
+    }
+
+    pub fn save(&self) -> Result<(), Error> {
+        let directory = settings_directory().ok_or(Error::from("there is no config directory to keep the settings in"))?;
+        fs::create_dir_all(&directory)?;
+
+        let mut contents = format!("ui_scale = {}\nhigh_contrast = {}\nrepository_state = {}\nperformance_overlay = {}\ncontrol_port = {}\ndouble_click = {}\nmiddle_click = {}\n",
+            self.ui_scale.0, self.high_contrast, self.repository_state, self.performance_overlay,
+            self.control_port.map(|port| port.to_string()).unwrap_or(String::from("off")), self.double_click.name(), self.middle_click.name());
+        let size = &self.graph_size;
+        contents.push_str(&format!("node_radius = {}\ncolumn_spacing = {}\nrow_spacing = {}\ntext_scale = {}\n",
+            size.node_radius, size.column_spacing, size.row_spacing, size.text_scale));
+        for (action, chord) in self.keymap.bindings() {
+            let chord = chord.map(|chord| chord.to_string()).unwrap_or(String::from("none"));
+            contents.push_str(&format!("key.{} = {}\n", action.name(), chord));
+        }
+        for bookmark in &self.bookmarks {
+            contents.push_str(&format!("bookmark = {}\t{}\t{}\t{}\n", bookmark.repository.display(), bookmark.commit, bookmark.color.name(), bookmark.label));
+        }
+        fs::write(directory.join("settings"), contents)?;
+        Ok(())
+    }
+
+    pub fn bookmarks_for(&self, repository: &Path) -> Vec<Bookmark> {
+        self.bookmarks.iter().filter(|bookmark| bookmark.repository == repository).cloned().collect()
+    }
+
+    // A commit has one bookmark, so marking it again replaces the label and color
+    pub fn set_bookmark(&mut self, bookmark: Bookmark) {
+        match self.bookmarks.iter_mut().find(|existing| existing.repository == bookmark.repository && existing.commit == bookmark.commit) {
+            Some(existing) => *existing = bookmark,
+            None => self.bookmarks.push(bookmark),
+        }
+    }
+
+    pub fn remove_bookmark(&mut self, repository: &Path, commit: &str) {
+        self.bookmarks.retain(|bookmark| bookmark.repository != repository || bookmark.commit != commit);
+    }
+}
@@ -0,0 +1,270 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use git2::{Config, Oid, Repository};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SignatureStatus {
+    // Signed, but the signature has not been checked yet
+    Unchecked,
+    Verified(String),
+    // A good signature that still isn't vouched for, like git's `U`: the key isn't trusted, has expired or was revoked,
+    // or isn't in the allowed signers. Holds the signer and why
+    Untrusted(String),
+    Unverified(String),
+}
+
+// How a commit is signed, which picks the program signing and verifying go through
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SignatureFormat {
+    OpenPgp,
+    X509,
+    Ssh,
+}
+
+impl SignatureFormat {
+    // From `gpg.format`, for signing
+    fn configured(config: &Config) -> SignatureFormat {
+        match config.get_string("gpg.format").as_deref() {
+            Ok("ssh") => SignatureFormat::Ssh,
+            Ok("x509") => SignatureFormat::X509,
+            _ => SignatureFormat::OpenPgp,
+        }
+    }
+
+    // From the armor the signature starts with, for verifying, the way git tells them apart
+    fn of_signature(signature: &[u8]) -> Option<SignatureFormat> {
+        if signature.starts_with(b"-----BEGIN PGP SIGNATURE-----") || signature.starts_with(b"-----BEGIN PGP MESSAGE-----") {
+            Some(SignatureFormat::OpenPgp)
+        } else if signature.starts_with(b"-----BEGIN SIGNED MESSAGE-----") {
+            Some(SignatureFormat::X509)
+        } else if signature.starts_with(b"-----BEGIN SSH SIGNATURE-----") {
+            Some(SignatureFormat::Ssh)
+        } else {
+            None
+        }
+    }
+
+    // `gpg.<format>.program`, with `gpg.program` still honoured for OpenPGP like git does
+    fn program(self, config: &Config) -> String {
+        match self {
+            SignatureFormat::OpenPgp => config.get_string("gpg.openpgp.program").or_else(|_| config.get_string("gpg.program")).unwrap_or_else(|_| String::from("gpg")),
+            SignatureFormat::X509 => config.get_string("gpg.x509.program").unwrap_or_else(|_| String::from("gpgsm")),
+            SignatureFormat::Ssh => config.get_string("gpg.ssh.program").unwrap_or_else(|_| String::from("ssh-keygen")),
+        }
+    }
+}
+
+// The temp directory is shared with other users, so the name can't be guessed and the file has to be a new one,
+// rather than a link someone put there to have another file written over
+fn write_temp_file(name: &str, contents: &[u8]) -> Result<PathBuf, git2::Error> {
+    let path = std::env::temp_dir().join(format!("gitui-{}-{:016x}-{}", std::process::id(), rand::random::<u64>(), name));
+    fs::OpenOptions::new().write(true).create_new(true).open(&path)
+        .and_then(|mut file| file.write_all(contents))
+        .map_err(|e| git2::Error::from_str(&e.to_string()))?;
+    Ok(path)
+}
+
+fn run_with_input(command: &mut Command, input: &[u8]) -> Result<std::process::Output, String> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| e.to_string())?;
+    child.stdin.take().unwrap().write_all(input).map_err(|e| e.to_string())?;
+    child.wait_with_output().map_err(|e| e.to_string())
+}
+
+fn last_line(output: &[u8]) -> String {
+    String::from_utf8_lossy(output).lines().last().unwrap_or_default().to_string()
+}
+
+// Reads gpg's or gpgsm's `--status-fd` output. Only a good, valid signature from a fully or ultimately trusted key is
+// verified; a good signature otherwise is only untrusted, and None is left when there is no good signature at all
+fn parse_gpg_status(status: &str) -> Option<SignatureStatus> {
+    let mut signer = None;
+    let mut problem = None;
+    let mut valid = false;
+    let mut trusted = false;
+    for line in status.lines() {
+        let Some(line) = line.strip_prefix("[GNUPG:] ") else { continue };
+        let (keyword, rest) = line.split_once(' ').unwrap_or((line, ""));
+        // The key id comes first, followed by the user id
+        let user = || rest.split_once(' ').map(|(_, user)| user).unwrap_or(rest).to_string();
+        match keyword {
+            "GOODSIG" => signer = Some(user()),
+            "EXPSIG" => (signer, problem) = (Some(user()), Some("the signature has expired")),
+            "EXPKEYSIG" => (signer, problem) = (Some(user()), Some("the key has expired")),
+            "REVKEYSIG" => (signer, problem) = (Some(user()), Some("the key was revoked")),
+            "BADSIG" => return Some(SignatureStatus::Unverified(format!("bad signature from {}", user()))),
+            "VALIDSIG" => valid = true,
+            "TRUST_FULLY" | "TRUST_ULTIMATE" => trusted = true,
+            _ => (),
+        }
+    }
+
+    let signer = signer?;
+    match problem {
+        Some(problem) => Some(SignatureStatus::Untrusted(format!("{}, but {}", signer, problem))),
+        None if valid && trusted => Some(SignatureStatus::Verified(signer)),
+        None if valid => Some(SignatureStatus::Untrusted(format!("{}, but the key is not trusted", signer))),
+        None => None,
+    }
+}
+
+fn verify_gpg(program: &str, signature: &Path, data: &[u8]) -> SignatureStatus {
+    let output = match run_with_input(Command::new(program).arg("--status-fd=1").arg("--verify").arg(signature).arg("-"), data) {
+        Ok(output) => output,
+        Err(e) => return SignatureStatus::Unverified(format!("could not run {}: {}", program, e)),
+    };
+
+    parse_gpg_status(&String::from_utf8_lossy(&output.stdout)).unwrap_or_else(|| SignatureStatus::Unverified(last_line(&output.stderr)))
+}
+
+// Without a principal in the allowed signers to check against, the signature can still be good, which git shows as
+// `U` like an untrusted gpg key
+fn check_ssh_without_signer(program: &str, signature: &Path, data: &[u8], why: &str) -> SignatureStatus {
+    let mut command = Command::new(program);
+    command.arg("-Y").arg("check-novalidate").arg("-n").arg("git").arg("-s").arg(signature);
+    match run_with_input(&mut command, data) {
+        Ok(output) if output.status.success() => SignatureStatus::Untrusted(format!("good signature, but {}", why)),
+        Ok(output) => SignatureStatus::Unverified(last_line(&output.stderr)),
+        Err(e) => SignatureStatus::Unverified(format!("could not run {}: {}", program, e)),
+    }
+}
+
+fn verify_ssh(program: &str, repository: &Repository, signature: &Path, data: &[u8]) -> SignatureStatus {
+    let allowed_signers = match repository.config().and_then(|c| c.get_path("gpg.ssh.allowedSignersFile")) {
+        Ok(path) => path,
+        Err(_) => return check_ssh_without_signer(program, signature, data, "gpg.ssh.allowedSignersFile is not configured"),
+    };
+
+    let principals = Command::new(program).arg("-Y").arg("find-principals").arg("-f").arg(&allowed_signers).arg("-s").arg(signature).output();
+    let principal = match principals {
+        Ok(output) if output.status.success() => last_line(&output.stdout),
+        Ok(_) => return check_ssh_without_signer(program, signature, data, "the key is not in the allowed signers"),
+        Err(e) => return SignatureStatus::Unverified(format!("could not run {}: {}", program, e)),
+    };
+
+    let mut command = Command::new(program);
+    command.arg("-Y").arg("verify").arg("-n").arg("git").arg("-f").arg(&allowed_signers).arg("-I").arg(&principal).arg("-s").arg(signature);
+    match run_with_input(&mut command, data) {
+        Ok(output) if output.status.success() => SignatureStatus::Verified(principal),
+        Ok(output) => SignatureStatus::Unverified(last_line(&output.stderr)),
+        Err(e) => SignatureStatus::Unverified(format!("could not run {}: {}", program, e)),
+    }
+}
+
+// Verifies with the same program `sign_commit_buffer` would sign with for the kind of signature the commit has
+pub fn verify_commit_signature(repository: &Repository, id: &str) -> Result<SignatureStatus, git2::Error> {
+    let (signature, data) = repository.extract_signature(&Oid::from_str(id)?, None)?;
+    let Some(format) = SignatureFormat::of_signature(&signature) else {
+        return Ok(SignatureStatus::Unverified(String::from("the signature is in a format gitui doesn't know")));
+    };
+    let program = format.program(&repository.config()?);
+
+    let signature_path = write_temp_file(&format!("{}.sig", id), &signature)?;
+    let status = match format {
+        SignatureFormat::Ssh => verify_ssh(&program, repository, &signature_path, &data),
+        SignatureFormat::OpenPgp | SignatureFormat::X509 => verify_gpg(&program, &signature_path, &data),
+    };
+    let _ = fs::remove_file(signature_path);
+
+    Ok(status)
+}
+
+pub fn is_signing_enabled(repository: &Repository) -> bool {
+    repository.config().and_then(|c| c.get_bool("commit.gpgsign")).unwrap_or(false)
+}
+
+fn sign_gpg(program: &str, key: Option<&str>, buffer: &str) -> Result<String, String> {
+    let mut command = Command::new(program);
+    command.arg("--status-fd=2").arg("-bsa");
+    if let Some(key) = key {
+        command.arg("-u").arg(key);
+    }
+
+    let output = run_with_input(&mut command, buffer.as_bytes())?;
+    if !output.status.success() {
+        return Err(last_line(&output.stderr));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+fn sign_ssh(program: &str, key: &str, buffer: &str) -> Result<String, String> {
+    // `user.signingkey` may hold the public key itself rather than a path to it
+    let literal_key = key.strip_prefix("key::").or(if key.starts_with("ssh-") { Some(key) } else { None });
+    let key_path = match literal_key {
+        Some(key) => write_temp_file("signingkey.pub", key.as_bytes()).map_err(|e| e.message().to_string())?,
+        None => PathBuf::from(key),
+    };
+
+    let result = run_with_input(Command::new(program).arg("-Y").arg("sign").arg("-n").arg("git").arg("-f").arg(&key_path), buffer.as_bytes());
+    if literal_key.is_some() {
+        let _ = fs::remove_file(&key_path);
+    }
+
+    let output = result?;
+    if !output.status.success() {
+        return Err(last_line(&output.stderr));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+// Signs a commit buffer the same way `git commit -S` would, based on `gpg.format` and `user.signingkey`
+pub fn sign_commit_buffer(repository: &Repository, buffer: &str) -> Result<String, git2::Error> {
+    let config = repository.config()?;
+    let format = SignatureFormat::configured(&config);
+    let program = format.program(&config);
+    let key = config.get_string("user.signingkey").ok();
+
+    let result = match format {
+        SignatureFormat::Ssh => match &key {
+            Some(key) => sign_ssh(&program, key, buffer),
+            None => Err(String::from("user.signingkey is required for SSH signing")),
+        },
+        SignatureFormat::OpenPgp | SignatureFormat::X509 => sign_gpg(&program, key.as_deref(), buffer),
+    };
+
+    result.map_err(|e| git2::Error::from_str(&format!("signing failed: {}", e)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{SignatureStatus, parse_gpg_status};
+
+    const GOOD: &str = "[GNUPG:] NEWSIG\n[GNUPG:] GOODSIG 0123456789ABCDEF Alice <alice@example.com>\n[GNUPG:] VALIDSIG 0123 2024-01-01\n";
+
+    #[test]
+    fn good_signatures_need_a_trusted_key_to_be_verified() {
+        for trust in ["TRUST_FULLY 0 pgp", "TRUST_ULTIMATE 0 pgp"] {
+            let status = parse_gpg_status(&format!("{}[GNUPG:] {}\n", GOOD, trust));
+            assert_eq!(status, Some(SignatureStatus::Verified(String::from("Alice <alice@example.com>"))));
+        }
+        for trust in ["TRUST_UNDEFINED 0 pgp", "TRUST_NEVER 0 pgp", "TRUST_MARGINAL 0 pgp"] {
+            let status = parse_gpg_status(&format!("{}[GNUPG:] {}\n", GOOD, trust));
+            assert_eq!(status, Some(SignatureStatus::Untrusted(String::from("Alice <alice@example.com>, but the key is not trusted"))));
+        }
+    }
+
+    #[test]
+    fn good_signatures_without_validsig_are_not_verified() {
+        let status = "[GNUPG:] GOODSIG 0123456789ABCDEF Alice <alice@example.com>\n[GNUPG:] TRUST_ULTIMATE 0 pgp\n";
+        assert_eq!(parse_gpg_status(status), None);
+    }
+
+    #[test]
+    fn expired_and_revoked_keys_are_untrusted() {
+        let status = "[GNUPG:] REVKEYSIG 0123456789ABCDEF Alice <alice@example.com>\n[GNUPG:] VALIDSIG 0123\n[GNUPG:] TRUST_ULTIMATE 0 pgp\n";
+        assert_eq!(parse_gpg_status(status), Some(SignatureStatus::Untrusted(String::from("Alice <alice@example.com>, but the key was revoked"))));
+    }
+
+    #[test]
+    fn bad_signatures_are_unverified() {
+        let status = "[GNUPG:] BADSIG 0123456789ABCDEF Mallory <mallory@example.com>\n";
+        assert_eq!(parse_gpg_status(status), Some(SignatureStatus::Unverified(String::from("bad signature from Mallory <mallory@example.com>"))));
+    }
+}
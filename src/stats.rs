@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+
+use chrono::{Datelike, Duration, NaiveDate, TimeZone, Utc};
+use git2::{DiffOptions, Repository, Sort};
+
+// Weeks shown in the commits per week chart, ending with the week of the newest commit
+const WEEKS: usize = 52;
+// Diffing every commit of a long history takes a while, so churn only covers the newest ones
+const CHURN_COMMITS: usize = 500;
+const HOTSPOTS: usize = 10;
+const AUTHORS: usize = 10;
+
+pub struct HistoryStats {
+    pub commits: usize,
+    // Most commits first
+    pub authors: Vec<(String, usize)>,
+    // Monday of each of the last weeks, oldest first, weeks without commits included
+    pub weeks: Vec<(NaiveDate, usize)>,
+    // Lines added and removed per file over the newest commits, most first
+    pub hotspots: Vec<(String, usize)>,
+    pub churn_commits: usize,
+    pub merges: usize,
+    // Over the whole span of the history, from the oldest commit to the newest
+    pub merges_per_week: f64,
+}
+
+fn week_of(seconds: i64) -> Option<NaiveDate> {
+    let date = Utc.timestamp_opt(seconds, 0).single()?.date_naive();
+    Some(date - Duration::days(date.weekday().num_days_from_monday() as i64))
+}
+
+// Everything reachable from the local branches and HEAD, the history a tab shows by default. Stops with an error once
+// `cancelled` says so, which is asked between commits
+pub fn compute_stats(repository: &Repository, cancelled: &dyn Fn() -> bool) -> Result<HistoryStats, git2::Error> {
+    let mut revwalk = repository.revwalk()?;
+    revwalk.set_sorting(Sort::TIME)?;
+    revwalk.push_glob("refs/heads/*")?;
+    // Unborn or pointing somewhere odd, the branches are enough then
+    let _ = revwalk.push_head();
+
+    let mut commits = 0;
+    let mut authors: HashMap<String, usize> = HashMap::new();
+    let mut weeks: HashMap<NaiveDate, usize> = HashMap::new();
+    let mut churn: HashMap<String, usize> = HashMap::new();
+    let mut churn_commits = 0;
+    let mut merges = 0;
+    let (mut oldest, mut newest) = (i64::MAX, i64::MIN);
+
+    for id in revwalk {
+        if cancelled() {
+            return Err(git2::Error::from_str("counting was cancelled"));
+        }
+        let commit = repository.find_commit(id?)?;
+        commits += 1;
+
+        *authors.entry(commit.author().name().unwrap_or("unknown").to_string()).or_default() += 1;
+        let time = commit.time().seconds();
+        (oldest, newest) = (oldest.min(time), newest.max(time));
+        if let Some(week) = week_of(time) {
+            *weeks.entry(week).or_default() += 1;
+        }
+
+        // Merges are left out of the churn like `git log --stat` leaves them out, their changes are already counted
+        if commit.parent_count() > 1 {
+            merges += 1;
+        } else if churn_commits < CHURN_COMMITS {
+            churn_commits += 1;
+            let parent_tree = commit.parents().next().map(|parent| parent.tree()).transpose()?;
+            let diff = repository.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), Some(DiffOptions::new().ignore_submodules(true)))?;
+            for i in 0..diff.deltas().len() {
+                let patch = match git2::Patch::from_diff(&diff, i)? {
+                    Some(patch) => patch,
+                    None => continue,
+                };
+                let (_, insertions, deletions) = patch.line_stats()?;
+                if let Some(path) = patch.delta().new_file().path().or(patch.delta().old_file().path()) {
+                    *churn.entry(path.to_string_lossy().to_string()).or_default() += insertions + deletions;
+                }
+            }
+        }
+    }
+
+    let mut authors: Vec<(String, usize)> = authors.into_iter().collect();
+    authors.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    authors.truncate(AUTHORS);
+
+    let mut hotspots: Vec<(String, usize)> = churn.into_iter().filter(|(_, lines)| *lines > 0).collect();
+    hotspots.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    hotspots.truncate(HOTSPOTS);
+
+    let weeks = match (week_of(oldest), week_of(newest)) {
+        (Some(first), Some(last)) => {
+            let first = first.max(last - Duration::weeks(WEEKS as i64 - 1));
+            let count = (last - first).num_weeks() + 1;
+            (0..count).map(|i| first + Duration::weeks(i))
+                .map(|week| (week, weeks.get(&week).copied().unwrap_or(0)))
+                .collect()
+        },
+        _ => Vec::new(),
+    };
+
+    // A history younger than a week counts as one week
+    let merges_per_week = match commits {
+        0 => 0.0,
+        _ => merges as f64 / ((newest - oldest) as f64 / (7.0 * 24.0 * 60.0 * 60.0)).max(1.0),
+    };
+
+    Ok(HistoryStats { commits, authors, weeks, hotspots, churn_commits, merges, merges_per_week })
+}
@@ -0,0 +1,376 @@
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+use git2::build::CheckoutBuilder;
+use git2::{ApplyLocation, Diff, DiffOptions, ObjectType, Oid, Patch, Repository, Status, StatusOptions};
+use tracing::instrument;
+
+use crate::backend::{describe_binary_change, get_delta_status_char, is_binary_content};
+use crate::lfs::{describe_change, describe_pointer, parse_pointer};
+use crate::maintenance::format_size;
+use crate::preview::{ImagePreview, is_image_path, load_preview};
+
+pub struct StatusEntry {
+    pub path: String,
+    // Status letters as in `git status --short`, for the index and the working tree
+    pub staged: Option<char>,
+    pub unstaged: Option<char>,
+}
+
+pub struct DiffLine {
+    // '+', '-' or ' '
+    pub origin: char,
+    pub content: String,
+}
+
+pub struct Hunk {
+    pub header: String,
+    pub old_start: u32,
+    pub new_start: u32,
+    pub lines: Vec<DiffLine>,
+}
+
+pub struct FileDiff {
+    pub path: String,
+    pub staged: bool,
+    pub status: char,
+    pub hunks: Vec<Hunk>,
+    // Shown instead of the hunks for files stored with Git LFS, what their pointers stand for, and for binary files
+    pub summary: Option<String>,
+    pub image: Option<ImagePreview>,
+}
+
+impl FileDiff {
+    // Hunks and lines can only be picked out of files that exist on both sides
+    pub fn is_partial_allowed(&self) -> bool {
+        self.status == 'M' && self.summary.is_none()
+    }
+}
+
+fn get_index_status(status: Status) -> Option<char> {
+    if status.is_index_new() {
+        Some('A')
+    } else if status.is_index_modified() {
+        Some('M')
+    } else if status.is_index_deleted() {
+        Some('D')
+    } else if status.is_index_renamed() {
+        Some('R')
+    } else if status.is_index_typechange() {
+        Some('T')
+    } else {
+        None
+    }
+}
+
+fn get_worktree_status(status: Status) -> Option<char> {
+    if status.is_wt_new() {
+        Some('?')
+    } else if status.is_wt_modified() {
+        Some('M')
+    } else if status.is_wt_deleted() {
+        Some('D')
+    } else if status.is_wt_renamed() {
+        Some('R')
+    } else if status.is_wt_typechange() {
+        Some('T')
+    } else if status.is_conflicted() {
+        Some('U')
+    } else {
+        None
+    }
+}
+
+#[instrument(skip(repository), err)]
+pub fn get_status(repository: &Repository) -> Result<Vec<StatusEntry>, git2::Error> {
+    let mut options = StatusOptions::new();
+    options.include_untracked(true).recurse_untracked_dirs(true).include_ignored(false);
+
+    let mut entries = Vec::new();
+    for entry in repository.statuses(Some(&mut options))?.iter() {
+        let status = entry.status();
+        entries.push(StatusEntry {
+            path: entry.path().unwrap_or_default().to_string(),
+            staged: get_index_status(status),
+            unstaged: get_worktree_status(status),
+        });
+    }
+    Ok(entries)
+}
+
+// The staged diff (HEAD to index) or the unstaged one (index to working tree) of a single file
+#[instrument(skip(repository), err)]
+pub fn get_file_diff(repository: &Repository, path: &str, staged: bool) -> Result<FileDiff, git2::Error> {
+    let mut options = DiffOptions::new();
+    options.pathspec(path).disable_pathspec_match(true).include_untracked(true).show_untracked_content(true);
+
+    let diff = if staged {
+        let head = repository.head().ok().and_then(|head| head.peel_to_tree().ok());
+        repository.diff_tree_to_index(head.as_ref(), None, Some(&mut options))?
+    } else {
+        repository.diff_index_to_workdir(None, Some(&mut options))?
+    };
+
+    let mut file = FileDiff { path: path.to_string(), staged, status: ' ', hunks: Vec::new(), summary: None, image: None };
+    if let Some(delta) = diff.deltas().next() {
+        file.status = get_delta_status_char(delta.status());
+
+        let (old, new) = (delta.old_file(), delta.new_file());
+        let old_content = repository.find_blob(old.id()).ok().map(|blob| blob.content().to_vec());
+        // libgit2 doesn't run the LFS filter, so the working tree side is read as it is
+        let new_content = match repository.workdir() {
+            Some(workdir) if !staged => fs::read(workdir.join(path)).ok(),
+            _ => repository.find_blob(new.id()).ok().map(|blob| blob.content().to_vec()),
+        };
+
+        let old_pointer = old_content.as_deref().and_then(parse_pointer);
+        let new_pointer = new_content.as_deref().and_then(parse_pointer);
+        file.summary = match (&old_pointer, &new_pointer, &new_content) {
+            // The content that the filter turns into a new pointer once it's staged
+            (Some(pointer), None, Some(content)) if !staged => {
+                Some(format!("{} -> {} changed in the working tree", describe_pointer(pointer), format_size(content.len() as u64)))
+            },
+            _ => describe_change(old_pointer.as_ref(), old_content.is_some(), new_pointer.as_ref(), new_content.is_some()),
+        };
+
+        if file.summary.is_none() && old_content.iter().chain(&new_content).any(|content| is_binary_content(content)) {
+            let side = |content: &Option<Vec<u8>>| content.as_ref().map(|content| (Oid::hash_object(ObjectType::Blob, content).unwrap_or(Oid::zero()), content.len() as u64));
+            file.summary = Some(describe_binary_change(side(&old_content), side(&new_content)));
+        }
+
+        if is_image_path(Path::new(path)) {
+            let load = |content: &Option<Vec<u8>>| content.as_deref().and_then(|content| load_preview(Path::new(path), content));
+            let (old_image, new_image) = (load(&old_content), load(&new_content));
+            if old_image.is_some() || new_image.is_some() {
+                file.image = Some(ImagePreview::new(path.to_string(), old_image, new_image));
+            }
+        }
+    }
+
+    if let Some(patch) = Patch::from_diff(&diff, 0)? {
+        for i in 0..patch.num_hunks() {
+            let (hunk, line_count) = patch.hunk(i)?;
+            let mut lines = Vec::new();
+            for j in 0..line_count {
+                let line = patch.line_in_hunk(i, j)?;
+                // Markers for a missing newline at the end of the file are carried by the line itself
+                if matches!(line.origin(), '+' | '-' | ' ') {
+                    lines.push(DiffLine { origin: line.origin(), content: String::from_utf8_lossy(line.content()).to_string() });
+                }
+            }
+            file.hunks.push(Hunk {
+                header: String::from_utf8_lossy(hunk.header()).trim_end().to_string(),
+                old_start: hunk.old_start(),
+                new_start: hunk.new_start(),
+                lines,
+            });
+        }
+    }
+
+    Ok(file)
+}
+
+fn push_patch_line(patch: &mut String, origin: char, content: &str) {
+    patch.push(origin);
+    patch.push_str(content);
+    if !content.ends_with('\n') {
+        patch.push_str("\n\\ No newline at end of file\n");
+    }
+}
+
+// Patch holding only the selected lines of the given hunks, with `selected[hunk][line]` saying which.
+// Unselected additions are dropped and unselected removals kept as context, like `git add -p` editing does;
+// for a staged diff the patch is reversed, so applying it to the index unstages the selection
+fn build_partial_patch(file: &FileDiff, selected: &[Vec<bool>]) -> String {
+    let mut patch = String::new();
+    let _ = writeln!(patch, "diff --git a/{0} b/{0}\n--- a/{0}\n+++ b/{0}", file.path);
+
+    // Lines added or removed by earlier hunks move the start of later ones on the new side
+    let mut offset: i64 = 0;
+    for (hunk, selected) in file.hunks.iter().zip(selected) {
+        let mut body = String::new();
+        let (mut old_lines, mut new_lines, mut changes) = (0, 0, 0);
+        for (line, selected) in hunk.lines.iter().zip(selected) {
+            // The index is the new side of the staged diff, so that side becomes the old one when unstaging
+            let origin = match (line.origin, file.staged) {
+                ('+', true) => '-',
+                ('-', true) => '+',
+                (origin, _) => origin,
+            };
+            match (origin, *selected) {
+                (' ', _) | ('-', false) => {
+                    push_patch_line(&mut body, ' ', &line.content);
+                    old_lines += 1;
+                    new_lines += 1;
+                },
+                ('-', true) => {
+                    push_patch_line(&mut body, '-', &line.content);
+                    old_lines += 1;
+                    changes += 1;
+                },
+                ('+', true) => {
+                    push_patch_line(&mut body, '+', &line.content);
+                    new_lines += 1;
+                    changes += 1;
+                },
+                _ => (),
+            }
+        }
+        if changes == 0 {
+            continue
+        }
+
+        let old_start = if file.staged { hunk.new_start } else { hunk.old_start } as i64;
+        let _ = writeln!(patch, "@@ -{},{} +{},{} @@", old_start, old_lines, old_start + offset, new_lines);
+        patch.push_str(&body);
+        offset += new_lines as i64 - old_lines as i64;
+    }
+
+    patch
+}
+
+// Stages the selected lines of an unstaged diff, or unstages those of a staged one
+#[instrument(skip_all, fields(path = file.path), err)]
+pub fn apply_selection(repository: &Repository, file: &FileDiff, selected: &[Vec<bool>]) -> Result<(), git2::Error> {
+    if !file.is_partial_allowed() {
+        return Err(git2::Error::from_str("only parts of modified files can be staged, stage the whole file instead"));
+    }
+    let patch = build_partial_patch(file, selected);
+    let diff = Diff::from_buffer(patch.as_bytes())?;
+    if diff.deltas().len() == 0 {
+        return Err(git2::Error::from_str("no changed lines are selected"));
+    }
+    repository.apply(&diff, ApplyLocation::Index, None)
+}
+
+#[instrument(skip(repository), err)]
+pub fn stage_file(repository: &Repository, path: &str) -> Result<(), git2::Error> {
+    let mut index = repository.index()?;
+    let exists = repository.workdir().is_some_and(|workdir| workdir.join(path).symlink_metadata().is_ok());
+    if exists {
+        index.add_path(Path::new(path))?;
+    } else {
+        index.remove_path(Path::new(path))?;
+    }
+    index.write()
+}
+
+// Like `git add --update`, leaving untracked files alone. Returns whether the index then differs from HEAD at all
+#[instrument(skip(repository), err)]
+pub fn stage_tracked_changes(repository: &Repository) -> Result<bool, git2::Error> {
+    let mut index = repository.index()?;
+    index.update_all(["*"], None)?;
+    index.write()?;
+    let head = repository.head()?.peel_to_tree()?;
+    Ok(repository.diff_tree_to_index(Some(&head), Some(&index), None)?.deltas().len() > 0)
+}
+
+#[instrument(skip(repository), err)]
+pub fn unstage_file(repository: &Repository, path: &str) -> Result<(), git2::Error> {
+    match repository.head().and_then(|head| head.peel_to_commit()) {
+        Ok(head) => repository.reset_default(Some(head.as_object()), [path]),
+        // Nothing is committed yet, so unstaging means dropping the file from the index
+        Err(_) => {
+            let mut index = repository.index()?;
+            index.remove_path(Path::new(path))?;
+            index.write()
+        },
+    }
+}
+
+// Puts the file back the way HEAD has it, dropping staged and unstaged changes alike; an untracked file is deleted
+#[instrument(skip(repository), err)]
+pub fn discard_file(repository: &Repository, path: &str) -> Result<(), git2::Error> {
+    let mut checkout = CheckoutBuilder::new();
+    checkout.force().remove_untracked(true).path(path);
+    repository.checkout_head(Some(&mut checkout))
+}
+
+// Puts the file back the way the commit has it, leaving HEAD where it is. The index is only changed when asked to
+#[instrument(skip(repository), err)]
+pub fn restore_file(repository: &Repository, commit: &str, path: &str, index: bool) -> Result<(), git2::Error> {
+    let commit = repository.revparse_single(commit)?.peel_to_commit()?;
+    commit.tree()?.get_path(Path::new(path))?;
+    let mut checkout = CheckoutBuilder::new();
+    checkout.force().update_index(index).path(path);
+    repository.checkout_tree(commit.as_object(), Some(&mut checkout))
+}
+
+#[cfg(test)]
+mod tests {
+    use git2::Diff;
+
+    use super::{DiffLine, FileDiff, Hunk, build_partial_patch};
+
+    // Lines are written like in a patch, the origin followed by the content
+    fn hunk(old_start: u32, new_start: u32, lines: &[&str]) -> Hunk {
+        let lines = lines.iter().map(|line| {
+            let (origin, content) = line.split_at(1);
+            DiffLine { origin: origin.chars().next().unwrap(), content: content.to_string() }
+        }).collect();
+        Hunk { header: String::new(), old_start, new_start, lines }
+    }
+
+    fn partial_patch(staged: bool, hunks: Vec<Hunk>, selected: &[&[bool]]) -> String {
+        let file = FileDiff { path: String::from("file.txt"), staged, status: 'M', hunks, summary: None, image: None };
+        let selected: Vec<Vec<bool>> = selected.iter().map(|selected| selected.to_vec()).collect();
+        let patch = build_partial_patch(&file, &selected);
+        Diff::from_buffer(patch.as_bytes()).expect("libgit2 should read the patch back");
+        patch
+    }
+
+    // 2 replaced by two and 2b, and 8 by eight further down
+    fn hunks() -> Vec<Hunk> {
+        vec![
+            hunk(1, 1, &[" 1\n", "-2\n", "+two\n", "+2b\n", " 3\n"]),
+            hunk(7, 8, &[" 7\n", "-8\n", "+eight\n", " 9\n"]),
+        ]
+    }
+
+    #[test]
+    fn staging_keeps_unselected_removals_and_moves_later_hunks() {
+        let patch = partial_patch(false, hunks(), &[&[false, false, true, true, false], &[false, true, true, false]]);
+        assert_eq!(patch, concat!(
+            "diff --git a/file.txt b/file.txt\n--- a/file.txt\n+++ b/file.txt\n",
+            "@@ -1,3 +1,5 @@\n 1\n 2\n+two\n+2b\n 3\n",
+            "@@ -7,3 +9,3 @@\n 7\n-8\n+eight\n 9\n",
+        ));
+    }
+
+    #[test]
+    fn unstaging_reverses_the_sides() {
+        let patch = partial_patch(true, hunks(), &[&[false, false, true, false, false], &[false, true, false, false]]);
+        assert_eq!(patch, concat!(
+            "diff --git a/file.txt b/file.txt\n--- a/file.txt\n+++ b/file.txt\n",
+            "@@ -1,4 +1,3 @@\n 1\n-two\n 2b\n 3\n",
+            "@@ -8,3 +7,4 @@\n 7\n+8\n eight\n 9\n",
+        ));
+    }
+
+    #[test]
+    fn hunks_without_selected_changes_are_left_out() {
+        let patch = partial_patch(false, hunks(), &[&[true, false, false, false, true], &[false, true, true, false]]);
+        assert_eq!(patch, concat!(
+            "diff --git a/file.txt b/file.txt\n--- a/file.txt\n+++ b/file.txt\n",
+            "@@ -7,3 +7,3 @@\n 7\n-8\n+eight\n 9\n",
+        ));
+    }
+
+    #[test]
+    fn lines_without_a_newline_are_marked() {
+        let hunks = vec![hunk(1, 1, &[" a\n", "-b", "+c"])];
+        let patch = partial_patch(false, hunks, &[&[false, true, true]]);
+        assert_eq!(patch, concat!(
+            "diff --git a/file.txt b/file.txt\n--- a/file.txt\n+++ b/file.txt\n",
+            "@@ -1,2 +1,2 @@\n a\n-b\n\\ No newline at end of file\n+c\n\\ No newline at end of file\n",
+        ));
+
+        let hunks = vec![hunk(1, 1, &[" a\n", "-b", "+c"])];
+        let patch = partial_patch(false, hunks, &[&[false, true, false]]);
+        assert_eq!(patch, concat!(
+            "diff --git a/file.txt b/file.txt\n--- a/file.txt\n+++ b/file.txt\n",
+            "@@ -1,2 +1,1 @@\n a\n-b\n\\ No newline at end of file\n",
+        ));
+    }
+}
@@ -0,0 +1,242 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::io::ErrorKind;
+use std::process::{Child, Command};
+use std::thread;
+
+use git2::{Config, Oid, Repository};
+
+use crate::error::Error;
+
+// Tools git knows without a `cmd`, as the program and its arguments for diffing and for merging. Terminal
+// tools like vimdiff are left out, there is no terminal to run them in
+const BUILTIN_TOOLS: [(&str, &str, &str, &str); 7] = [
+    ("meld", "meld", r#""$LOCAL" "$REMOTE""#, r#""$LOCAL" "$BASE" "$REMOTE" --output="$MERGED""#),
+    ("kdiff3", "kdiff3", r#"--L1 "$MERGED (A)" --L2 "$MERGED (B)" "$LOCAL" "$REMOTE""#,
+        r#"--auto --L1 "$MERGED (Base)" --L2 "$MERGED (Local)" --L3 "$MERGED (Remote)" -o "$MERGED" "$BASE" "$LOCAL" "$REMOTE""#),
+    ("bc", "bcompare", r#""$LOCAL" "$REMOTE""#, r#""$LOCAL" "$REMOTE" "$BASE" -mergeoutput="$MERGED""#),
+    ("bc3", "bcompare", r#""$LOCAL" "$REMOTE""#, r#""$LOCAL" "$REMOTE" "$BASE" -mergeoutput="$MERGED""#),
+    ("bc4", "bcompare", r#""$LOCAL" "$REMOTE""#, r#""$LOCAL" "$REMOTE" "$BASE" -mergeoutput="$MERGED""#),
+    ("opendiff", "opendiff", r#""$LOCAL" "$REMOTE""#, r#""$LOCAL" "$REMOTE" -ancestor "$BASE" -merge "$MERGED""#),
+    ("vscode", "code", r#"--wait --diff "$LOCAL" "$REMOTE""#, r#"--wait --merge "$REMOTE" "$LOCAL" "$BASE" "$MERGED""#),
+];
+
+// What to check once a merge tool exits
+struct MergeCheck {
+    repository: PathBuf,
+    path: String,
+    before: Vec<u8>,
+    // Whether the exit code says if the merge worked; otherwise it is taken to have worked when the file changed
+    trust_exit_code: bool,
+}
+
+// A difftool or mergetool ready to run, with the temporary files it was given
+pub struct ToolRun {
+    name: String,
+    command: Command,
+    temporary_files: Vec<PathBuf>,
+    merge: Option<MergeCheck>,
+}
+
+impl ToolRun {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    // Waits for the tool to be closed, which can take as long as the user likes. Once a merge went well the
+    // file is staged, like `git mergetool` marks it resolved
+    pub fn run(mut self) -> Result<String, Error> {
+        let status = self.command.status();
+        for file in &self.temporary_files {
+            let _ = fs::remove_file(file);
+        }
+        let status = status.map_err(|e| Error::from(format!("couldn't run {}: {}", self.name, e)))?;
+
+        let check = match self.merge {
+            Some(check) => check,
+            None => return Ok(format!("Closed {}", self.name)),
+        };
+        let repository = Repository::open(&check.repository)?;
+        let merged = repository.workdir().ok_or(Error::from("there is no working tree to merge in"))?.join(&check.path);
+        let resolved = if check.trust_exit_code {
+            status.success()
+        } else {
+            fs::read(&merged).map(|after| after != check.before).unwrap_or(false)
+        };
+        if !resolved {
+            return Err(Error::from(format!("{} is still unresolved", check.path)));
+        }
+
+        let mut index = repository.index()?;
+        index.add_path(Path::new(&check.path))?;
+        index.write()?;
+        Ok(format!("Resolved {}", check.path))
+    }
+}
+
+// `<kind>.tool`, and for diffing `merge.tool` when no diff tool is configured, like git falls back to
+fn configured_tool(config: &Config, merge: bool) -> Result<String, Error> {
+    let tool = match merge {
+        true => config.get_string("merge.tool").ok(),
+        false => config.get_string("diff.tool").or_else(|_| config.get_string("merge.tool")).ok(),
+    };
+    tool.ok_or(Error::from(if merge { "set merge.tool in git config to use a merge tool" } else { "set diff.tool in git config to use a diff tool" }))
+}
+
+// The shell command line for the tool: `<kind>tool.<tool>.cmd` when set, otherwise the built in one, run with
+// `<kind>tool.<tool>.path` in place of the program when that is set
+fn tool_command(config: &Config, tool: &str, merge: bool) -> Result<String, Error> {
+    let section = if merge { "mergetool" } else { "difftool" };
+    if let Ok(cmd) = config.get_string(&format!("{}.{}.cmd", section, tool)) {
+        return Ok(cmd);
+    }
+
+    let (_, program, diff_args, merge_args) = BUILTIN_TOOLS.iter()
+        .find(|(name, ..)| *name == tool)
+        .ok_or(Error::from(format!("{} isn't one of the tools known here, set {}.{}.cmd to run it", tool, section, tool)))?;
+    let program = config.get_string(&format!("{}.{}.path", section, tool)).unwrap_or(program.to_string());
+    Ok(format!("'{}' {}", program.replace('\'', r"'\''"), if merge { merge_args } else { diff_args }))
+}
+
+// Written to the temporary directory and named after the file, so tools show which file it is and pick the
+// right syntax for it
+fn write_temporary_file(path: &str, label: &str, contents: &[u8]) -> Result<PathBuf, Error> {
+    let path = Path::new(path);
+    let stem = path.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+    let extension = path.extension().map(|extension| format!(".{}", extension.to_string_lossy())).unwrap_or_default();
+    let file = std::env::temp_dir().join(format!("{}_{}_{}_{:08x}{}", stem, label, std::process::id(), rand::random::<u32>(), extension));
+    fs::write(&file, contents)?;
+    Ok(file)
+}
+
+// The repository keeps its index in memory, which is stale once git changed it on disk
+fn current_index(repository: &Repository) -> Result<git2::Index, git2::Error> {
+    let mut index = repository.index()?;
+    index.read(false)?;
+    Ok(index)
+}
+
+fn blob_contents(repository: &Repository, id: Option<Oid>) -> Result<Vec<u8>, git2::Error> {
+    match id {
+        Some(id) => Ok(repository.find_blob(id)?.content().to_vec()),
+        // The file doesn't exist on that side
+        None => Ok(Vec::new()),
+    }
+}
+
+// Run through the shell with the files in `$LOCAL`, `$REMOTE`, `$MERGED` and `$BASE`, from the top of the working
+// tree, like git runs tools
+fn shell_command(repository: &Repository, command_line: &str, files: [(&str, &Path); 4]) -> Command {
+    let mut command = Command::new("sh");
+    command.arg("-c").arg(command_line).current_dir(repository.workdir().unwrap_or(repository.path()));
+    for (name, file) in files {
+        command.env(name, file);
+    }
+    command
+}
+
+// The configured difftool on one file, for its staged changes (HEAD against the index) or its unstaged
+// ones (the index against the working tree, handing the tool the file itself so edits to it stick)
+pub fn prepare_difftool(repository: &Repository, path: &str, staged: bool) -> Result<ToolRun, Error> {
+    let config = repository.config()?.snapshot()?;
+    let tool = configured_tool(&config, false)?;
+    let command_line = tool_command(&config, &tool, false)?;
+    let workdir = repository.workdir().ok_or(Error::from("there is no working tree to diff against"))?;
+
+    let index_id = current_index(repository)?.get_path(Path::new(path), 0).map(|entry| entry.id);
+    let mut temporary_files = Vec::new();
+    let (local, remote) = if staged {
+        let head_id = match repository.head().and_then(|head| head.peel_to_tree()) {
+            Ok(tree) => tree.get_path(Path::new(path)).ok().map(|entry| entry.id()),
+            Err(_) => None,
+        };
+        let local = write_temporary_file(path, "HEAD", &blob_contents(repository, head_id)?)?;
+        temporary_files.push(local.clone());
+        let remote = write_temporary_file(path, "INDEX", &blob_contents(repository, index_id)?)?;
+        temporary_files.push(remote.clone());
+        (local, remote)
+    } else {
+        let local = write_temporary_file(path, "INDEX", &blob_contents(repository, index_id)?)?;
+        temporary_files.push(local.clone());
+        (local, workdir.join(path))
+    };
+
+    let merged = workdir.join(path);
+    let command = shell_command(repository, &command_line, [("LOCAL", &local), ("REMOTE", &remote), ("MERGED", &merged), ("BASE", &merged)]);
+    Ok(ToolRun { name: tool, command, temporary_files, merge: None })
+}
+
+// The configured mergetool on a conflicted file, given the common ancestor, our side and their side
+pub fn prepare_mergetool(repository: &Repository, path: &str) -> Result<ToolRun, Error> {
+    let config = repository.config()?.snapshot()?;
+    let tool = configured_tool(&config, true)?;
+    let command_line = tool_command(&config, &tool, true)?;
+    let workdir = repository.workdir().ok_or(Error::from("there is no working tree to merge in"))?;
+
+    let conflict = current_index(repository)?.conflicts()?
+        .filter_map(|conflict| conflict.ok())
+        .find(|conflict| {
+            [&conflict.ancestor, &conflict.our, &conflict.their].into_iter()
+                .flatten()
+                .any(|entry| entry.path == path.as_bytes())
+        })
+        .ok_or(Error::from(format!("{} has no conflict to resolve", path)))?;
+
+    let base = write_temporary_file(path, "BASE", &blob_contents(repository, conflict.ancestor.map(|entry| entry.id))?)?;
+    let local = write_temporary_file(path, "LOCAL", &blob_contents(repository, conflict.our.map(|entry| entry.id))?)?;
+    let remote = write_temporary_file(path, "REMOTE", &blob_contents(repository, conflict.their.map(|entry| entry.id))?)?;
+    let merged = workdir.join(path);
+
+    // Tools git knows have exit codes that can be trusted, a custom `cmd` only when configured so
+    let trust_exit_code = match config.get_string(&format!("mergetool.{}.cmd", tool)) {
+        Ok(_) => config.get_bool(&format!("mergetool.{}.trustExitCode", tool)).unwrap_or(false),
+        Err(_) => true,
+    };
+    let merge = MergeCheck { repository: repository.path().to_path_buf(), path: path.to_string(), before: fs::read(&merged).unwrap_or_default(), trust_exit_code };
+
+    let command = shell_command(repository, &command_line, [("LOCAL", &local), ("REMOTE", &remote), ("MERGED", &merged), ("BASE", &base)]);
+    Ok(ToolRun { name: tool, command, temporary_files: vec![base, local, remote], merge: Some(merge) })
+}
+
+// Tried in order when neither `gitui.terminal` nor `$TERMINAL` says which terminal to use
+#[cfg(all(unix, not(target_os = "macos")))]
+const TERMINALS: [&str; 7] = ["x-terminal-emulator", "gnome-terminal", "konsole", "xfce4-terminal", "alacritty", "kitty", "xterm"];
+
+#[cfg(all(unix, not(target_os = "macos")))]
+fn spawn_default_terminal(directory: &Path) -> std::io::Result<Child> {
+    let configured = std::env::var("TERMINAL").ok();
+    for terminal in configured.iter().map(String::as_str).chain(TERMINALS) {
+        match Command::new(terminal).current_dir(directory).spawn() {
+            Err(e) if e.kind() == ErrorKind::NotFound => continue,
+            result => return result,
+        }
+    }
+    Err(std::io::Error::new(ErrorKind::NotFound, "no terminal was found, set gitui.terminal in git config"))
+}
+
+#[cfg(target_os = "macos")]
+fn spawn_default_terminal(directory: &Path) -> std::io::Result<Child> {
+    Command::new("open").arg("-a").arg("Terminal").arg(directory).spawn()
+}
+
+#[cfg(windows)]
+fn spawn_default_terminal(directory: &Path) -> std::io::Result<Child> {
+    Command::new("cmd").args(["/C", "start", "cmd"]).current_dir(directory).spawn()
+}
+
+// Opens a terminal at the top of the working tree. `gitui.terminal` is a shell command line to use instead of
+// the platform's terminal
+pub fn open_terminal(repository: &Repository) -> Result<(), Error> {
+    let directory = repository.workdir().unwrap_or(repository.path());
+    let configured = repository.config()?.get_string("gitui.terminal").ok();
+
+    let child = match configured {
+        Some(command_line) if cfg!(windows) => Command::new("cmd").arg("/C").arg(command_line).current_dir(directory).spawn(),
+        Some(command_line) => Command::new("sh").arg("-c").arg(command_line).current_dir(directory).spawn(),
+        None => spawn_default_terminal(directory),
+    };
+    let mut child = child.map_err(|e| Error::from(format!("couldn't open a terminal: {}", e)))?;
+    // Reaped in the background, since the terminal stays open for as long as it is used
+    thread::spawn(move || child.wait());
+    Ok(())
+}
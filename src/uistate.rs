@@ -0,0 +1,146 @@
+use std::fmt::Write;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::backend::ReferenceKind;
+use crate::error::Error;
+use crate::json::{JsonValue, escape_json, parse_json};
+use crate::settings::{Bookmark, BookmarkColor};
+
+// In the git directory, so it goes along with the checkout instead of staying on the machine
+const STATE_FILE: &str = "gitui-state.json";
+
+// How a repository was last looked at, for when the settings keep it in the repository
+#[derive(Debug, Clone, PartialEq)]
+pub struct RepositoryUiState {
+    pub zoom: f32,
+    pub offset: (f32, f32),
+    pub author: Option<String>,
+    pub since: String,
+    pub until: String,
+    pub first_parent: bool,
+    pub collapse_chains: bool,
+    pub hidden_references: Vec<(String, ReferenceKind)>,
+    pub bookmarks: Vec<Bookmark>,
+}
+
+impl Default for RepositoryUiState {
+    fn default() -> Self {
+        Self {
+            zoom: 1.0,
+            offset: (0.0, 0.0),
+            author: None,
+            since: String::new(),
+            until: String::new(),
+            first_parent: false,
+            collapse_chains: false,
+            hidden_references: Vec::new(),
+            bookmarks: Vec::new(),
+        }
+    }
+}
+
+fn kind_name(kind: ReferenceKind) -> &'static str {
+    match kind {
+        ReferenceKind::Branch => "branch",
+        ReferenceKind::Remote => "remote",
+        ReferenceKind::Tag => "tag",
+    }
+}
+
+fn kind_from_name(name: &str) -> Option<ReferenceKind> {
+    [ReferenceKind::Branch, ReferenceKind::Remote, ReferenceKind::Tag].into_iter().find(|kind| kind_name(*kind) == name)
+}
+
+fn state_path(git_directory: &Path) -> PathBuf {
+    git_directory.join(STATE_FILE)
+}
+
+impl RepositoryUiState {
+    // None when there is no state file yet or it doesn't parse. Entries that don't make sense are left out, and
+    // missing fields keep their defaults
+    pub fn load(git_directory: &Path) -> Option<RepositoryUiState> {
+        let json = parse_json(&fs::read_to_string(state_path(git_directory)).ok()?)?;
+        let mut state = RepositoryUiState::default();
+
+        if let Some(viewport) = json.get("viewport") {
+            if let Some(zoom) = viewport.get("zoom").and_then(JsonValue::as_f32) {
+                state.zoom = zoom;
+            }
+            if let [x, y] = viewport.get("offset").map(JsonValue::as_array).unwrap_or(&[]) {
+                if let (Some(x), Some(y)) = (x.as_f32(), y.as_f32()) {
+                    state.offset = (x, y);
+                }
+            }
+        }
+
+        if let Some(filters) = json.get("filters") {
+            state.author = filters.get("author").and_then(JsonValue::as_str).map(str::to_string);
+            let text = |key| filters.get(key).and_then(JsonValue::as_str).unwrap_or_default().to_string();
+            state.since = text("since");
+            state.until = text("until");
+            state.first_parent = filters.get("first_parent").and_then(JsonValue::as_bool).unwrap_or(false);
+            state.collapse_chains = filters.get("collapse_chains").and_then(JsonValue::as_bool).unwrap_or(false);
+            state.hidden_references = filters.get("hidden_references").map(JsonValue::as_array).unwrap_or(&[]).iter()
+                .filter_map(|reference| {
+                    let name = reference.get("name")?.as_str()?;
+                    Some((name.to_string(), kind_from_name(reference.get("kind")?.as_str()?)?))
+                })
+                .collect();
+        }
+
+        state.bookmarks = json.get("bookmarks").map(JsonValue::as_array).unwrap_or(&[]).iter()
+            .filter_map(|bookmark| Some(Bookmark {
+                repository: git_directory.to_path_buf(),
+                commit: bookmark.get("commit")?.as_str()?.to_string(),
+                label: bookmark.get("label")?.as_str()?.to_string(),
+                color: BookmarkColor::from_name(bookmark.get("color")?.as_str()?)?,
+            }))
+            .collect();
+
+        Some(state)
+    }
+
+    pub fn save(&self, git_directory: &Path) -> Result<(), Error> {
+        let string = |text: &str| format!("\"{}\"", escape_json(text));
+
+        let mut json = String::new();
+        let _ = writeln!(json, "{{");
+        let _ = writeln!(json, "  \"viewport\": {{ \"zoom\": {}, \"offset\": [{}, {}] }},", self.zoom, self.offset.0, self.offset.1);
+        let _ = writeln!(json, "  \"filters\": {{");
+        let _ = writeln!(json, "    \"author\": {},", self.author.as_deref().map(string).unwrap_or(String::from("null")));
+        let _ = writeln!(json, "    \"since\": {},", string(&self.since));
+        let _ = writeln!(json, "    \"until\": {},", string(&self.until));
+        let _ = writeln!(json, "    \"first_parent\": {},", self.first_parent);
+        let _ = writeln!(json, "    \"collapse_chains\": {},", self.collapse_chains);
+        let hidden: Vec<String> = self.hidden_references.iter()
+            .map(|(name, kind)| format!("{{ \"name\": {}, \"kind\": \"{}\" }}", string(name), kind_name(*kind)))
+            .collect();
+        let _ = writeln!(json, "    \"hidden_references\": [{}]", hidden.join(", "));
+        let _ = writeln!(json, "  }},");
+        let bookmarks: Vec<String> = self.bookmarks.iter()
+            .map(|bookmark| format!("    {{ \"commit\": {}, \"label\": {}, \"color\": \"{}\" }}", string(&bookmark.commit), string(&bookmark.label), bookmark.color.name()))
+            .collect();
+        if bookmarks.is_empty() {
+            let _ = writeln!(json, "  \"bookmarks\": []");
+        } else {
+            let _ = writeln!(json, "  \"bookmarks\": [\n{}\n  ]", bookmarks.join(",\n"));
+        }
+        let _ = writeln!(json, "}}");
+
+        fs::write(state_path(git_directory), json)?;
+        Ok(())
+    }
+
+    // A commit has one bookmark, like in the settings
+    pub fn set_bookmark(&mut self, bookmark: Bookmark) {
+        match self.bookmarks.iter_mut().find(|existing| existing.commit == bookmark.commit) {
+            Some(existing) => *existing = bookmark,
+            None => self.bookmarks.push(bookmark),
+        }
+    }
+
+    pub fn remove_bookmark(&mut self, commit: &str) {
+        self.bookmarks.retain(|bookmark| bookmark.commit != commit);
+    }
+}
@@ -0,0 +1,90 @@
+mod common;
+
+use std::process::Command;
+
+use git2::Oid;
+
+use gitui::backend::{count_commits, ReferenceGroups};
+use gitui::commitgraph::{graph_ahead_behind, graph_descendant_of, CommitGraph};
+
+use common::Fixture;
+
+// Forks, plain merges and octopus merges of three and four parents, whose parents past the first continue in the
+// EDGE chunk, with tags and remote branches on commits no local branch reaches
+fn fixture(name: &str) -> Fixture {
+    let mut fixture = Fixture::new(name);
+    fixture.commit("a", &[]).commit("b", &["a"]).commit("c", &["a"]).commit("d", &["a"]).commit("e", &["b", "c", "d"]);
+    fixture.commit("f", &["e"]).commit("g", &["e"]).commit("h", &["f", "g"]).commit("i", &["c"]).commit("j", &["i"]);
+    fixture.commit("k", &["h", "j", "d", "b"]).commit("l", &["k"]).commit("m", &["j"]).commit("n", &["l"]);
+    fixture.commit("tagged", &["m"]).commit("remote", &["tagged"]);
+    fixture.reference("refs/heads/main", "n").reference("refs/heads/topic", "m");
+    fixture.reference("refs/tags/v1", "tagged").reference("refs/remotes/origin/main", "remote");
+    fixture
+}
+
+fn write_commit_graph(fixture: &Fixture) -> CommitGraph {
+    let status = Command::new("git").arg("--git-dir").arg(fixture.repository.path()).args(["commit-graph", "write", "--reachable"]).status().unwrap();
+    assert!(status.success());
+    CommitGraph::open(&fixture.repository).expect("the commit-graph git wrote should be readable")
+}
+
+fn ids(fixture: &Fixture) -> Vec<Oid> {
+    ["a", "b", "c", "d", "e", "f", "g", "h", "i", "j", "k", "l", "m", "n", "tagged", "remote"].iter().map(|summary| fixture.id(summary)).collect()
+}
+
+#[test]
+fn ahead_behind_matches_libgit2() {
+    let fixture = fixture("commit-graph-ahead-behind");
+    let graph = write_commit_graph(&fixture);
+    assert_eq!(graph.commit_count(), 16);
+
+    let ids = ids(&fixture);
+    for local in &ids {
+        for upstream in &ids {
+            let expected = fixture.repository.graph_ahead_behind(*local, *upstream).unwrap();
+            assert_eq!(graph.ahead_behind(*local, *upstream), Some(expected), "{} against {}", local, upstream);
+            assert_eq!(graph_ahead_behind(&fixture.repository, Some(&graph), *local, *upstream).unwrap(), expected);
+        }
+    }
+}
+
+#[test]
+fn descendant_of_matches_libgit2() {
+    let fixture = fixture("commit-graph-descendant");
+    let graph = write_commit_graph(&fixture);
+
+    let ids = ids(&fixture);
+    for commit in &ids {
+        for ancestor in &ids {
+            let expected = fixture.repository.graph_descendant_of(*commit, *ancestor).unwrap();
+            assert_eq!(graph_descendant_of(&fixture.repository, Some(&graph), *commit, *ancestor).unwrap(), expected, "{} from {}", commit, ancestor);
+        }
+    }
+}
+
+#[test]
+fn commits_newer_than_the_graph_fall_back_to_libgit2() {
+    let mut fixture = fixture("commit-graph-newer");
+    let graph = write_commit_graph(&fixture);
+    fixture.commit("o", &["n", "m"]);
+
+    let (new, old) = (fixture.id("o"), fixture.id("a"));
+    assert_eq!(graph.ahead_behind(new, old), None);
+    assert_eq!(graph.reachable_count(&[new]), None);
+    assert_eq!(graph_ahead_behind(&fixture.repository, Some(&graph), new, old).unwrap(), fixture.repository.graph_ahead_behind(new, old).unwrap());
+}
+
+#[test]
+fn counting_leaves_out_excluded_references() {
+    let fixture = fixture("commit-graph-count");
+    let all = count_commits(&fixture.repository, ReferenceGroups::ALL).unwrap();
+    let local = count_commits(&fixture.repository, ReferenceGroups { remotes: false, tags: false }).unwrap();
+    let no_remotes = count_commits(&fixture.repository, ReferenceGroups { remotes: false, tags: true }).unwrap();
+    assert_eq!((all, local, no_remotes), (16, 14, 15));
+
+    // Read from the commit-graph the same
+    write_commit_graph(&fixture);
+    assert_eq!(count_commits(&fixture.repository, ReferenceGroups::ALL).unwrap(), all);
+    assert_eq!(count_commits(&fixture.repository, ReferenceGroups { remotes: false, tags: false }).unwrap(), local);
+    assert_eq!(count_commits(&fixture.repository, ReferenceGroups { remotes: false, tags: true }).unwrap(), no_remotes);
+}
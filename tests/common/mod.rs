@@ -0,0 +1,76 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+
+use git2::{Oid, Repository, Signature, Time};
+
+// A bare repository of empty commits named by their summaries, every commit a minute after the one before, so every
+// run builds the same ids and the chronological layout has an order to go by. Removed again once dropped
+pub struct Fixture {
+    path: PathBuf,
+    pub repository: Repository,
+    tree: Oid,
+    commits: HashMap<String, Oid>,
+    count: i64,
+}
+
+#[allow(dead_code)]
+impl Fixture {
+    pub fn new(name: &str) -> Fixture {
+        let path = env::temp_dir().join(format!("gitui-fixture-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&path);
+        let repository = Repository::init_bare(&path).unwrap();
+        let tree = repository.treebuilder(None).unwrap().write().unwrap();
+        Fixture { path, repository, tree, commits: HashMap::new(), count: 0 }
+    }
+
+    // Parents are named like the commits made before
+    pub fn commit(&mut self, summary: &str, parents: &[&str]) -> &mut Fixture {
+        let parents: Vec<Oid> = parents.iter().map(|parent| self.id(parent)).collect();
+        let id = self.commit_with(summary, &parents);
+        self.commits.insert(summary.to_string(), id);
+        self
+    }
+
+    // For generated histories too long to name every commit, which are told apart by their number instead
+    pub fn numbered_commit(&mut self, parents: &[Oid]) -> Oid {
+        self.commit_with(&format!("commit {}", self.count + 1), parents)
+    }
+
+    fn commit_with(&mut self, summary: &str, parents: &[Oid]) -> Oid {
+        let time = Time::new(1_700_000_000 + self.count * 60, 0);
+        self.count += 1;
+        let signature = Signature::new("fixture", "fixture@example.com", &time).unwrap();
+        let tree = self.repository.find_tree(self.tree).unwrap();
+        let parents: Vec<_> = parents.iter().map(|parent| self.repository.find_commit(*parent).unwrap()).collect();
+        self.repository.commit(None, &signature, &signature, summary, &tree, &parents.iter().collect::<Vec<_>>()).unwrap()
+    }
+
+    pub fn id(&self, summary: &str) -> Oid {
+        self.commits[summary]
+    }
+
+    // Creates the reference, or moves it when it's there already
+    pub fn reference(&mut self, name: &str, commit: &str) -> &mut Fixture {
+        self.repository.reference(name, self.id(commit), true, "").unwrap();
+        self
+    }
+
+    pub fn branch(&mut self, name: &str, commit: Oid) -> &mut Fixture {
+        self.repository.reference(&format!("refs/heads/{}", name), commit, true, "").unwrap();
+        self
+    }
+
+    pub fn delete_reference(&mut self, name: &str) -> &mut Fixture {
+        self.repository.find_reference(name).unwrap().delete().unwrap();
+        self
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
@@ -0,0 +1,52 @@
+mod common;
+
+use std::collections::BTreeMap;
+
+use gitui::backend::{load_commits, update_commits, CommitNode, ReferenceGroups, ReferenceLabel};
+
+use common::Fixture;
+
+type Projection = BTreeMap<String, (Vec<String>, Vec<String>, Vec<ReferenceLabel>, String)>;
+
+// Everything loading decides about a commit, for comparing graphs
+fn project(commits: &BTreeMap<String, CommitNode>) -> Projection {
+    commits.values()
+        .map(|commit| (commit.id.clone(), (commit.parents.clone(), commit.children.clone(), commit.references.clone(), commit.summary.clone())))
+        .collect()
+}
+
+fn load(fixture: &Fixture, groups: ReferenceGroups) -> BTreeMap<String, CommitNode> {
+    load_commits(&fixture.repository, groups, &mut |_| true).unwrap().unwrap()
+}
+
+fn assert_update_matches_load(fixture: &Fixture, previous: BTreeMap<String, CommitNode>, groups: ReferenceGroups) {
+    let updated = update_commits(&fixture.repository, previous, groups, &mut |_| true).unwrap().unwrap();
+    assert_eq!(project(&updated), project(&load(fixture, groups)));
+}
+
+#[test]
+fn updating_matches_a_fresh_load() {
+    let mut fixture = Fixture::new("updates");
+    fixture.commit("a", &[]).commit("b", &["a"]).commit("c", &["b"]).commit("d", &["c"]).commit("x", &["b"]).commit("y", &["x"]);
+    fixture.reference("refs/heads/main", "d").reference("refs/heads/zeta", "c").reference("refs/heads/gone", "y");
+    let previous = load(&fixture, ReferenceGroups::ALL);
+
+    // A branch named before the others forks off a commit that already had a child, main moves on, one branch is
+    // reset back, another is deleted along with the only history it reached, and a tag lands on a known commit
+    fixture.commit("e", &["d"]).commit("f", &["b"]).commit("g", &["f"]);
+    fixture.reference("refs/heads/main", "e").reference("refs/heads/alpha", "g").reference("refs/heads/zeta", "b");
+    fixture.delete_reference("refs/heads/gone").reference("refs/tags/v1", "c");
+
+    assert_update_matches_load(&fixture, previous, ReferenceGroups::ALL);
+}
+
+#[test]
+fn updating_without_tags_drops_what_only_tags_reach() {
+    let mut fixture = Fixture::new("update-groups");
+    fixture.commit("a", &[]).commit("b", &["a"]).commit("c", &["b"]).commit("t", &["b"]).commit("u", &["t"]);
+    fixture.reference("refs/heads/main", "c").reference("refs/tags/v1", "u");
+    let previous = load(&fixture, ReferenceGroups::ALL);
+    assert!(previous.contains_key(&fixture.id("u").to_string()));
+
+    assert_update_matches_load(&fixture, previous, ReferenceGroups::default());
+}
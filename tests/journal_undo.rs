@@ -0,0 +1,49 @@
+mod common;
+
+use gitui::journal::{take_snapshot, Journal};
+
+use common::Fixture;
+
+// A commit on main recorded in the journal, kept staged on undo since the fixture is bare and has no working tree
+fn committed(name: &str) -> (Fixture, Journal) {
+    let mut fixture = Fixture::new(name);
+    fixture.commit("a", &[]).commit("b", &["a"]).commit("c", &["b"]).reference("refs/heads/main", "a");
+    fixture.repository.set_head("refs/heads/main").unwrap();
+
+    let mut journal = Journal::default();
+    let before = take_snapshot(&fixture.repository).unwrap();
+    fixture.reference("refs/heads/main", "b");
+    journal.record_keeping_changes(&fixture.repository, String::from("commit"), before).unwrap();
+    (fixture, journal)
+}
+
+fn target(fixture: &Fixture, name: &str) -> Option<git2::Oid> {
+    fixture.repository.refname_to_id(name).ok()
+}
+
+#[test]
+fn undo_restores_what_the_operation_changed() {
+    let (fixture, mut journal) = committed("undo-restores");
+    assert_eq!(journal.undo(&fixture.repository).unwrap(), "commit");
+    assert_eq!(target(&fixture, "refs/heads/main"), Some(fixture.id("a")));
+}
+
+#[test]
+fn undo_leaves_references_made_since_alone() {
+    let (mut fixture, mut journal) = committed("undo-leaves-others");
+    fixture.reference("refs/heads/topic", "c").reference("refs/tags/v1", "b");
+    journal.undo(&fixture.repository).unwrap();
+    assert_eq!(target(&fixture, "refs/heads/main"), Some(fixture.id("a")));
+    assert_eq!(target(&fixture, "refs/heads/topic"), Some(fixture.id("c")));
+    assert_eq!(target(&fixture, "refs/tags/v1"), Some(fixture.id("b")));
+}
+
+#[test]
+fn undo_refuses_once_a_changed_reference_moved_again() {
+    let (mut fixture, mut journal) = committed("undo-refuses");
+    fixture.reference("refs/heads/main", "c");
+    assert!(journal.undo(&fixture.repository).is_err());
+    assert_eq!(target(&fixture, "refs/heads/main"), Some(fixture.id("c")));
+    // Still there to undo once main is back where the commit left it
+    assert!(journal.last().is_some());
+}
@@ -0,0 +1,91 @@
+mod common;
+
+use std::env;
+use std::fmt::Write;
+use std::fs;
+use std::path::Path;
+
+use gitui::backend::{layout_commits, load_commits, GraphSize, LayoutMode, ReferenceGroups};
+use gitui::config::Orientation;
+
+use common::Fixture;
+
+// The layouts every fixture is snapshotted in
+const LAYOUTS: [(LayoutMode, Orientation); 3] = [
+    (LayoutMode::Topological, Orientation::Horizontal),
+    (LayoutMode::Topological, Orientation::Vertical),
+    (LayoutMode::Chronological, Orientation::Horizontal),
+];
+
+// Every node as its summary and position, a list per layout
+fn render(fixture: &Fixture) -> String {
+    let mut commits = load_commits(&fixture.repository, ReferenceGroups::ALL, &mut |_| true).unwrap().unwrap();
+    let mut rendered = String::new();
+    for (mode, orientation) in LAYOUTS {
+        let _ = writeln!(rendered, "[{} {}]", mode, orientation);
+        let locations = layout_commits(&mut commits, mode, orientation, GraphSize::default()).locations;
+        let mut nodes: Vec<(&str, f32, f32)> = locations.iter()
+            // Adding zero turns -0 into 0, which flipping an axis leaves behind
+            .map(|(id, location)| (commits[id].summary.as_str(), location.x + 0.0, location.y + 0.0))
+            .collect();
+        nodes.sort_by(|a, b| a.0.cmp(b.0));
+        for (summary, x, y) in nodes {
+            let _ = writeln!(rendered, "{} {:.1} {:.1}", summary, x, y);
+        }
+    }
+    rendered
+}
+
+// Compares with `tests/snapshots/<name>.snap`. Running with UPDATE_SNAPSHOTS=1 writes the snapshots instead, for new
+// fixtures and when a layout change is intended
+fn assert_snapshot(name: &str, fixture: &Fixture) {
+    let rendered = render(fixture);
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("snapshots").join(format!("{}.snap", name));
+    if env::var_os("UPDATE_SNAPSHOTS").is_some() {
+        fs::write(&path, &rendered).unwrap();
+        return;
+    }
+    let Ok(expected) = fs::read_to_string(&path) else {
+        panic!("there is no snapshot of {} yet, run with UPDATE_SNAPSHOTS=1 to write it\n--- actual\n{}", name, rendered);
+    };
+    assert!(expected == rendered, "the layout of {} changed, rerun with UPDATE_SNAPSHOTS=1 if that's intended\n--- expected\n{}\n--- actual\n{}", name, expected, rendered);
+}
+
+#[test]
+fn linear_history() {
+    let mut fixture = Fixture::new("linear");
+    fixture.commit("a", &[]).commit("b", &["a"]).commit("c", &["b"]).commit("d", &["c"]).reference("refs/heads/main", "d");
+    assert_snapshot("linear", &fixture);
+}
+
+#[test]
+fn forked_branches() {
+    let mut fixture = Fixture::new("forks");
+    fixture.commit("a", &[]).commit("b", &["a"]).commit("c", &["a"]).commit("d", &["b"]).commit("e", &["b"]).commit("f", &["c"]);
+    fixture.reference("refs/heads/main", "d").reference("refs/heads/topic", "e").reference("refs/heads/other", "f");
+    assert_snapshot("forks", &fixture);
+}
+
+#[test]
+fn merged_branches() {
+    let mut fixture = Fixture::new("merges");
+    fixture.commit("a", &[]).commit("b", &["a"]).commit("c", &["a"]).commit("d", &["b", "c"]).commit("e", &["d"]).commit("f", &["d"]).commit("g", &["e", "f"]);
+    fixture.reference("refs/heads/main", "g");
+    assert_snapshot("merges", &fixture);
+}
+
+#[test]
+fn octopus_merge() {
+    let mut fixture = Fixture::new("octopus");
+    fixture.commit("a", &[]).commit("b", &["a"]).commit("c", &["a"]).commit("d", &["a"]).commit("e", &["b", "c", "d"]);
+    fixture.reference("refs/heads/main", "e");
+    assert_snapshot("octopus", &fixture);
+}
+
+#[test]
+fn orphan_histories_and_tags() {
+    let mut fixture = Fixture::new("orphans");
+    fixture.commit("a", &[]).commit("b", &["a"]).commit("x", &[]).commit("c", &["b"]).commit("y", &["x"]).commit("z", &["x"]);
+    fixture.reference("refs/heads/main", "c").reference("refs/heads/pages", "y").reference("refs/tags/v1", "b").reference("refs/remotes/origin/pages", "z");
+    assert_snapshot("orphans", &fixture);
+}
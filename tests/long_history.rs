@@ -0,0 +1,31 @@
+mod common;
+
+use gitui::backend::{layout_commits, load_commits, GraphSize, LayoutMode, ReferenceGroups};
+use gitui::config::Orientation;
+
+use common::Fixture;
+
+// Building the graph recursively overflowed a test thread's stack from about a thousand commits in debug builds and
+// before five thousand in release ones
+const CHAIN_LENGTH: usize = 5_000;
+
+#[test]
+fn long_chains_load_on_a_default_stack() {
+    let mut fixture = Fixture::new("long-history");
+    let mut tip = fixture.numbered_commit(&[]);
+    for _ in 1..CHAIN_LENGTH {
+        tip = fixture.numbered_commit(&[tip]);
+    }
+    fixture.branch("main", tip);
+
+    let mut commits = load_commits(&fixture.repository, ReferenceGroups::ALL, &mut |_| true).unwrap().unwrap();
+    assert_eq!(commits.len(), CHAIN_LENGTH);
+    let tip = tip.to_string();
+    assert_eq!(commits[&tip].references.len(), 1);
+    assert_eq!(commits[&tip].parents.len(), 1);
+
+    for mode in [LayoutMode::Topological, LayoutMode::Chronological] {
+        let locations = layout_commits(&mut commits, mode, Orientation::Horizontal, GraphSize::default()).locations;
+        assert_eq!(locations.len(), CHAIN_LENGTH);
+    }
+}